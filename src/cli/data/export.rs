@@ -69,6 +69,8 @@ impl Context for Export {
             use_cache: false,
             clear_cache: false,
             local_mode: None,
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         };
 
         match SearchService::search("", &c.org, stream_type, None, &req).await {
@@ -13,7 +13,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    atomic::{AtomicI64, Ordering},
+};
 
 use config::{
     RwAHashMap, RwHashMap,
@@ -35,6 +38,7 @@ use hashbrown::HashMap;
 use infra::table::short_urls::ShortUrlRecord;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 use vector_enrichment::TableRegistry;
 
 use crate::{
@@ -43,7 +47,9 @@ use crate::{
         organization::{Organization, OrganizationSetting},
     },
     service::{
-        db::scheduler as db_scheduler, enrichment::StreamTable, enrichment_table::geoip::Geoip,
+        db::{self, scheduler as db_scheduler},
+        enrichment::StreamTable,
+        enrichment_table::geoip::Geoip,
         pipeline::batch_execution::ExecutablePipeline,
     },
 };
@@ -109,6 +115,114 @@ pub static USER_ROLES_CACHE: Lazy<RwAHashMap<String, CachedUserRoles>> =
 pub static SYSTEM_SETTINGS: Lazy<Arc<RwAHashMap<String, SystemSetting>>> =
     Lazy::new(|| Arc::new(tokio::sync::RwLock::new(HashMap::new())));
 
+/// The caches which can be reloaded from the meta store one by one with [`refresh_cache`],
+/// instead of reloading all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum CacheKind {
+    /// the stream schemas and settings
+    Schema,
+    /// ORGANIZATIONS
+    Organization,
+    /// USERS
+    User,
+    /// USER_SESSIONS and USER_SESSIONS_EXPIRY
+    Session,
+    /// QUERY_FUNCTIONS
+    Functions,
+    /// STREAM_EXECUTABLE_PIPELINES, PIPELINE_STREAM_MAPPING and SCHEDULED_PIPELINES
+    Pipeline,
+    /// ALERTS and STREAM_ALERTS
+    Alerts,
+    /// DESTINATIONS
+    Destinations,
+    /// ALERTS_TEMPLATES
+    Templates,
+    /// SHORT_URLS
+    ShortUrl,
+    /// REALTIME_ALERT_TRIGGERS
+    RealtimeTriggers,
+    /// ORG_USERS, USERS_RUM_TOKEN and ROOT_USER
+    OrgUsers,
+    /// the stream data retention of the compactor
+    CompactRetention,
+}
+
+/// The unix timestamp in seconds of the last refresh of each cache, by a full reload or a watch
+/// event
+static CACHE_REFRESHED_AT: Lazy<HashMap<CacheKind, AtomicI64>> = Lazy::new(|| {
+    CacheKind::iter()
+        .map(|kind| (kind, AtomicI64::new(0)))
+        .collect()
+});
+
+/// Reloads the given cache from the meta store
+pub async fn refresh_cache(kind: CacheKind) -> Result<(), anyhow::Error> {
+    load_cache(kind).await?;
+    mark_cache_refreshed(kind);
+    Ok(())
+}
+
+/// Records the cache as refreshed now, called by the watchers when an event of the cache is
+/// received
+pub fn mark_cache_refreshed(kind: CacheKind) {
+    CACHE_REFRESHED_AT[&kind].store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+}
+
+/// Returns the unix timestamp in seconds of the last refresh of the cache, None if it was never
+/// refreshed
+pub fn cache_refreshed_at(kind: CacheKind) -> Option<i64> {
+    let ts = CACHE_REFRESHED_AT[&kind].load(Ordering::Relaxed);
+    (ts > 0).then_some(ts)
+}
+
+/// Returns the number of entries of the major caches by cache name
+pub async fn cache_entries() -> Vec<(&'static str, usize)> {
+    vec![
+        ("users", USERS.len()),
+        ("org_users", ORG_USERS.len()),
+        ("organizations", ORGANIZATIONS.read().await.len()),
+        ("query_functions", QUERY_FUNCTIONS.len()),
+        ("user_sessions", USER_SESSIONS.len()),
+        ("alerts", ALERTS.read().await.len()),
+        ("stream_alerts", STREAM_ALERTS.read().await.len()),
+        (
+            "realtime_alert_triggers",
+            REALTIME_ALERT_TRIGGERS.read().await.len(),
+        ),
+        ("alerts_templates", ALERTS_TEMPLATES.len()),
+        ("destinations", DESTINATIONS.len()),
+        ("enrichment_tables", ENRICHMENT_TABLES.len()),
+        (
+            "stream_executable_pipelines",
+            STREAM_EXECUTABLE_PIPELINES.read().await.len(),
+        ),
+        (
+            "scheduled_pipelines",
+            SCHEDULED_PIPELINES.read().await.len(),
+        ),
+        ("short_urls", SHORT_URLS.len()),
+    ]
+}
+
+async fn load_cache(kind: CacheKind) -> Result<(), anyhow::Error> {
+    match kind {
+        CacheKind::Schema => db::schema::cache().await,
+        CacheKind::Organization => db::organization::cache().await,
+        CacheKind::User => db::user::cache().await,
+        CacheKind::Session => db::session::cache().await,
+        CacheKind::Functions => db::functions::cache().await,
+        CacheKind::Pipeline => db::pipeline::cache().await,
+        CacheKind::Alerts => db::alerts::alert::cache().await,
+        CacheKind::Destinations => db::alerts::destinations::cache().await,
+        CacheKind::Templates => db::alerts::templates::cache().await,
+        CacheKind::ShortUrl => db::short_url::cache().await,
+        CacheKind::RealtimeTriggers => db::alerts::realtime_triggers::cache().await,
+        CacheKind::OrgUsers => db::org_users::cache().await,
+        CacheKind::CompactRetention => db::compact::retention::cache().await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -265,6 +379,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cache_kind_names() {
+        use std::str::FromStr;
+
+        assert_eq!(CacheKind::iter().count(), 13);
+        for kind in CacheKind::iter() {
+            assert_eq!(CacheKind::from_str(&kind.to_string()).unwrap(), kind);
+        }
+        assert_eq!(CacheKind::ShortUrl.to_string(), "short_url");
+        assert_eq!(
+            CacheKind::from_str("realtime_triggers").unwrap(),
+            CacheKind::RealtimeTriggers
+        );
+        assert!(CacheKind::from_str("unknown").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_refreshed_at() {
+        assert_eq!(cache_refreshed_at(CacheKind::Templates), None);
+        CACHE_REFRESHED_AT[&CacheKind::Templates].store(1_700_000_000, Ordering::Relaxed);
+        assert_eq!(
+            cache_refreshed_at(CacheKind::Templates),
+            Some(1_700_000_000)
+        );
+
+        // a watch event refreshes the cache as well
+        let now = chrono::Utc::now().timestamp();
+        mark_cache_refreshed(CacheKind::Templates);
+        assert!(cache_refreshed_at(CacheKind::Templates).unwrap() >= now);
+
+        let entries = cache_entries().await;
+        assert!(entries.iter().any(|(name, _)| *name == "alerts"));
+    }
+
     #[test]
     fn test_dashmap_operations() {
         // Test basic DashMap operations on KVS
@@ -197,6 +197,19 @@ impl HttpResponse {
             .into_response()
     }
 
+    /// Send a PayloadTooLarge response in json format and associate the
+    /// provided error as `error` field.
+    pub fn payload_too_large(error: impl ToString) -> Response {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(Self::error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                error.to_string(),
+            )),
+        )
+            .into_response()
+    }
+
     /// Send a response in json format, status code is 200.
     /// The payload should be serde-serializable.
     pub fn json<T: Serialize>(payload: T) -> Response {
@@ -21,6 +21,7 @@ use maxminddb::Reader;
 #[derive(Clone)]
 pub struct MaxmindClient {
     pub city_reader: Arc<Reader<Vec<u8>>>,
+    pub asn_reader: Option<Arc<Reader<Vec<u8>>>>,
 }
 
 impl MaxmindClient {
@@ -28,6 +29,7 @@ impl MaxmindClient {
     pub fn new_with_reader(city_reader: Reader<Vec<u8>>) -> Self {
         Self {
             city_reader: Arc::new(city_reader),
+            asn_reader: None,
         }
     }
 
@@ -43,4 +45,17 @@ impl MaxmindClient {
             })?;
         Ok(MaxmindClient::new_with_reader(city_reader))
     }
+
+    /// Load the asn database, the asn lookup is skipped when it isn't loaded
+    pub fn set_asn_database<T: AsRef<Path>>(&mut self, asn_database: T) -> Result<()> {
+        let asn_reader: Reader<Vec<u8>> =
+            Reader::open_readfile(&asn_database).with_context(|| {
+                format!(
+                    "Failed to find asn-database from path {:?}",
+                    asn_database.as_ref()
+                )
+            })?;
+        self.asn_reader = Some(Arc::new(asn_reader));
+        Ok(())
+    }
 }
@@ -13,9 +13,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, net::IpAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use axum::{body::Body, extract::Path, http::Request, middleware::Next, response::Response};
+use dashmap::DashMap;
 use maxminddb::geoip2::city::Location;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -23,7 +29,11 @@ use uaparser::{Parser, UserAgentParser};
 
 use crate::{
     USER_AGENT_REGEX_FILE,
-    common::{infra::config::MAXMIND_DB_CLIENT, utils::http::parse_ip_addr},
+    common::{
+        infra::config::MAXMIND_DB_CLIENT,
+        meta::{http::HttpResponse as MetaHttpResponse, maxmind::client::MaxmindClient},
+        utils::http::get_forwarded_client_ip,
+    },
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -32,49 +42,218 @@ pub struct GeoInfoData<'a> {
     pub country: Option<&'a str>,
     pub country_iso_code: Option<&'a str>,
     pub location: Option<Location<'a>>,
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub asn: Option<AsnInfoData<'a>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct AsnInfoData<'a> {
+    pub asn_number: Option<u32>,
+    pub asn_org: Option<&'a str>,
 }
 
 /// This is a global cache for user agent parser. This is lazily initialized only when
 /// the first request comes in.
 static UA_PARSER: Lazy<Arc<UserAgentParser>> = Lazy::new(|| Arc::new(initialize_ua_parser()));
 
+/// The RUM rate limits of the orgs and their token buckets
+static RUM_RATE_LIMITERS: Lazy<DashMap<String, RumRateLimiter>> = Lazy::new(DashMap::new);
+
+/// How long the RUM rate limit of an org is used before it is loaded from the org settings again
+const RUM_RATE_LIMIT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The RUM rate limit of an org as loaded at `loaded_at`, 0 means no limit
+#[derive(Debug)]
+struct RumRateLimiter {
+    rate: u32,
+    loaded_at: Instant,
+    bucket: TokenBucket,
+}
+
+impl RumRateLimiter {
+    fn try_acquire(&mut self, now: Instant) -> bool {
+        self.rate == 0 || self.bucket.try_acquire(self.rate, now)
+    }
+}
+
+/// Token bucket refilled with `rate` tokens per second, holding at most `rate` tokens so a burst
+/// can't exceed one second of requests.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32, now: Instant) -> Self {
+        Self {
+            tokens: rate as f64,
+            updated_at: now,
+        }
+    }
+
+    fn try_acquire(&mut self, rate: u32, now: Instant) -> bool {
+        let rate = rate as f64;
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.updated_at = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// Checks the RUM rate limit of the org, the requests are always allowed when the org has no
+/// limit set. The limit is cached and loaded again every `RUM_RATE_LIMIT_REFRESH_INTERVAL`.
+async fn check_rum_rate_limit(org_id: &str) -> bool {
+    let now = Instant::now();
+    if let Some(mut limiter) = RUM_RATE_LIMITERS.get_mut(org_id)
+        && now.saturating_duration_since(limiter.loaded_at) < RUM_RATE_LIMIT_REFRESH_INTERVAL
+    {
+        return limiter.try_acquire(now);
+    }
+
+    let rate = crate::service::db::organization::get_org_setting(org_id)
+        .await
+        .ok()
+        .and_then(|settings| settings.rum_rate_limit)
+        .unwrap_or_default();
+    let mut limiter = RUM_RATE_LIMITERS
+        .entry(org_id.to_string())
+        .or_insert_with(|| RumRateLimiter {
+            rate,
+            loaded_at: now,
+            bucket: TokenBucket::new(rate, now),
+        });
+    limiter.rate = rate;
+    limiter.loaded_at = now;
+    limiter.try_acquire(now)
+}
+
 pub fn initialize_ua_parser() -> UserAgentParser {
     UserAgentParser::builder()
         .build_from_bytes(USER_AGENT_REGEX_FILE)
         .expect("User Agent Parser creation failed")
 }
 
+/// Looks up the geo information of the given ip address in the city database, and the asn
+/// information in the asn database when it is loaded.
+/// Returns empty geo info when the database is not loaded or the lookup fails.
+pub fn lookup_geo(client: Option<&MaxmindClient>, ip: IpAddr) -> GeoInfoData<'_> {
+    let Some(client) = client else {
+        return GeoInfoData::default();
+    };
+    let mut geo_info = match client
+        .city_reader
+        .lookup(ip)
+        .ok()
+        .and_then(|r| r.decode::<maxminddb::geoip2::City>().ok())
+        .flatten()
+    {
+        Some(city_info) => GeoInfoData {
+            city: city_info.city.names.english,
+            country: city_info.country.names.english,
+            country_iso_code: city_info.country.iso_code,
+            location: Some(city_info.location),
+            asn: None,
+        },
+        None => GeoInfoData::default(),
+    };
+    geo_info.asn = lookup_asn(client, ip);
+    geo_info
+}
+
+/// Looks up the asn information of the given ip address in the asn database.
+/// Returns None when the database is not loaded or the lookup fails.
+pub fn lookup_asn(client: &MaxmindClient, ip: IpAddr) -> Option<AsnInfoData<'_>> {
+    let asn_info = client
+        .asn_reader
+        .as_ref()?
+        .lookup(ip)
+        .ok()?
+        .decode::<maxminddb::geoip2::Asn>()
+        .ok()??;
+    Some(AsnInfoData {
+        asn_number: asn_info.autonomous_system_number,
+        asn_org: asn_info.autonomous_system_organization,
+    })
+}
+
+/// Parses the user agent string into its device, os and browser parts.
+pub fn parse_user_agent(user_agent: &str) -> serde_json::Value {
+    let parsed_user_agent = (*UA_PARSER).parse(user_agent);
+    serde_json::to_value(parsed_user_agent).unwrap_or_default()
+}
+
 /// This is the custom data which is provided by `browser-sdk`
 /// in form of query-parameters.
-/// NOTE: the only condition is that the prefix of such params is `oo`.
+/// NOTE: the only condition is that the prefix of such params is one of `ZO_RUM_PARAM_PREFIXES`.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RumExtraData {
     pub data: HashMap<String, serde_json::Value>,
 }
 
+/// Parses the comma separated custom param prefixes, falls back to `oo,o2` when none is set.
+fn parse_param_prefixes(prefixes: &str) -> Vec<String> {
+    let prefixes: Vec<String> = prefixes
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect();
+    if prefixes.is_empty() {
+        vec!["oo".to_string(), "o2".to_string()]
+    } else {
+        prefixes
+    }
+}
+
 impl RumExtraData {
-    fn filter_api_keys(data: &mut HashMap<String, String>) {
+    fn filter_api_keys(data: &mut HashMap<String, String>, prefixes: &[String]) {
+        // drop the api key of any prefix, `oo-api-key` also starts with the prefix `o`
         data.retain(|k, _| {
-            (k.starts_with("oo") || k.starts_with("o2") || k.starts_with("batch_time"))
-                && !(k.eq("oo-api-key") || k.eq("o2-api-key"))
+            k.starts_with("batch_time")
+                || (prefixes.iter().any(|p| k.starts_with(p.as_str()))
+                    && !prefixes
+                        .iter()
+                        .any(|p| k.strip_prefix(p.as_str()) == Some("-api-key")))
         })
     }
 
-    fn filter_tags(data: &HashMap<String, String>) -> HashMap<String, serde_json::Value> {
-        data.get("ootags")
-            .or_else(|| data.get("o2tags"))
+    fn filter_tags(
+        data: &HashMap<String, String>,
+        prefixes: &[String],
+    ) -> HashMap<String, serde_json::Value> {
+        prefixes
+            .iter()
+            .find_map(|p| data.get(&format!("{p}tags")))
             .map_or_else(HashMap::default, |tags| {
+                // skip the tags without a key or a separator, the value may contain `:`
                 tags.split(',')
-                    .map(|tag| {
-                        let key_val: Vec<_> = tag.split(':').collect();
-                        (key_val[0].to_string(), key_val[1].into())
+                    .filter_map(|tag| {
+                        let (key, val) = tag.split_once(':')?;
+                        let key = key.trim();
+                        (!key.is_empty()).then(|| (key.to_string(), val.into()))
                     })
                     .collect()
             })
     }
 
-    /// Middleware function for axum to extract RUM extra data
-    pub async fn extractor_middleware(mut request: Request<Body>, next: Next) -> Response {
+    /// Middleware function for axum to extract RUM extra data, the requests over the RUM rate
+    /// limit of the org are rejected before the enrichment
+    pub async fn extractor_middleware(
+        Path(org_id): Path<String>,
+        mut request: Request<Body>,
+        next: Next,
+    ) -> Response {
+        if !check_rum_rate_limit(&org_id).await {
+            return MetaHttpResponse::too_many_requests(format!(
+                "RUM rate limit exceeded for org {org_id}"
+            ));
+        }
+
         // Parse query parameters
         let query_string = request.uri().query().unwrap_or("");
         let mut data: HashMap<String, String> =
@@ -82,10 +261,11 @@ impl RumExtraData {
                 .into_owned()
                 .collect();
 
-        Self::filter_api_keys(&mut data);
+        let prefixes = parse_param_prefixes(&config::get_config().rum.param_prefixes);
+        Self::filter_api_keys(&mut data, &prefixes);
 
-        // These are the tags which come in `ootags` or `o2tags`
-        let tags: HashMap<String, serde_json::Value> = Self::filter_tags(&data);
+        // These are the tags which come in `{prefix}tags`, e.g. `ootags` or `o2tags`
+        let tags: HashMap<String, serde_json::Value> = Self::filter_tags(&data, &prefixes);
 
         let mut user_agent_hashmap: HashMap<String, serde_json::Value> = data
             .into_iter()
@@ -95,48 +275,18 @@ impl RumExtraData {
         // Now extend the existing hashmap with tags.
         user_agent_hashmap.extend(tags);
         {
-            let headers = request.headers();
-            // Get IP address from headers or connection info
-            let ip_address = headers
-                .get("X-Forwarded-For")
-                .or_else(|| headers.get("Forwarded"))
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("127.0.0.1")
-                .to_string();
-
-            let ip = match parse_ip_addr(&ip_address) {
-                Ok((ip, _)) => ip,
-                // Default to ipv4 loopback address
-                Err(_) => IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)),
-            };
+            // Get the client IP address from the proxy headers
+            let ip = get_forwarded_client_ip(
+                request.headers(),
+                config::get_config().rum.trusted_proxy_count,
+            )
+            // Default to ipv4 loopback address
+            .unwrap_or(IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)));
 
-            user_agent_hashmap.insert("ip".into(), ip_address.into());
+            user_agent_hashmap.insert("ip".into(), ip.to_string().into());
 
             let maxminddb_client = MAXMIND_DB_CLIENT.read().await;
-            let geo_info = if let Some(client) = maxminddb_client.as_ref() {
-                if let Some(city_info) = client
-                    .city_reader
-                    .lookup(ip)
-                    .ok()
-                    .and_then(|r| r.decode::<maxminddb::geoip2::City>().ok())
-                    .flatten()
-                {
-                    let country = city_info.country.names.english;
-                    let city = city_info.city.names.english;
-                    let country_iso_code = city_info.country.iso_code;
-                    GeoInfoData {
-                        city,
-                        country,
-                        country_iso_code,
-                        location: Some(city_info.location),
-                    }
-                } else {
-                    GeoInfoData::default()
-                }
-            } else {
-                GeoInfoData::default()
-            };
-
+            let geo_info = lookup_geo(maxminddb_client.as_ref(), ip);
             let geo_info = serde_json::to_value(geo_info).unwrap_or_default();
             drop(maxminddb_client);
 
@@ -151,12 +301,7 @@ impl RumExtraData {
                 .and_then(|v| v.to_str().ok())
                 .unwrap_or_default();
 
-            let parsed_user_agent = (*UA_PARSER).parse(user_agent);
-
-            user_agent_hashmap.insert(
-                "user_agent".into(),
-                serde_json::to_value(parsed_user_agent).unwrap_or_default(),
-            );
+            user_agent_hashmap.insert("user_agent".into(), parse_user_agent(user_agent));
         }
 
         let rum_extracted_data = RumExtraData {
@@ -174,6 +319,93 @@ impl RumExtraData {
 mod tests {
     use super::*;
 
+    fn default_prefixes() -> Vec<String> {
+        parse_param_prefixes("oo,o2")
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+        assert!(bucket.try_acquire(2, start));
+        assert!(bucket.try_acquire(2, start));
+        assert!(!bucket.try_acquire(2, start));
+
+        // refilled with 2 tokens per second
+        let now = start + std::time::Duration::from_millis(500);
+        assert!(bucket.try_acquire(2, now));
+        assert!(!bucket.try_acquire(2, now));
+
+        // a long pause doesn't exceed one second of requests
+        let now = start + std::time::Duration::from_secs(60);
+        assert!(bucket.try_acquire(2, now));
+        assert!(bucket.try_acquire(2, now));
+        assert!(!bucket.try_acquire(2, now));
+    }
+
+    #[test]
+    fn test_rum_rate_limiter() {
+        let now = Instant::now();
+        let mut limiter = RumRateLimiter {
+            rate: 0,
+            loaded_at: now,
+            bucket: TokenBucket::new(0, now),
+        };
+        // no limit set
+        assert!((0..10).all(|_| limiter.try_acquire(now)));
+
+        limiter.rate = 1;
+        let now = now + Duration::from_secs(1);
+        assert!(limiter.try_acquire(now));
+        assert!(!limiter.try_acquire(now));
+    }
+
+    #[test]
+    fn test_custom_param_prefixes() {
+        let prefixes = parse_param_prefixes(" xy , ,ab");
+        assert_eq!(prefixes, vec!["xy", "ab"]);
+        assert_eq!(parse_param_prefixes(" , "), default_prefixes());
+
+        let mut data: HashMap<String, String> = [
+            ("xy-api-key", "secret"),
+            ("ab-api-key", "secret"),
+            ("xy-param", "value"),
+            ("ab-param", "value"),
+            ("oo-param", "value"),
+            ("batch_time", "123456"),
+            ("abtags", "env:prod"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        RumExtraData::filter_api_keys(&mut data, &prefixes);
+        let mut keys: Vec<&str> = data.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["ab-param", "abtags", "batch_time", "xy-param"]);
+
+        let tags = RumExtraData::filter_tags(&data, &prefixes);
+        assert_eq!(tags.get("env"), Some(&serde_json::json!("prod")));
+        assert!(RumExtraData::filter_tags(&data, &default_prefixes()).is_empty());
+    }
+
+    #[test]
+    fn test_filter_api_keys_overlapping_prefixes() {
+        let prefixes = parse_param_prefixes("o,oo");
+        let mut data: HashMap<String, String> = [
+            ("o-api-key", "secret"),
+            ("oo-api-key", "secret"),
+            ("o-param", "value"),
+            ("oo-param", "value"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        RumExtraData::filter_api_keys(&mut data, &prefixes);
+        let mut keys: Vec<&str> = data.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, vec!["o-param", "oo-param"]);
+    }
+
     #[tokio::test]
     async fn test_data_filtering() {
         // Create a mock query string
@@ -186,7 +418,7 @@ mod tests {
                 .into_owned()
                 .collect();
 
-        RumExtraData::filter_api_keys(&mut data);
+        RumExtraData::filter_api_keys(&mut data, &default_prefixes());
 
         // Assert that the data is filtered correctly
         assert_eq!(data.len(), 3);
@@ -208,7 +440,7 @@ mod tests {
                 .into_owned()
                 .collect();
 
-            let data = RumExtraData::filter_tags(&query_data);
+            let data = RumExtraData::filter_tags(&query_data, &default_prefixes());
 
             // Assert that the tags are filtered correctly
             assert!(!data.is_empty());
@@ -228,6 +460,7 @@ mod tests {
             country: Some("United States"),
             country_iso_code: Some("US"),
             location: None,
+            asn: None,
         };
 
         assert_eq!(geo_info.city, Some("New York"));
@@ -262,6 +495,47 @@ mod tests {
         assert!(!parsed.user_agent.family.is_empty());
     }
 
+    #[test]
+    fn test_lookup_geo_without_client() {
+        let ip = IpAddr::V4(std::net::Ipv4Addr::new(8, 8, 8, 8));
+        let geo_info = lookup_geo(None, ip);
+        assert!(geo_info.city.is_none());
+        assert!(geo_info.country.is_none());
+        assert!(geo_info.country_iso_code.is_none());
+        assert!(geo_info.location.is_none());
+    }
+
+    #[test]
+    fn test_geo_info_data_asn_serialization() {
+        // the event shape doesn't change without asn info
+        let geo_info = serde_json::to_value(GeoInfoData::default()).unwrap();
+        assert!(geo_info.get("asn").is_none());
+
+        let geo_info = GeoInfoData {
+            city: Some("Mountain View"),
+            asn: Some(AsnInfoData {
+                asn_number: Some(15169),
+                asn_org: Some("GOOGLE"),
+            }),
+            ..Default::default()
+        };
+        let geo_info = serde_json::to_value(geo_info).unwrap();
+        assert_eq!(geo_info["city"], "Mountain View");
+        assert_eq!(geo_info["asn"]["asn_number"], 15169);
+        assert_eq!(geo_info["asn"]["asn_org"], "GOOGLE");
+    }
+
+    #[test]
+    fn test_parse_user_agent() {
+        let user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+        let parsed = parse_user_agent(user_agent);
+        assert_eq!(parsed["user_agent"]["family"], "Chrome");
+        assert_eq!(parsed["os"]["family"], "Windows");
+
+        let parsed = parse_user_agent("");
+        assert!(parsed.is_object());
+    }
+
     #[test]
     fn test_filter_api_keys_edge_cases() {
         // Test edge cases for filter_api_keys function
@@ -292,7 +566,7 @@ mod tests {
         ];
 
         for (mut input, expected_len) in test_cases {
-            RumExtraData::filter_api_keys(&mut input);
+            RumExtraData::filter_api_keys(&mut input, &default_prefixes());
             assert_eq!(input.len(), expected_len);
 
             // Verify no API keys remain
@@ -315,7 +589,7 @@ mod tests {
         .into_iter()
         .collect();
 
-        RumExtraData::filter_api_keys(&mut data);
+        RumExtraData::filter_api_keys(&mut data, &default_prefixes());
 
         // Should only keep the valid keys (case-sensitive matching)
         assert_eq!(data.len(), 0); // All keys are filtered out due to case sensitivity
@@ -339,7 +613,7 @@ mod tests {
         ];
 
         for (input, expected_len) in test_cases {
-            let result = RumExtraData::filter_tags(&input);
+            let result = RumExtraData::filter_tags(&input, &default_prefixes());
             assert_eq!(result.len(), expected_len);
         }
     }
@@ -359,11 +633,37 @@ mod tests {
             let mut data = HashMap::new();
             data.insert("ootags".to_string(), tags_str.to_string());
 
-            let result = RumExtraData::filter_tags(&data);
+            let result = RumExtraData::filter_tags(&data, &default_prefixes());
             assert_eq!(result.len(), expected_count);
         }
     }
 
+    #[test]
+    fn test_filter_tags_without_separator() {
+        let test_cases = [
+            // value contains the separator
+            ("a:b:c", vec![("a", "b:c")]),
+            // empty segments and a tag without the separator
+            ("foo,,bar:baz,", vec![("bar", "baz")]),
+            // trailing comma
+            ("env:prod,", vec![("env", "prod")]),
+            // whitespace around the key, empty key
+            (" service :web, :no-key", vec![("service", "web")]),
+            ("", vec![]),
+        ];
+
+        for (tags_str, expected) in test_cases {
+            let mut data = HashMap::new();
+            data.insert("ootags".to_string(), tags_str.to_string());
+
+            let result = RumExtraData::filter_tags(&data, &default_prefixes());
+            assert_eq!(result.len(), expected.len(), "tags: {tags_str}");
+            for (key, value) in expected {
+                assert_eq!(result.get(key).unwrap(), value, "tags: {tags_str}");
+            }
+        }
+    }
+
     #[test]
     fn test_filter_tags_no_tags_present() {
         // Test when neither ootags nor o2tags are present
@@ -371,7 +671,7 @@ mod tests {
         data.insert("other-param".to_string(), "other-value".to_string());
         data.insert("oo-param".to_string(), "oo-value".to_string());
 
-        let result = RumExtraData::filter_tags(&data);
+        let result = RumExtraData::filter_tags(&data, &default_prefixes());
         assert!(result.is_empty());
     }
 }
@@ -330,6 +330,9 @@ pub struct OrganizationSettingPayload {
     pub dark_mode_theme_color: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_series_per_query: Option<usize>,
+    /// The RUM requests accepted per second, 0 removes the limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rum_rate_limit: Option<u32>,
     #[cfg(feature = "enterprise")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub claim_parser_function: Option<String>,
@@ -363,6 +366,9 @@ pub struct OrganizationSetting {
     pub dark_mode_theme_color: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_series_per_query: Option<usize>,
+    /// The RUM requests accepted per second for the org, the requests aren't limited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rum_rate_limit: Option<u32>,
     #[cfg(feature = "enterprise")]
     #[serde(default = "default_claim_parser_function")]
     pub claim_parser_function: String,
@@ -394,6 +400,7 @@ impl Default for OrganizationSetting {
             light_mode_theme_color,
             dark_mode_theme_color,
             max_series_per_query: None,
+            rum_rate_limit: None,
             #[cfg(feature = "enterprise")]
             claim_parser_function: default_claim_parser_function(),
         }
@@ -22,6 +22,7 @@ use strum::IntoEnumIterator;
 use utoipa::ToSchema;
 
 use super::organization::OrgRoleMapping;
+use crate::common::utils::auth::PresignedUrlMode;
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserRequest {
@@ -457,6 +458,9 @@ pub struct AuthTokensExt {
     pub refresh_token: String,
     pub request_time: i64,
     pub expires_in: i64,
+    /// how the presigned URL which issued the token was signed
+    #[serde(default)]
+    pub mode: PresignedUrlMode,
 }
 
 impl AuthTokensExt {
@@ -765,12 +769,20 @@ mod tests {
             refresh_token: "refresh123".to_string(),
             request_time: 1234567890,
             expires_in: 3600,
+            mode: PresignedUrlMode::Hmac,
         };
 
         assert_eq!(tokens.auth_ext, "auth123");
         assert_eq!(tokens.refresh_token, "refresh123");
         assert_eq!(tokens.request_time, 1234567890);
         assert_eq!(tokens.expires_in, 3600);
+
+        // the tokens issued before the mode existed are argon2
+        let tokens: AuthTokensExt = serde_json::from_str(
+            r#"{"auth_ext":"auth123","refresh_token":"","request_time":1,"expires_in":2}"#,
+        )
+        .unwrap();
+        assert_eq!(tokens.mode, PresignedUrlMode::Argon2);
     }
 
     #[test]
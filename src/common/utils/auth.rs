@@ -26,8 +26,10 @@ use config::{
     meta::user::UserRole,
     utils::{hash::get_passcode_hash, json},
 };
+use hmac::{Hmac, Mac};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use sha2::Sha256;
 #[cfg(feature = "enterprise")]
 use {
     crate::{
@@ -1421,6 +1423,81 @@ pub fn extract_basic_auth_str_from_parts(parts: &Parts) -> String {
     }
 }
 
+/// How the signature in the `auth` param of a presigned URL is computed.
+///
+/// `Argon2` is a chain of three argon2 hashes, it's slow by design. `Hmac` is a single
+/// HMAC-SHA256 keyed on the server salt and is cheap to generate and verify. The signatures
+/// aren't interchangeable, a URL has to be verified with the mode it was generated with, so
+/// hmac URLs carry `mode=hmac` and URLs without a mode are argon2.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PresignedUrlMode {
+    #[default]
+    Argon2,
+    Hmac,
+}
+
+impl PresignedUrlMode {
+    /// Signs the request time and expiration with `password_ext`, the hash of the password with
+    /// `salt` which is stored for the user, so the server can verify without the password.
+    pub fn sign(&self, password_ext: &str, salt: &str, time: i64, exp_in: i64) -> String {
+        match self {
+            PresignedUrlMode::Argon2 => {
+                let stage2 = get_hash(&format!("{password_ext}{time}"), salt);
+                get_hash(&format!("{stage2}{exp_in}"), salt)
+            }
+            PresignedUrlMode::Hmac => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(salt.as_bytes())
+                    .expect("HMAC can take key of any size");
+                mac.update(format!("{password_ext}:{time}:{exp_in}").as_bytes());
+                hex::encode(mac.finalize().into_bytes())
+            }
+        }
+    }
+}
+
+/// The params of a presigned URL received by `/auth/login`, see [`generate_presigned_url`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresignedUrlParams {
+    pub auth: String,
+    pub request_time: i64,
+    pub exp_in: i64,
+    pub mode: PresignedUrlMode,
+}
+
+impl PresignedUrlParams {
+    /// Parses the params of the login query, returns None if `auth` is missing, if the request
+    /// time or the expiration isn't an integer or if the mode is unknown. URLs without a mode
+    /// are argon2.
+    pub fn from_query(query: &HashMap<String, String>) -> Option<Self> {
+        // the base64 `auth` isn't url encoded, the query parser decodes its `+` as a space
+        let auth = query.get("auth")?.replace(' ', "+");
+        let request_time = query.get("request_time")?.parse().ok()?;
+        let exp_in = query.get("exp_in")?.parse().ok()?;
+        let mode = match query.get("mode").map(String::as_str) {
+            None | Some("argon2") => PresignedUrlMode::Argon2,
+            Some("hmac") => PresignedUrlMode::Hmac,
+            Some(_) => return None,
+        };
+        Some(Self {
+            auth,
+            request_time,
+            exp_in,
+            mode,
+        })
+    }
+}
+
 /// Constructs the login URL with the provided parameters.
 ///
 /// # Arguments
@@ -1428,7 +1505,7 @@ pub fn extract_basic_auth_str_from_parts(parts: &Parts) -> String {
 /// * `base_url` - The base URL of the authentication service.
 /// * `time` - The request time.
 /// * `exp_in` - The expiration time.
-/// * `auth` - The authentication token.
+/// * `mode` - How the `auth` param is signed, see [`PresignedUrlMode`].
 ///
 /// # Returns
 ///
@@ -1440,16 +1517,86 @@ pub fn generate_presigned_url(
     base_url: &str,
     exp_in: i64,
     time: i64,
+    mode: PresignedUrlMode,
 ) -> String {
-    // let time = chrono::Utc::now().timestamp();
-    let stage1 = get_hash(password, salt);
-    let stage2 = get_hash(&format!("{}{}", &stage1, time), salt);
-    let stage3 = get_hash(&format!("{}{}", &stage2, exp_in), salt);
-
-    let user_pass = format!("{username}:{stage3}");
+    let signature = mode.sign(&get_hash(password, salt), salt, time, exp_in);
+    let user_pass = format!("{username}:{signature}");
     let auth = base64::engine::general_purpose::STANDARD.encode(user_pass);
 
-    format!("{base_url}/auth/login?request_time={time}&exp_in={exp_in}&auth={auth}")
+    let url = format!("{base_url}/auth/login?request_time={time}&exp_in={exp_in}&auth={auth}");
+    match mode {
+        PresignedUrlMode::Argon2 => url,
+        PresignedUrlMode::Hmac => format!("{url}&mode=hmac"),
+    }
+}
+
+/// Verifies the `auth` param of a login URL built by [`generate_presigned_url`].
+///
+/// Recomputes the signature of `mode` from the password, salt, request time and expiration and
+/// compares it to the hash in `auth` in constant time. Returns false if the URL is expired
+/// at `now`, or if `auth` isn't a base64 encoded `user:hash` of the given user.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_presigned_url(
+    username: &str,
+    password: &str,
+    salt: &str,
+    auth: &str,
+    request_time: i64,
+    exp_in: i64,
+    now: i64,
+    mode: PresignedUrlMode,
+) -> bool {
+    let password_ext = get_hash(password, salt);
+    verify_presigned_url_ext(
+        username,
+        &password_ext,
+        salt,
+        auth,
+        request_time,
+        exp_in,
+        now,
+        mode,
+    )
+}
+
+/// Same as [`verify_presigned_url`] with the stored `password_ext` of the user instead of the
+/// password, used by the login handler which only knows the hash.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_presigned_url_ext(
+    username: &str,
+    password_ext: &str,
+    salt: &str,
+    auth: &str,
+    request_time: i64,
+    exp_in: i64,
+    now: i64,
+    mode: PresignedUrlMode,
+) -> bool {
+    if request_time.saturating_add(exp_in) < now {
+        return false;
+    }
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(auth) else {
+        return false;
+    };
+    let Ok(decoded) = String::from_utf8(decoded) else {
+        return false;
+    };
+    let Some((user, hash)) = decoded.split_once(':') else {
+        return false;
+    };
+    if user != username {
+        return false;
+    }
+
+    let signature = mode.sign(password_ext, salt, request_time, exp_in);
+    constant_time_eq(signature.as_bytes(), hash.as_bytes())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 #[cfg(not(feature = "enterprise"))]
@@ -1652,12 +1799,186 @@ mod tests {
             "dXNlcjokYXJnb24yZCR2PTE2JG09MjA0OCx0PTQscD0yJGMyRnNkSE5oYkhRJGNwTElHZzdEaFl1Vi9nSWxMaCtRZksrS29Vd2ZFaGVpdHkwc3Z0c243Y1E="
         );
 
-        let generated_url =
-            generate_presigned_url(username, password, salt, base_url, exp_in, time);
+        let generated_url = generate_presigned_url(
+            username,
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Argon2,
+        );
 
         assert_eq!(generated_url, expected_url);
     }
 
+    #[test]
+    fn test_verify_presigned_url() {
+        let password = "password";
+        let salt = "saltsalt";
+        let username = "user";
+        let base_url = "https://example.com";
+        let exp_in = 3600;
+        let time = 1634567890;
+
+        for mode in [PresignedUrlMode::Argon2, PresignedUrlMode::Hmac] {
+            let url =
+                generate_presigned_url(username, password, salt, base_url, exp_in, time, mode);
+            let auth = url.split("auth=").nth(1).unwrap();
+            let auth = auth.split('&').next().unwrap();
+            let verify = |username: &str, password: &str, auth: &str, exp_in: i64, now: i64| {
+                verify_presigned_url(username, password, salt, auth, time, exp_in, now, mode)
+            };
+            assert!(verify(username, password, auth, exp_in, time + 10));
+
+            // expired
+            assert!(!verify(username, password, auth, exp_in, time + exp_in + 1));
+            // tampered expiration, user or password
+            assert!(!verify(username, password, auth, exp_in * 2, time + 10));
+            assert!(!verify("other", password, auth, exp_in, time));
+            assert!(!verify(username, "wrong", auth, exp_in, time));
+            // malformed auth
+            let no_separator = base64::engine::general_purpose::STANDARD.encode("user");
+            assert!(!verify(username, password, &no_separator, exp_in, time));
+            assert!(!verify(username, password, "not base64!", exp_in, time));
+        }
+    }
+
+    #[test]
+    fn test_presigned_url_params_from_query() {
+        let query = |params: &[(&str, &str)]| {
+            params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert_eq!(
+            PresignedUrlParams::from_query(&query(&[
+                ("auth", "dXNlcjp h"),
+                ("request_time", "1634567890"),
+                ("exp_in", "3600"),
+            ])),
+            Some(PresignedUrlParams {
+                auth: "dXNlcjp+h".to_string(),
+                request_time: 1634567890,
+                exp_in: 3600,
+                mode: PresignedUrlMode::Argon2,
+            })
+        );
+        assert_eq!(
+            PresignedUrlParams::from_query(&query(&[
+                ("auth", "dXNlcjph"),
+                ("request_time", "1634567890"),
+                ("exp_in", "3600"),
+                ("mode", "hmac"),
+            ]))
+            .unwrap()
+            .mode,
+            PresignedUrlMode::Hmac
+        );
+        // a malformed or missing param is rejected instead of panicking
+        assert!(
+            PresignedUrlParams::from_query(&query(&[
+                ("auth", "dXNlcjph"),
+                ("request_time", "1634567890"),
+                ("exp_in", "1h"),
+            ]))
+            .is_none()
+        );
+        assert!(
+            PresignedUrlParams::from_query(&query(&[("auth", "dXNlcjph"), ("exp_in", "3600")]))
+                .is_none()
+        );
+        assert!(
+            PresignedUrlParams::from_query(&query(&[
+                ("auth", "dXNlcjph"),
+                ("request_time", "1634567890"),
+                ("exp_in", "3600"),
+                ("mode", "md5"),
+            ]))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_presigned_url_hmac_mode() {
+        let password = "password";
+        let salt = "saltsalt";
+        let username = "user";
+        let base_url = "https://example.com";
+        let exp_in = 3600;
+        let time = 1634567890;
+
+        let url = generate_presigned_url(
+            username,
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Hmac,
+        );
+        assert!(url.ends_with("&mode=hmac"));
+        let auth = url
+            .split("auth=")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches("&mode=hmac");
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(auth)
+            .unwrap();
+        let (user, signature) = std::str::from_utf8(&decoded)
+            .unwrap()
+            .split_once(':')
+            .unwrap();
+        assert_eq!(user, username);
+        assert_eq!(signature.len(), 64);
+
+        // a different salt gives a different signature
+        let other = generate_presigned_url(
+            username,
+            password,
+            "othersalt",
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Hmac,
+        );
+        assert_ne!(url, other);
+
+        // the signatures of the modes aren't interchangeable
+        let argon2_url = generate_presigned_url(
+            username,
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Argon2,
+        );
+        let argon2_auth = argon2_url.split("auth=").nth(1).unwrap();
+        assert!(!verify_presigned_url(
+            username,
+            password,
+            salt,
+            argon2_auth,
+            time,
+            exp_in,
+            time,
+            PresignedUrlMode::Hmac
+        ));
+        assert!(!verify_presigned_url(
+            username,
+            password,
+            salt,
+            auth,
+            time,
+            exp_in,
+            time,
+            PresignedUrlMode::Argon2
+        ));
+    }
+
     #[tokio::test]
     async fn test_is_root_user() {
         assert!(!is_root_user("dummy"));
@@ -1822,7 +2143,15 @@ mod tests {
         let exp_in = 7200;
         let time = 1600000000;
 
-        let url = generate_presigned_url(username, password, salt, base_url, exp_in, time);
+        let url = generate_presigned_url(
+            username,
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Argon2,
+        );
 
         assert!(url.starts_with(base_url));
         assert!(url.contains("/auth/login"));
@@ -1831,10 +2160,26 @@ mod tests {
         assert!(url.contains("auth="));
 
         // Test with different parameters
-        let url2 = generate_presigned_url(username, password, salt, base_url, exp_in, time + 1);
+        let url2 = generate_presigned_url(
+            username,
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time + 1,
+            PresignedUrlMode::Argon2,
+        );
         assert_ne!(url, url2); // Different time should generate different URL
 
-        let url3 = generate_presigned_url("different", password, salt, base_url, exp_in, time);
+        let url3 = generate_presigned_url(
+            "different",
+            password,
+            salt,
+            base_url,
+            exp_in,
+            time,
+            PresignedUrlMode::Argon2,
+        );
         assert_ne!(url, url3); // Different username should generate different URL
     }
 
@@ -178,10 +178,16 @@ pub(crate) fn get_or_create_trace_id(headers: &HeaderMap, span: &tracing::Span)
     }
 }
 
-/// This function can handle IPv4 and IPv6 addresses which may have port numbers appended
+/// This function can handle IPv4 and IPv6 addresses which may have port numbers appended, IPv6
+/// addresses may be bracketed as `[::1]` or `[::1]:8080`
 pub fn parse_ip_addr(ip_address: &str) -> Result<(IpAddr, Option<u16>), AddrParseError> {
+    let ip_address = ip_address.trim();
     let mut port: Option<u16> = None;
-    let ip = ip_address.parse::<IpAddr>().or_else(|_| {
+    let unbracketed = ip_address
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip_address);
+    let ip = unbracketed.parse::<IpAddr>().or_else(|_| {
         ip_address
             .parse::<SocketAddr>()
             .map(|sock_addr| {
@@ -197,6 +203,43 @@ pub fn parse_ip_addr(ip_address: &str) -> Result<(IpAddr, Option<u16>), AddrPars
     Ok((ip, port))
 }
 
+/// Gets the client IP of a request passed through proxies from the `X-Forwarded-For` header, or
+/// the `for` parameters of the `Forwarded` header.
+///
+/// Every proxy appends the address it received the request from, so with `trusted_proxies`
+/// proxies in front of the server the client is that many addresses from the right, the
+/// addresses left of it may be spoofed by the client. `0` takes the leftmost address and a
+/// chain shorter than the trusted hops falls back to it as well. The addresses which can't be
+/// parsed are skipped.
+pub fn get_forwarded_client_ip(headers: &HeaderMap, trusted_proxies: usize) -> Option<IpAddr> {
+    let chain: Vec<&str> =
+        if let Some(value) = headers.get("X-Forwarded-For").and_then(|v| v.to_str().ok()) {
+            value.split(',').collect()
+        } else if let Some(value) = headers.get("Forwarded").and_then(|v| v.to_str().ok()) {
+            value
+                .split(',')
+                .filter_map(|element| {
+                    element.split(';').find_map(|pair| {
+                        let (key, value) = pair.split_once('=')?;
+                        key.trim()
+                            .eq_ignore_ascii_case("for")
+                            .then(|| value.trim().trim_matches('"'))
+                    })
+                })
+                .collect()
+        } else {
+            return None;
+        };
+    let chain: Vec<IpAddr> = chain
+        .into_iter()
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty() && !ip.eq_ignore_ascii_case("unknown"))
+        .filter_map(|ip| parse_ip_addr(ip).ok().map(|(ip, _)| ip))
+        .collect();
+    let index = chain.len().checked_sub(trusted_proxies).unwrap_or_default();
+    chain.get(index).or_else(|| chain.first()).copied()
+}
+
 // Extractor for request headers
 pub struct RequestHeaderExtractor<'a> {
     headers: &'a HeaderMap,
@@ -396,6 +439,8 @@ mod tests {
                 "2001:db8::1".parse::<IpAddr>().unwrap(),
                 Some(8080),
             ),
+            ("[::1]", "::1".parse::<IpAddr>().unwrap(), None),
+            (" 10.0.0.1 ", "10.0.0.1".parse::<IpAddr>().unwrap(), None),
         ];
 
         for (ip, exp_ip, exp_port) in ip_port_exp_map {
@@ -406,6 +451,48 @@ mod tests {
         assert!(parse_ip_addr("invalid").is_err());
     }
 
+    #[test]
+    fn test_get_forwarded_client_ip() {
+        let headers = |name: &'static str, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+            headers
+        };
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+
+        // single hop
+        let single = headers("X-Forwarded-For", "203.0.113.7");
+        assert_eq!(get_forwarded_client_ip(&single, 0), ip("203.0.113.7"));
+        assert_eq!(get_forwarded_client_ip(&single, 1), ip("203.0.113.7"));
+
+        // the client spoofed the first address, then passed two trusted proxies
+        let multi = headers("X-Forwarded-For", "1.1.1.1, 203.0.113.7, 10.0.0.2");
+        assert_eq!(get_forwarded_client_ip(&multi, 0), ip("1.1.1.1"));
+        assert_eq!(get_forwarded_client_ip(&multi, 1), ip("10.0.0.2"));
+        assert_eq!(get_forwarded_client_ip(&multi, 2), ip("203.0.113.7"));
+        // more trusted hops than addresses fall back to the leftmost address
+        assert_eq!(get_forwarded_client_ip(&multi, 5), ip("1.1.1.1"));
+
+        // ipv6 clients and invalid entries
+        let ipv6 = headers("X-Forwarded-For", "[2001:db8::1]:4711, unknown, 10.0.0.2");
+        assert_eq!(get_forwarded_client_ip(&ipv6, 2), ip("2001:db8::1"));
+        let forwarded = headers(
+            "Forwarded",
+            r#"for="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.2;by=10.0.0.3"#,
+        );
+        assert_eq!(
+            get_forwarded_client_ip(&forwarded, 2),
+            ip("2001:db8:cafe::17")
+        );
+        assert_eq!(get_forwarded_client_ip(&forwarded, 1), ip("10.0.0.2"));
+
+        assert_eq!(
+            get_forwarded_client_ip(&headers("X-Forwarded-For", "invalid"), 1),
+            None
+        );
+        assert_eq!(get_forwarded_client_ip(&HeaderMap::new(), 1), None);
+    }
+
     #[test]
     fn test_request_header_extractor() {
         let mut headers = HeaderMap::new();
@@ -1095,6 +1095,18 @@ pub struct Common {
         help = "Toggle tantivy result cache."
     )]
     pub inverted_index_result_cache_enabled: bool,
+    #[env_config(
+        name = "ZO_INVERTED_INDEX_EXPLAIN",
+        default = false,
+        help = "Log why the inverted index search kept or removed each file with the search inspector, for diagnostics only"
+    )]
+    pub inverted_index_explain: bool,
+    #[env_config(
+        name = "ZO_STORAGE_RESULT_CACHE_ENABLED",
+        default = false,
+        help = "Toggle storage search result cache, it reuses the loaded file list of identical storage searches."
+    )]
+    pub storage_result_cache_enabled: bool,
     #[env_config(
         name = "ZO_INVERTED_INDEX_OLD_FORMAT",
         default = false,
@@ -1133,6 +1145,12 @@ pub struct Common {
     pub skip_formatting_stream_name: bool,
     #[env_config(name = "ZO_FORMAT_STREAM_NAME_TO_LOWERCASE", default = true)]
     pub format_stream_name_to_lower: bool,
+    #[env_config(
+        name = "ZO_DISTINCT_VALUE_FIELDS_LOWERCASE",
+        default = false,
+        help = "Lowercase the field names of the distinct value fields before registering and looking them up, existing mixed-case registrations are not rewritten"
+    )]
+    pub distinct_value_fields_lowercase: bool,
     #[env_config(name = "ZO_BULK_RESPONSE_INCLUDE_ERRORS_ONLY", default = false)]
     pub bulk_api_response_errors_only: bool,
     #[env_config(name = "ZO_ALLOW_USER_DEFINED_SCHEMAS", default = false)]
@@ -1229,7 +1247,11 @@ pub struct Common {
     pub aggregation_topk_enabled: bool,
     #[env_config(name = "ZO_SEARCH_INSPECTOR_ENABLED", default = false)]
     pub search_inspector_enabled: bool,
-    #[env_config(name = "ZO_UTF8_VIEW_ENABLED", default = true)]
+    #[env_config(
+        name = "ZO_UTF8_VIEW_ENABLED",
+        default = true,
+        help = "Search the string fields as Utf8View, it speeds up filtering and comparing strings. Disable it for workloads which materialize most of the strings, where the views add memory churn instead."
+    )]
     pub utf8_view_enabled: bool,
     #[env_config(
         name = "ZO_DASHBOARD_SHOW_SYMBOL_ENABLED",
@@ -1345,6 +1367,12 @@ pub struct Limit {
     pub query_thread_num: usize,
     #[env_config(name = "ZO_QUERY_INDEX_THREAD_NUM", default = 0)]
     pub query_index_thread_num: usize,
+    #[env_config(
+        name = "ZO_MIN_QUERY_PARTITIONS",
+        default = 1,
+        help = "Minimum number of partitions of a follower storage search and index search, capped at the number of files. The cached scans use cpu_num and query_index_thread_num partitions, which is a single partition on a node with one cpu. The partitions share the threads of the query runtime, so raising this only helps the scans waiting on IO."
+    )]
+    pub min_query_partitions: usize,
     #[env_config(name = "ZO_FILE_DOWNLOAD_THREAD_NUM", default = 0)]
     pub file_download_thread_num: usize,
     #[env_config(name = "ZO_FILE_DOWNLOAD_PRIORITY_QUEUE_THREAD_NUM", default = 0)]
@@ -1353,6 +1381,12 @@ pub struct Limit {
     pub file_download_priority_queue_window_secs: i64,
     #[env_config(name = "ZO_FILE_DOWNLOAD_ENABLE_PRIORITY_QUEUE", default = true)]
     pub file_download_enable_priority_queue: bool,
+    #[env_config(
+        name = "ZO_FILE_DOWNLOAD_WAIT_TIMEOUT_MS",
+        default = 500,
+        help = "How long a query waits for the files already being downloaded by other queries, 0 means no wait."
+    )]
+    pub file_download_wait_timeout_ms: u64,
     #[env_config(name = "ZO_GRPC_INGEST_TIMEOUT", default = 600)]
     pub grpc_ingest_timeout: u64,
     #[env_config(name = "ZO_QUERY_TIMEOUT", default = 600)]
@@ -1412,6 +1446,12 @@ pub struct Limit {
     pub metrics_cache_max_entries: usize,
     #[env_config(name = "ZO_METRICS_INLIST_FILTER_ENABLED", default = false)]
     pub metrics_inlist_filter_enabled: bool,
+    #[env_config(
+        name = "ZO_METRICS_IDEMPOTENCY_TTL",
+        default = 300, // seconds
+        help = "Time to live in seconds of the idempotency keys of the metrics _json ingestion, a batch sent again with the same X-Idempotency-Key within the ttl is ignored."
+    )]
+    pub metrics_idempotency_ttl: i64,
     #[env_config(name = "ZO_COLS_PER_RECORD_LIMIT", default = 1000)]
     pub req_cols_per_record_limit: usize,
     #[env_config(name = "ZO_NODE_HEARTBEAT_TTL", default = 30)] // seconds
@@ -1520,6 +1560,18 @@ pub struct Limit {
         help = "Seconds, Maximum lifetime of individual connections."
     )]
     pub sql_db_connections_max_lifetime: u64,
+    #[env_config(
+        name = "ZO_META_CONNECTION_POOL_METRICS_INTERVAL",
+        default = 15,
+        help = "Seconds, interval to report the size of the sqlite connection pools, 0 disables it"
+    )]
+    pub sql_db_pool_metrics_interval: u64,
+    #[env_config(
+        name = "ZO_META_WATCH_CHANNEL_CAPACITY",
+        default = 1024,
+        help = "The capacity of the channel used to deliver sqlite watch events to each watcher, while it is full the pending events are coalesced by key"
+    )]
+    pub sql_db_watch_channel_capacity: usize,
     #[env_config(
         name = "ZO_META_TRANSACTION_RETRIES",
         default = 3,
@@ -1532,10 +1584,34 @@ pub struct Limit {
         help = "timeout of transaction lock"
     )] // seconds
     pub meta_transaction_lock_timeout: usize,
+    #[env_config(
+        name = "ZO_META_AUTO_MIGRATION",
+        default = true,
+        help = "Apply the pending migrations of the meta table on start, when disabled they are only logged and have to be applied explicitly"
+    )]
+    pub meta_auto_migration: bool,
     #[env_config(name = "ZO_DISTINCT_VALUES_INTERVAL", default = 10)] // seconds
     pub distinct_values_interval: u64,
     #[env_config(name = "ZO_DISTINCT_VALUES_HOURLY", default = false)]
     pub distinct_values_hourly: bool,
+    #[env_config(
+        name = "ZO_DISTINCT_VALUES_CLEANUP_INTERVAL",
+        default = 86400,
+        help = "Interval of the job removing the distinct value fields of deleted dashboards and streams, 0 disables it"
+    )] // seconds
+    pub distinct_values_cleanup_interval: u64,
+    #[env_config(
+        name = "ZO_DISTINCT_VALUES_CLEANUP_BATCH_SIZE",
+        default = 100,
+        help = "Number of stale origins removed per statement by the distinct value fields cleanup"
+    )]
+    pub distinct_values_cleanup_batch_size: usize,
+    #[env_config(
+        name = "ZO_DISTINCT_VALUES_CLEANUP_DRY_RUN",
+        default = false,
+        help = "Only count and log the stale distinct value fields instead of removing them"
+    )]
+    pub distinct_values_cleanup_dry_run: bool,
     #[env_config(name = "ZO_CONSISTENT_HASH_VNODES", default = 1000)]
     pub consistent_hash_vnodes: usize,
     #[env_config(
@@ -1544,6 +1620,12 @@ pub struct Limit {
         help = "Maximum number of entries in the file stat cache. Higher values increase memory usage but may improve query performance."
     )]
     pub datafusion_file_stat_cache_max_entries: usize,
+    #[env_config(
+        name = "ZO_DATAFUSION_FILE_STAT_CACHE_UPDATE_ALL",
+        default = true,
+        help = "Store the file statistics collected by every query in the file stat cache. When disabled only the queries using the result cache, e.g. dashboard refreshes, store them, the other queries still read the cache."
+    )]
+    pub datafusion_file_stat_cache_update_all: bool,
     #[env_config(
         name = "ZO_DATAFUSION_STREAMING_AGGS_CACHE_MAX_ENTRIES",
         default = 10000,
@@ -1572,12 +1654,30 @@ pub struct Limit {
         help = "Maximum size of a single entry in the inverted index result cache. Higher values increase memory usage but may improve query performance."
     )]
     pub inverted_index_result_cache_max_entry_size: usize,
+    #[env_config(
+        name = "ZO_STORAGE_RESULT_CACHE_MAX_ENTRIES",
+        default = 1000,
+        help = "Maximum number of entries in the storage search result cache."
+    )]
+    pub storage_result_cache_max_entries: usize,
+    #[env_config(
+        name = "ZO_STORAGE_RESULT_CACHE_TTL",
+        default = 300, // seconds
+        help = "Time to live in seconds of the entries in the storage search result cache."
+    )]
+    pub storage_result_cache_ttl: u64,
     #[env_config(
         name = "ZO_INVERTED_INDEX_SKIP_THRESHOLD",
         default = 35,
         help = "If the inverted index returns row_id more than this threshold(%), it will skip the inverted index."
     )]
     pub inverted_index_skip_threshold: usize,
+    #[env_config(
+        name = "ZO_INVERTED_INDEX_SEARCH_CONCURRENCY",
+        default = 0,
+        help = "Maximum number of index files searched at the same time by a query on a node, each one holds an open tantivy reader. 0 means the same as the query thread num."
+    )]
+    pub inverted_index_search_concurrency: usize,
     #[env_config(
         name = "ZO_INVERTED_INDEX_MIN_TOKEN_LENGTH",
         default = 2,
@@ -1797,6 +1897,11 @@ pub struct DiskCache {
     pub gc_interval: u64,
     #[env_config(name = "ZO_DISK_CACHE_MULTI_DIR", default = "")] // dir1,dir2,dir3...
     pub multi_dir: String,
+    // percent, if the cached files of a query less than this ratio, the query will read the
+    // files from object storage directly, 0 means always use cache, 100 means only use cache
+    // when all the files are cached
+    #[env_config(name = "ZO_DISK_CACHE_DOWNLOAD_RATIO_THRESHOLD", default = 50)]
+    pub download_ratio_threshold: usize,
 }
 
 #[derive(Serialize, EnvConfig, Default)]
@@ -1897,6 +2002,66 @@ pub struct Nats {
         default = ""
     )]
     pub kv_watch_modules: String,
+    #[env_config(
+        name = "ZO_NATS_WATCH_CHANNEL_CAPACITY",
+        help = "The capacity of the channel used to deliver kv watch events to the consumer",
+        default = 65535
+    )]
+    pub watch_channel_capacity: usize,
+    #[env_config(
+        name = "ZO_NATS_WATCH_SEND_TIMEOUT",
+        help = "How long to block the kv watcher when the watch channel is full, unit: ms. 0 means drop the event immediately when the channel is full. Blocking slows down the watcher but avoids losing events, dropping keeps the watcher fast but the local cache may become stale",
+        default = 0
+    )]
+    pub watch_send_timeout: u64,
+    #[env_config(
+        name = "ZO_NATS_TLS_ENABLED",
+        help = "Require TLS for the connection to NATS, it is enabled automatically when any of the TLS certificate paths is set",
+        default = false
+    )]
+    pub tls_enabled: bool,
+    #[env_config(
+        name = "ZO_NATS_TLS_CA_CERT_PATH",
+        help = "The path of the CA bundle used to verify the NATS server certificate",
+        default = ""
+    )]
+    pub tls_ca_cert_path: String,
+    #[env_config(
+        name = "ZO_NATS_TLS_CLIENT_CERT_PATH",
+        help = "The path of the client certificate for NATS mutual TLS, must be set together with ZO_NATS_TLS_CLIENT_KEY_PATH",
+        default = ""
+    )]
+    pub tls_client_cert_path: String,
+    #[env_config(
+        name = "ZO_NATS_TLS_CLIENT_KEY_PATH",
+        help = "The path of the client private key for NATS mutual TLS",
+        default = ""
+    )]
+    pub tls_client_key_path: String,
+    #[env_config(
+        name = "ZO_NATS_CREDENTIALS_FILE",
+        help = "The path of the NATS .creds file (JWT and nkey seed) used for authentication",
+        default = ""
+    )]
+    pub credentials_file: String,
+    #[env_config(
+        name = "ZO_NATS_JETSTREAM_DOMAIN",
+        help = "The JetStream domain to use, required by leaf node or multi-tenant deployments, empty means the default domain",
+        default = ""
+    )]
+    pub jetstream_domain: String,
+    #[env_config(
+        name = "ZO_NATS_COMPRESSION",
+        help = "Compress the kv values larger than ZO_NATS_COMPRESSION_THRESHOLD, supports zstd, empty means no compression",
+        default = ""
+    )]
+    pub compression: String,
+    #[env_config(
+        name = "ZO_NATS_COMPRESSION_THRESHOLD",
+        help = "The kv values larger than this size in bytes are compressed",
+        default = 16384
+    )]
+    pub compression_threshold: usize,
 }
 
 #[derive(Serialize, Debug, Default, EnvConfig)]
@@ -1998,6 +2163,18 @@ pub struct RUM {
     pub api_version: String,
     #[env_config(name = "ZO_RUM_INSECURE_HTTP", default = false)]
     pub insecure_http: bool,
+    #[env_config(
+        name = "ZO_RUM_PARAM_PREFIXES",
+        default = "oo,o2",
+        help = "Comma separated prefixes of the custom query params kept from the browser-sdk requests, `{prefix}-api-key` is always dropped and `{prefix}tags` is parsed as tags"
+    )]
+    pub param_prefixes: String,
+    #[env_config(
+        name = "ZO_RUM_TRUSTED_PROXY_COUNT",
+        default = 0,
+        help = "Number of trusted proxies in front of the RUM ingestion, the client IP is taken that many hops from the right of the X-Forwarded-For chain, 0 takes the leftmost address"
+    )]
+    pub trusted_proxy_count: usize,
 }
 
 #[derive(Serialize, Debug, EnvConfig, Default)]
@@ -2379,6 +2556,9 @@ fn check_limit_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
             cfg.limit.query_index_thread_num = cpu_num * 4;
         }
     }
+    if cfg.limit.min_query_partitions == 0 {
+        cfg.limit.min_query_partitions = 1;
+    }
 
     if cfg.limit.file_download_thread_num == 0 {
         cfg.limit.file_download_thread_num = std::cmp::max(1, cpu_num / 2);
@@ -2433,6 +2613,10 @@ fn check_limit_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     cfg.limit.sql_db_connections_max =
         max(REQUIRED_DB_CONNECTIONS, cfg.limit.sql_db_connections_max);
 
+    if cfg.limit.sql_db_watch_channel_capacity == 0 {
+        cfg.limit.sql_db_watch_channel_capacity = 1024;
+    }
+
     if cfg.limit.consistent_hash_vnodes < 1 {
         cfg.limit.consistent_hash_vnodes = 1000;
     }
@@ -2512,6 +2696,9 @@ fn check_common_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     if cfg.limit.metrics_cache_max_entries == 0 {
         cfg.limit.metrics_cache_max_entries = 10_000;
     }
+    if cfg.limit.metrics_idempotency_ttl <= 0 {
+        cfg.limit.metrics_idempotency_ttl = 300;
+    }
 
     // check search job retention
     if cfg.limit.search_job_retention == 0 {
@@ -2904,6 +3091,10 @@ fn check_disk_cache_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.disk_cache.gc_size *= 1024 * 1024;
     }
 
+    if cfg.disk_cache.download_ratio_threshold > 100 {
+        cfg.disk_cache.download_ratio_threshold = 100;
+    }
+
     if cfg.disk_cache.multi_dir.contains('/') {
         return Err(anyhow::anyhow!(
             "ZO_DISK_CACHE_MULTI_DIR only supports a single directory level, can not contains / "
@@ -3169,6 +3360,27 @@ fn check_nats_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
         cfg.nats.queue_max_size = 2048; // 2GB
     }
     cfg.nats.queue_max_size *= 1024 * 1024; // convert to bytes
+    if cfg.nats.watch_channel_capacity == 0 {
+        cfg.nats.watch_channel_capacity = 65535;
+    }
+    cfg.nats.compression = cfg.nats.compression.trim().to_lowercase();
+    if cfg.nats.compression == "none" {
+        cfg.nats.compression.clear();
+    }
+    if !cfg.nats.compression.is_empty() && cfg.nats.compression != "zstd" {
+        return Err(anyhow::anyhow!(
+            "ZO_NATS_COMPRESSION only supports zstd, got: {}",
+            cfg.nats.compression
+        ));
+    }
+    if cfg.nats.tls_client_cert_path.is_empty() != cfg.nats.tls_client_key_path.is_empty() {
+        return Err(anyhow::anyhow!(
+            "ZO_NATS_TLS_CLIENT_CERT_PATH and ZO_NATS_TLS_CLIENT_KEY_PATH must be set together"
+        ));
+    }
+    if !cfg.nats.tls_ca_cert_path.is_empty() || !cfg.nats.tls_client_cert_path.is_empty() {
+        cfg.nats.tls_enabled = true;
+    }
     Ok(())
 }
 
@@ -3179,6 +3391,12 @@ fn check_inverted_index_config(cfg: &mut Config) -> Result<(), anyhow::Error> {
     if cfg.limit.inverted_index_result_cache_max_entry_size == 0 {
         cfg.limit.inverted_index_result_cache_max_entry_size = 20480;
     }
+    if cfg.limit.storage_result_cache_max_entries == 0 {
+        cfg.limit.storage_result_cache_max_entries = 1000;
+    }
+    if cfg.limit.storage_result_cache_ttl == 0 {
+        cfg.limit.storage_result_cache_ttl = 300;
+    }
     if cfg.limit.inverted_index_skip_threshold == 0 {
         cfg.limit.inverted_index_skip_threshold = 35;
     }
@@ -3291,6 +3509,17 @@ mod tests {
         assert!(check_route_config(&cfg).is_err());
     }
 
+    #[test]
+    fn test_check_nats_tls_config() {
+        let mut cfg = Config::init().unwrap();
+        // NATS TLS requires both the client cert and key
+        cfg.nats.tls_client_cert_path = "/certs/client.pem".to_string();
+        assert!(check_nats_config(&mut cfg).is_err());
+        cfg.nats.tls_client_key_path = "/certs/client.key".to_string();
+        check_nats_config(&mut cfg).unwrap();
+        assert!(cfg.nats.tls_enabled);
+    }
+
     #[test]
     fn test_usage_report_to_own_org_field_exists() {
         // Test that usage_report_to_own_org field exists and is accessible
@@ -33,6 +33,8 @@ pub struct Request {
     pub use_cache: bool,
     pub overwrite_cache: bool,
     pub histogram_interval: i64,
+    pub disable_index_skip_threshold: bool,
+    pub strict_index_only: bool,
 }
 
 impl Default for Request {
@@ -52,6 +54,8 @@ impl Default for Request {
             use_cache: default_use_cache(),
             overwrite_cache: false,
             histogram_interval: 0,
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         }
     }
 }
@@ -84,6 +88,8 @@ impl Request {
             use_cache: default_use_cache(),
             overwrite_cache,
             histogram_interval,
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         }
     }
 
@@ -103,6 +109,16 @@ impl Request {
     pub fn set_use_cache(&mut self, use_cache: bool) {
         self.use_cache = use_cache;
     }
+
+    /// used by recall-sensitive queries to use the inverted index result of every file
+    pub fn set_disable_index_skip_threshold(&mut self, disable_index_skip_threshold: bool) {
+        self.disable_index_skip_threshold = disable_index_skip_threshold;
+    }
+
+    /// used by the queries which only need the indexed data
+    pub fn set_strict_index_only(&mut self, strict_index_only: bool) {
+        self.strict_index_only = strict_index_only;
+    }
 }
 
 impl From<FlightSearchRequest> for Request {
@@ -122,6 +138,8 @@ impl From<FlightSearchRequest> for Request {
             use_cache: req.search_info.use_cache,
             overwrite_cache: req.search_info.clear_cache,
             histogram_interval: req.search_info.histogram_interval,
+            disable_index_skip_threshold: req.search_info.disable_index_skip_threshold,
+            strict_index_only: req.search_info.strict_index_only,
         }
     }
 }
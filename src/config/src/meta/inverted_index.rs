@@ -22,6 +22,9 @@ pub enum IndexOptimizeMode {
     SimpleSelect(usize, bool),
     SimpleCount,
     SimpleHistogram(i64, u64, usize),
+    /// histogram of `_timestamp` with the given bucket boundaries, bucket `i` counts the
+    /// values in `[boundaries[i], boundaries[i + 1])`
+    SimpleHistogramBuckets(Vec<i64>),
     SimpleTopN(String, usize, bool),
     SimpleDistinct(String, usize, bool),
 }
@@ -34,6 +37,9 @@ impl IndexOptimizeMode {
             IndexOptimizeMode::SimpleHistogram(min_value, bucket_width, num_buckets) => {
                 format!("h(m:{min_value},b:{bucket_width},n:{num_buckets})")
             }
+            IndexOptimizeMode::SimpleHistogramBuckets(boundaries) => {
+                format!("hb(b:{boundaries:?})")
+            }
             IndexOptimizeMode::SimpleTopN(field, limit, ascend) => {
                 format!("t(f{field},l:{limit},a:{ascend})")
             }
@@ -57,6 +63,9 @@ impl std::fmt::Display for IndexOptimizeMode {
                     "histogram(min_value: {min_value}, bucket_width: {bucket_width}, num_buckets: {num_buckets})"
                 )
             }
+            IndexOptimizeMode::SimpleHistogramBuckets(boundaries) => {
+                write!(f, "histogram(boundaries: {boundaries:?})")
+            }
             IndexOptimizeMode::SimpleTopN(field, limit, ascend) => {
                 write!(f, "topn(field: {field}, limit: {limit}, ascend: {ascend})")
             }
@@ -70,6 +79,25 @@ impl std::fmt::Display for IndexOptimizeMode {
     }
 }
 
+/// Checks the boundaries of [`IndexOptimizeMode::SimpleHistogramBuckets`], there must be at
+/// least two of them and they must be strictly increasing.
+pub fn check_histogram_boundaries(boundaries: &[i64]) -> anyhow::Result<()> {
+    if boundaries.len() < 2 {
+        anyhow::bail!(
+            "histogram needs at least 2 bucket boundaries, got {}",
+            boundaries.len()
+        );
+    }
+    if let Some(w) = boundaries.windows(2).find(|w| w[0] >= w[1]) {
+        anyhow::bail!(
+            "histogram bucket boundaries must be sorted, {} is followed by {}",
+            w[0],
+            w[1]
+        );
+    }
+    Ok(())
+}
+
 impl From<cluster_rpc::IdxOptimizeMode> for IndexOptimizeMode {
     fn from(cluster_rpc_mode: cluster_rpc::IdxOptimizeMode) -> Self {
         match cluster_rpc_mode.mode {
@@ -135,6 +163,10 @@ mod tests {
                 IndexOptimizeMode::SimpleHistogram(-100, 25, 20),
                 "histogram(min_value: -100, bucket_width: 25, num_buckets: 20)",
             ),
+            (
+                IndexOptimizeMode::SimpleHistogramBuckets(vec![0, 10, 100, 1000]),
+                "histogram(boundaries: [0, 10, 100, 1000])",
+            ),
             (
                 IndexOptimizeMode::SimpleTopN("cpu_usage".to_string(), 10, true),
                 "topn(field: cpu_usage, limit: 10, ascend: true)",
@@ -326,4 +358,14 @@ mod tests {
             assert_eq!(mode, converted_back);
         }
     }
+
+    #[test]
+    fn test_check_histogram_boundaries() {
+        assert!(check_histogram_boundaries(&[0, 10, 100, 1000]).is_ok());
+        assert!(check_histogram_boundaries(&[-5, 5]).is_ok());
+        assert!(check_histogram_boundaries(&[]).is_err());
+        assert!(check_histogram_boundaries(&[10]).is_err());
+        assert!(check_histogram_boundaries(&[0, 100, 10]).is_err());
+        assert!(check_histogram_boundaries(&[0, 10, 10]).is_err());
+    }
 }
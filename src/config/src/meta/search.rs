@@ -74,6 +74,14 @@ pub struct Request {
     pub clear_cache: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub local_mode: Option<bool>,
+    /// Always use the inverted index result of a file even if its matched docs exceed
+    /// `ZO_INVERTED_INDEX_SKIP_THRESHOLD`, for recall-sensitive queries
+    #[serde(default)]
+    pub disable_index_skip_threshold: bool,
+    /// Treat a file without an inverted index as no match instead of scanning it, for queries
+    /// which only need the indexed data
+    #[serde(default)]
+    pub strict_index_only: bool,
 }
 
 pub fn default_use_cache() -> bool {
@@ -685,6 +693,8 @@ impl SearchHistoryRequest {
             use_cache: default_use_cache(),
             clear_cache: false,
             local_mode: None,
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         };
         Ok(search_req)
     }
@@ -838,6 +848,26 @@ pub struct ScanStats {
     pub file_list_took: i64,
     pub aggs_cache_ratio: i64,
     pub peak_memory_usage: i64,
+    #[serde(default)]
+    pub parquet_cache_hits: i64,
+    #[serde(default)]
+    pub parquet_cache_misses: i64,
+    #[serde(default)]
+    pub index_cache_hits: i64,
+    #[serde(default)]
+    pub index_cache_misses: i64,
+    /// bytes downloaded by the background file downloader for this query
+    #[serde(default)]
+    pub downloaded_size: i64,
+    /// bytes the background file downloader skipped because they were already cached or queued
+    #[serde(default)]
+    pub download_skipped_size: i64,
+    /// files whose statistics were read from the file statistics cache
+    #[serde(default)]
+    pub file_stat_cache_hits: i64,
+    /// files whose statistics were collected from the file
+    #[serde(default)]
+    pub file_stat_cache_misses: i64,
 }
 
 impl ScanStats {
@@ -864,6 +894,14 @@ impl ScanStats {
             std::cmp::min(self.aggs_cache_ratio, other.aggs_cache_ratio)
         };
         self.peak_memory_usage = std::cmp::max(self.peak_memory_usage, other.peak_memory_usage);
+        self.parquet_cache_hits += other.parquet_cache_hits;
+        self.parquet_cache_misses += other.parquet_cache_misses;
+        self.index_cache_hits += other.index_cache_hits;
+        self.index_cache_misses += other.index_cache_misses;
+        self.downloaded_size += other.downloaded_size;
+        self.download_skipped_size += other.download_skipped_size;
+        self.file_stat_cache_hits += other.file_stat_cache_hits;
+        self.file_stat_cache_misses += other.file_stat_cache_misses;
     }
 
     pub fn format_to_mb(&mut self) {
@@ -871,6 +909,8 @@ impl ScanStats {
         self.compressed_size = self.compressed_size / 1024 / 1024;
         self.idx_scan_size = self.idx_scan_size / 1024 / 1024;
         self.peak_memory_usage = self.peak_memory_usage / 1024 / 1024;
+        self.downloaded_size = self.downloaded_size / 1024 / 1024;
+        self.download_skipped_size = self.download_skipped_size / 1024 / 1024;
     }
 }
 
@@ -910,6 +950,14 @@ impl From<&ScanStats> for cluster_rpc::ScanStats {
             file_list_took: req.file_list_took,
             aggs_cache_ratio: req.aggs_cache_ratio,
             peak_memory_usage: req.peak_memory_usage,
+            parquet_cache_hits: req.parquet_cache_hits,
+            parquet_cache_misses: req.parquet_cache_misses,
+            index_cache_hits: req.index_cache_hits,
+            index_cache_misses: req.index_cache_misses,
+            downloaded_size: req.downloaded_size,
+            download_skipped_size: req.download_skipped_size,
+            file_stat_cache_hits: req.file_stat_cache_hits,
+            file_stat_cache_misses: req.file_stat_cache_misses,
         }
     }
 }
@@ -929,6 +977,14 @@ impl From<&cluster_rpc::ScanStats> for ScanStats {
             file_list_took: req.file_list_took,
             aggs_cache_ratio: req.aggs_cache_ratio,
             peak_memory_usage: req.peak_memory_usage,
+            parquet_cache_hits: req.parquet_cache_hits,
+            parquet_cache_misses: req.parquet_cache_misses,
+            index_cache_hits: req.index_cache_hits,
+            index_cache_misses: req.index_cache_misses,
+            downloaded_size: req.downloaded_size,
+            download_skipped_size: req.download_skipped_size,
+            file_stat_cache_hits: req.file_stat_cache_hits,
+            file_stat_cache_misses: req.file_stat_cache_misses,
         }
     }
 }
@@ -1294,6 +1350,8 @@ impl MultiStreamRequest {
                 use_cache: default_use_cache(),
                 clear_cache: false,
                 local_mode: None,
+                disable_index_skip_threshold: false,
+                strict_index_only: false,
             });
         }
         res
@@ -2260,6 +2318,14 @@ mod tests {
             file_list_took: 30,
             aggs_cache_ratio: 80,
             peak_memory_usage: 1024000,
+            parquet_cache_hits: 8,
+            parquet_cache_misses: 2,
+            index_cache_hits: 4,
+            index_cache_misses: 1,
+            downloaded_size: 4096,
+            download_skipped_size: 1024,
+            file_stat_cache_hits: 3,
+            file_stat_cache_misses: 1,
         };
 
         let stats2 = ScanStats {
@@ -2275,6 +2341,14 @@ mod tests {
             file_list_took: 40,
             aggs_cache_ratio: 90,
             peak_memory_usage: 2048000,
+            parquet_cache_hits: 16,
+            parquet_cache_misses: 4,
+            index_cache_hits: 8,
+            index_cache_misses: 2,
+            downloaded_size: 2048,
+            download_skipped_size: 0,
+            file_stat_cache_hits: 5,
+            file_stat_cache_misses: 0,
         };
 
         stats1.add(&stats2);
@@ -2290,6 +2364,27 @@ mod tests {
         assert_eq!(stats1.file_list_took, 40); // max
         assert_eq!(stats1.aggs_cache_ratio, 80); // min
         assert_eq!(stats1.peak_memory_usage, 2048000); // max
+        assert_eq!(stats1.parquet_cache_hits, 24);
+        assert_eq!(stats1.parquet_cache_misses, 6);
+        assert_eq!(stats1.index_cache_hits, 12);
+        assert_eq!(stats1.index_cache_misses, 3);
+        assert_eq!(stats1.downloaded_size, 6144);
+        assert_eq!(stats1.download_skipped_size, 1024);
+        assert_eq!(stats1.file_stat_cache_hits, 8);
+        assert_eq!(stats1.file_stat_cache_misses, 1);
+    }
+
+    #[test]
+    fn test_scan_stats_deserialize_without_cache_hits() {
+        // the scan stats from older nodes have no cache hit fields
+        let stats: ScanStats = serde_json::from_str(
+            r#"{"files":10,"records":100,"original_size":1024,"compressed_size":512,"querier_files":5,"querier_memory_cached_files":3,"querier_disk_cached_files":2,"idx_scan_size":256,"idx_took":50,"file_list_took":30,"aggs_cache_ratio":80,"peak_memory_usage":1024000}"#,
+        )
+        .unwrap();
+        assert_eq!(stats.files, 10);
+        assert_eq!(stats.parquet_cache_hits, 0);
+        assert_eq!(stats.index_cache_misses, 0);
+        assert_eq!(stats.downloaded_size, 0);
     }
 
     #[test]
@@ -2453,6 +2548,14 @@ mod tests {
             file_list_took: 30,
             aggs_cache_ratio: 80,
             peak_memory_usage: 1024000,
+            parquet_cache_hits: 4,
+            parquet_cache_misses: 1,
+            index_cache_hits: 3,
+            index_cache_misses: 2,
+            downloaded_size: 2048,
+            download_skipped_size: 512,
+            file_stat_cache_hits: 6,
+            file_stat_cache_misses: 2,
         };
 
         // Test conversion to cluster_rpc::ScanStats
@@ -2468,6 +2571,12 @@ mod tests {
         assert_eq!(cluster_stats.idx_took, 50);
         assert_eq!(cluster_stats.file_list_took, 30);
         assert_eq!(cluster_stats.aggs_cache_ratio, 80);
+        assert_eq!(cluster_stats.parquet_cache_hits, 4);
+        assert_eq!(cluster_stats.index_cache_misses, 2);
+        assert_eq!(cluster_stats.downloaded_size, 2048);
+        assert_eq!(cluster_stats.download_skipped_size, 512);
+        assert_eq!(cluster_stats.file_stat_cache_hits, 6);
+        assert_eq!(cluster_stats.file_stat_cache_misses, 2);
 
         // Test conversion from cluster_rpc::ScanStats
         let converted_stats: ScanStats = (&cluster_stats).into();
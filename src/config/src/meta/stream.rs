@@ -688,6 +688,10 @@ pub struct UpdateStreamSettings {
     #[serde(default)]
     pub full_text_search_keys: UpdateSettingsWrapper<String>,
     #[serde(default)]
+    pub full_text_search_exclude_keys: UpdateSettingsWrapper<String>,
+    #[serde(skip_serializing_if = "Option::None", default)]
+    pub full_text_search_tokenizer: Option<String>,
+    #[serde(default)]
     pub index_fields: UpdateSettingsWrapper<String>,
     #[serde(default)]
     pub bloom_filter_fields: UpdateSettingsWrapper<String>,
@@ -717,6 +721,38 @@ pub struct UpdateStreamSettings {
     pub enable_distinct_fields: Option<bool>,
     #[serde(default)]
     pub enable_log_patterns_extraction: Option<bool>,
+    /// an empty policy removes the cache policy of the stream
+    #[serde(skip_serializing_if = "Option::None", default)]
+    pub cache_policy: Option<StreamCachePolicy>,
+}
+
+/// Overrides how the querier caches the files of a stream, the unset values fall back to
+/// `ZO_MEMORY_CACHE_SKIP_SIZE` and `ZO_DISK_CACHE_SKIP_SIZE`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StreamCachePolicy {
+    /// the files are cached in memory when the scan size is less than it, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_skip_size: Option<usize>,
+    /// the files are cached on disk when the scan size is less than it, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_skip_size: Option<usize>,
+    /// always use this cache regardless of the scan size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_type: Option<StreamCacheType>,
+}
+
+impl StreamCachePolicy {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamCacheType {
+    Memory,
+    Disk,
+    None,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
@@ -819,6 +855,13 @@ pub struct StreamSettings {
     pub partition_keys: Vec<StreamPartition>,
     #[serde(default)]
     pub full_text_search_keys: Vec<String>,
+    /// fields excluded from the full text search, even if they are default full text search
+    /// fields
+    #[serde(default)]
+    pub full_text_search_exclude_keys: Vec<String>,
+    /// tokenizer of the full text search fields in the inverted index, default is o2
+    #[serde(default)]
+    pub full_text_search_tokenizer: Option<String>,
     #[serde(default)]
     pub index_fields: Vec<String>,
     #[serde(default)]
@@ -849,6 +892,8 @@ pub struct StreamSettings {
     pub enable_distinct_fields: bool,
     #[serde(default)]
     pub enable_log_patterns_extraction: bool,
+    #[serde(default)]
+    pub cache_policy: Option<StreamCachePolicy>,
 }
 
 impl Default for StreamSettings {
@@ -857,6 +902,8 @@ impl Default for StreamSettings {
             partition_time_level: None,
             partition_keys: Vec::new(),
             full_text_search_keys: Vec::new(),
+            full_text_search_exclude_keys: Vec::new(),
+            full_text_search_tokenizer: None,
             index_fields: Vec::new(),
             bloom_filter_fields: Vec::new(),
             data_retention: 0,
@@ -872,6 +919,7 @@ impl Default for StreamSettings {
             index_all_values: false,
             enable_distinct_fields: true,
             enable_log_patterns_extraction: false,
+            cache_policy: None,
         }
     }
 }
@@ -892,6 +940,10 @@ impl Serialize for StreamSettings {
         )?;
         state.serialize_field("partition_keys", &part_keys)?;
         state.serialize_field("full_text_search_keys", &self.full_text_search_keys)?;
+        state.serialize_field(
+            "full_text_search_exclude_keys",
+            &self.full_text_search_exclude_keys,
+        )?;
         state.serialize_field("index_fields", &self.index_fields)?;
         state.serialize_field("bloom_filter_fields", &self.bloom_filter_fields)?;
         state.serialize_field("distinct_value_fields", &self.distinct_value_fields)?;
@@ -925,6 +977,22 @@ impl Serialize for StreamSettings {
                 state.skip_field("flatten_level")?;
             }
         }
+        match self.full_text_search_tokenizer.as_ref() {
+            Some(tokenizer) => {
+                state.serialize_field("full_text_search_tokenizer", tokenizer)?;
+            }
+            None => {
+                state.skip_field("full_text_search_tokenizer")?;
+            }
+        }
+        match self.cache_policy.as_ref() {
+            Some(cache_policy) => {
+                state.serialize_field("cache_policy", cache_policy)?;
+            }
+            None => {
+                state.skip_field("cache_policy")?;
+            }
+        }
         state.end()
     }
 }
@@ -966,6 +1034,20 @@ impl From<&str> for StreamSettings {
             }
         }
 
+        let mut full_text_search_exclude_keys = Vec::new();
+        let fields = settings.get("full_text_search_exclude_keys");
+        if let Some(value) = fields {
+            let v: Vec<_> = value.as_array().unwrap().iter().collect();
+            for item in v {
+                full_text_search_exclude_keys.push(item.as_str().unwrap().to_string())
+            }
+        }
+
+        let full_text_search_tokenizer = settings
+            .get("full_text_search_tokenizer")
+            .and_then(Value::as_str)
+            .map(String::from);
+
         let mut index_fields = Vec::new();
         let fields = settings.get("index_fields");
         if let Some(value) = fields {
@@ -1070,10 +1152,16 @@ impl From<&str> for StreamSettings {
             .get("enable_log_patterns_extraction")
             .and_then(Value::as_bool)
             .unwrap_or_default();
+        let cache_policy = settings
+            .get("cache_policy")
+            .and_then(|v| json::from_value::<StreamCachePolicy>(v.clone()).ok())
+            .filter(|policy| !policy.is_empty());
         Self {
             partition_time_level,
             partition_keys,
             full_text_search_keys,
+            full_text_search_exclude_keys,
+            full_text_search_tokenizer,
             index_fields,
             bloom_filter_fields,
             data_retention,
@@ -1089,6 +1177,7 @@ impl From<&str> for StreamSettings {
             index_all_values,
             enable_distinct_fields,
             enable_log_patterns_extraction,
+            cache_policy,
         }
     }
 }
@@ -1455,4 +1544,30 @@ mod tests {
         let expected_res = vec![TimeRange::new(0, 199), TimeRange::new(200, 300)];
         assert_eq!(TimeRange::flatten_overlapping_ranges(ranges), expected_res);
     }
+
+    #[test]
+    fn test_stream_settings_cache_policy() {
+        let settings = StreamSettings {
+            cache_policy: Some(StreamCachePolicy {
+                memory_skip_size: Some(1024),
+                cache_type: Some(StreamCacheType::Disk),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let data = json::to_string(&settings).unwrap();
+        assert!(data.contains(r#""cache_policy":{"memory_skip_size":1024,"cache_type":"disk"}"#));
+        let settings = StreamSettings::from(data.as_str());
+        let policy = settings.cache_policy.unwrap();
+        assert_eq!(policy.memory_skip_size, Some(1024));
+        assert_eq!(policy.disk_skip_size, None);
+        assert_eq!(policy.cache_type, Some(StreamCacheType::Disk));
+
+        let settings = StreamSettings::from(
+            json::to_string(&StreamSettings::default())
+                .unwrap()
+                .as_str(),
+        );
+        assert!(settings.cache_policy.is_none());
+    }
 }
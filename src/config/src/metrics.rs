@@ -771,6 +771,27 @@ pub static META_NUM_NODES: Lazy<IntGaugeVec> = Lazy::new(|| {
     )
     .expect("Metric created")
 });
+pub static META_CACHE_ENTRIES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new("meta_cache_entries", "Metadata in-memory cache entry nums")
+            .namespace(NAMESPACE)
+            .const_labels(create_const_labels()),
+        &["cache"],
+    )
+    .expect("Metric created")
+});
+pub static META_CACHE_REFRESH_AGE_SECONDS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "meta_cache_refresh_age_seconds",
+            "Seconds since the metadata in-memory cache was last reloaded or updated by a watch event",
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["cache"],
+    )
+    .expect("Metric created")
+});
 pub static META_NUM_ORGANIZATIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new("meta_num_organizations", "Metadata organization nums")
@@ -998,6 +1019,19 @@ pub static DB_QUERY_NUMS: Lazy<IntCounterVec> = Lazy::new(|| {
     .expect("Metric created")
 });
 
+pub static DB_WATCH_DROPPED_EVENTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new(
+            "db_watch_dropped_events",
+            "number of watch events dropped because the consumer is too slow",
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["backend", "prefix"],
+    )
+    .expect("Metric created")
+});
+
 pub static DB_QUERY_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     HistogramVec::new(
         HistogramOpts::new("db_query_time", "db query time.".to_owned())
@@ -1008,6 +1042,35 @@ pub static DB_QUERY_TIME: Lazy<HistogramVec> = Lazy::new(|| {
     .expect("Metric created")
 });
 
+pub static DB_POOL_CONNECTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    IntGaugeVec::new(
+        Opts::new(
+            "db_pool_connections",
+            "number of connections of the db pool, by state",
+        )
+        .namespace(NAMESPACE)
+        .const_labels(create_const_labels()),
+        &["pool", "state"],
+    )
+    .expect("Metric created")
+});
+
+pub static DB_POOL_LOCK_WAIT_TIME: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "db_pool_lock_wait_time",
+            "time waiting for the lock of the db pool, in milliseconds",
+        )
+        .namespace(NAMESPACE)
+        .buckets(vec![
+            0.2, 0.5, 1.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0,
+        ])
+        .const_labels(create_const_labels()),
+        &["pool"],
+    )
+    .expect("Metric created")
+});
+
 pub static FILE_LIST_ID_SELECT_COUNT: Lazy<IntGaugeVec> = Lazy::new(|| {
     IntGaugeVec::new(
         Opts::new(
@@ -1812,6 +1875,12 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(META_NUM_DASHBOARDS.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(META_CACHE_ENTRIES.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(META_CACHE_REFRESH_AGE_SECONDS.clone()))
+        .expect("Metric registered");
 
     // alert deduplication metrics
     registry
@@ -1845,9 +1914,18 @@ fn register_metrics(registry: &Registry) {
     registry
         .register(Box::new(DB_QUERY_NUMS.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(DB_WATCH_DROPPED_EVENTS.clone()))
+        .expect("Metric registered");
     registry
         .register(Box::new(DB_QUERY_TIME.clone()))
         .expect("Metric registered");
+    registry
+        .register(Box::new(DB_POOL_CONNECTIONS.clone()))
+        .expect("Metric registered");
+    registry
+        .register(Box::new(DB_POOL_LOCK_WAIT_TIME.clone()))
+        .expect("Metric registered");
 
     // file list specific metrics
     registry
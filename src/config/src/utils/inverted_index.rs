@@ -100,4 +100,22 @@ mod tests {
             assert_eq!(convert_parquet_file_name_to_tantivy_file(input), expected);
         }
     }
+
+    #[test]
+    fn test_tantivy_file_name_is_unique_per_parquet_file() {
+        // the stream type suffix keeps the streams whose names end with another stream type
+        // apart, so every parquet file has its own index file
+        let files = [
+            "files/default/logs/app/2024/02/16/16/7164299619311026293.parquet",
+            "files/default/metrics/app/2024/02/16/16/7164299619311026293.parquet",
+            "files/default/logs/app_metrics/2024/02/16/16/7164299619311026293.parquet",
+            "files/default/metrics/app_logs/2024/02/16/16/7164299619311026293.parquet",
+            "files/default/logs/app/2024/02/16/16/7164299619311026294.parquet",
+        ];
+        let index_files = files
+            .iter()
+            .map(|f| convert_parquet_file_name_to_tantivy_file(f).unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(index_files.len(), files.len());
+    }
 }
@@ -17,31 +17,104 @@ mod o2_tokenizer;
 mod remove_short;
 
 pub use o2_tokenizer::{CollectType, O2Tokenizer};
-use tantivy::tokenizer::{TextAnalyzer, Token};
+use tantivy::tokenizer::{
+    LowerCaser, NgramTokenizer, RemoveLongFilter, TextAnalyzer, Token, TokenizerManager,
+    WhitespaceTokenizer,
+};
 
 use crate::{get_config, utils::tantivy::tokenizer::remove_short::RemoveShortFilter};
 
 pub const O2_TOKENIZER: &str = "o2";
+pub const WHITESPACE_TOKENIZER: &str = "whitespace";
+pub const NGRAM_TOKENIZER: &str = "ngram";
 const MIN_TOKEN_LENGTH: usize = 2;
 const MAX_TOKEN_LENGTH: usize = 64;
+const NGRAM_MIN_GRAM: usize = 2;
+const NGRAM_MAX_GRAM: usize = 3;
+
+type TokenizerBuilder = fn(CollectType) -> TextAnalyzer;
+
+/// The tokenizers can be used for the full text search fields of a stream, the name is stored
+/// in the schema of the tantivy index so the search uses the same tokenizer as the ingestion.
+const TOKENIZERS: [(&str, TokenizerBuilder); 3] = [
+    (O2_TOKENIZER, o2_tokenizer_build),
+    (WHITESPACE_TOKENIZER, whitespace_tokenizer_build),
+    (NGRAM_TOKENIZER, ngram_tokenizer_build),
+];
+
+pub fn is_fts_tokenizer(name: &str) -> bool {
+    TOKENIZERS.iter().any(|(n, _)| *n == name)
+}
+
+pub fn fts_tokenizer_names() -> Vec<&'static str> {
+    TOKENIZERS.iter().map(|(n, _)| *n).collect()
+}
+
+/// Returns the tokenizer of the name, the unknown name falls back to the o2 tokenizer
+pub fn fts_tokenizer_build(name: &str, collect_type: CollectType) -> TextAnalyzer {
+    let builder = TOKENIZERS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, builder)| *builder)
+        .unwrap_or(o2_tokenizer_build);
+    builder(collect_type)
+}
+
+/// Registers all the full text search tokenizers, an index only uses the ones in its schema
+pub fn register_fts_tokenizers(manager: &TokenizerManager, collect_type: CollectType) {
+    for (name, builder) in TOKENIZERS.iter() {
+        manager.register(name, builder(collect_type.clone()));
+    }
+}
 
 pub fn o2_tokenizer_build(collect_type: CollectType) -> TextAnalyzer {
     let cfg = get_config();
     let min_token_length =
         std::cmp::max(cfg.limit.inverted_index_min_token_length, MIN_TOKEN_LENGTH);
-    let max_token_length =
-        std::cmp::max(cfg.limit.inverted_index_max_token_length, MAX_TOKEN_LENGTH);
-    tantivy::tokenizer::TextAnalyzer::builder(O2Tokenizer::new(collect_type))
+    TextAnalyzer::builder(O2Tokenizer::new(collect_type))
         .filter(RemoveShortFilter::limit(min_token_length))
-        .filter(tantivy::tokenizer::RemoveLongFilter::limit(
-            max_token_length,
-        ))
-        .filter(tantivy::tokenizer::LowerCaser)
+        .filter(RemoveLongFilter::limit(max_token_length()))
+        .filter(LowerCaser)
+        .build()
+}
+
+fn whitespace_tokenizer_build(_collect_type: CollectType) -> TextAnalyzer {
+    TextAnalyzer::builder(WhitespaceTokenizer::default())
+        .filter(RemoveLongFilter::limit(max_token_length()))
+        .filter(LowerCaser)
         .build()
 }
 
+fn ngram_tokenizer_build(_collect_type: CollectType) -> TextAnalyzer {
+    let tokenizer = NgramTokenizer::all_ngrams(NGRAM_MIN_GRAM, NGRAM_MAX_GRAM)
+        .expect("ngram tokenizer with valid gram sizes");
+    TextAnalyzer::builder(tokenizer).filter(LowerCaser).build()
+}
+
+fn max_token_length() -> usize {
+    std::cmp::max(
+        get_config().limit.inverted_index_max_token_length,
+        MAX_TOKEN_LENGTH,
+    )
+}
+
 pub fn o2_collect_search_tokens(text: &str) -> Vec<String> {
-    let mut a = o2_tokenizer_build(CollectType::Search);
+    collect_tokens(o2_tokenizer_build(CollectType::Search), text)
+}
+
+/// Collects the search tokens of the text with the tokenizer, the wildcards at the start and the
+/// end of the text are kept out of the tokens like the o2 tokenizer does.
+pub fn collect_search_tokens(tokenizer: &str, text: &str) -> Vec<String> {
+    if tokenizer == O2_TOKENIZER || !is_fts_tokenizer(tokenizer) {
+        return o2_collect_search_tokens(text);
+    }
+    collect_tokens(
+        fts_tokenizer_build(tokenizer, CollectType::Search),
+        text.trim_matches('*'),
+    )
+}
+
+fn collect_tokens(mut a: TextAnalyzer, text: &str) -> Vec<String> {
     let mut token_stream = a.token_stream(text);
 
     let mut tokens: Vec<String> = Vec::new();
@@ -51,3 +124,40 @@ pub fn o2_collect_search_tokens(text: &str) -> Vec<String> {
     token_stream.process(&mut add_token);
     tokens
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_search_tokens() {
+        assert_eq!(
+            collect_search_tokens(O2_TOKENIZER, "Hello world-foo"),
+            o2_collect_search_tokens("Hello world-foo")
+        );
+        assert_eq!(
+            collect_search_tokens(WHITESPACE_TOKENIZER, "*Hello world-foo*"),
+            vec!["hello", "world-foo"]
+        );
+        assert_eq!(
+            collect_search_tokens(NGRAM_TOKENIZER, "东京都"),
+            vec!["东京", "东京都", "京都"]
+        );
+        // the unknown tokenizer falls back to the o2 tokenizer
+        assert_eq!(
+            collect_search_tokens("unknown", "Hello world"),
+            vec!["hello", "world"]
+        );
+    }
+
+    #[test]
+    fn test_register_fts_tokenizers() {
+        let manager = TokenizerManager::default();
+        register_fts_tokenizers(&manager, CollectType::Search);
+        for name in fts_tokenizer_names() {
+            assert!(is_fts_tokenizer(name));
+            assert!(manager.get(name).is_some());
+        }
+        assert!(!is_fts_tokenizer("raw"));
+    }
+}
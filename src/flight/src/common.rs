@@ -103,12 +103,16 @@ pub enum PreCustomMessage {
     MetricsRef(Vec<Arc<Mutex<Vec<Metrics>>>>),
     // use for storing memory pool reference to extract peak later
     PeakMemoryRef(Option<Arc<AtomicUsize>>),
+    // scan stats collected when the stream is done, sent after the data instead of before it
+    ScanStatsFn(Box<dyn Fn() -> Option<ScanStats> + Send>),
 }
 
 pub struct MetricsInfo {
     pub plan: Arc<dyn ExecutionPlan>,
     pub is_super_cluster: bool,
     pub func: Box<dyn Fn() -> bool + Send>,
+    /// the file pruning and partition decisions of the storage search of the node, in JSON
+    pub storage_plan: Option<String>,
 }
 
 impl PreCustomMessage {
@@ -135,6 +139,7 @@ impl PreCustomMessage {
             PreCustomMessage::PeakMemoryRef(peak_memory_ref) => peak_memory_ref
                 .as_ref()
                 .map(|peak| CustomMessage::PeakMemory(peak.load(Ordering::Relaxed))),
+            PreCustomMessage::ScanStatsFn(func) => func().map(CustomMessage::ScanStats),
         }
     }
 }
@@ -144,10 +149,13 @@ fn collect_metrics(metrics_info: &MetricsInfo) -> Vec<Metrics> {
     let plan = &metrics_info.plan;
     let is_super_cluster = metrics_info.is_super_cluster;
     let func = &metrics_info.func;
-    let plan_with_metrics = DisplayableExecutionPlan::with_metrics(plan.as_ref())
+    let mut plan_with_metrics = DisplayableExecutionPlan::with_metrics(plan.as_ref())
         .set_show_statistics(false)
         .indent(true)
         .to_string();
+    if let Some(storage_plan) = metrics_info.storage_plan.as_ref() {
+        plan_with_metrics.push_str(&format!("StoragePlan: {storage_plan}\n"));
+    }
     let stage = if func() {
         if is_super_cluster { 1 } else { 2 }
     } else {
@@ -186,6 +194,7 @@ mod tests {
             file_list_took: 50,
             aggs_cache_ratio: 80,
             peak_memory_usage: 1024000,
+            ..Default::default()
         }
     }
 
@@ -227,6 +236,10 @@ mod tests {
         // Test MetricsRef variant (should be false)
         let pre_msg = PreCustomMessage::MetricsRef(vec![]);
         assert!(!pre_msg.is_scan_stats());
+
+        // Test ScanStatsFn variant (should be false, it is sent after the data)
+        let pre_msg = PreCustomMessage::ScanStatsFn(Box::new(|| None));
+        assert!(!pre_msg.is_scan_stats());
     }
 
     #[test]
@@ -269,6 +282,26 @@ mod tests {
         assert!(custom_msg.is_none());
     }
 
+    #[test]
+    fn test_pre_custom_message_get_custom_message_scan_stats_fn() {
+        let pre_msg = PreCustomMessage::ScanStatsFn(Box::new(|| {
+            Some(ScanStats {
+                downloaded_size: 4096,
+                ..Default::default()
+            })
+        }));
+        match pre_msg.get_custom_message().unwrap() {
+            CustomMessage::ScanStats(stats) => {
+                assert_eq!(stats.downloaded_size, 4096);
+                assert_eq!(stats.files, 0);
+            }
+            _ => panic!("Expected ScanStats variant"),
+        }
+
+        let pre_msg = PreCustomMessage::ScanStatsFn(Box::new(|| None));
+        assert!(pre_msg.get_custom_message().is_none());
+    }
+
     #[test]
     fn test_pre_custom_message_get_custom_message_metrics() {
         let plan = create_test_execution_plan();
@@ -276,6 +309,7 @@ mod tests {
             plan,
             is_super_cluster: false,
             func: Box::new(|| true),
+            storage_plan: None,
         };
 
         let pre_msg = PreCustomMessage::Metrics(Some(metrics_info));
@@ -349,6 +383,7 @@ mod tests {
             plan,
             is_super_cluster: true,
             func: Box::new(|| true),
+            storage_plan: None,
         };
 
         let metrics = collect_metrics(&metrics_info);
@@ -365,6 +400,7 @@ mod tests {
             plan,
             is_super_cluster: false,
             func: Box::new(|| true),
+            storage_plan: None,
         };
 
         let metrics = collect_metrics(&metrics_info);
@@ -379,6 +415,7 @@ mod tests {
             plan,
             is_super_cluster: true,
             func: Box::new(|| false),
+            storage_plan: None,
         };
 
         let metrics = collect_metrics(&metrics_info);
@@ -386,6 +423,24 @@ mod tests {
         assert_eq!(metrics[0].stage, 1); // func() returns false, stage defaults to 1
     }
 
+    #[test]
+    fn test_collect_metrics_with_storage_plan() {
+        let plan = create_test_execution_plan();
+        let metrics_info = MetricsInfo {
+            plan,
+            is_super_cluster: false,
+            func: Box::new(|| true),
+            storage_plan: Some(r#"{"files":3,"files_loaded":1}"#.to_string()),
+        };
+
+        let metrics = collect_metrics(&metrics_info);
+        assert!(
+            metrics[0]
+                .metrics
+                .ends_with("StoragePlan: {\"files\":3,\"files_loaded\":1}\n")
+        );
+    }
+
     #[test]
     fn test_custom_message_serialization() {
         let scan_stats = create_test_scan_stats();
@@ -259,6 +259,7 @@ mod tests {
             file_list_took: 50,
             aggs_cache_ratio: 80,
             peak_memory_usage: 1024000,
+            ..Default::default()
         };
         CustomMessage::ScanStats(scan_stats)
     }
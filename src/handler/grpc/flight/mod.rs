@@ -23,7 +23,7 @@ use arrow_flight::{
 };
 use config::{
     PARQUET_BATCH_SIZE, cluster::LOCAL_NODE, datafusion::request::FlightSearchRequest,
-    meta::search::ScanStats,
+    meta::search::ScanStats, utils::json,
 };
 use datafusion::{
     common::{DataFusionError, Result},
@@ -53,7 +53,7 @@ use crate::{
         },
     },
     service::search::{
-        grpc::flight as grpcFlight,
+        grpc::{flight as grpcFlight, storage::StoragePlan},
         inspector::{SearchInspectorFieldsBuilder, search_inspector_fields},
         work_group::DeferredLock,
     },
@@ -150,7 +150,7 @@ impl FlightService for FlightServiceImpl {
         );
 
         // prepare dataufion context
-        let (ctx, physical_plan, lock, scan_stats) = match result {
+        let (ctx, physical_plan, lock, scan_stats, storage_plan) = match result {
             Ok(v) => v,
             Err(e) => {
                 // clear session data
@@ -198,6 +198,7 @@ impl FlightService for FlightServiceImpl {
             plan: physical_plan.clone(),
             is_super_cluster,
             func: Box::new(super_cluster_enabled),
+            storage_plan: storage_plan.map(|plan| json::to_string(&plan).unwrap_or_default()),
         });
 
         // Get the peak memory usage from the memory pool
@@ -218,6 +219,8 @@ impl FlightService for FlightServiceImpl {
             Status::internal(e.to_string())
         })?;
 
+        // the background downloads of the query are reported after the data
+        let download_trace_id = trace_id.clone();
         let mut stream = FlightEncoderStreamBuilder::new(write_options, 33554432)
             .with_trace_id(trace_id.to_string())
             .with_is_super(is_super_cluster)
@@ -229,6 +232,9 @@ impl FlightService for FlightServiceImpl {
             .with_custom_message(PreCustomMessage::MetricsRef(metrics_ref))
             .with_custom_message(PreCustomMessage::PeakMemoryRef(Some(peak_memory)))
             .with_custom_message(PreCustomMessage::PeakMemoryRef(peak_memory_ref))
+            .with_custom_message(PreCustomMessage::ScanStatsFn(Box::new(move || {
+                crate::job::take_download_stats(&download_trace_id)
+            })))
             .build(stream, span);
 
         let stream = async_stream::stream! {
@@ -323,6 +329,7 @@ type PlanResult = (
     Arc<dyn datafusion::physical_plan::ExecutionPlan>,
     Option<DeferredLock>,
     ScanStats,
+    Option<StoragePlan>,
 );
 
 #[cfg(feature = "enterprise")]
@@ -334,10 +341,11 @@ async fn get_ctx_and_physical_plan(
     if req.super_cluster_info.is_super_cluster {
         let (ctx, physical_plan, lock, scan_stats) =
             crate::service::search::super_cluster::follower::search(trace_id, req).await?;
-        Ok((ctx, physical_plan, Some(lock), scan_stats))
+        Ok((ctx, physical_plan, Some(lock), scan_stats, None))
     } else {
-        let (ctx, physical_plan, scan_stats) = grpcFlight::search(trace_id, req).await?;
-        Ok((ctx, physical_plan, None, scan_stats))
+        let (ctx, physical_plan, scan_stats, storage_plan) =
+            grpcFlight::search(trace_id, req).await?;
+        Ok((ctx, physical_plan, None, scan_stats, storage_plan))
     }
 }
 
@@ -347,8 +355,8 @@ async fn get_ctx_and_physical_plan(
     trace_id: &str,
     req: &FlightSearchRequest,
 ) -> Result<PlanResult, infra::errors::Error> {
-    let (ctx, physical_plan, scan_stats) = grpcFlight::search(trace_id, req).await?;
-    Ok((ctx, physical_plan, None, scan_stats))
+    let (ctx, physical_plan, scan_stats, storage_plan) = grpcFlight::search(trace_id, req).await?;
+    Ok((ctx, physical_plan, None, scan_stats, storage_plan))
 }
 
 fn clear_session_data(trace_id: &str) {
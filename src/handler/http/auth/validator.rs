@@ -43,7 +43,10 @@ use crate::{
             },
         },
         utils::{
-            auth::{AuthExtractor, V2_API_PREFIX, get_hash, is_root_user},
+            auth::{
+                AuthExtractor, PresignedUrlMode, PresignedUrlParams, V2_API_PREFIX, get_hash,
+                is_root_user, verify_presigned_url_ext,
+            },
             redirect_response::RedirectResponseBuilder,
         },
     },
@@ -527,16 +530,12 @@ pub async fn validate_credentials_ext(
     }
     let user = user.unwrap();
 
-    let hashed_pass = get_hash(
-        &format!(
-            "{}{}",
-            get_hash(
-                &format!("{}{}", user.password_ext.unwrap(), auth_token.request_time),
-                password_ext_salt
-            ),
-            auth_token.expires_in
-        ),
+    // the cookie keeps the signature of the presigned URL, signed with the mode of the URL
+    let hashed_pass = auth_token.mode.sign(
+        &user.password_ext.unwrap(),
         password_ext_salt,
+        auth_token.request_time,
+        auth_token.expires_in,
     );
     if !hashed_pass.eq(&in_password) {
         return Ok(TokenValidationResponse::default());
@@ -627,15 +626,13 @@ pub async fn validate_credentials_ext(
 async fn validate_user_from_db(
     db_user: Result<DBUser, anyhow::Error>,
     user_password: &str,
-    req_time: Option<&String>,
-    exp_in: i64,
     password_ext_salt: &str,
 ) -> Result<TokenValidationResponse, AuthError> {
     // let db_user = db::user::get_db_user(user_id).await;
     match db_user {
         Ok(mut user) => {
             let in_pass = get_hash(user_password, &user.salt);
-            if req_time.is_none() && user.password.eq(&in_pass) {
+            if user.password.eq(&in_pass) {
                 if user.password_ext.is_none() {
                     let password_ext = get_hash(user_password, password_ext_salt);
                     user.password_ext = Some(password_ext);
@@ -650,29 +647,6 @@ async fn validate_user_from_db(
                 }
                 let resp = TokenValidationResponseBuilder::from_db_user(&user).build();
                 Ok(resp)
-            } else if user.password_ext.is_some() && req_time.is_some() {
-                log::debug!("Validating user for query params");
-                let hashed_pass = get_hash(
-                    &format!(
-                        "{}{}",
-                        get_hash(
-                            &format!(
-                                "{}{}",
-                                user.password_ext.as_ref().unwrap(),
-                                req_time.unwrap()
-                            ),
-                            password_ext_salt
-                        ),
-                        exp_in
-                    ),
-                    password_ext_salt,
-                );
-                if hashed_pass.eq(&user_password) {
-                    let resp = TokenValidationResponseBuilder::from_db_user(&user).build();
-                    Ok(resp)
-                } else {
-                    Err(AuthError::Forbidden("Not allowed".to_string()))
-                }
             } else {
                 Err(AuthError::Forbidden("Not allowed".to_string()))
             }
@@ -689,25 +663,53 @@ pub async fn validate_user(
         .await
         .map(|user| DBUser::from(&user));
     let cfg = get_config();
-    validate_user_from_db(db_user, user_password, None, 0, &cfg.auth.ext_auth_salt).await
+    validate_user_from_db(db_user, user_password, &cfg.auth.ext_auth_salt).await
 }
 
-pub async fn validate_user_for_query_params(
+/// Validates the params of a presigned login URL against the stored `password_ext` of the user,
+/// see [`verify_presigned_url_ext`].
+pub async fn validate_presigned_url(
     user_id: &str,
-    user_password: &str,
-    req_time: Option<&String>,
-    exp_in: i64,
+    params: &PresignedUrlParams,
 ) -> Result<TokenValidationResponse, AuthError> {
     let db_user = db::user::get_db_user(user_id).await;
     let cfg = get_config();
-    validate_user_from_db(
+    validate_presigned_url_from_db(
         db_user,
-        user_password,
-        req_time,
-        exp_in,
+        user_id,
+        params,
+        chrono::Utc::now().timestamp(),
         &cfg.auth.ext_auth_salt,
     )
-    .await
+}
+
+fn validate_presigned_url_from_db(
+    db_user: Result<DBUser, anyhow::Error>,
+    user_id: &str,
+    params: &PresignedUrlParams,
+    now: i64,
+    password_ext_salt: &str,
+) -> Result<TokenValidationResponse, AuthError> {
+    let Ok(user) = db_user else {
+        return Err(AuthError::Forbidden("Not allowed".to_string()));
+    };
+    let Some(password_ext) = user.password_ext.as_deref() else {
+        return Err(AuthError::Forbidden("Not allowed".to_string()));
+    };
+    if verify_presigned_url_ext(
+        user_id,
+        password_ext,
+        password_ext_salt,
+        &params.auth,
+        params.request_time,
+        params.exp_in,
+        now,
+        params.mode,
+    ) {
+        Ok(TokenValidationResponseBuilder::from_db_user(&user).build())
+    } else {
+        Err(AuthError::Forbidden("Not allowed".to_string()))
+    }
 }
 
 /// Validates AWS Firehose requests
@@ -1278,6 +1280,68 @@ mod tests {
         assert!(resp_from_builder.given_name.eq(&resp.given_name));
     }
 
+    #[test]
+    fn test_validate_presigned_url_from_db() {
+        for mode in [PresignedUrlMode::Argon2, PresignedUrlMode::Hmac] {
+            validate_presigned_url_round_trip(mode);
+        }
+    }
+
+    fn validate_presigned_url_round_trip(mode: PresignedUrlMode) {
+        let salt = "ext_salt";
+        let password = "Complexpass#123";
+        let user = DBUser {
+            email: "user@example.com".into(),
+            first_name: "first_name".into(),
+            last_name: "last_name".into(),
+            password: "some_pass".into(),
+            salt: "some_salt".into(),
+            organizations: vec![],
+            is_external: false,
+            password_ext: Some(get_hash(password, salt)),
+        };
+        let time = chrono::Utc::now().timestamp();
+        let url = crate::common::utils::auth::generate_presigned_url(
+            &user.email,
+            password,
+            salt,
+            "https://example.com",
+            600,
+            time,
+            mode,
+        );
+        let query = url::form_urlencoded::parse(url.split_once('?').unwrap().1.as_bytes())
+            .into_owned()
+            .collect();
+        let params = PresignedUrlParams::from_query(&query).unwrap();
+        assert_eq!(params.mode, mode);
+
+        let validate = |user: DBUser, user_id: &str, now: i64| {
+            validate_presigned_url_from_db(Ok(user), user_id, &params, now, salt)
+        };
+        assert!(validate(user.clone(), &user.email, time).unwrap().is_valid);
+        assert!(validate(user.clone(), "other@example.com", time).is_err());
+        assert!(validate(user.clone(), &user.email, time + 601).is_err());
+        let without_ext = DBUser {
+            password_ext: None,
+            ..user.clone()
+        };
+        assert!(validate(without_ext, &user.email, time).is_err());
+
+        // the signature of one mode isn't accepted by the other
+        let other_mode = PresignedUrlParams {
+            mode: match mode {
+                PresignedUrlMode::Argon2 => PresignedUrlMode::Hmac,
+                PresignedUrlMode::Hmac => PresignedUrlMode::Argon2,
+            },
+            ..params.clone()
+        };
+        assert!(
+            validate_presigned_url_from_db(Ok(user.clone()), &user.email, &other_mode, time, salt)
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_validation_response_default() {
         let actual = TokenValidationResponse {
@@ -13,30 +13,43 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::{
+    fmt::{self, Display},
+    io::Read,
+};
+
 use axum::{
-    body::Bytes,
+    body::{Body, Bytes},
     extract::Path,
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, StatusCode, header},
     response::{IntoResponse, Response},
 };
-use config::axum::middlewares::{
-    HEADER_O2_PROCESS_TIME, get_process_time, insert_process_time_header,
-};
 #[cfg(feature = "cloud")]
 use config::meta::stream::StreamType;
+use config::{
+    axum::middlewares::{HEADER_O2_PROCESS_TIME, get_process_time, insert_process_time_header},
+    get_config,
+};
 
 #[cfg(feature = "cloud")]
 use crate::service::ingestion::check_ingestion_allowed;
 use crate::{
     common::{
-        meta::{http::HttpResponse as MetaHttpResponse, ingestion::IngestUser},
+        meta::{
+            http::HttpResponse as MetaHttpResponse,
+            ingestion::{IngestUser, IngestionResponse},
+        },
         utils::auth::UserEmail,
     },
     handler::http::{
         extractors::Headers,
         request::{CONTENT_TYPE_JSON, CONTENT_TYPE_PROTO},
+        router::decompression::X_ORIGINAL_ENCODING,
+    },
+    service::metrics::{
+        self,
+        idempotency::{self, IDEMPOTENCY_KEY_HEADER},
     },
-    service::metrics,
 };
 
 /// _json ingestion API
@@ -49,12 +62,14 @@ use crate::{
     summary = "Ingest metrics via JSON",
     description = "Ingests metrics data using JSON format. Accepts an array of metric objects containing metric name, type \
                    (counter, gauge, histogram, or summary), labels, timestamp, and value. This endpoint is ideal for custom \
-                   applications and systems that generate metrics in JSON format rather than protocol buffers.",
+                   applications and systems that generate metrics in JSON format rather than protocol buffers. A request \
+                   with the same X-Idempotency-Key header as an earlier one within the idempotency ttl is ignored.",
     security(
         ("Authorization"= [])
     ),
     params(
         ("org_id" = String, Path, description = "Organization name"),
+        ("X-Idempotency-Key" = Option<String>, Header, description = "Ignore the request if a request with the same key was already ingested"),
     ),
     extensions(
         ("x-o2-mcp" = json!({"enabled": false}))
@@ -68,6 +83,7 @@ use crate::{
 pub async fn json(
     Path(org_id): Path<String>,
     Headers(user_email): Headers<UserEmail>,
+    headers: HeaderMap,
     body: Bytes,
 ) -> Response {
     // log start processing time
@@ -80,7 +96,120 @@ pub async fn json(
         return MetaHttpResponse::too_many_requests(e);
     }
 
-    let mut resp = match metrics::json::ingest(&org_id, None, body, user).await {
+    let body = match decompress_body(&headers, body, get_config().limit.req_payload_limit) {
+        Ok(v) => v,
+        Err(e) => return e.into_response(),
+    };
+
+    // a retried batch with the same idempotency key is ignored, the requests without the key are
+    // always ingested
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    let mut reserved = false;
+    if let Some(key) = idempotency_key {
+        match idempotency::reserve(&org_id, key).await {
+            Ok(true) => reserved = true,
+            Ok(false) => {
+                let mut resp = MetaHttpResponse::ok("duplicate, ignored");
+                insert_process_time_header(process_time, resp.headers_mut());
+                return resp;
+            }
+            Err(e) => {
+                log::warn!("Error checking idempotency key of {org_id}/metrics/_json: {e}");
+            }
+        }
+    }
+
+    let ret = metrics::json::ingest(&org_id, None, body, user).await;
+    // the failed batch can be retried with the same key
+    if let Some(key) = idempotency_key.filter(|_| reserved)
+        && !ret
+            .as_ref()
+            .is_ok_and(|v| v.code == StatusCode::OK.as_u16())
+        && let Err(e) = idempotency::release(&org_id, key).await
+    {
+        log::error!("Error releasing idempotency key of {org_id}/metrics/_json: {e}");
+    }
+    let mut resp = json_ingestion_response(&org_id, "_json", ret);
+
+    insert_process_time_header(process_time, resp.headers_mut());
+    resp
+}
+
+/// _multi ingestion API
+#[utoipa::path(
+    post,
+    path = "/{org_id}/ingest/metrics/_multi",
+    context_path = "/api",
+    tag = "Metrics",
+    operation_id = "MetricsIngestionMulti",
+    summary = "Ingest metrics via multi-line JSON",
+    description = "Ingests metrics data using multi-line JSON format where each line contains a separate metric object \
+                   with the same fields as the _json endpoint. The lines are ingested in batches, so clients can push \
+                   metrics without building one large array. Malformed lines are counted as failed with their line \
+                   number instead of failing the request. If a batch is rejected by the ingestion limits, the rest of \
+                   the body is not ingested and the response keeps the status of the lines ingested before.",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    extensions(
+        ("x-o2-mcp" = json!({"enabled": false}))
+    ),
+    request_body(content = String, description = "Ingest data (multiple line json)", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = Object, example = json!({"code": 200,"status": [{"name": "up","successful": 3,"failed": 0}, {"name": "_failed_lines","successful": 0,"failed": 1,"error": "line 2: expected value at line 1 column 1"}]})),
+        (status = 500, description = "Failure", content_type = "application/json", body = ()),
+    )
+)]
+pub async fn multi(
+    Path(org_id): Path<String>,
+    Headers(user_email): Headers<UserEmail>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    // log start processing time
+    let process_time = get_process_time();
+
+    let user = IngestUser::from_user_email(&user_email.user_id);
+
+    #[cfg(feature = "cloud")]
+    if let Err(e) = check_ingestion_allowed(&org_id, StreamType::Metrics, None).await {
+        return MetaHttpResponse::too_many_requests(e);
+    }
+
+    // the lines are read as the body arrives, so the body can only be decompressed by the router
+    if let Some(encoding) = body_encoding(&headers) {
+        return DecompressError::Invalid(format!(
+            "Unsupported content encoding: {encoding}, only gzip and zstd are supported"
+        ))
+        .into_response();
+    }
+
+    let ret = metrics::json::ingest_multi(
+        &org_id,
+        body.into_data_stream(),
+        get_config().limit.req_payload_limit,
+        user,
+    )
+    .await;
+    let mut resp = json_ingestion_response(&org_id, "_multi", ret);
+
+    insert_process_time_header(process_time, resp.headers_mut());
+    resp
+}
+
+fn json_ingestion_response(
+    org_id: &str,
+    endpoint: &str,
+    ret: anyhow::Result<IngestionResponse>,
+) -> Response {
+    match ret {
         Ok(v) => {
             if v.code == StatusCode::OK.as_u16() {
                 MetaHttpResponse::json(v)
@@ -93,13 +222,10 @@ pub async fn json(
             }
         }
         Err(e) => {
-            log::error!("Error processing request {org_id}/metrics/_json: {e}");
+            log::error!("Error processing request {org_id}/metrics/{endpoint}: {e}");
             MetaHttpResponse::bad_request(e)
         }
-    };
-
-    insert_process_time_header(process_time, resp.headers_mut());
-    resp
+    }
 }
 
 /// MetricsIngest
@@ -139,10 +265,30 @@ pub async fn otlp_metrics_write(
         return MetaHttpResponse::too_many_requests(e);
     }
 
+    let body = match decompress_body(&headers, body, get_config().limit.req_payload_limit) {
+        Ok(v) => v,
+        Err(e) => return e.into_response(),
+    };
+
     let content_type = headers
         .get("Content-Type")
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
+    // some exporters omit or mislabel the content type, detect it from the payload then
+    let content_type = if content_type.eq(CONTENT_TYPE_PROTO)
+        || content_type.starts_with(CONTENT_TYPE_JSON)
+    {
+        content_type
+    } else {
+        match sniff_otlp_content_type(&body) {
+            Some(v) => v,
+            None => {
+                return MetaHttpResponse::bad_request(format!(
+                    "Unsupported content type '{content_type}' and the payload is neither an OTLP protobuf nor a JSON object"
+                ));
+            }
+        }
+    };
 
     let resp = if content_type.eq(CONTENT_TYPE_PROTO) {
         match metrics::otlp::otlp_proto(&org_id, body, user).await {
@@ -169,3 +315,186 @@ pub async fn otlp_metrics_write(
         resp
     }
 }
+
+/// Detects the content type of an OTLP payload from its first bytes. A JSON payload starts with
+/// `{`, a protobuf `ExportMetricsServiceRequest` starts with a field tag, and `{` is never a valid
+/// tag as its wire type is the deprecated start group.
+fn sniff_otlp_content_type(body: &[u8]) -> Option<&'static str> {
+    if body.trim_ascii_start().first() == Some(&b'{') {
+        return Some(CONTENT_TYPE_JSON);
+    }
+    match body.first() {
+        // an empty request is a valid protobuf message
+        None => Some(CONTENT_TYPE_PROTO),
+        // field number > 0 with the wire type varint, i64, length delimited or i32
+        Some(tag) if tag >> 3 > 0 && matches!(tag & 0x07, 0 | 1 | 2 | 5) => {
+            Some(CONTENT_TYPE_PROTO)
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+enum DecompressError {
+    /// the decompressed body is larger than the payload limit
+    TooLarge(usize),
+    Invalid(String),
+}
+
+impl Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(limit) => write!(
+                f,
+                "Decompressed request body exceeds the payload limit of {limit} bytes"
+            ),
+            Self::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl IntoResponse for DecompressError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::TooLarge(_) => MetaHttpResponse::payload_too_large(self),
+            Self::Invalid(e) => MetaHttpResponse::bad_request(e),
+        }
+    }
+}
+
+/// Decompresses the gzip or zstd request body. Both are usually decompressed by the router
+/// already and then the header is gone, snappy is moved to `X-Original-Content-Encoding` by the
+/// router and rejected here.
+///
+/// The compressed body is limited by the router, the decompressed one is stopped at `limit`
+/// so a small compressed body can't expand without bound.
+fn decompress_body(
+    headers: &HeaderMap,
+    body: Bytes,
+    limit: usize,
+) -> Result<Bytes, DecompressError> {
+    match body_encoding(headers).as_deref() {
+        None => Ok(body),
+        Some("gzip") => read_limited(flate2::read::GzDecoder::new(body.as_ref()), "gzip", limit),
+        Some("zstd") => {
+            let decoder = zstd::stream::read::Decoder::new(body.as_ref()).map_err(|e| {
+                DecompressError::Invalid(format!("Failed to decompress zstd request body: {e}"))
+            })?;
+            read_limited(decoder, "zstd", limit)
+        }
+        Some(encoding) => Err(DecompressError::Invalid(format!(
+            "Unsupported content encoding: {encoding}, only gzip and zstd are supported"
+        ))),
+    }
+}
+
+/// The encoding the request body is still compressed with, if any
+fn body_encoding(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::CONTENT_ENCODING)
+        .or_else(|| headers.get(X_ORIGINAL_ENCODING))
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty() && v != "identity")
+}
+
+fn read_limited(reader: impl Read, encoding: &str, limit: usize) -> Result<Bytes, DecompressError> {
+    let mut decompressed = Vec::new();
+    reader
+        .take((limit as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|e| {
+            DecompressError::Invalid(format!("Failed to decompress {encoding} request body: {e}"))
+        })?;
+    if decompressed.len() > limit {
+        return Err(DecompressError::TooLarge(limit));
+    }
+    Ok(decompressed.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const DATA: &str =
+        r#"[{"__name__":"up","__type__":"gauge","_timestamp":1687175143,"value":1}]"#;
+
+    fn headers(name: &'static str, encoding: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_static(encoding));
+        headers
+    }
+
+    #[test]
+    fn test_decompress_body() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(DATA.as_bytes()).unwrap();
+        let gzip = Bytes::from(encoder.finish().unwrap());
+        let body = decompress_body(&headers("content-encoding", "gzip"), gzip, DATA.len()).unwrap();
+        assert_eq!(body, DATA.as_bytes());
+
+        let zstd = Bytes::from(zstd::encode_all(DATA.as_bytes(), 3).unwrap());
+        let body = decompress_body(&headers("content-encoding", "zstd"), zstd, DATA.len()).unwrap();
+        assert_eq!(body, DATA.as_bytes());
+
+        let body = decompress_body(&HeaderMap::new(), Bytes::from(DATA), DATA.len()).unwrap();
+        assert_eq!(body, DATA.as_bytes());
+    }
+
+    #[test]
+    fn test_decompress_body_too_large() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(DATA.as_bytes()).unwrap();
+        let gzip = Bytes::from(encoder.finish().unwrap());
+        let err = decompress_body(&headers("content-encoding", "gzip"), gzip, DATA.len() - 1);
+        assert!(matches!(err, Err(DecompressError::TooLarge(_))));
+
+        let zstd = Bytes::from(zstd::encode_all(DATA.as_bytes(), 3).unwrap());
+        let err = decompress_body(&headers("content-encoding", "zstd"), zstd, 10);
+        let resp = err.unwrap_err().into_response();
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_sniff_otlp_content_type() {
+        assert_eq!(
+            sniff_otlp_content_type(br#" {"resourceMetrics":[]}"#),
+            Some(CONTENT_TYPE_JSON)
+        );
+        // ExportMetricsServiceRequest { resource_metrics: [ResourceMetrics::default()] }
+        assert_eq!(
+            sniff_otlp_content_type(&[0x0a, 0x00]),
+            Some(CONTENT_TYPE_PROTO)
+        );
+        assert_eq!(sniff_otlp_content_type(b""), Some(CONTENT_TYPE_PROTO));
+        assert_eq!(sniff_otlp_content_type(b"[1, 2]"), None);
+        assert_eq!(sniff_otlp_content_type(&[0x07]), None);
+    }
+
+    #[test]
+    fn test_decompress_body_error() {
+        let limit = usize::MAX;
+        let err = decompress_body(
+            &headers("content-encoding", "gzip"),
+            Bytes::from(DATA),
+            limit,
+        );
+        assert!(err.unwrap_err().to_string().contains("gzip"));
+        let err = decompress_body(
+            &headers("content-encoding", "zstd"),
+            Bytes::from(DATA),
+            limit,
+        );
+        assert!(err.unwrap_err().to_string().contains("zstd"));
+        let err = decompress_body(
+            &headers(X_ORIGINAL_ENCODING, "snappy"),
+            Bytes::from(DATA),
+            limit,
+        );
+        assert!(err.unwrap_err().to_string().contains("Unsupported"));
+    }
+}
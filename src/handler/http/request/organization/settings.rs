@@ -134,6 +134,11 @@ pub async fn create(
         data.max_series_per_query = Some(max_series_per_query);
     }
 
+    if let Some(rum_rate_limit) = settings.rum_rate_limit {
+        field_found = true;
+        data.rum_rate_limit = (rum_rate_limit > 0).then_some(rum_rate_limit);
+    }
+
     #[cfg(feature = "enterprise")]
     if let Some(claim_parser_function) = settings.claim_parser_function {
         field_found = true;
@@ -1010,6 +1010,8 @@ pub async fn build_search_request_per_field(
         use_cache: req.use_cache,
         clear_cache: req.clear_cache,
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     let distinct_prefix = if can_use_distinct_stream {
@@ -1226,6 +1228,8 @@ async fn values_v1(
         use_cache: default_use_cache(),
         clear_cache: get_clear_cache_from_request(query),
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     req.use_cache = get_use_cache_from_request(query);
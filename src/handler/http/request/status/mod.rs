@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 use arrow_schema::Schema;
 use axum::{
@@ -44,6 +44,7 @@ use infra::{
     schema::{STREAM_SCHEMAS, STREAM_SCHEMAS_LATEST},
 };
 use serde::Serialize;
+use strum::IntoEnumIterator;
 use time;
 use utoipa::ToSchema;
 #[cfg(feature = "enterprise")]
@@ -70,9 +71,12 @@ use {
 };
 
 use crate::{
-    common::meta::{
-        http::HttpResponse as MetaHttpResponse,
-        user::{AuthTokens, AuthTokensExt},
+    common::{
+        infra::config::{CacheKind, refresh_cache},
+        meta::{
+            http::HttpResponse as MetaHttpResponse,
+            user::{AuthTokens, AuthTokensExt},
+        },
     },
     service::{
         db,
@@ -1261,40 +1265,11 @@ pub async fn refresh_user_sessions() -> Response {
     MetaHttpResponse::json("user sessions refreshed")
 }
 
-// List of all available cache modules
-const CACHE_MODULES: &[&str] = &[
-    "schema",
-    "organization",
-    "user",
-    "session",
-    "functions",
-    "pipeline",
-    "alerts",
-    "destinations",
-    "templates",
-    "short_url",
-    "realtime_triggers",
-    "org_users",
-    "compact_retention",
-];
-
 // Helper function to reload cache for a specific module
 async fn reload_module_cache(module: &str) -> Result<(), anyhow::Error> {
-    match module {
-        "schema" => db::schema::cache().await,
-        "organization" => db::organization::cache().await,
-        "user" => db::user::cache().await,
-        "session" => db::session::cache().await,
-        "functions" => db::functions::cache().await,
-        "pipeline" => db::pipeline::cache().await,
-        "alerts" => db::alerts::alert::cache().await,
-        "destinations" => db::alerts::destinations::cache().await,
-        "templates" => db::alerts::templates::cache().await,
-        "short_url" => db::short_url::cache().await,
-        "realtime_triggers" => db::alerts::realtime_triggers::cache().await,
-        "org_users" => db::org_users::cache().await,
-        "compact_retention" => db::compact::retention::cache().await,
-        _ => Err(anyhow::anyhow!("unsupported module")),
+    match CacheKind::from_str(module) {
+        Ok(kind) => refresh_cache(kind).await,
+        Err(_) => Err(anyhow::anyhow!("unsupported module")),
     }
 }
 
@@ -1310,14 +1285,17 @@ pub async fn cache_reload(
         }
     };
 
-    let mut modules: Vec<&str> = modules_str.split(',').map(|s| s.trim()).collect();
+    let mut modules: Vec<String> = modules_str
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .collect();
     if modules.is_empty() {
         return MetaHttpResponse::bad_request("module parameter cannot be empty");
     }
 
     // Expand "all" to all available modules
-    if modules.contains(&"all") {
-        modules = CACHE_MODULES.to_vec();
+    if modules.iter().any(|m| m == "all") {
+        modules = CacheKind::iter().map(|kind| kind.to_string()).collect();
     }
 
     let total_modules = modules.len();
@@ -1325,7 +1303,7 @@ pub async fn cache_reload(
     let mut success_count = 0;
     let mut failed_count = 0;
 
-    for module in modules {
+    for module in modules.iter().map(String::as_str) {
         match reload_module_cache(module).await {
             Ok(_) => {
                 results.insert(module.to_string(), "success".to_string());
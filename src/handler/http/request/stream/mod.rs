@@ -22,7 +22,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use config::{
-    meta::stream::{StreamType, TimeRange, UpdateStreamSettings},
+    meta::stream::{PartitionTimeLevel, StreamType, TimeRange, UpdateStreamSettings},
     utils::schema::format_stream_name,
 };
 use hashbrown::HashMap;
@@ -827,6 +827,95 @@ pub async fn get_delete_stream_data_status(
     (StatusCode::OK, Json(response)).into_response()
 }
 
+/// StreamCheckIndexFiles
+
+#[utoipa::path(
+    get,
+    path = "/{org_id}/streams/{stream_name}/index_files/check",
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamCheckIndexFiles",
+    summary = "Check stream index files",
+    description = "Checks that the index file of every file flagged as indexed within the time range can be opened and lists the files where it can't. Nothing is changed",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("type" = String, Query, description = "Stream type"),
+        ("start" = i64, Query, description = "Start timestamp in microseconds"),
+        ("end" = i64, Query, description = "End timestamp in microseconds"),
+    ),
+    responses(
+        (status = 200, description = "Success", content_type = "application/json", body = Object),
+        (status = 400, description = "Failure", content_type = "application/json", body = ()),
+    ),
+    extensions(
+        ("x-o2-ratelimit" = json!({"module": "Streams", "operation": "get"})),
+        ("x-o2-mcp" = json!({"enabled": false}))
+    )
+)]
+pub async fn check_index_files(
+    Path((org_id, stream_name)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let mut stream_name = stream_name;
+    if !config::get_config().common.skip_formatting_stream_name {
+        stream_name = format_stream_name(stream_name);
+    }
+    let stream_type = get_stream_type_from_request(&query).unwrap_or_default();
+    let (start, end) = match (
+        get_ts_from_request_with_key(&query, "start"),
+        get_ts_from_request_with_key(&query, "end"),
+    ) {
+        (Ok(start), Ok(end)) => (start, end),
+        (Err(e), _) | (_, Err(e)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(MetaHttpResponse::error(StatusCode::BAD_REQUEST, e)),
+            )
+                .into_response();
+        }
+    };
+
+    let trace_id = config::ider::generate_trace_id();
+    let files = match crate::service::file_list::query(
+        &trace_id,
+        &org_id,
+        stream_type,
+        &stream_name,
+        PartitionTimeLevel::default(),
+        start,
+        end,
+    )
+    .await
+    {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!(
+                "check_index_files {org_id}/{stream_type}/{stream_name} query file list error: {e}",
+            );
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(MetaHttpResponse::error(
+                    StatusCode::BAD_REQUEST,
+                    e.to_string(),
+                )),
+            )
+                .into_response();
+        }
+    };
+    let mismatches =
+        crate::service::search::grpc::storage::check_tantivy_index_files(&trace_id, &files).await;
+
+    let res = serde_json::json!({
+        "checked": files.iter().filter(|f| f.meta.index_size > 0).count(),
+        "mismatches": mismatches,
+    });
+    (StatusCode::OK, Json(res)).into_response()
+}
+
 async fn get_local_delete_status(id: &str) -> CompactorManualJobStatusRes {
     let job = match crate::service::db::compact::compactor_manual_jobs::get_job(id).await {
         Ok(job) => job,
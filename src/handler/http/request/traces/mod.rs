@@ -339,6 +339,8 @@ pub async fn get_latest_traces(
         use_cache: default_use_cache(),
         clear_cache: false,
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     req.use_cache = get_use_cache_from_request(&query);
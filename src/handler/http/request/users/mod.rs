@@ -30,7 +30,7 @@ use config::{
 use serde::Serialize;
 #[cfg(feature = "enterprise")]
 use {
-    crate::common::utils::auth::check_permissions,
+    crate::common::utils::auth::{PresignedUrlParams, check_permissions},
     crate::service::self_reporting::audit,
     config::utils::time::now_micros,
     o2_dex::config::get_config as get_dex_config,
@@ -50,7 +50,7 @@ use crate::{
                 UserOrgRole, UserRequest, UserRoleRequest, UserUpdateMode, get_roles,
             },
         },
-        utils::auth::{UserEmail, generate_presigned_url, is_valid_email},
+        utils::auth::{PresignedUrlMode, UserEmail, generate_presigned_url, is_valid_email},
     },
     handler::http::{
         extractors::Headers,
@@ -631,6 +631,9 @@ pub async fn authentication(
 pub struct PresignedURLGenerator {
     #[serde(default = "default_exp_in")]
     exp_in: u32,
+    /// hmac links are cheaper to verify, they aren't interchangeable with argon2 links
+    #[serde(default)]
+    mode: PresignedUrlMode,
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -688,6 +691,7 @@ pub async fn get_presigned_url(
         &base_url,
         params.exp_in as i64,
         time,
+        params.mode,
     );
 
     let payload = PresignedURLGeneratorResponse { url };
@@ -734,7 +738,7 @@ pub async fn get_auth(
 
         let mut resp = SignInResponse::default();
 
-        let mut request_time = None;
+        let mut presigned = None;
         let mut expires_in = 300;
         let mut req_ts = 0;
 
@@ -756,37 +760,21 @@ pub async fn get_auth(
         };
 
         let (name, password) = {
-            let auth_header = if let Some(s) = query.get("auth") {
-                match query.get("request_time") {
-                    Some(req_time_str) => {
-                        if let Ok(ts) = config::utils::time::parse_str_to_time(req_time_str) {
-                            req_ts = ts.timestamp();
-                        } else {
-                            audit_unauthorized_error(audit_message).await;
-                            return unauthorized_error(resp);
-                        }
-                        request_time = Some(req_time_str);
-                    }
-                    None => {
-                        audit_unauthorized_error(audit_message).await;
-                        return unauthorized_error(resp);
-                    }
-                };
-
-                match query.get("exp_in") {
-                    Some(exp_in_str) => {
-                        expires_in = exp_in_str.parse::<i64>().unwrap();
-                    }
-                    None => {
-                        audit_unauthorized_error(audit_message).await;
-                        return unauthorized_error(resp);
-                    }
+            let auth_header = if query.contains_key("auth") {
+                // a malformed request time or expiration is rejected like a wrong signature
+                let Some(params) = PresignedUrlParams::from_query(&query) else {
+                    audit_unauthorized_error(audit_message).await;
+                    return unauthorized_error(resp);
                 };
+                req_ts = params.request_time;
+                expires_in = params.exp_in;
                 if chrono::Utc::now().timestamp() - req_ts > expires_in {
                     audit_unauthorized_error(audit_message).await;
                     return unauthorized_error(resp);
                 }
-                format!("q_auth {s}")
+                let auth_header = format!("q_auth {}", params.auth);
+                presigned = Some(params);
+                auth_header
             } else if let Some(auth_header) = headers.get(header::AUTHORIZATION) {
                 match auth_header.to_str() {
                     Ok(auth_header_str) => auth_header_str.to_string(),
@@ -802,17 +790,14 @@ pub async fn get_auth(
 
             use o2_dex::service::auth::get_user_from_token;
 
-            use crate::handler::http::auth::validator::{
-                validate_user, validate_user_for_query_params,
-            };
+            use crate::handler::http::auth::validator::{validate_presigned_url, validate_user};
 
             let (name, password) = if let Some((name, password)) = get_user_from_token(&auth_header)
             {
-                let token_validation_response = match request_time {
-                    Some(req_ts) => {
+                let token_validation_response = match &presigned {
+                    Some(params) => {
                         log::debug!("Validating user for query params");
-                        validate_user_for_query_params(&name, &password, Some(req_ts), expires_in)
-                            .await
+                        validate_presigned_url(&name, params).await
                     }
                     None => {
                         log::debug!("Validating user for basic auth header");
@@ -876,6 +861,7 @@ pub async fn get_auth(
                     refresh_token: "".to_string(),
                     request_time: req_ts,
                     expires_in,
+                    mode: presigned.map(|params| params.mode).unwrap_or_default(),
                 };
 
                 log::debug!("Setting cookie for user: {name} - {cookie_name}");
@@ -1122,6 +1108,7 @@ mod tests {
     fn test_presigned_url_generator_default() {
         let generator = PresignedURLGenerator {
             exp_in: default_exp_in(),
+            mode: PresignedUrlMode::default(),
         };
         assert_eq!(generator.exp_in, 600);
     }
@@ -525,6 +525,7 @@ pub fn service_routes() -> Router {
         .route("/{org_id}/streams/{stream_name}/cache/results", delete(stream::delete_stream_cache))
         .route("/{org_id}/streams/{stream_name}/data_by_time_range", delete(stream::delete_stream_data_by_time_range))
         .route("/{org_id}/streams/{stream_name}/data_by_time_range/status/{id}", get(stream::get_delete_stream_data_status))
+        .route("/{org_id}/streams/{stream_name}/index_files/check", get(stream::check_index_files))
 
         // Logs ingestion
         .route("/{org_id}/_bulk", post(logs::ingest::bulk))
@@ -545,6 +546,7 @@ pub fn service_routes() -> Router {
 
         // Metrics
         .route("/{org_id}/ingest/metrics/_json", post(metrics::ingest::json))
+        .route("/{org_id}/ingest/metrics/_multi", post(metrics::ingest::multi))
 
         // PromQL
         .route("/{org_id}/prometheus/api/v1/write", post(promql::remote_write))
@@ -832,7 +834,7 @@ pub fn service_routes() -> Router {
     // -> audit -> blocked orgs NOTE: Preprocessing middleware removes Content-Encoding: snappy
     // header before tower_http sees it. This prevents 415 errors while allowing handlers to
     // manually decompress snappy data. tower_http's RequestDecompressionLayer handles gzip,
-    // deflate, and brotli.
+    // deflate, brotli and zstd.
     router
         .layer(middleware::from_fn(blocked_orgs_middleware))
         .layer(middleware::from_fn(audit_middleware))
@@ -60,6 +60,7 @@ use crate::{common::meta, handler::http::request};
         request::traces::traces_write,
         request::traces::get_latest_traces,
         request::metrics::ingest::json,
+        request::metrics::ingest::multi,
         request::promql::remote_write,
         request::promql::query_get,
         request::promql::query_range_get,
@@ -242,6 +243,8 @@ use crate::{common::meta, handler::http::request};
             config::meta::stream::StreamStats,
             config::meta::stream::PartitionTimeLevel,
             config::meta::stream::UpdateStreamSettings,
+            config::meta::stream::StreamCachePolicy,
+            config::meta::stream::StreamCacheType,
             config::meta::dashboards::Dashboard,
             config::meta::dashboards::v1::AxisItem,
             config::meta::dashboards::v1::Dashboard,
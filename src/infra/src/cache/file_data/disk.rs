@@ -19,7 +19,7 @@ use std::{
     ops::Range,
     path::{Path, PathBuf},
     sync::atomic::{AtomicBool, AtomicUsize, Ordering},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
 use async_recursion::async_recursion;
@@ -32,10 +32,11 @@ use config::{
         time::{get_ymdh_from_micros, now_micros},
     },
 };
-use hashbrown::HashMap;
+use futures::StreamExt;
+use hashbrown::{HashMap, HashSet};
 use object_store::{GetOptions, GetResult, GetResultPayload, ObjectMeta};
 use once_cell::sync::Lazy;
-use tokio::sync::RwLock;
+use tokio::{io::AsyncWriteExt, sync::RwLock};
 
 use super::CacheStrategy;
 use crate::{cache::meta::ResultCacheMeta, storage};
@@ -106,6 +107,12 @@ pub static METRICS_RESULT_CACHE: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock
 
 pub static LOADING_FROM_DISK_NUM: Lazy<AtomicUsize> = Lazy::new(|| AtomicUsize::new(0));
 pub static LOADING_FROM_DISK_DONE: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(false));
+static DOWNLOADING_PART_FILES: Lazy<parking_lot::Mutex<HashSet<String>>> =
+    Lazy::new(|| parking_lot::Mutex::new(HashSet::new()));
+
+/// The `.part` files not written for this long are removed by the gc, their downloads are not
+/// resumed anymore
+const PART_FILE_TTL: Duration = Duration::from_secs(24 * 3600);
 
 pub struct FileData {
     max_size: usize,
@@ -607,10 +614,21 @@ pub async fn set(file: &str, data: Bytes) -> Result<(), anyhow::Error> {
     // write to tmp file
     let data_size = data.len();
     let (file, tmp_file) = write_tmp_file(file, data).await?;
+    set_tmp_file(&file, &tmp_file, data_size).await
+}
+
+/// Moves the written tmp file into the cache
+async fn set_tmp_file(file: &str, tmp_file: &str, data_size: usize) -> Result<(), anyhow::Error> {
+    if !get_config().disk_cache.enabled {
+        if let Err(e) = tokio::fs::remove_file(tmp_file).await {
+            log::warn!("disk->cache: remove tmp file {tmp_file} error: {e}");
+        }
+        return Ok(());
+    }
 
     // hash the file name and get the bucket index
     let start = std::time::Instant::now();
-    let idx = get_bucket_idx(&file);
+    let idx = get_bucket_idx(file);
 
     // get all the files from the bucket
     let mut files = if file.starts_with("files") {
@@ -628,9 +646,9 @@ pub async fn set(file: &str, data: Bytes) -> Result<(), anyhow::Error> {
         log::info!("disk->cache: set file {file} get lock took: {get_lock_took} ms");
     }
 
-    if files.exist(&file).await {
+    if files.exist(file).await {
         // remove the tmp file
-        if let Err(e) = tokio::fs::remove_file(&tmp_file).await {
+        if let Err(e) = tokio::fs::remove_file(tmp_file).await {
             log::warn!(
                 "[CacheType:{}] File disk cache remove tmp file {} error: {}",
                 files.file_type,
@@ -640,7 +658,7 @@ pub async fn set(file: &str, data: Bytes) -> Result<(), anyhow::Error> {
         }
         return Ok(());
     }
-    let ret = files.set(&file, &tmp_file, data_size).await;
+    let ret = files.set(file, tmp_file, data_size).await;
 
     let set_took = start.elapsed().as_millis() as usize;
     if set_took > 100 {
@@ -900,6 +918,54 @@ async fn gc() -> Result<(), anyhow::Error> {
         w.gc(cfg.disk_cache.gc_size).await?;
         drop(w);
     }
+
+    gc_part_files(PART_FILE_TTL).await
+}
+
+/// Removes the `.part` files which are not being downloaded and not written for `ttl`, they are
+/// left by the downloads interrupted and never requested again
+async fn gc_part_files(ttl: Duration) -> Result<(), anyhow::Error> {
+    let part_dir = PathBuf::from(get_part_dir());
+    if !part_dir.exists() {
+        return Ok(());
+    }
+    let downloading = DOWNLOADING_PART_FILES
+        .lock()
+        .iter()
+        .map(|file| get_part_file_path(file))
+        .collect::<HashSet<_>>();
+    remove_stale_part_files(&part_dir, &downloading, ttl).await
+}
+
+#[async_recursion]
+async fn remove_stale_part_files(
+    dir: &PathBuf,
+    downloading: &HashSet<String>,
+    ttl: Duration,
+) -> Result<(), anyhow::Error> {
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let meta = entry.metadata().await?;
+        if meta.is_dir() {
+            remove_stale_part_files(&path, downloading, ttl).await?;
+            continue;
+        }
+        if path.to_str().is_some_and(|v| downloading.contains(v)) {
+            continue;
+        }
+        let is_stale = meta
+            .modified()
+            .ok()
+            .and_then(|v| v.elapsed().ok())
+            .is_some_and(|age| age >= ttl);
+        if is_stale {
+            log::info!("disk->cache: removing stale part file {}", path.display());
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                log::warn!("Failed to remove part file: {}, error: {e}", path.display());
+            }
+        }
+    }
     Ok(())
 }
 
@@ -963,6 +1029,26 @@ pub async fn download(
     file: &str,
     size: Option<usize>,
 ) -> Result<usize, anyhow::Error> {
+    if let Some(size) = size
+        && size > 0
+        && get_config().disk_cache.enabled
+        && DOWNLOADING_PART_FILES.lock().insert(file.to_string())
+    {
+        let ret = download_resumable(account, file, size).await;
+        DOWNLOADING_PART_FILES.lock().remove(file);
+        match ret {
+            Ok(part_file) => {
+                set_part_file(file, &part_file, size)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("set file {file} to disk cache failed: {e}"))?;
+                return Ok(size);
+            }
+            Err(e) => {
+                log::warn!("download file {file} by range failed, will download whole file: {e}");
+            }
+        }
+    }
+
     let (data_len, data_bytes) = super::download_from_storage(account, file, size).await?;
     if let Err(e) = set(file, data_bytes).await {
         return Err(anyhow::anyhow!(
@@ -974,6 +1060,81 @@ pub async fn download(
     Ok(data_len)
 }
 
+/// The `.part` files are kept next to the cache dir, the tmp dir is wiped on start so the
+/// downloads couldn't be resumed after a restart, and the files in the cache dir are loaded as
+/// cached files
+fn get_part_dir() -> String {
+    format!(
+        "{}_download",
+        get_config().common.data_cache_dir.trim_end_matches('/')
+    )
+}
+
+fn get_part_file_path(file: &str) -> String {
+    format!("{}/{file}.part", get_part_dir())
+}
+
+/// Moves the downloaded `.part` file into the cache, the file is only cached if its size matches
+/// the expected size
+async fn set_part_file(file: &str, part_file: &str, size: usize) -> Result<(), anyhow::Error> {
+    let part_size = tokio::fs::metadata(part_file).await?.len() as usize;
+    if part_size != size {
+        _ = tokio::fs::remove_file(part_file).await;
+        return Err(anyhow::anyhow!(
+            "file {file} downloaded size mismatch, expected: {size}, actual: {part_size}"
+        ));
+    }
+    set_tmp_file(file, part_file, size).await
+}
+
+/// Downloads the file into a `.part` file chunk by chunk, the `.part` file left by an
+/// interrupted download is resumed from its current length. Returns the `.part` file path.
+async fn download_resumable(
+    account: &str,
+    file: &str,
+    size: usize,
+) -> Result<String, anyhow::Error> {
+    // the size in the file list may be wrong, the whole file download will correct it
+    let meta = storage::head(account, file).await?;
+    if meta.size as usize != size {
+        return Err(anyhow::anyhow!(
+            "file {file} size mismatch with blob store, expected: {size}, remote: {}",
+            meta.size
+        ));
+    }
+
+    let part_file = get_part_file_path(file);
+    tokio::fs::create_dir_all(Path::new(&part_file).parent().unwrap()).await?;
+    let mut offset = match tokio::fs::metadata(&part_file).await {
+        Ok(meta) => meta.len() as usize,
+        Err(_) => 0,
+    };
+    if offset > size {
+        // the part file is longer than the file, it can't be resumed
+        tokio::fs::remove_file(&part_file).await?;
+        offset = 0;
+    }
+    if offset < size {
+        let options = GetOptions {
+            range: Some((offset as u64..size as u64).into()),
+            ..Default::default()
+        };
+        let mut stream = storage::get_opts(account, file, options)
+            .await?
+            .into_stream();
+        let mut writer = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&part_file)
+            .await?;
+        while let Some(chunk) = stream.next().await {
+            writer.write_all(&chunk?).await?;
+        }
+        writer.sync_all().await?;
+    }
+    Ok(part_file)
+}
+
 fn get_bucket_idx(file: &str) -> usize {
     let cfg = get_config();
     if cfg.disk_cache.bucket_num <= 1 {
@@ -1102,6 +1263,74 @@ async fn write_tmp_file(file: &str, data: Bytes) -> Result<(String, String), any
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_disk_download_resume_partial_file() {
+        let file = "files/default/logs/resume/2022/10/03/10/6982652937134804993_1.parquet";
+        let content = Bytes::from("0123456789abcdefghijklmnopqrstuvwxyz");
+        storage::put("", file, content.clone()).await.unwrap();
+
+        // a part file left by an interrupted download
+        let part_file = get_part_file_path(file);
+        std::fs::create_dir_all(Path::new(&part_file).parent().unwrap()).unwrap();
+        std::fs::write(&part_file, &content[..10]).unwrap();
+        let ret = download_resumable("", file, content.len()).await.unwrap();
+        assert_eq!(ret, part_file);
+        assert_eq!(std::fs::read(&part_file).unwrap(), content.to_vec());
+
+        // a corrupted part file longer than the file is re-fetched
+        std::fs::write(&part_file, [content.to_vec(), b"garbage".to_vec()].concat()).unwrap();
+        download_resumable("", file, content.len()).await.unwrap();
+        assert_eq!(std::fs::read(&part_file).unwrap(), content.to_vec());
+        std::fs::remove_file(&part_file).unwrap();
+
+        // the size mismatch with the blob store is not resumed
+        assert!(
+            download_resumable("", file, content.len() + 1)
+                .await
+                .is_err()
+        );
+        assert!(!Path::new(&part_file).exists());
+    }
+
+    #[tokio::test]
+    async fn test_disk_set_part_file_size_mismatch() {
+        let file = "files/default/logs/part_size/2022/10/03/10/6982652937134804993_1.parquet";
+        let part_file = get_part_file_path(file);
+        assert!(!part_file.starts_with(&get_config().common.data_tmp_dir));
+        std::fs::create_dir_all(Path::new(&part_file).parent().unwrap()).unwrap();
+        std::fs::write(&part_file, "0123456789").unwrap();
+
+        // a truncated part file is not cached
+        assert!(set_part_file(file, &part_file, 20).await.is_err());
+        assert!(!Path::new(&part_file).exists());
+    }
+
+    #[tokio::test]
+    async fn test_disk_gc_stale_part_files() {
+        let dir = "files/default/logs/part_gc/2022/10/03/10";
+        let stale_file = get_part_file_path(&format!("{dir}/6982652937134804993_1.parquet"));
+        let downloading_file = get_part_file_path(&format!("{dir}/6982652937134804993_2.parquet"));
+        std::fs::create_dir_all(Path::new(&stale_file).parent().unwrap()).unwrap();
+        std::fs::write(&stale_file, "0123456789").unwrap();
+        std::fs::write(&downloading_file, "0123456789").unwrap();
+
+        let downloading = [downloading_file.clone()]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let part_dir = PathBuf::from(get_part_dir()).join(dir);
+        remove_stale_part_files(&part_dir, &downloading, PART_FILE_TTL)
+            .await
+            .unwrap();
+        assert!(Path::new(&stale_file).exists());
+
+        remove_stale_part_files(&part_dir, &downloading, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(!Path::new(&stale_file).exists());
+        assert!(Path::new(&downloading_file).exists());
+        std::fs::remove_file(&downloading_file).unwrap();
+    }
+
     #[tokio::test]
     async fn test_disk_lru_cache_set_file() {
         let mut file_data = FileData::with_capacity_and_cache_strategy(FileType::Data, 1024, "lru");
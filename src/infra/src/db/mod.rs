@@ -13,11 +13,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::Arc;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use config::{get_config, meta::meta_store::MetaStore};
+use config::{
+    get_config,
+    meta::{meta_store::MetaStore, sql::OrderBy},
+};
 use hashbrown::HashMap;
 use sea_orm::{DatabaseConnection, SqlxMySqlConnector, SqlxPostgresConnector, SqlxSqliteConnector};
 use tokio::sync::{OnceCell, mpsc};
@@ -39,6 +48,10 @@ static SUPER_CLUSTER: OnceCell<Box<dyn Db>> = OnceCell::const_new();
 
 pub const SQLITE_STORE: &str = "sqlite";
 
+// every node writes its own key under it, see [`health_check`]
+const HEALTH_CHECK_KEY: &str = "/health/check";
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
 pub static ORM_CLIENT: OnceCell<DatabaseConnection> = OnceCell::const_new();
 pub static ORM_CLIENT_DDL: OnceCell<DatabaseConnection> = OnceCell::const_new();
 
@@ -163,6 +176,43 @@ pub async fn delete_from_db_coordinator(
         .await
 }
 
+/// Checks the meta store with a put, get and delete round trip of the health key of this node,
+/// the key is not watched. In cluster mode the cluster coordinator, e.g. NATS, is a different
+/// store and is checked the same way. It's cheap enough to run every few seconds for a readiness
+/// probe. Returns [`DbError::HealthCheckTimeout`] when a store is up but slow and
+/// [`DbError::HealthCheckFailed`] when a round trip fails.
+pub async fn health_check() -> Result<()> {
+    let db = get_db().await;
+    if get_config().common.local_mode {
+        return health_check_with_timeout(db.as_ref(), HEALTH_CHECK_TIMEOUT).await;
+    }
+    let coordinator = get_coordinator().await;
+    tokio::try_join!(
+        health_check_with_timeout(db.as_ref(), HEALTH_CHECK_TIMEOUT),
+        health_check_with_timeout(coordinator.as_ref(), HEALTH_CHECK_TIMEOUT),
+    )?;
+    Ok(())
+}
+
+async fn health_check_with_timeout(db: &dyn Db, timeout: Duration) -> Result<()> {
+    let key = format!("{HEALTH_CHECK_KEY}/{}", config::cluster::LOCAL_NODE.uuid);
+    let value = Bytes::from(config::utils::time::now_micros().to_string());
+    let round_trip = async {
+        db.put(&key, value.clone(), NO_NEED_WATCH, None).await?;
+        let stored = db.get(&key).await?;
+        db.delete(&key, false, NO_NEED_WATCH, None).await?;
+        if stored != value {
+            return Err(Error::Message(format!("read a different value of {key}")));
+        }
+        Ok(())
+    };
+    match tokio::time::timeout(timeout, round_trip).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(DbError::HealthCheckFailed(e.to_string()).into()),
+        Err(_) => Err(DbError::HealthCheckTimeout(timeout.as_millis() as u64).into()),
+    }
+}
+
 async fn init_super_cluster() -> Box<dyn Db> {
     if get_config().common.local_mode {
         panic!("super cluster is not supported in local mode");
@@ -184,11 +234,48 @@ pub async fn create_table() -> Result<()> {
 pub type UpdateFn = dyn FnOnce(Option<Bytes>) -> Result<Option<(Option<Bytes>, Option<(String, Bytes, Option<i64>)>)>>
     + Send;
 
+/// The outcome of [`Db::get_for_update`], tells an intentional no-op of the `update_fn` apart
+/// from a missing key and a created value from an updated one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateOutcome {
+    /// whether the key had a value before the update
+    pub existed: bool,
+    /// whether the values returned by the `update_fn` were written, `update_fn` returning
+    /// `Ok(None)` writes nothing
+    pub written: bool,
+}
+
+impl UpdateOutcome {
+    /// The `update_fn` returned `Ok(None)`, nothing was written
+    pub fn unchanged(existed: bool) -> Self {
+        Self {
+            existed,
+            written: false,
+        }
+    }
+
+    /// Whether the update wrote a value for a key which didn't exist
+    pub fn created(&self) -> bool {
+        !self.existed && self.written
+    }
+}
+
 #[async_trait]
 pub trait Db: Sync + Send + 'static {
     async fn create_table(&self) -> Result<()>;
     async fn stats(&self) -> Result<Stats>;
     async fn get(&self, key: &str) -> Result<Bytes>;
+
+    /// Returns whether `key` exists, the backends override it to check without reading the
+    /// value.
+    async fn exists(&self, key: &str) -> Result<bool> {
+        match self.get(key).await {
+            Ok(_) => Ok(true),
+            Err(Error::DbError(DbError::KeyNotExists(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     async fn put(
         &self,
         key: &str,
@@ -196,13 +283,21 @@ pub trait Db: Sync + Send + 'static {
         need_watch: bool,
         start_dt: Option<i64>,
     ) -> Result<()>;
+    /// Puts all the items of `(key, value, start_dt)` and sends a watch event for each key.
+    async fn put_batch(&self, items: Vec<(String, Bytes, Option<i64>)>) -> Result<()> {
+        for (key, value, start_dt) in items {
+            self.put(&key, value, true, start_dt).await?;
+        }
+        Ok(())
+    }
+
     async fn get_for_update(
         &self,
         key: &str,
         need_watch: bool,
         start_dt: Option<i64>,
         update_fn: Box<UpdateFn>,
-    ) -> Result<()>;
+    ) -> Result<UpdateOutcome>;
     async fn delete(
         &self,
         key: &str,
@@ -211,6 +306,11 @@ pub trait Db: Sync + Send + 'static {
         start_dt: Option<i64>,
     ) -> Result<()>;
 
+    /// Deletes all the entries of the org, i.e. the entries whose key1 is the org_id, in all
+    /// the modules. The delete events are sent so the caches of the org are invalidated.
+    /// Returns the number of deleted entries.
+    async fn delete_org(&self, org_id: &str) -> Result<u64>;
+
     /// Contrary to `delete`, this call won't fail if `key` is missing.
     async fn delete_if_exists(&self, key: &str, with_prefix: bool, need_watch: bool) -> Result<()> {
         match self.delete(key, with_prefix, need_watch, None).await {
@@ -219,18 +319,74 @@ pub trait Db: Sync + Send + 'static {
         }
     }
 
+    /// Adds `delta` to the numeric value of `key` and returns the new value,
+    /// a missing key counts as 0. It won't send watch events.
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64> {
+        let ret = Arc::new(AtomicI64::new(0));
+        let ret_clone = ret.clone();
+        let counter_key = key.to_string();
+        self.get_for_update(
+            key,
+            false,
+            None,
+            Box::new(move |value| {
+                let value = parse_counter(&counter_key, value.as_ref())? + delta;
+                ret_clone.store(value, Ordering::SeqCst);
+                Ok(Some((Some(Bytes::from(value.to_string())), None)))
+            }),
+        )
+        .await?;
+        Ok(ret.load(Ordering::SeqCst))
+    }
+
     async fn list(&self, prefix: &str) -> Result<HashMap<String, Bytes>>;
+
+    /// Lists at most `limit` items after skipping `offset` items, also returns whether
+    /// more items exist.
+    async fn list_paginated(
+        &self,
+        prefix: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<(String, Bytes)>, bool)> {
+        let mut items = self.list(prefix).await?.into_iter().collect::<Vec<_>>();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        let has_more = items.len() > offset.saturating_add(limit);
+        let items = items.into_iter().skip(offset).take(limit).collect();
+        Ok((items, has_more))
+    }
+
     async fn list_keys(&self, prefix: &str) -> Result<Vec<String>>;
     async fn list_values(&self, prefix: &str) -> Result<Vec<Bytes>>;
+    /// Lists the values with their start_dt in the given order, `None` or `(0, 0)` lists all
+    /// the versions and their start_dt is returned as `0`.
     async fn list_values_by_start_dt(
         &self,
         prefix: &str,
         start_dt: Option<(i64, i64)>,
+        order: OrderBy,
     ) -> Result<Vec<(i64, Bytes)>>;
     async fn count(&self, prefix: &str) -> Result<i64>;
     async fn watch(&self, prefix: &str) -> Result<Arc<mpsc::Receiver<Event>>>;
     async fn close(&self) -> Result<()>;
     async fn add_start_dt_column(&self) -> Result<()>;
+
+    /// Reports the migration steps [`Db::add_start_dt_column`] would apply without applying
+    /// them, so they can be reviewed before upgrading.
+    async fn check_migrations(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+}
+
+/// The distinct keys of the deleted entries, the versions of a key share one delete event
+fn deleted_keys(records: Vec<(String, String, String)>) -> Vec<String> {
+    let mut keys = records
+        .into_iter()
+        .map(|(module, key1, key2)| format!("/{module}/{key1}/{key2}"))
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    keys
 }
 
 pub fn parse_key(mut key: &str) -> (String, String, String) {
@@ -267,6 +423,20 @@ pub fn parse_key(mut key: &str) -> (String, String, String) {
     (module, key1, key2)
 }
 
+/// Parses the value of a counter key, a missing or empty value counts as 0
+pub(crate) fn parse_counter(key: &str, value: Option<&Bytes>) -> Result<i64> {
+    let Some(value) = value else {
+        return Ok(0);
+    };
+    let value = std::str::from_utf8(value).unwrap_or_default().trim();
+    if value.is_empty() {
+        return Ok(0);
+    }
+    value
+        .parse::<i64>()
+        .map_err(|e| Error::Message(format!("value of key {key} is not a counter: {e}")))
+}
+
 pub fn build_key(module: &str, key1: &str, key2: &str, start_dt: i64) -> String {
     if key1.is_empty() {
         format!("/{module}/")
@@ -346,6 +516,159 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_list_paginated() {
+        create_table().await.unwrap();
+        let db = get_db().await;
+        db.delete_if_exists("/foo/page/", true, false)
+            .await
+            .unwrap();
+        for i in 0..5 {
+            db.put(
+                &format!("/foo/page/bar{i}"),
+                Bytes::from(format!("hello{i}")),
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        let mut keys = vec![];
+        let mut offset = 0;
+        loop {
+            let (items, has_more) = db.list_paginated("/foo/page/", 2, offset).await.unwrap();
+            assert!(items.len() <= 2);
+            offset += items.len();
+            keys.extend(items.into_iter().map(|(k, _)| k));
+            if !has_more {
+                break;
+            }
+        }
+        assert_eq!(
+            keys,
+            (0..5)
+                .map(|i| format!("/foo/page/bar{i}"))
+                .collect::<Vec<_>>()
+        );
+
+        let (items, has_more) = db.list_paginated("/foo/page/", 2, 4).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(!has_more);
+        let (items, has_more) = db.list_paginated("/foo/page/", 2, 10).await.unwrap();
+        assert!(items.is_empty());
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_put_batch() {
+        create_table().await.unwrap();
+        let db = get_db().await;
+        db.put("/foo/batch/bar1", Bytes::from("old"), false, None)
+            .await
+            .unwrap();
+        let items = (1..=3)
+            .map(|i| {
+                (
+                    format!("/foo/batch/bar{i}"),
+                    Bytes::from(format!("hello{i}")),
+                    None,
+                )
+            })
+            .collect();
+        db.put_batch(items).await.unwrap();
+        for i in 1..=3 {
+            assert_eq!(
+                db.get(&format!("/foo/batch/bar{i}")).await.unwrap(),
+                Bytes::from(format!("hello{i}"))
+            );
+        }
+        db.put_batch(vec![]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_increment_concurrent() {
+        create_table().await.unwrap();
+        let db = get_db().await;
+        db.delete_if_exists("/foo/incr/counter", false, false)
+            .await
+            .unwrap();
+        let tasks = (0..10).map(|_| {
+            tokio::spawn(async {
+                let db = get_db().await;
+                for _ in 0..10 {
+                    db.increment("/foo/incr/counter", 2).await.unwrap();
+                }
+            })
+        });
+        for task in futures::future::join_all(tasks).await {
+            task.unwrap();
+        }
+        assert_eq!(db.increment("/foo/incr/counter", -1).await.unwrap(), 199);
+        assert_eq!(
+            db.get("/foo/incr/counter").await.unwrap(),
+            Bytes::from("199")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_for_update_outcome() {
+        create_table().await.unwrap();
+        let db = get_db().await;
+        db.delete_if_exists("/foo/upsert/bar", false, false)
+            .await
+            .unwrap();
+        let upsert = |value: &'static str| -> Box<UpdateFn> {
+            Box::new(move |old| {
+                if old.as_deref() == Some(value.as_bytes()) {
+                    return Ok(None);
+                }
+                Ok(Some((Some(Bytes::from(value)), None)))
+            })
+        };
+
+        let ret = db
+            .get_for_update("/foo/upsert/bar", false, None, upsert("hello"))
+            .await
+            .unwrap();
+        assert!(ret.created());
+        let ret = db
+            .get_for_update("/foo/upsert/bar", false, None, upsert("world"))
+            .await
+            .unwrap();
+        assert_eq!(
+            ret,
+            UpdateOutcome {
+                existed: true,
+                written: true
+            }
+        );
+        let ret = db
+            .get_for_update("/foo/upsert/bar", false, None, upsert("world"))
+            .await
+            .unwrap();
+        assert_eq!(ret, UpdateOutcome::unchanged(true));
+        assert_eq!(
+            db.get("/foo/upsert/bar").await.unwrap(),
+            Bytes::from("world")
+        );
+
+        let ret = db
+            .get_for_update("/foo/upsert/missing", false, None, Box::new(|_| Ok(None)))
+            .await
+            .unwrap();
+        assert_eq!(ret, UpdateOutcome::unchanged(false));
+    }
+
+    #[test]
+    fn test_parse_counter() {
+        assert_eq!(parse_counter("k", None).unwrap(), 0);
+        assert_eq!(parse_counter("k", Some(&Bytes::from(""))).unwrap(), 0);
+        assert_eq!(parse_counter("k", Some(&Bytes::from("42"))).unwrap(), 42);
+        assert_eq!(parse_counter("k", Some(&Bytes::from("-7"))).unwrap(), -7);
+        assert!(parse_counter("k", Some(&Bytes::from("abc"))).is_err());
+    }
+
     #[tokio::test]
     async fn test_get() {
         create_table().await.unwrap();
@@ -388,4 +711,56 @@ mod tests {
         assert_eq!(db.list_keys("/foo/del/").await.unwrap().len(), 3);
         assert_eq!(db.list_values("/foo/del/").await.unwrap().len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_delete_org() {
+        create_table().await.unwrap();
+        let db = get_db().await;
+        let hello = Bytes::from("hello");
+        for key in [
+            "/foo/delorg/bar",
+            "/foo/delorg/bar/baz",
+            "/qux/delorg/bar",
+            "/foo/delorg2/bar",
+        ] {
+            db.put(key, hello.clone(), false, None).await.unwrap();
+        }
+
+        assert_eq!(db.delete_org("delorg").await.unwrap(), 3);
+        assert!(db.list_keys("/foo/delorg/").await.unwrap().is_empty());
+        assert!(db.list_keys("/qux/delorg/").await.unwrap().is_empty());
+        assert_eq!(db.list_keys("/foo/delorg2/").await.unwrap().len(), 1);
+        assert_eq!(db.delete_org("delorg").await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_deleted_keys() {
+        let record = |k2: &str| ("foo".to_string(), "org".to_string(), k2.to_string());
+        assert_eq!(
+            deleted_keys(vec![record("b"), record("a"), record("b")]),
+            vec!["/foo/org/a", "/foo/org/b"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        create_table().await.unwrap();
+        health_check().await.unwrap();
+        // the health key doesn't stay in the store
+        let db = get_db().await;
+        assert!(db.list_keys(HEALTH_CHECK_KEY).await.unwrap().is_empty());
+
+        // the cluster coordinator is checked by the same round trip
+        let coordinator = get_coordinator().await;
+        health_check_with_timeout(coordinator.as_ref(), HEALTH_CHECK_TIMEOUT)
+            .await
+            .unwrap();
+        assert!(
+            coordinator
+                .list_keys(HEALTH_CHECK_KEY)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
@@ -27,41 +27,228 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use config::{
     cluster, get_config, ider,
+    metrics::DB_WATCH_DROPPED_EVENTS,
     utils::{
-        base64,
+        base64, rand,
         time::{now_micros, second_micros},
     },
 };
-use futures::{StreamExt, TryStreamExt};
-use hashbrown::HashMap;
+use futures::{Stream, StreamExt, TryStreamExt};
+use hashbrown::{HashMap, HashSet};
 use once_cell::sync::Lazy;
 use tokio::{
-    sync::{Mutex, OnceCell, mpsc},
-    task::JoinHandle,
+    sync::{Mutex, OnceCell, mpsc, watch},
+    task::{AbortHandle, JoinHandle},
 };
 
 use crate::{
     coordinator,
-    db::{Event, EventData},
+    db::{Event, EventData, OrderBy},
     dist_lock,
     errors::*,
 };
 
 const SUPER_CLUSTER_PREFIX: &str = "super_cluster_kv_";
+const INCREMENT_MAX_RETRIES: usize = 100;
+// the attempts to acquire the lock of get_for_update, they share ZO_NATS_LOCK_WAIT_TIMEOUT
+const GET_FOR_UPDATE_LOCK_RETRIES: usize = 3;
+// the retries of the bucket operations failed by the connection errors
+const RETRY_MAX_ATTEMPTS: u32 = 3;
+const RETRY_BACKOFF_MS: u64 = 100;
+// the values are JSON or plain text, they never start with a zero byte
+const COMPRESSION_MAGIC: &[u8] = b"\0O2C";
+const COMPRESSION_VERSION: u8 = 1;
+const COMPRESSION_CODEC_ZSTD: u8 = 1;
+const COMPRESSION_HEADER_LEN: usize = COMPRESSION_MAGIC.len() + 2;
 
 static NATS_CLIENT: OnceCell<Client> = OnceCell::const_new();
+// set on close, stops the kv watchers of this node
+static SHUTDOWN: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+// the heartbeat buckets whose ttl was already checked against the config
+static TTL_CHECKED_BUCKETS: Lazy<parking_lot::Mutex<HashSet<String>>> =
+    Lazy::new(|| parking_lot::Mutex::new(HashSet::new()));
+
+/// The kv watchers of this node. The writes of this node are sent to the matching watchers
+/// right away instead of waiting for JetStream to deliver them back, and JetStream's copy is
+/// skipped so a watcher doesn't get the same write twice.
+static LOCAL_KV_WATCHERS: Lazy<parking_lot::RwLock<Vec<Arc<LocalKvWatcher>>>> =
+    Lazy::new(Default::default);
+
+struct LocalKvWatcher {
+    db_prefix: String,
+    prefix: String,
+    tx: mpsc::Sender<Event>,
+    /// writes of this node by key and is_delete, not yet delivered back by JetStream
+    pending: parking_lot::Mutex<HashMap<(String, bool), usize>>,
+    /// the kv revision of the latest event sent by key, locked during the send so an older
+    /// event can't overtake a newer one
+    revisions: tokio::sync::Mutex<HashMap<String, u64>>,
+}
+
+impl LocalKvWatcher {
+    fn new(db_prefix: &str, prefix: &str, tx: mpsc::Sender<Event>) -> Self {
+        Self {
+            db_prefix: db_prefix.to_string(),
+            prefix: watch_key(prefix),
+            tx,
+            pending: parking_lot::Mutex::new(HashMap::new()),
+            revisions: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sends the event of the key's `revision`, returns false without sending when a newer
+    /// revision of the key was already sent. The local event of a write can arrive after
+    /// JetStream delivered a newer write of another node, it would overwrite the newer value.
+    async fn send(
+        &self,
+        key: &str,
+        revision: u64,
+        event: Event,
+        timeout: Duration,
+    ) -> std::result::Result<bool, String> {
+        let mut revisions = self.revisions.lock().await;
+        if revisions.get(key).is_some_and(|r| *r > revision) {
+            return Ok(false);
+        }
+        send_watch_event(&self.tx, event, timeout).await?;
+        revisions.insert(key.to_string(), revision);
+        Ok(true)
+    }
+
+    fn matches(&self, db_prefix: &str, key: &str) -> bool {
+        !self.tx.is_closed() && self.db_prefix == db_prefix && key.starts_with(&self.prefix)
+    }
+
+    fn mark(&self, key: &str, is_delete: bool) {
+        *self
+            .pending
+            .lock()
+            .entry((key.to_string(), is_delete))
+            .or_default() += 1;
+    }
+
+    /// Returns true if the write was marked, i.e. it was already sent by this node.
+    fn unmark(&self, key: &str, is_delete: bool) -> bool {
+        let mut pending = self.pending.lock();
+        let id = (key.to_string(), is_delete);
+        match pending.get_mut(&id) {
+            Some(n) => {
+                *n -= 1;
+                if *n == 0 {
+                    pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn register_local_kv_watcher(watcher: Arc<LocalKvWatcher>) {
+    let mut watchers = LOCAL_KV_WATCHERS.write();
+    watchers.retain(|w| !w.tx.is_closed());
+    watchers.push(watcher);
+}
+
+/// Returns the local watchers of the key and marks the write as sent by this node, this has to
+/// happen before the write so JetStream's copy can't be delivered before the mark.
+fn mark_local_kv_watchers(db_prefix: &str, key: &str, is_delete: bool) -> Vec<Arc<LocalKvWatcher>> {
+    let watchers: Vec<_> = LOCAL_KV_WATCHERS
+        .read()
+        .iter()
+        .filter(|w| w.matches(db_prefix, key))
+        .cloned()
+        .collect();
+    for w in watchers.iter() {
+        w.mark(key, is_delete);
+    }
+    watchers
+}
+
+fn unmark_local_kv_watchers(watchers: &[Arc<LocalKvWatcher>], key: &str, is_delete: bool) {
+    for w in watchers.iter() {
+        w.unmark(key, is_delete);
+    }
+}
+
+async fn notify_local_kv_watchers(watchers: &[Arc<LocalKvWatcher>], event: Event, revision: u64) {
+    let (key, is_delete) = match &event {
+        Event::Put(data) => (data.key.as_str(), false),
+        Event::Delete(data) => (data.key.as_str(), true),
+        Event::Empty => return,
+    };
+    let send_timeout = Duration::from_millis(get_config().nats.watch_send_timeout);
+    for w in watchers.iter() {
+        match w.send(key, revision, event.clone(), send_timeout).await {
+            Ok(true) => {}
+            Ok(false) => log::debug!(
+                "[NATS:local_watch] prefix: {}, key: {key}, revision: {revision} is stale, skipped",
+                w.prefix
+            ),
+            Err(e) => {
+                // let JetStream deliver it instead
+                w.unmark(key, is_delete);
+                DB_WATCH_DROPPED_EVENTS
+                    .with_label_values(&["nats", w.prefix.as_str()])
+                    .inc();
+                log::warn!(
+                    "[NATS:local_watch] prefix: {}, key: {key}, event dropped: {e}",
+                    w.prefix
+                );
+            }
+        }
+    }
+}
+
+/// The revision of the delete of the key, `purge` doesn't return the revision it wrote. None
+/// when the key was written again meanwhile or the lookup failed.
+async fn delete_revision(bucket: &jetstream::kv::Store, key: &str) -> Option<u64> {
+    match bucket.entry(key).await {
+        Ok(Some(entry)) if entry.operation != jetstream::kv::Operation::Put => Some(entry.revision),
+        _ => None,
+    }
+}
+
+/// Sends the local delete event in order with the other writes of the key, or leaves it to
+/// JetStream when its revision is unknown.
+async fn notify_local_kv_delete(
+    watchers: &[Arc<LocalKvWatcher>],
+    bucket: &jetstream::kv::Store,
+    purge_key: &str,
+    watch_key: String,
+) {
+    match delete_revision(bucket, purge_key).await {
+        Some(revision) => {
+            notify_local_kv_watchers(watchers, delete_event(watch_key), revision).await
+        }
+        None => unmark_local_kv_watchers(watchers, &watch_key, true),
+    }
+}
 
 pub async fn get_nats_client() -> &'static Client {
     NATS_CLIENT.get_or_init(connect).await
 }
 
+/// Creates the JetStream context in the configured domain
+pub(crate) fn jetstream_context(client: Client) -> jetstream::Context {
+    match jetstream_domain(&get_config().nats.jetstream_domain) {
+        Some(domain) => jetstream::with_domain(client, domain),
+        None => jetstream::new(client),
+    }
+}
+
+fn jetstream_domain(domain: &str) -> Option<&str> {
+    let domain = domain.trim();
+    (!domain.is_empty()).then_some(domain)
+}
+
 async fn get_bucket_by_key<'a>(
     prefix: &'a str,
     key: &'a str,
 ) -> Result<(jetstream::kv::Store, &'a str)> {
     let cfg = get_config();
     let client = get_nats_client().await.clone();
-    let jetstream = jetstream::new(client);
+    let jetstream = jetstream_context(client);
     let key = key.trim_start_matches('/');
     let bucket_name = key.split('/').next().unwrap();
     let mut bucket = jetstream::kv::Config {
@@ -70,23 +257,80 @@ async fn get_bucket_by_key<'a>(
         history: cfg.nats.history,
         ..Default::default()
     };
+    let mut heartbeat_ttl = None;
     if bucket_name == "nodes" || bucket_name == "clusters" {
-        // if changed ttl need recreate the bucket
-        // CMD: nats kv del -f o2_nodes
+        // an existing bucket keeps its ttl, it is checked against the config below
         let ttl = Duration::from_secs(cfg.limit.node_heartbeat_ttl as u64);
         bucket.max_age = ttl;
         if cfg.nats.v211_support {
             bucket.limit_markers = Some(ttl);
         }
+        heartbeat_ttl = Some(ttl);
     }
     let kv = jetstream.create_key_value(bucket).await.map_err(|e| {
         Error::Message(format!(
             "[NATS:get_bucket_by_key] create jetstream kv {bucket_name} error: {e}"
         ))
     })?;
+    if let Some(ttl) = heartbeat_ttl {
+        let first_use = TTL_CHECKED_BUCKETS.lock().insert(kv.name.clone());
+        if first_use {
+            check_bucket_ttl(&jetstream, &kv, ttl).await;
+        }
+    }
     Ok((kv, key.trim_start_matches(bucket_name)))
 }
 
+/// Returns the ttl of the existing bucket if it differs from the configured one.
+fn bucket_ttl_drift(existing: &jetstream::stream::Config, ttl: Duration) -> Option<Duration> {
+    (existing.max_age != ttl).then_some(existing.max_age)
+}
+
+/// JetStream keeps the ttl of an existing bucket when it is created again, so a changed
+/// `ZO_NODE_HEARTBEAT_TTL` is applied to the bucket stream here. The bucket is checked once per
+/// process.
+async fn check_bucket_ttl(
+    jetstream: &jetstream::Context,
+    kv: &jetstream::kv::Store,
+    ttl: Duration,
+) {
+    let mut stream = match jetstream.get_stream(&kv.stream_name).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::error!(
+                "[NATS:check_bucket_ttl] get stream of bucket {} error: {e}",
+                kv.name
+            );
+            return;
+        }
+    };
+    let mut config = match stream.info().await {
+        Ok(info) => info.config.clone(),
+        Err(e) => {
+            log::error!(
+                "[NATS:check_bucket_ttl] get stream info of bucket {} error: {e}",
+                kv.name
+            );
+            return;
+        }
+    };
+    let Some(existing) = bucket_ttl_drift(&config, ttl) else {
+        return;
+    };
+    log::warn!(
+        "[NATS:check_bucket_ttl] bucket {} has ttl {existing:?} but the configured heartbeat ttl is {ttl:?}, updating the bucket",
+        kv.name
+    );
+    config.max_age = ttl;
+    if let Err(e) = jetstream.update_stream(&config).await {
+        log::error!(
+            "[NATS:check_bucket_ttl] update ttl of bucket {} error: {e}, the heartbeats will expire after {existing:?}, recreate the bucket with: nats kv del -f {}",
+            kv.name,
+            kv.name
+        );
+    }
+}
+
 pub async fn init() {
     _ = get_nats_client().await;
 }
@@ -111,12 +355,11 @@ impl NatsDb {
         let (bucket, new_key) = get_bucket_by_key(&self.prefix, key).await?;
         let bucket_name = bucket.name.clone();
         let en_key = key_encode(new_key);
-        if let Some(v) = bucket
-            .get(&en_key)
+        if let Some(v) = get_value(&bucket, &en_key)
             .await
             .map_err(|e| Error::Message(format!("[NATS:get_key_value] bucket.get error: {e}")))?
         {
-            return Ok((key.to_string(), v));
+            return Ok((key.to_string(), decode_value(v)?));
         }
         let keys = keys(&bucket, new_key)
             .await
@@ -126,8 +369,7 @@ impl NatsDb {
         }
         let key = keys.last().unwrap();
         let en_key = key_encode(key);
-        match bucket
-            .get(&en_key)
+        match get_value(&bucket, &en_key)
             .await
             .map_err(|e| Error::Message(format!("[NATS:get_key_value] bucket.get error: {e}")))?
         {
@@ -135,18 +377,86 @@ impl NatsDb {
             Some(v) => {
                 let bucket_prefix = "/".to_string() + bucket_name.trim_start_matches(&self.prefix);
                 let key = bucket_prefix.to_string() + key;
-                Ok((key, v))
+                Ok((key, decode_value(v)?))
             }
         }
     }
 
+    /// Yields the entries under the prefix as their values are fetched, the values are not
+    /// buffered so the caller can process and drop them one by one.
+    pub fn list_stream(
+        &self,
+        prefix: &str,
+    ) -> impl Stream<Item = Result<(String, Bytes)>> + Send + 'static {
+        let self_prefix = self.prefix.to_string();
+        let prefix = prefix.to_string();
+        futures::stream::once(async move {
+            let (bucket, new_key) = get_bucket_by_key(&self_prefix, &prefix).await?;
+            let bucket_prefix = "/".to_string() + bucket.name.trim_start_matches(&self_prefix);
+            let keys = keys(&bucket, new_key)
+                .await
+                .map_err(|e| Error::Message(format!("[NATS:list] bucket.keys error: {e}")))?;
+            let values = futures::stream::iter(keys)
+                .map(move |key| {
+                    let bucket = bucket.clone();
+                    let bucket_prefix = bucket_prefix.clone();
+                    async move {
+                        let encoded_key = key_encode(&key);
+                        let value = get_value(&bucket, &encoded_key).await.map_err(|e| {
+                            Error::Message(format!("[NATS:list] bucket.get error: {e}"))
+                        })?;
+                        let value = value.map(decode_value).transpose()?;
+                        Ok::<_, Error>(value.map(|v| (bucket_prefix + &key, v)))
+                    }
+                })
+                .buffer_unordered(get_config().limit.cpu_num)
+                .try_filter_map(futures::future::ok);
+            Ok::<_, Error>(values)
+        })
+        .try_flatten()
+    }
+
+    /// Counts the keys under the prefix and sums the size of their stored values, the size
+    /// is the compressed size when the value is compressed. Unlike [`super::Db::stats`] only
+    /// the bucket of the prefix is scanned, each value is fetched to get its size.
+    pub async fn stats_prefix(&self, prefix: &str) -> Result<super::Stats> {
+        let (bucket, new_key) = get_bucket_by_key(&self.prefix, prefix).await?;
+        let bucket = &bucket;
+        let keys = keys(bucket, new_key)
+            .await
+            .map_err(|e| Error::Message(format!("[NATS:stats_prefix] bucket.keys error: {e}")))?;
+        let sizes = futures::stream::iter(keys)
+            .map(|key| async move {
+                let encoded_key = key_encode(&key);
+                let value = get_value(bucket, &encoded_key).await.map_err(|e| {
+                    Error::Message(format!("[NATS:stats_prefix] bucket.get error: {e}"))
+                })?;
+                Ok::<_, Error>(value.map(|v| v.len()))
+            })
+            .buffer_unordered(get_config().limit.cpu_num)
+            .try_filter_map(futures::future::ok)
+            .try_collect::<Vec<usize>>()
+            .await?;
+        Ok(super::Stats {
+            bytes_len: sizes.iter().sum::<usize>() as i64,
+            keys_count: sizes.len() as i64,
+        })
+    }
+
     async fn kv_watch(&self, prefix: &str) -> Result<Arc<mpsc::Receiver<Event>>> {
-        let (tx, rx) = mpsc::channel(65535);
+        let cfg = get_config();
+        let (tx, rx) = mpsc::channel(cfg.nats.watch_channel_capacity);
+        let send_timeout = Duration::from_millis(cfg.nats.watch_send_timeout);
         let prefix = prefix.to_string();
         let self_prefix = self.prefix.to_string();
+        // registered before the JetStream watcher starts, so the writes of this node made
+        // meanwhile are not missed
+        let local_watcher = Arc::new(LocalKvWatcher::new(&self_prefix, &prefix, tx));
+        register_local_kv_watcher(local_watcher.clone());
+        let mut shutdown_rx = SHUTDOWN.subscribe();
         let _task: JoinHandle<Result<()>> = tokio::task::spawn(async move {
             loop {
-                if cluster::is_offline() {
+                if cluster::is_offline() || *shutdown_rx.borrow() {
                     break;
                 }
                 let (bucket, new_key) = match get_bucket_by_key(&self_prefix, &prefix).await {
@@ -173,8 +483,17 @@ impl NatsDb {
                         continue;
                     }
                 };
+                // the writes marked before can't be delivered by the new watcher
+                local_watcher.pending.lock().clear();
                 loop {
-                    match entries.next().await {
+                    let entry = tokio::select! {
+                        _ = shutdown_rx.changed() => {
+                            log::info!("[NATS:kv_watch] prefix: {prefix}, watcher closed");
+                            return Ok(());
+                        }
+                        entry = entries.next() => entry,
+                    };
+                    match entry {
                         None => {
                             log::error!("[NATS:kv_watch] prefix: {prefix}, get message error");
                             break;
@@ -193,28 +512,52 @@ impl NatsDb {
                             if !item_key.starts_with(new_key) {
                                 continue;
                             }
+                            let revision = entry.revision;
                             let new_key = bucket_prefix.to_string() + &item_key;
-                            let ret = match entry.operation {
+                            let event = match entry.operation {
                                 jetstream::kv::Operation::Put => {
-                                    tx.try_send(Event::Put(EventData {
+                                    let value = match decode_value(entry.value) {
+                                        Ok(v) => v,
+                                        Err(e) => {
+                                            log::error!(
+                                                "[NATS:kv_watch] prefix: {prefix}, key: {new_key}, decode value error: {e}"
+                                            );
+                                            continue;
+                                        }
+                                    };
+                                    Event::Put(EventData {
                                         key: new_key.clone(),
-                                        value: Some(entry.value),
+                                        value: Some(value),
                                         start_dt: None,
-                                    }))
+                                    })
                                 }
                                 jetstream::kv::Operation::Delete
-                                | jetstream::kv::Operation::Purge => {
-                                    tx.try_send(Event::Delete(EventData {
-                                        key: new_key.clone(),
-                                        value: None,
-                                        start_dt: None,
-                                    }))
-                                }
+                                | jetstream::kv::Operation::Purge => Event::Delete(EventData {
+                                    key: new_key.clone(),
+                                    value: None,
+                                    start_dt: None,
+                                }),
                             };
-                            if let Err(e) = ret {
-                                log::warn!(
-                                    "[NATS:kv_watch] prefix: {prefix}, key: {new_key}, send error: {e}"
-                                );
+                            if local_watcher.unmark(&new_key, matches!(event, Event::Delete(_))) {
+                                // already sent by this node
+                                continue;
+                            }
+                            match local_watcher
+                                .send(&new_key, revision, event, send_timeout)
+                                .await
+                            {
+                                Ok(true) => {}
+                                Ok(false) => log::debug!(
+                                    "[NATS:kv_watch] prefix: {prefix}, key: {new_key}, revision: {revision} is stale, skipped"
+                                ),
+                                Err(e) => {
+                                    DB_WATCH_DROPPED_EVENTS
+                                        .with_label_values(&["nats", prefix.as_str()])
+                                        .inc();
+                                    log::warn!(
+                                        "[NATS:kv_watch] prefix: {prefix}, key: {new_key}, event dropped: {e}"
+                                    );
+                                }
                             }
                         }
                     }
@@ -240,7 +583,7 @@ impl super::Db for NatsDb {
 
     async fn stats(&self) -> Result<super::Stats> {
         let client = get_nats_client().await.clone();
-        let jetstream = async_nats::jetstream::new(client);
+        let jetstream = jetstream_context(client);
         let mut keys_count = 0;
         let mut bytes_len = 0;
         let mut streams = jetstream.streams();
@@ -257,12 +600,11 @@ impl super::Db for NatsDb {
     async fn get(&self, key: &str) -> Result<Bytes> {
         let (bucket, new_key) = get_bucket_by_key(&self.prefix, key).await?;
         let key = key_encode(new_key);
-        if let Some(v) = bucket
-            .get(&key)
+        if let Some(v) = get_value(&bucket, &key)
             .await
             .map_err(|e| Error::Message(format!("[NATS:get] bucket.get error: {e}")))?
         {
-            return Ok(v);
+            return decode_value(v);
         }
         let keys = keys(&bucket, new_key)
             .await
@@ -271,13 +613,33 @@ impl super::Db for NatsDb {
             return Err(Error::from(DbError::KeyNotExists(key.to_string())));
         }
         let key = keys.last().unwrap();
-        match bucket
-            .get(key)
+        match get_value(&bucket, key)
             .await
             .map_err(|e| Error::Message(format!("[NATS:get] bucket.get error: {e}")))?
         {
             None => Err(Error::from(DbError::KeyNotExists(key.to_string()))),
-            Some(v) => Ok(v),
+            Some(v) => decode_value(v),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let (bucket, new_key) = get_bucket_by_key(&self.prefix, key).await?;
+        let en_key = key_encode(new_key);
+        if key_exists(&bucket, &en_key)
+            .await
+            .map_err(|e| Error::Message(format!("[NATS:exists] key_exists error: {e}")))?
+        {
+            return Ok(true);
+        }
+        // same as get, a missing key falls back to the last key under it
+        let keys = keys(&bucket, new_key)
+            .await
+            .map_err(|e| Error::Message(format!("[NATS:exists] bucket.keys error: {e}")))?;
+        match keys.last() {
+            None => Ok(false),
+            Some(key) => key_exists(&bucket, &key_encode(key))
+                .await
+                .map_err(|e| Error::Message(format!("[NATS:exists] key_exists error: {e}"))),
         }
     }
 
@@ -296,13 +658,43 @@ impl super::Db for NatsDb {
         };
         let (bucket, new_key) = get_bucket_by_key(&self.prefix, &key).await?;
         let encode_key = key_encode(new_key);
-        _ = bucket
-            .put(&encode_key, value.clone())
-            .await
-            .map_err(|e| Error::Message(format!("[NATS:put] bucket.put error: {e}")))?;
+        let watch_key = watch_key(&key);
+        let local_watchers = if need_watch && use_kv_watcher(&local_key) {
+            mark_local_kv_watchers(&self.prefix, &watch_key, false)
+        } else {
+            Vec::new()
+        };
+        let encoded_value = encode_value(value.clone())?;
+        let revision =
+            match with_retry("put", || bucket.put(&encode_key, encoded_value.clone())).await {
+                Ok(revision) => revision,
+                Err(e) => {
+                    unmark_local_kv_watchers(&local_watchers, &watch_key, false);
+                    return Err(Error::Message(format!("[NATS:put] bucket.put error: {e}")));
+                }
+            };
         if need_watch && !use_kv_watcher(&local_key) {
             coordinator::events::put_event(&local_key, start_dt, Some(value)).await?;
         }
+        if !local_watchers.is_empty() {
+            let event = Event::Put(EventData {
+                key: watch_key,
+                value: Some(value),
+                start_dt: None,
+            });
+            notify_local_kv_watchers(&local_watchers, event, revision).await;
+        }
+        Ok(())
+    }
+
+    async fn put_batch(&self, items: Vec<(String, Bytes, Option<i64>)>) -> Result<()> {
+        futures::stream::iter(items)
+            .map(
+                |(key, value, start_dt)| async move { self.put(&key, value, true, start_dt).await },
+            )
+            .buffer_unordered(get_config().limit.cpu_num)
+            .try_collect::<Vec<()>>()
+            .await?;
         Ok(())
     }
 
@@ -312,15 +704,25 @@ impl super::Db for NatsDb {
         need_watch: bool,
         start_dt: Option<i64>,
         update_fn: Box<super::UpdateFn>,
-    ) -> Result<()> {
+    ) -> Result<super::UpdateOutcome> {
         // acquire lock and update
         let lock_key = format!("/meta{key}/{}", start_dt.unwrap_or_default());
-        let locker = match dist_lock::lock(&lock_key, 0).await {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(Error::Message(format!(
-                    "dist_lock key: {lock_key}, acquire error: {e}"
-                )));
+        // the wait is split across the attempts so the worst case stays the lock wait timeout
+        let wait_ttl =
+            (get_config().nats.lock_wait_timeout / GET_FOR_UPDATE_LOCK_RETRIES as u64).max(1);
+        let mut attempt = 1;
+        let locker = loop {
+            match dist_lock::lock(&lock_key, wait_ttl).await {
+                Ok(v) => break v,
+                // only a contended lock is worth another try, the store failing isn't
+                Err(Error::LockTimeout(e)) if attempt < GET_FOR_UPDATE_LOCK_RETRIES => {
+                    log::warn!("dist_lock key: {lock_key}, attempt {attempt}: {e}, retrying");
+                    attempt += 1;
+                }
+                Err(e) => {
+                    log::error!("dist_lock key: {lock_key}, acquire error: {e}");
+                    return Err(e);
+                }
             }
         };
         log::info!("Acquired lock for cluster key: {lock_key}");
@@ -329,10 +731,12 @@ impl super::Db for NatsDb {
         let value = self.get_key_value(key).await.ok();
         let old_key = value.as_ref().map(|v| v.0.clone());
         let old_value = value.map(|v| v.1);
+        let existed = old_value.is_some();
         let ret = match update_fn(old_value) {
             Err(e) => Err(e),
-            Ok(None) => Ok(()),
+            Ok(None) => Ok(super::UpdateOutcome::unchanged(existed)),
             Ok(Some((value, new_value))) => {
+                let written = value.is_some() || new_value.is_some();
                 if let Some(value) = value
                     && let Err(e) = self.put(&old_key.unwrap(), value, need_watch, None).await
                 {
@@ -353,7 +757,7 @@ impl super::Db for NatsDb {
                     log::info!("Released lock for cluster key: {lock_key}");
                     return Err(e);
                 }
-                Ok(())
+                Ok(super::UpdateOutcome { existed, written })
             }
         };
 
@@ -365,6 +769,38 @@ impl super::Db for NatsDb {
         ret
     }
 
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64> {
+        let (bucket, new_key) = get_bucket_by_key(&self.prefix, key).await?;
+        let encode_key = key_encode(new_key);
+        // optimistic update, retry when the revision was changed by others
+        for _ in 0..INCREMENT_MAX_RETRIES {
+            let entry = bucket
+                .entry(&encode_key)
+                .await
+                .map_err(|e| Error::Message(format!("[NATS:increment] bucket.entry error: {e}")))?;
+            let entry = entry.filter(|e| e.operation == jetstream::kv::Operation::Put);
+            let value = super::parse_counter(key, entry.as_ref().map(|e| &e.value))? + delta;
+            let new_value = Bytes::from(value.to_string());
+            let ret = match entry {
+                Some(entry) => bucket
+                    .update(&encode_key, new_value, entry.revision)
+                    .await
+                    .map_err(|e| e.to_string()),
+                None => bucket
+                    .create(&encode_key, new_value)
+                    .await
+                    .map_err(|e| e.to_string()),
+            };
+            match ret {
+                Ok(_) => return Ok(value),
+                Err(e) => log::debug!("[NATS:increment] key: {key}, conflict, retry: {e}"),
+            }
+        }
+        Err(Error::Message(format!(
+            "[NATS:increment] key: {key}, exceeded max retries {INCREMENT_MAX_RETRIES}"
+        )))
+    }
+
     async fn delete(
         &self,
         key: &str,
@@ -383,15 +819,27 @@ impl super::Db for NatsDb {
         } else {
             new_key.to_string()
         };
+        let bucket_prefix = "/".to_string() + bucket.name.trim_start_matches(&self.prefix);
         if !with_prefix {
             let purge_key = key_encode(&new_key);
-            bucket
-                .purge(purge_key)
-                .await
-                .map_err(|e| Error::Message(format!("[NATS:delete] bucket.purge error: {e}")))?;
+            let watch_key = bucket_prefix + &new_key;
+            let local_watchers = if need_watch && use_kv_watcher(key) {
+                mark_local_kv_watchers(&self.prefix, &watch_key, true)
+            } else {
+                Vec::new()
+            };
+            if let Err(e) = with_retry("delete", || bucket.purge(&purge_key)).await {
+                unmark_local_kv_watchers(&local_watchers, &watch_key, true);
+                return Err(Error::Message(format!(
+                    "[NATS:delete] bucket.purge error: {e}"
+                )));
+            }
             if need_watch && !use_kv_watcher(key) {
                 coordinator::events::delete_event(key, start_dt).await?;
             }
+            if !local_watchers.is_empty() {
+                notify_local_kv_delete(&local_watchers, &bucket, &purge_key, watch_key).await;
+            }
             return Ok(());
         }
         let keys = keys(&bucket, &new_key)
@@ -399,45 +847,101 @@ impl super::Db for NatsDb {
             .map_err(|e| Error::Message(format!("[NATS:delete] bucket.keys error: {e}")))?;
         for purge_key in keys {
             let encode_key = key_encode(&purge_key);
-            bucket
-                .purge(encode_key)
-                .await
-                .map_err(|e| Error::Message(format!("[NATS:delete] bucket.purge error: {e}")))?;
+            let watch_key = bucket_prefix.to_string() + &purge_key;
+            let local_watchers = if need_watch && use_kv_watcher(&watch_key) {
+                mark_local_kv_watchers(&self.prefix, &watch_key, true)
+            } else {
+                Vec::new()
+            };
+            if let Err(e) = with_retry("delete", || bucket.purge(&encode_key)).await {
+                unmark_local_kv_watchers(&local_watchers, &watch_key, true);
+                return Err(Error::Message(format!(
+                    "[NATS:delete] bucket.purge error: {e}"
+                )));
+            }
             if need_watch && !use_kv_watcher(&purge_key) {
                 coordinator::events::delete_event(&purge_key, start_dt).await?;
             }
+            if !local_watchers.is_empty() {
+                notify_local_kv_delete(&local_watchers, &bucket, &encode_key, watch_key).await;
+            }
         }
         Ok(())
     }
 
+    async fn delete_org(&self, org_id: &str) -> Result<u64> {
+        // every module is a bucket, the org is the first part of the keys in the bucket
+        let client = get_nats_client().await.clone();
+        let jetstream = jetstream_context(client);
+        let stream_prefix = format!("KV_{}", self.prefix);
+        let modules = jetstream
+            .stream_names()
+            .try_collect::<Vec<String>>()
+            .await
+            .map_err(|e| Error::Message(format!("[NATS:delete_org] stream_names error: {e}")))?
+            .into_iter()
+            .filter_map(|name| name.strip_prefix(&stream_prefix).map(|v| v.to_string()))
+            .collect::<Vec<_>>();
+
+        let org_key = format!("/{org_id}");
+        let mut deleted = 0;
+        for module in modules {
+            let (bucket, _) = get_bucket_by_key(&self.prefix, &module).await?;
+            let keys = keys(&bucket, &org_key)
+                .await
+                .map_err(|e| Error::Message(format!("[NATS:delete_org] bucket.keys error: {e}")))?;
+            for key in keys {
+                if key != org_key && !key.starts_with(&format!("{org_key}/")) {
+                    continue;
+                }
+                // the purged keys are still listed by their history
+                if !key_exists(&bucket, &key_encode(&key)).await.map_err(|e| {
+                    Error::Message(format!("[NATS:delete_org] key_exists error: {e}"))
+                })? {
+                    continue;
+                }
+                self.delete(&format!("/{module}{key}"), false, true, None)
+                    .await?;
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
     async fn list(&self, prefix: &str) -> Result<HashMap<String, Bytes>> {
+        self.list_stream(prefix).try_collect().await
+    }
+
+    async fn list_paginated(
+        &self,
+        prefix: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<(String, Bytes)>, bool)> {
         let (bucket, new_key) = get_bucket_by_key(&self.prefix, prefix).await?;
         let bucket_prefix = "/".to_string() + bucket.name.trim_start_matches(&self.prefix);
         let bucket = &bucket;
-        let keys = keys(bucket, new_key)
+        let keys = live_keys(bucket, new_key)
             .await
-            .map_err(|e| Error::Message(format!("[NATS:list] bucket.keys error: {e}")))?;
-        if keys.is_empty() {
-            return Ok(HashMap::new());
-        }
-
-        let values = futures::stream::iter(keys)
+            .map_err(|e| Error::Message(format!("[NATS:list_paginated] bucket.keys error: {e}")))?;
+        // the deleted keys are already dropped, so only the values of the page are fetched
+        let has_more = keys.len() > offset.saturating_add(limit);
+        let page = keys.into_iter().skip(offset).take(limit);
+        let bucket_prefix = &bucket_prefix;
+        let items = futures::stream::iter(page)
             .map(|key| async move {
                 let encoded_key = key_encode(&key);
-                let value = bucket
-                    .get(&encoded_key)
-                    .await
-                    .map_err(|e| Error::Message(format!("[NATS:list] bucket.get error: {e}")))?;
-                Ok::<(String, Option<Bytes>), Error>((key, value))
+                let value = get_value(bucket, &encoded_key).await.map_err(|e| {
+                    Error::Message(format!("[NATS:list_paginated] bucket.get error: {e}"))
+                })?;
+                let value = value.map(decode_value).transpose()?;
+                Ok::<_, Error>(value.map(|value| (bucket_prefix.to_string() + &key, value)))
             })
-            .buffer_unordered(get_config().limit.cpu_num)
-            .try_collect::<Vec<(String, Option<Bytes>)>>()
+            .buffered(get_config().limit.cpu_num)
+            .try_filter_map(futures::future::ok)
+            .try_collect::<Vec<_>>()
             .await?;
-        let result = values
-            .into_iter()
-            .filter_map(|(k, v)| v.map(|v| (bucket_prefix.to_string() + &k, v)))
-            .collect();
-        Ok(result)
+        Ok((items, has_more))
     }
 
     async fn list_keys(&self, prefix: &str) -> Result<Vec<String>> {
@@ -468,10 +972,10 @@ impl super::Db for NatsDb {
         let values = futures::stream::iter(keys)
             .map(|key| async move {
                 let encoded_key = key_encode(&key);
-                let value = bucket.get(&encoded_key).await.map_err(|e| {
+                let value = get_value(bucket, &encoded_key).await.map_err(|e| {
                     Error::Message(format!("[NATS:list_values] bucket.get error: {e}"))
                 })?;
-                Ok::<Option<Bytes>, Error>(value)
+                value.map(decode_value).transpose()
             })
             .buffer_unordered(get_config().limit.cpu_num)
             .try_collect::<Vec<Option<Bytes>>>()
@@ -485,12 +989,10 @@ impl super::Db for NatsDb {
         &self,
         prefix: &str,
         start_dt: Option<(i64, i64)>,
+        order: OrderBy,
     ) -> Result<Vec<(i64, Bytes)>> {
-        if start_dt.is_none() || start_dt == Some((0, 0)) {
-            let vals = self.list_values(prefix).await?;
-            return Ok(vals.into_iter().map(|v| (0, v)).collect());
-        }
-        let (min_dt, max_dt) = start_dt.unwrap();
+        // all the versions are listed with start_dt 0, but still in the requested order
+        let range = start_dt.filter(|dt| *dt != (0, 0));
 
         let (bucket, new_key) = get_bucket_by_key(&self.prefix, prefix).await?;
         let bucket = &bucket;
@@ -499,39 +1001,43 @@ impl super::Db for NatsDb {
                 "[NATS:list_values_by_start_dt] bucket.keys error: {e}"
             ))
         })?;
-        let keys = keys
+        let mut keys = keys
             .into_iter()
-            .filter(|key| {
+            .map(|key| {
                 let start_dt = key
                     .split('/')
                     .next_back()
                     .unwrap()
                     .parse::<i64>()
                     .unwrap_or_default();
-                start_dt >= min_dt && start_dt <= max_dt
+                (start_dt, key)
+            })
+            .filter(|(start_dt, _)| match range {
+                Some((min_dt, max_dt)) => *start_dt >= min_dt && *start_dt <= max_dt,
+                None => true,
             })
-            .collect::<Vec<String>>();
+            .collect::<Vec<_>>();
         if keys.is_empty() {
             return Ok(vec![]);
         }
+        match order {
+            OrderBy::Asc => keys.sort_by(|a, b| a.cmp(b)),
+            OrderBy::Desc => keys.sort_by(|a, b| b.cmp(a)),
+        }
 
         let values = futures::stream::iter(keys)
-            .map(|key| async move {
+            .map(|(start_dt, key)| async move {
                 let encoded_key = key_encode(&key);
-                let start_dt = key
-                    .split('/')
-                    .next_back()
-                    .unwrap()
-                    .parse::<i64>()
-                    .unwrap_or_default();
-                let value = bucket.get(&encoded_key).await.map_err(|e| {
+                let start_dt = if range.is_some() { start_dt } else { 0 };
+                let value = get_value(bucket, &encoded_key).await.map_err(|e| {
                     Error::Message(format!(
                         "[NATS:list_values_by_start_dt] bucket.get error: {e}"
                     ))
                 })?;
+                let value = value.map(decode_value).transpose()?;
                 Ok::<Option<(i64, Bytes)>, Error>(value.map(|value| (start_dt, value)))
             })
-            .buffer_unordered(get_config().limit.cpu_num)
+            .buffered(get_config().limit.cpu_num)
             .try_collect::<Vec<Option<(i64, Bytes)>>>()
             .await
             .map_err(|e| Error::Message(e.to_string()))?;
@@ -552,7 +1058,13 @@ impl super::Db for NatsDb {
         }
     }
 
+    /// Stops the kv watchers, the events already sent to the watchers can still be received
+    /// and the receivers end after them. The locks held by this node are released instead of
+    /// lingering until their ttl expires.
     async fn close(&self) -> Result<()> {
+        SHUTDOWN.send_replace(true);
+        LOCAL_KV_WATCHERS.write().clear();
+        release_held_locks().await;
         Ok(())
     }
     async fn add_start_dt_column(&self) -> Result<()> {
@@ -578,6 +1090,30 @@ pub async fn connect() -> async_nats::Client {
     if !cfg.nats.user.is_empty() {
         opts = opts.user_and_password(cfg.nats.user.to_string(), cfg.nats.password.to_string());
     }
+    if !cfg.nats.credentials_file.is_empty() {
+        opts = match opts.credentials_file(&cfg.nats.credentials_file).await {
+            Ok(opts) => opts,
+            Err(e) => {
+                log::error!(
+                    "NATS load credentials file {} failed, err: {e}",
+                    cfg.nats.credentials_file
+                );
+                panic!("NATS load credentials file failed: {e}");
+            }
+        };
+    }
+    if cfg.nats.tls_enabled {
+        opts = opts.require_tls(true);
+    }
+    if !cfg.nats.tls_ca_cert_path.is_empty() {
+        opts = opts.add_root_certificates(cfg.nats.tls_ca_cert_path.clone().into());
+    }
+    if !cfg.nats.tls_client_cert_path.is_empty() {
+        opts = opts.add_client_certificate(
+            cfg.nats.tls_client_cert_path.clone().into(),
+            cfg.nats.tls_client_key_path.clone().into(),
+        );
+    }
     let addrs = cfg
         .nats
         .addr
@@ -587,13 +1123,36 @@ pub async fn connect() -> async_nats::Client {
     match async_nats::connect_with_options(addrs.clone(), opts).await {
         Ok(client) => client,
         Err(e) => {
-            log::error!("NATS connect failed for address(es): {addrs:?}, err: {e}");
-            panic!("NATS connect failed");
+            // the TLS errors are wrapped, log the whole chain to show the cause
+            let mut err = e.to_string();
+            let mut source = std::error::Error::source(&e);
+            while let Some(s) = source {
+                err = format!("{err}: {s}");
+                source = s.source();
+            }
+            log::error!("NATS connect failed for address(es): {addrs:?}, err: {err}");
+            panic!("NATS connect failed: {err}");
         }
     }
 }
 
+/// Lists the keys of the bucket with the prefix, retried on the connection errors. The deleted
+/// and purged keys are still listed by their history.
 async fn keys(kv: &jetstream::kv::Store, prefix: &str) -> Result<Vec<String>> {
+    with_retry("keys", || keys_once(kv, prefix, false)).await
+}
+
+/// Lists the keys of the bucket with the prefix whose latest entry is a value, retried on the
+/// connection errors
+async fn live_keys(kv: &jetstream::kv::Store, prefix: &str) -> Result<Vec<String>> {
+    with_retry("live_keys", || keys_once(kv, prefix, true)).await
+}
+
+async fn keys_once(
+    kv: &jetstream::kv::Store,
+    prefix: &str,
+    skip_deleted: bool,
+) -> Result<Vec<String>> {
     let mut consumer = kv
         .stream
         .create_consumer(jetstream::consumer::push::OrderedConfig {
@@ -607,11 +1166,11 @@ async fn keys(kv: &jetstream::kv::Store, prefix: &str) -> Result<Vec<String>> {
         })
         .await?;
 
-    let mut keys = Vec::new();
+    let mut keys = HashSet::new();
     if let Ok(info) = consumer.info().await
         && info.num_pending == 0
     {
-        return Ok(keys);
+        return Ok(vec![]);
     }
     let mut messages = consumer.messages().await?;
     while let Ok(Some(message)) = messages.try_next().await {
@@ -623,7 +1182,12 @@ async fn keys(kv: &jetstream::kv::Store, prefix: &str) -> Result<Vec<String>> {
             .to_string();
         let key = key_decode(&key);
         if key.starts_with(prefix) {
-            keys.push(key);
+            // the history is delivered in order, the latest entry of the key wins
+            if skip_deleted && is_delete_marker(message.headers.as_ref()) {
+                keys.remove(&key);
+            } else {
+                keys.insert(key);
+            }
         }
         if let Ok(info) = message.info()
             && info.pending == 0
@@ -631,17 +1195,70 @@ async fn keys(kv: &jetstream::kv::Store, prefix: &str) -> Result<Vec<String>> {
             break;
         }
     }
+    let mut keys = keys.into_iter().collect::<Vec<_>>();
     keys.sort();
-    keys.dedup();
     Ok(keys)
 }
 
+/// Returns whether the latest entry of the encoded key is a value, not a delete or purge.
+/// Only the headers of the entry are read, the value isn't downloaded.
+async fn key_exists(kv: &jetstream::kv::Store, key: &str) -> Result<bool> {
+    with_retry("key_exists", || key_exists_once(kv, key)).await
+}
+
+async fn key_exists_once(kv: &jetstream::kv::Store, key: &str) -> Result<bool> {
+    let mut consumer = kv
+        .stream
+        .create_consumer(jetstream::consumer::push::OrderedConfig {
+            deliver_subject: ider::uuid(),
+            description: Some("kv key exists consumer".to_string()),
+            headers_only: true,
+            replay_policy: jetstream::consumer::ReplayPolicy::Instant,
+            deliver_policy: jetstream::consumer::DeliverPolicy::LastPerSubject,
+            filter_subject: format!("{}{key}", kv.prefix),
+            ..Default::default()
+        })
+        .await?;
+    if let Ok(info) = consumer.info().await
+        && info.num_pending == 0
+    {
+        return Ok(false);
+    }
+    let mut messages = consumer.messages().await?;
+    let message = messages
+        .try_next()
+        .await
+        .map_err(|e| Error::Message(format!("[NATS:key_exists] messages error: {e}")))?;
+    Ok(message.is_some_and(|message| !is_delete_marker(message.headers.as_ref())))
+}
+
+/// Whether the kv message is a delete or purge marker, the values carry no operation header
+fn is_delete_marker(headers: Option<&async_nats::HeaderMap>) -> bool {
+    headers
+        .and_then(|headers| headers.get(KV_OPERATION_HEADER))
+        .is_some_and(|op| matches!(op.as_str(), "DEL" | "PURGE"))
+}
+
+// the header of the kv operation of a message, set on the delete and purge markers
+const KV_OPERATION_HEADER: &str = "KV-Operation";
+
 // global locker for nats
 static LOCAL_LOCKER: Lazy<Mutex<HashMap<String, Arc<Mutex<bool>>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 // even the watcher no response still need to check if the key exists. unit: second
 const LOCKER_WATCHER_CHECK_TTL: u64 = 1;
 const LOCKER_WATCHER_UPDATE_TTL: i64 = 10;
+const LOCKER_BACKOFF_MIN_MS: u64 = 10;
+const LOCKER_BACKOFF_MAX_MS: u64 = 1000;
+
+// the locks held by this node by lock_id, released on close
+static HELD_LOCKS: Lazy<parking_lot::Mutex<HashMap<String, HeldLock>>> =
+    Lazy::new(|| parking_lot::Mutex::new(HashMap::new()));
+
+struct HeldLock {
+    key: String,
+    keep_alive: AbortHandle,
+}
 
 pub(crate) struct Locker {
     pub key: String,
@@ -661,9 +1278,16 @@ impl Locker {
     }
 
     /// lock with timeout, 0 means use default timeout, unit: second
+    ///
+    /// Returns [`Error::LockTimeout`] if the lock is still held by someone else after the
+    /// timeout and [`Error::LockUnavailable`] if nats failed.
     pub(crate) async fn lock(&mut self, timeout: u64) -> Result<()> {
         let cfg = get_config();
-        let (bucket, new_key) = get_bucket_by_key(&cfg.nats.prefix, &self.key).await?;
+        let (bucket, new_key) = get_bucket_by_key(&cfg.nats.prefix, &self.key)
+            .await
+            .map_err(|e| {
+                Error::LockUnavailable(format!("nats lock for key: {}, error: {e}", self.key))
+            })?;
         let timeout = if timeout == 0 {
             cfg.nats.lock_wait_timeout
         } else {
@@ -695,6 +1319,7 @@ impl Locker {
         _ = check_exist_lock(&bucket, &key, &self.key).await?;
 
         let mut last_err = None;
+        let mut attempt = 0;
 
         let expiration = now + second_micros(timeout);
         while expiration > now_micros() {
@@ -710,17 +1335,22 @@ impl Locker {
                     if let Err(e) = wait_for_delete(&bucket, &key, &self.key).await {
                         log::error!("nats wait_for_delete key: {key}, error: {e}");
                     }
+                    // back off before racing the other waiters for the released key
+                    let remaining = (expiration - now_micros()).max(0) as u64;
+                    let backoff = lock_backoff(attempt).min(Duration::from_micros(remaining));
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
                 }
             };
         }
         if let Some(err) = last_err {
             if err.contains("key already exists") {
-                return Err(Error::Message(format!(
+                return Err(Error::LockTimeout(format!(
                     "nats lock for key: {}, acquire timeout in {timeout}s",
                     self.key
                 )));
             } else {
-                return Err(Error::Message(format!(
+                return Err(Error::LockUnavailable(format!(
                     "nats lock for key: {}, error: {}",
                     self.key, err
                 )));
@@ -733,13 +1363,20 @@ impl Locker {
         let lock_id = self.lock_id.clone();
         let lock_key = self.key.clone();
         let bucket_key = key.clone();
-        tokio::task::spawn(async move {
+        let keep_alive = tokio::task::spawn(async move {
             if let Err(e) =
                 keep_alive_lock(&mut rx, &bucket, &bucket_key, &lock_key, &lock_id).await
             {
                 log::error!("nats keep alive for key: {lock_key}, error: {e}");
             }
         });
+        HELD_LOCKS.lock().insert(
+            self.lock_id.clone(),
+            HeldLock {
+                key: self.key.clone(),
+                keep_alive: keep_alive.abort_handle(),
+            },
+        );
 
         Ok(())
     }
@@ -748,6 +1385,7 @@ impl Locker {
         if self.state.load(Ordering::SeqCst) != 1 {
             return Ok(());
         }
+        HELD_LOCKS.lock().remove(&self.lock_id);
 
         let cfg = get_config();
         let (bucket, new_key) = get_bucket_by_key(&cfg.nats.prefix, &self.key).await?;
@@ -772,6 +1410,132 @@ impl Locker {
     }
 }
 
+/// Stops the keep alive of the locks held by this node and deletes the locks which are still
+/// owned by them.
+async fn release_held_locks() {
+    let locks = std::mem::take(&mut *HELD_LOCKS.lock());
+    let cfg = get_config();
+    for (lock_id, lock) in locks {
+        lock.keep_alive.abort();
+        let ret = async {
+            let (bucket, new_key) = get_bucket_by_key(&cfg.nats.prefix, &lock.key).await?;
+            let key = key_encode(new_key);
+            if let Some(value) = bucket.get(&key).await?
+                && value.starts_with(lock_id.as_bytes())
+            {
+                bucket
+                    .purge(&key)
+                    .await
+                    .map_err(|e| Error::Message(e.to_string()))?;
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        match ret {
+            Ok(()) => log::info!("nats released lock for key: {} on close", lock.key),
+            Err(e) => log::error!(
+                "nats release lock for key: {} on close, error: {e}",
+                lock.key
+            ),
+        }
+    }
+}
+
+/// Errors of the bucket operations that are worth retrying, i.e. the connection level errors
+/// like a timed out or failed request. The logical errors like an invalid key are not retried.
+trait RetryableError: std::fmt::Display {
+    fn is_retryable(&self) -> bool;
+}
+
+impl RetryableError for jetstream::kv::EntryError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            jetstream::kv::EntryErrorKind::TimedOut | jetstream::kv::EntryErrorKind::Other
+        )
+    }
+}
+
+impl RetryableError for jetstream::kv::PutError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            jetstream::kv::PutErrorKind::Publish | jetstream::kv::PutErrorKind::Ack
+        )
+    }
+}
+
+impl RetryableError for jetstream::kv::PurgeError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            jetstream::kv::PurgeErrorKind::Publish | jetstream::kv::PurgeErrorKind::Ack
+        )
+    }
+}
+
+impl RetryableError for Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::NatsKJetstreamStreamConsumerError(e) => matches!(
+                e.kind(),
+                jetstream::stream::ConsumerErrorKind::TimedOut
+                    | jetstream::stream::ConsumerErrorKind::Request
+            ),
+            Error::NatsKJetstreamConsumerStreamError(e) => {
+                matches!(e.kind(), jetstream::consumer::StreamErrorKind::TimedOut)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Runs the bucket operation and retries it up to `RETRY_MAX_ATTEMPTS` times with backoff when
+/// it fails with a retryable error, so a brief NATS outage doesn't fail the operation.
+async fn with_retry<T, E, F, Fut>(op: &str, mut f: F) -> std::result::Result<T, E>
+where
+    E: RetryableError,
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = std::result::Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Err(e) if attempt < RETRY_MAX_ATTEMPTS && e.is_retryable() => {
+                attempt += 1;
+                log::warn!("[NATS:{op}] attempt {attempt}: {e}, retrying");
+                tokio::time::sleep(retry_backoff(attempt)).await;
+            }
+            ret => return ret,
+        }
+    }
+}
+
+/// The delay before the next retry, it doubles per attempt from `RETRY_BACKOFF_MS`
+fn retry_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(RETRY_BACKOFF_MS.saturating_mul(1 << attempt.saturating_sub(1).min(16)))
+}
+
+/// Gets the value of the encoded key, retried on the connection errors
+async fn get_value(
+    kv: &jetstream::kv::Store,
+    key: &str,
+) -> std::result::Result<Option<Bytes>, jetstream::kv::EntryError> {
+    with_retry("get", || kv.get(key)).await
+}
+
+/// The delay before the next lock attempt, the ceiling doubles per attempt from
+/// `LOCKER_BACKOFF_MIN_MS` up to `LOCKER_BACKOFF_MAX_MS` and the delay is randomized
+/// between half of the ceiling and the ceiling
+fn lock_backoff(attempt: u32) -> Duration {
+    let ceiling = LOCKER_BACKOFF_MIN_MS
+        .saturating_mul(1 << attempt.min(16))
+        .min(LOCKER_BACKOFF_MAX_MS);
+    let half = ceiling / 2;
+    let jitter = (rand::get_rand_u128() % (half as u128 + 1)) as u64;
+    Duration::from_millis(half + jitter)
+}
+
 async fn wait_for_delete(bucket: &jetstream::kv::Store, key: &str, orig_key: &str) -> Result<()> {
     let mut ticker =
         tokio::time::interval(tokio::time::Duration::from_secs(LOCKER_WATCHER_CHECK_TTL));
@@ -831,7 +1595,9 @@ async fn check_exist_lock(
             if expiration < now_micros() {
                 if let Err(err) = bucket.purge(&key).await {
                     log::error!("nats purge lock for key: {orig_key}, error: {err}");
-                    return Err(Error::Message("nats purge lock error".to_string()));
+                    return Err(Error::LockUnavailable(format!(
+                        "nats purge lock for key: {orig_key}, error: {err}"
+                    )));
                 };
                 true
             } else {
@@ -881,6 +1647,66 @@ async fn keep_alive_lock(
     Ok(())
 }
 
+/// Send the watch event to the consumer.
+///
+/// When `timeout` is zero the event is dropped immediately if the channel is full, which keeps the
+/// watcher fast but the consumer may miss updates. Otherwise the watcher waits up to `timeout` for
+/// the consumer to catch up, slow consumers then apply backpressure to the watcher instead of
+/// losing events, and the event is only dropped when the timeout expires.
+async fn send_watch_event(
+    tx: &mpsc::Sender<Event>,
+    event: Event,
+    timeout: Duration,
+) -> std::result::Result<(), String> {
+    if timeout.is_zero() {
+        tx.try_send(event).map_err(|e| e.to_string())
+    } else {
+        tx.send_timeout(event, timeout)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Compresses the kv value when `ZO_NATS_COMPRESSION` is set and the value is larger than
+/// `ZO_NATS_COMPRESSION_THRESHOLD`. The compressed value starts with [`COMPRESSION_MAGIC`],
+/// the format version and the codec, so it can be told apart from the uncompressed values.
+fn encode_value(value: Bytes) -> Result<Bytes> {
+    let cfg = get_config();
+    compress_value(value, &cfg.nats.compression, cfg.nats.compression_threshold)
+}
+
+fn compress_value(value: Bytes, compression: &str, threshold: usize) -> Result<Bytes> {
+    if compression != "zstd" || value.len() <= threshold {
+        return Ok(value);
+    }
+    let compressed = zstd::encode_all(value.as_ref(), 3)
+        .map_err(|e| Error::Message(format!("[NATS:encode_value] zstd error: {e}")))?;
+    let mut buf = Vec::with_capacity(COMPRESSION_HEADER_LEN + compressed.len());
+    buf.extend_from_slice(COMPRESSION_MAGIC);
+    buf.push(COMPRESSION_VERSION);
+    buf.push(COMPRESSION_CODEC_ZSTD);
+    buf.extend_from_slice(&compressed);
+    Ok(buf.into())
+}
+
+/// Decompresses the kv value written by [`encode_value`], the values without the header are
+/// returned as they are, whether compression is enabled or not.
+fn decode_value(value: Bytes) -> Result<Bytes> {
+    if value.len() < COMPRESSION_HEADER_LEN || !value.starts_with(COMPRESSION_MAGIC) {
+        return Ok(value);
+    }
+    let version = value[COMPRESSION_MAGIC.len()];
+    let codec = value[COMPRESSION_MAGIC.len() + 1];
+    if version != COMPRESSION_VERSION || codec != COMPRESSION_CODEC_ZSTD {
+        return Err(Error::Message(format!(
+            "[NATS:decode_value] unsupported compression, version: {version}, codec: {codec}"
+        )));
+    }
+    zstd::decode_all(&value[COMPRESSION_HEADER_LEN..])
+        .map(Bytes::from)
+        .map_err(|e| Error::Message(format!("[NATS:decode_value] zstd error: {e}")))
+}
+
 #[inline]
 fn key_encode(key: &str) -> String {
     base64::encode(key).replace('+', "-").replace('/', "_")
@@ -891,6 +1717,21 @@ fn key_decode(key: &str) -> String {
     base64::decode(key.replace('-', "+").replace('_', "/")).unwrap()
 }
 
+/// The key as it's delivered by the kv watcher, i.e. `/{bucket}/{key}`
+#[inline]
+fn watch_key(key: &str) -> String {
+    format!("/{}", key.trim_start_matches('/'))
+}
+
+#[inline]
+fn delete_event(key: String) -> Event {
+    Event::Delete(EventData {
+        key,
+        value: None,
+        start_dt: None,
+    })
+}
+
 #[inline]
 fn use_kv_watcher(key: &str) -> bool {
     config::NATS_KV_WATCH_MODULES
@@ -902,6 +1743,38 @@ fn use_kv_watcher(key: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bucket_ttl_drift() {
+        let existing = jetstream::stream::Config {
+            name: "KV_o2_nodes".to_string(),
+            max_age: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert_eq!(bucket_ttl_drift(&existing, Duration::from_secs(30)), None);
+        assert_eq!(
+            bucket_ttl_drift(&existing, Duration::from_secs(60)),
+            Some(Duration::from_secs(30))
+        );
+        // a bucket created without ttl drifts as well
+        let existing = jetstream::stream::Config::default();
+        assert_eq!(
+            bucket_ttl_drift(&existing, Duration::from_secs(30)),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_is_delete_marker() {
+        assert!(!is_delete_marker(None));
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Nats-Msg-Id", "1");
+        assert!(!is_delete_marker(Some(&headers)));
+        headers.insert(KV_OPERATION_HEADER, "DEL");
+        assert!(is_delete_marker(Some(&headers)));
+        headers.insert(KV_OPERATION_HEADER, "PURGE");
+        assert!(is_delete_marker(Some(&headers)));
+    }
+
     #[test]
     fn test_use_kv_watcher() {
         assert!(!use_kv_watcher("/super_cluster_kv_nodes/"));
@@ -909,6 +1782,78 @@ mod tests {
         assert!(!use_kv_watcher("/other_prefix/"));
     }
 
+    #[tokio::test]
+    async fn test_local_kv_watcher() {
+        let (tx, mut rx) = mpsc::channel(10);
+        let watcher = Arc::new(LocalKvWatcher::new("o2_", "nodes/", tx));
+        assert!(watcher.matches("o2_", "/nodes/node1"));
+        assert!(!watcher.matches("super_cluster_kv_", "/nodes/node1"));
+        assert!(!watcher.matches("o2_", "/clusters/c1"));
+
+        let watchers = vec![watcher.clone()];
+        for w in watchers.iter() {
+            w.mark("/nodes/node1", false);
+        }
+        let event = Event::Put(EventData {
+            key: "/nodes/node1".to_string(),
+            value: Some(Bytes::from("v1")),
+            start_dt: None,
+        });
+        notify_local_kv_watchers(&watchers, event.clone(), 10).await;
+        assert_eq!(rx.recv().await, Some(event.clone()));
+
+        // JetStream's copy of the write is skipped once, the next one is delivered
+        assert!(!watcher.unmark("/nodes/node1", true));
+        assert!(watcher.unmark("/nodes/node1", false));
+        assert!(!watcher.unmark("/nodes/node1", false));
+
+        // a newer write delivered by JetStream isn't overwritten by an older local event
+        let newer = Event::Put(EventData {
+            key: "/nodes/node1".to_string(),
+            value: Some(Bytes::from("v3")),
+            start_dt: None,
+        });
+        assert!(
+            watcher
+                .send("/nodes/node1", 12, newer.clone(), Duration::ZERO)
+                .await
+                .unwrap()
+        );
+        assert_eq!(rx.recv().await, Some(newer));
+        watcher.mark("/nodes/node1", false);
+        notify_local_kv_watchers(&watchers, event, 11).await;
+        assert!(rx.try_recv().is_err());
+
+        drop(rx);
+        assert!(!watcher.matches("o2_", "/nodes/node1"));
+    }
+
+    #[test]
+    fn test_compress_value_roundtrip() {
+        let large = Bytes::from(r#"{"name":"schema","fields":[]}"#.repeat(100));
+        let compressed = compress_value(large.clone(), "zstd", 1024).unwrap();
+        assert!(compressed.starts_with(COMPRESSION_MAGIC));
+        assert!(compressed.len() < large.len());
+        assert_eq!(decode_value(compressed).unwrap(), large);
+
+        // below the threshold or compression disabled the value is kept as it is
+        let small = Bytes::from(r#"{"name":"schema"}"#);
+        let value = compress_value(small.clone(), "zstd", 1024).unwrap();
+        assert_eq!(value, small);
+        assert_eq!(decode_value(value).unwrap(), small);
+        let value = compress_value(large.clone(), "", 1024).unwrap();
+        assert_eq!(value, large);
+
+        // legacy values and values shorter than the header
+        assert_eq!(decode_value(large.clone()).unwrap(), large);
+        assert_eq!(decode_value(Bytes::from("1")).unwrap(), Bytes::from("1"));
+        assert_eq!(decode_value(Bytes::new()).unwrap(), Bytes::new());
+
+        let mut unknown = COMPRESSION_MAGIC.to_vec();
+        unknown.extend_from_slice(&[2, 1, 0]);
+        assert!(decode_value(unknown.into()).is_err());
+    }
+
     #[test]
     fn test_key_encode_simple() {
         let key = "test_key";
@@ -1080,6 +2025,25 @@ mod tests {
         assert_eq!(LOCKER_WATCHER_UPDATE_TTL, 10);
     }
 
+    #[test]
+    fn test_jetstream_domain() {
+        assert_eq!(jetstream_domain(""), None);
+        assert_eq!(jetstream_domain("  "), None);
+        assert_eq!(jetstream_domain("leaf"), Some("leaf"));
+        assert_eq!(jetstream_domain(" hub "), Some("hub"));
+    }
+
+    #[test]
+    fn test_lock_backoff() {
+        for attempt in 0..20 {
+            let ceiling = (LOCKER_BACKOFF_MIN_MS << attempt.min(16)).min(LOCKER_BACKOFF_MAX_MS);
+            let backoff = lock_backoff(attempt).as_millis() as u64;
+            assert!(backoff >= ceiling / 2 && backoff <= ceiling);
+        }
+        assert!(lock_backoff(0).as_millis() as u64 <= LOCKER_BACKOFF_MIN_MS);
+        assert!(lock_backoff(u32::MAX).as_millis() as u64 >= LOCKER_BACKOFF_MAX_MS / 2);
+    }
+
     #[test]
     fn test_key_encode_long_string() {
         let long_key = "a".repeat(1000);
@@ -1122,6 +2086,51 @@ mod tests {
         assert_eq!(db.prefix, "");
     }
 
+    #[tokio::test]
+    async fn test_send_watch_event_drop_when_full() {
+        let (tx, _rx) = mpsc::channel(1);
+        assert!(
+            send_watch_event(&tx, Event::Empty, Duration::ZERO)
+                .await
+                .is_ok()
+        );
+        assert!(
+            send_watch_event(&tx, Event::Empty, Duration::ZERO)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_watch_event_blocking_not_lost() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let consumer = tokio::spawn(async move {
+            let mut keys = Vec::new();
+            while let Some(event) = rx.recv().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                if let Event::Put(data) = event {
+                    keys.push(data.key);
+                }
+            }
+            keys
+        });
+        for i in 0..10 {
+            let event = Event::Put(EventData {
+                key: format!("/test/watch/{i}"),
+                value: None,
+                start_dt: None,
+            });
+            send_watch_event(&tx, event, Duration::from_secs(5))
+                .await
+                .unwrap();
+        }
+        drop(tx);
+        let keys = consumer.await.unwrap();
+        assert_eq!(keys.len(), 10);
+        assert_eq!(keys[0], "/test/watch/0");
+        assert_eq!(keys[9], "/test/watch/9");
+    }
+
     #[test]
     fn test_key_encode_base64_padding() {
         // Test various lengths to ensure base64 padding is handled correctly
@@ -1133,4 +2142,45 @@ mod tests {
             assert_eq!(decoded, key, "Failed for key of length: {}", key.len());
         }
     }
+
+    #[tokio::test]
+    async fn test_with_retry() {
+        use jetstream::kv::{EntryError, EntryErrorKind};
+
+        // a timed out request is retried until it succeeds
+        let mut calls = 0;
+        let ret = with_retry("get", || {
+            calls += 1;
+            let ret = if calls < 3 {
+                Err(EntryError::new(EntryErrorKind::TimedOut))
+            } else {
+                Ok(Some(Bytes::from("v")))
+            };
+            async move { ret }
+        })
+        .await;
+        assert_eq!(ret.unwrap(), Some(Bytes::from("v")));
+        assert_eq!(calls, 3);
+
+        // it gives up after the max attempts
+        let mut calls = 0;
+        let ret = with_retry("get", || {
+            calls += 1;
+            async { Err::<(), _>(EntryError::new(EntryErrorKind::TimedOut)) }
+        })
+        .await;
+        assert!(ret.is_err());
+        assert_eq!(calls, RETRY_MAX_ATTEMPTS + 1);
+
+        // a logical error is returned right away
+        let mut calls = 0;
+        let ret = with_retry("get", || {
+            calls += 1;
+            async { Err::<(), _>(EntryError::new(EntryErrorKind::InvalidKey)) }
+        })
+        .await;
+        assert!(ret.is_err());
+        assert_eq!(calls, 1);
+        assert!(!Error::from(DbError::KeyNotExists("/a".to_string())).is_retryable());
+    }
 }
@@ -29,7 +29,7 @@ use sqlx::{
 };
 use tokio::sync::{OnceCell, mpsc};
 
-use super::{DBIndex, IndexStatement};
+use super::{DBIndex, IndexStatement, OrderBy};
 use crate::errors::*;
 
 pub static CLIENT: Lazy<Pool<Postgres>> = Lazy::new(|| connect(false, false));
@@ -143,6 +143,22 @@ impl super::Db for PostgresDb {
         Ok(Bytes::from(value))
     }
 
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let (module, key1, key2) = super::parse_key(key);
+        let pool = CLIENT_RO.clone();
+        DB_QUERY_NUMS.with_label_values(&["select", "meta"]).inc();
+        let ret: Option<i32> = sqlx::query_scalar(
+            r#"SELECT 1 FROM meta WHERE module = $1 AND key1 = $2 AND key2 = $3 LIMIT 1;"#,
+        )
+        .bind(&module)
+        .bind(&key1)
+        .bind(&key2)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| Error::from(DbError::DBOperError(e.to_string(), key.to_string())))?;
+        Ok(ret.is_some())
+    }
+
     async fn put(
         &self,
         key: &str,
@@ -221,7 +237,7 @@ impl super::Db for PostgresDb {
         need_watch: bool,
         start_dt: Option<i64>,
         update_fn: Box<super::UpdateFn>,
-    ) -> Result<()> {
+    ) -> Result<super::UpdateOutcome> {
         let (module, key1, key2) = super::parse_key(key);
         let pool = CLIENT.clone();
         let mut tx = pool.begin().await?;
@@ -303,10 +319,11 @@ impl super::Db for PostgresDb {
                 if let Err(e) = tx.rollback().await {
                     log::error!("[POSTGRES] rollback get_for_update error: {e}");
                 }
-                return Ok(());
+                return Ok(super::UpdateOutcome::unchanged(exist));
             }
             Ok(Some(v)) => v,
         };
+        let written = value.is_some() || new_value.is_some();
 
         // update value
         if let Some(value) = value {
@@ -379,7 +396,10 @@ impl super::Db for PostgresDb {
                 .await?;
         }
 
-        Ok(())
+        Ok(super::UpdateOutcome {
+            existed: exist,
+            written,
+        })
     }
 
     async fn delete(
@@ -442,6 +462,28 @@ impl super::Db for PostgresDb {
         Ok(())
     }
 
+    async fn delete_org(&self, org_id: &str) -> Result<u64> {
+        let pool = CLIENT.clone();
+        DB_QUERY_NUMS.with_label_values(&["delete", "meta"]).inc();
+        let records: Vec<(String, String, String)> =
+            sqlx::query_as(r#"DELETE FROM meta WHERE key1 = $1 RETURNING module, key1, key2;"#)
+                .bind(org_id)
+                .fetch_all(&pool)
+                .await?;
+
+        let deleted = records.len() as u64;
+        let items = super::deleted_keys(records);
+        let cluster_coordinator = super::get_coordinator().await;
+        tokio::task::spawn(async move {
+            for key in items {
+                if let Err(e) = cluster_coordinator.delete(&key, false, true, None).await {
+                    log::error!("[POSTGRES] send event error: {e}");
+                }
+            }
+        });
+        Ok(deleted)
+    }
+
     async fn list(&self, prefix: &str) -> Result<HashMap<String, Bytes>> {
         let (module, key1, key2) = super::parse_key(prefix);
         let mut sql = "SELECT id, module, key1, key2, start_dt, value FROM meta".to_string();
@@ -510,13 +552,10 @@ impl super::Db for PostgresDb {
         &self,
         prefix: &str,
         start_dt: Option<(i64, i64)>,
+        order: OrderBy,
     ) -> Result<Vec<(i64, Bytes)>> {
-        if start_dt.is_none() || start_dt == Some((0, 0)) {
-            let vals = self.list_values(prefix).await?;
-            return Ok(vals.into_iter().map(|v| (0, v)).collect());
-        }
-
-        let (min_dt, max_dt) = start_dt.unwrap();
+        // all the versions are listed with start_dt 0, but still in the requested order
+        let range = start_dt.filter(|dt| *dt != (0, 0));
         let (module, key1, key2) = super::parse_key(prefix);
         let mut sql = "SELECT id, module, key1, key2, start_dt, value FROM meta".to_string();
         if !module.is_empty() {
@@ -528,8 +567,15 @@ impl super::Db for PostgresDb {
         if !key2.is_empty() {
             sql = format!("{sql} AND (key2 = '{key2}' OR key2 LIKE '{key2}/%')");
         }
-        sql = format!("{sql} AND start_dt >= {min_dt} AND start_dt <= {max_dt}");
-        sql = format!("{sql} ORDER BY start_dt ASC");
+        if let Some((min_dt, max_dt)) = range {
+            sql = format!("{sql} AND start_dt >= {min_dt} AND start_dt <= {max_dt}");
+        }
+        sql = match order {
+            OrderBy::Asc => format!("{sql} ORDER BY start_dt ASC, module ASC, key1 ASC, key2 ASC"),
+            OrderBy::Desc => {
+                format!("{sql} ORDER BY start_dt DESC, module DESC, key1 DESC, key2 DESC")
+            }
+        };
 
         let pool = CLIENT_RO.clone();
         DB_QUERY_NUMS.with_label_values(&["select", "meta"]).inc();
@@ -538,7 +584,10 @@ impl super::Db for PostgresDb {
             .await?;
         Ok(ret
             .into_iter()
-            .map(|r| (r.start_dt, Bytes::from(r.value)))
+            .map(|r| {
+                let start_dt = if range.is_some() { r.start_dt } else { 0 };
+                (start_dt, Bytes::from(r.value))
+            })
             .collect())
     }
 
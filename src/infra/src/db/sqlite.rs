@@ -13,23 +13,34 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{
+        Arc, Once,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use config::{FxIndexMap, cluster, utils::util::zero_or};
+use config::{FxIndexMap, cluster, metrics, utils::util::zero_or};
 use hashbrown::HashMap;
 use once_cell::sync::Lazy;
 use sqlx::{
-    Pool, Sqlite,
+    Pool, QueryBuilder, Sqlite,
     sqlite::{
         SqliteConnectOptions, SqliteJournalMode, SqliteLockingMode, SqlitePoolOptions,
         SqliteSynchronous,
     },
 };
-use tokio::sync::{Mutex, OnceCell, RwLock, mpsc};
+use tokio::{
+    sync::{Mutex, MutexGuard, Notify, OnceCell, RwLock, mpsc, watch},
+    task::JoinHandle,
+};
 
-use super::{DBIndex, IndexStatement};
+use super::{DBIndex, IndexStatement, OrderBy};
 use crate::{
     db::{Event, EventData},
     errors::*,
@@ -39,14 +50,83 @@ pub static CLIENT_RO: Lazy<Pool<Sqlite>> = Lazy::new(connect_ro);
 pub static CLIENT_RW: Lazy<Arc<Mutex<Pool<Sqlite>>>> =
     Lazy::new(|| Arc::new(Mutex::new(connect_rw())));
 static INDICES: OnceCell<HashSet<DBIndex>> = OnceCell::const_new();
+static MAINTENANCE_LOCKER: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static POOL_METRICS_REPORTER: Once = Once::new();
 
-pub static CHANNEL: Lazy<SqliteDbChannel> = Lazy::new(SqliteDbChannel::new);
+/// Pages in the WAL before a write checkpoints it, this is the sqlite default but it is set
+/// explicitly so the WAL stays bounded between the maintenance checkpoints regardless of how
+/// sqlite was built
+const WAL_AUTOCHECKPOINT_PAGES: u32 = 1000;
+
+/// Seconds to wait for the watch events to be delivered on close
+const CLOSE_TIMEOUT: u64 = 5;
 
-static WATCHERS: Lazy<RwLock<FxIndexMap<String, EventChannel>>> =
-    Lazy::new(|| RwLock::new(Default::default()));
+pub static CHANNEL: Lazy<SqliteDbChannel> = Lazy::new(SqliteDbChannel::new);
 
 type EventChannel = Arc<mpsc::Sender<Event>>;
 
+type Watchers = Arc<RwLock<FxIndexMap<String, Arc<Watcher>>>>;
+
+/// The events waiting to be delivered to the channel of a watcher, coalesced by key.
+///
+/// Each watcher is fed by its own task, so a slow watcher doesn't delay the others. While its
+/// channel is full only the latest event of each key is kept, the watcher then still gets the
+/// last change of every key and no event is dropped.
+struct Watcher {
+    pending: parking_lot::Mutex<FxIndexMap<String, Event>>,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+impl Watcher {
+    fn spawn(tx: mpsc::Sender<Event>) -> Arc<Self> {
+        let watcher = Arc::new(Self {
+            pending: parking_lot::Mutex::new(FxIndexMap::default()),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+        });
+        let w = watcher.clone();
+        tokio::task::spawn(async move { w.forward(tx).await });
+        watcher
+    }
+
+    fn push(&self, key: String, event: Event) {
+        if self.closed.load(Ordering::Acquire) {
+            return;
+        }
+        let mut pending = self.pending.lock();
+        // the latest event of the key replaces the pending one and is delivered last
+        pending.shift_remove(&key);
+        pending.insert(key, event);
+        drop(pending);
+        self.notify.notify_one();
+    }
+
+    /// Stops the watcher once the pending events are delivered, the receiver then ends.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    async fn forward(&self, tx: mpsc::Sender<Event>) {
+        loop {
+            let next = self.pending.lock().shift_remove_index(0);
+            match next {
+                Some((_, event)) => {
+                    if tx.send(event).await.is_err() {
+                        // the receiver is dropped, nobody watches the prefix anymore
+                        self.closed.store(true, Ordering::Release);
+                        self.pending.lock().clear();
+                        break;
+                    }
+                }
+                None if self.closed.load(Ordering::Acquire) => break,
+                None => self.notify.notified().await,
+            }
+        }
+    }
+}
+
 fn connect_rw() -> Pool<Sqlite> {
     let cfg = config::get_config();
     let url = format!("{}{}", cfg.common.data_db_dir, "metadata.sqlite");
@@ -66,6 +146,7 @@ fn connect_rw() -> Pool<Sqlite> {
         .synchronous(SqliteSynchronous::Normal)
         .locking_mode(SqliteLockingMode::Normal)
         .busy_timeout(Duration::from_secs(acquire_timeout))
+        .pragma("wal_autocheckpoint", WAL_AUTOCHECKPOINT_PAGES.to_string())
         .create_if_missing(true);
 
     SqlitePoolOptions::new()
@@ -81,12 +162,14 @@ fn connect_ro() -> Pool<Sqlite> {
     let cfg = config::get_config();
 
     let url = format!("{}{}", cfg.common.data_db_dir, "metadata.sqlite");
+    // the readers wait for the checkpoints of the writer as long as the writers wait for it
+    let acquire_timeout = zero_or(cfg.limit.sql_db_connections_acquire_timeout, 30);
     let db_opts = SqliteConnectOptions::from_str(&url)
         .expect("sqlite connect options create failed")
         .journal_mode(SqliteJournalMode::Wal)
         .synchronous(SqliteSynchronous::Normal)
         .locking_mode(SqliteLockingMode::Normal)
-        .busy_timeout(Duration::from_secs(30))
+        .busy_timeout(Duration::from_secs(acquire_timeout))
         // .disable_statement_logging()
         .read_only(true);
 
@@ -101,10 +184,60 @@ fn connect_ro() -> Pool<Sqlite> {
         .min_connections(cfg.limit.sql_db_connections_min)
         .max_connections(cfg.limit.sql_db_connections_max)
         .max_lifetime(max_lifetime)
-        .acquire_timeout(Duration::from_secs(30))
+        .acquire_timeout(Duration::from_secs(acquire_timeout))
         .connect_lazy_with(db_opts)
 }
 
+/// Locks the write pool and records how long the lock took, the writes are serialized behind
+/// it so a slow write shows up here rather than as a busy timeout of sqlite.
+pub(crate) async fn lock_rw() -> MutexGuard<'static, Pool<Sqlite>> {
+    let start = Instant::now();
+    let client = CLIENT_RW.lock().await;
+    metrics::DB_POOL_LOCK_WAIT_TIME
+        .with_label_values(&["sqlite_rw"])
+        .observe(start.elapsed().as_secs_f64() * 1000.0);
+    client
+}
+
+fn report_pool_metrics(name: &str, pool: &Pool<Sqlite>) {
+    let size = pool.size() as i64;
+    let idle = pool.num_idle() as i64;
+    metrics::DB_POOL_CONNECTIONS
+        .with_label_values(&[name, "total"])
+        .set(size);
+    metrics::DB_POOL_CONNECTIONS
+        .with_label_values(&[name, "idle"])
+        .set(idle);
+    metrics::DB_POOL_CONNECTIONS
+        .with_label_values(&[name, "active"])
+        .set(size - idle);
+}
+
+/// Spawns the task reporting the size of the pools, it exits when the cluster goes offline.
+fn start_pool_metrics_reporter() {
+    let interval = config::get_config().limit.sql_db_pool_metrics_interval;
+    if interval == 0 {
+        return;
+    }
+    POOL_METRICS_REPORTER.call_once(|| {
+        tokio::task::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval));
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if cluster::is_offline() {
+                    break;
+                }
+                report_pool_metrics("sqlite_ro", &CLIENT_RO);
+                // the pool is cloned so the lock is released before reading it
+                let rw = { CLIENT_RW.lock().await.clone() };
+                report_pool_metrics("sqlite_rw", &rw);
+            }
+            log::info!("[SQLITE] pool metrics reporter exit");
+        });
+    });
+}
+
 async fn cache_indices() -> HashSet<DBIndex> {
     let client = CLIENT_RO.clone();
     let sql = r#"SELECT name,tbl_name FROM sqlite_master where type = 'index';"#;
@@ -122,61 +255,85 @@ async fn cache_indices() -> HashSet<DBIndex> {
 
 pub struct SqliteDbChannel {
     pub watch_tx: EventChannel,
+    watchers: Watchers,
+    shutdown_tx: watch::Sender<bool>,
+    task: parking_lot::Mutex<Option<JoinHandle<()>>>,
 }
 
 impl SqliteDbChannel {
     pub fn new() -> Self {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let watchers = Watchers::default();
+        let (watch_tx, task) = SqliteDbChannel::handle_watch_channel(watchers.clone(), shutdown_rx);
         Self {
-            watch_tx: SqliteDbChannel::handle_watch_channel(),
+            watch_tx,
+            watchers,
+            shutdown_tx,
+            task: parking_lot::Mutex::new(Some(task)),
         }
     }
 
-    fn handle_watch_channel() -> EventChannel {
+    /// Stops the watch event loop. The events already sent to the loop are delivered to the
+    /// watchers first, then the watchers are removed so their receivers end after the buffered
+    /// events are consumed. Waits at most `timeout` for the loop to exit.
+    pub async fn close(&self, timeout: Duration) {
+        self.shutdown_tx.send_replace(true);
+        let Some(task) = self.task.lock().take() else {
+            return;
+        };
+        if tokio::time::timeout(timeout, task).await.is_err() {
+            log::warn!("[SQLITE] watch event loop didn't exit in {timeout:?}");
+        }
+    }
+
+    fn handle_watch_channel(
+        watchers: Watchers,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) -> (EventChannel, JoinHandle<()>) {
         let (tx, mut rx) = mpsc::channel::<Event>(10000);
-        tokio::task::spawn(async move {
+        let task = tokio::task::spawn(async move {
             loop {
                 if cluster::is_offline() {
                     break;
                 }
-                let event = match rx.recv().await {
-                    Some(v) => v,
-                    None => {
-                        log::info!("[SQLITE] watch event channel closed");
-                        break;
-                    }
-                };
-                if config::get_config().common.print_key_event {
-                    log::info!("[SQLITE] watch event: {event:?}");
-                }
-                for (prefix, tx) in WATCHERS.read().await.iter() {
-                    match event.clone() {
-                        Event::Put(e) => {
-                            if e.key.starts_with(prefix) {
-                                let tx = tx.clone();
-                                tokio::task::spawn(async move {
-                                    if let Err(e) = tx.send(Event::Put(e)).await {
-                                        log::error!("[SQLITE] send put event error: {e}");
-                                    }
-                                });
-                            }
+                let event = tokio::select! {
+                    _ = shutdown_rx.changed() => {
+                        // drain the events sent before the shutdown
+                        while let Ok(event) = rx.try_recv() {
+                            dispatch_watch_event(&watchers, event).await;
                         }
-                        Event::Delete(e) => {
-                            if e.key.starts_with(prefix) {
-                                let tx = tx.clone();
-                                tokio::task::spawn(async move {
-                                    if let Err(e) = tx.send(Event::Delete(e)).await {
-                                        log::error!("[SQLITE] send delete event error: {e}");
-                                    }
-                                });
-                            }
+                        for (_, watcher) in watchers.write().await.drain(..) {
+                            watcher.close();
                         }
-                        Event::Empty => {}
+                        break;
                     }
-                }
+                    event = rx.recv() => event,
+                };
+                let Some(event) = event else {
+                    log::info!("[SQLITE] watch event channel closed");
+                    break;
+                };
+                dispatch_watch_event(&watchers, event).await;
             }
             log::info!("[SQLITE] watch event loop exit");
         });
-        Arc::new(tx)
+        (Arc::new(tx), task)
+    }
+}
+
+/// Sends the event to the watchers whose prefix matches the key of the event.
+async fn dispatch_watch_event(watchers: &Watchers, event: Event) {
+    if config::get_config().common.print_key_event {
+        log::info!("[SQLITE] watch event: {event:?}");
+    }
+    let key = match &event {
+        Event::Put(e) | Event::Delete(e) => e.key.clone(),
+        Event::Empty => return,
+    };
+    for (prefix, watcher) in watchers.read().await.iter() {
+        if key.starts_with(prefix.as_str()) {
+            watcher.push(key.clone(), event.clone());
+        }
     }
 }
 
@@ -192,6 +349,66 @@ impl SqliteDb {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// Returns the number of rows and the byte size of the values of each module, the largest
+    /// modules first.
+    pub async fn stats_by_module(&self) -> Result<Vec<(String, i64, i64)>> {
+        let pool = CLIENT_RO.clone();
+        stats_by_module(&pool).await
+    }
+}
+
+/// Checks the key without selecting its value.
+async fn key_exists(pool: &Pool<Sqlite>, key: &str) -> Result<bool> {
+    let (module, key1, key2) = super::parse_key(key);
+    let ret: Option<i64> = sqlx::query_scalar(
+        r#"SELECT 1 FROM meta WHERE module = $1 AND key1 = $2 AND key2 = $3 LIMIT 1;"#,
+    )
+    .bind(&module)
+    .bind(&key1)
+    .bind(&key2)
+    .fetch_optional(pool)
+    .await?;
+    Ok(ret.is_some())
+}
+
+/// Adds `delta` to the counter of the key, the caller holds the write lock so the read and the
+/// write aren't interleaved with other writes.
+async fn increment(pool: &Pool<Sqlite>, key: &str, delta: i64) -> Result<i64> {
+    let (module, key1, key2) = super::parse_key(key);
+    let value: Option<String> = sqlx::query_scalar(
+        r#"SELECT value FROM meta WHERE module = $1 AND key1 = $2 AND key2 = $3 AND start_dt = 0;"#,
+    )
+    .bind(&module)
+    .bind(&key1)
+    .bind(&key2)
+    .fetch_optional(pool)
+    .await?;
+    let exists = value.is_some();
+    // a non-numeric value is an error like in the other backends, CAST would turn it into 0
+    let value = super::parse_counter(key, value.map(Bytes::from).as_ref())? + delta;
+    let sql = if exists {
+        r#"UPDATE meta SET value = $4 WHERE module = $1 AND key1 = $2 AND key2 = $3 AND start_dt = 0;"#
+    } else {
+        r#"INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, $3, 0, $4);"#
+    };
+    sqlx::query(sql)
+        .bind(&module)
+        .bind(&key1)
+        .bind(&key2)
+        .bind(value.to_string())
+        .execute(pool)
+        .await?;
+    Ok(value)
+}
+
+async fn stats_by_module(pool: &Pool<Sqlite>) -> Result<Vec<(String, i64, i64)>> {
+    let ret = sqlx::query_as::<_, (String, i64, i64)>(
+        r#"SELECT module, COUNT(*) AS num, SUM(LENGTH(CAST(value AS BLOB))) AS size FROM meta GROUP BY module ORDER BY size DESC, module;"#,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ret)
 }
 
 impl Default for SqliteDb {
@@ -224,10 +441,15 @@ impl super::Db for SqliteDb {
     async fn get(&self, key: &str) -> Result<Bytes> {
         let (module, key1, key2) = super::parse_key(key);
         let pool = CLIENT_RO.clone();
-        let query = format!(
-            "SELECT value FROM meta WHERE module = '{module}' AND key1 = '{key1}' AND key2 = '{key2}' ORDER BY start_dt DESC;"
-        );
-        let value: String = match sqlx::query_scalar(&query).fetch_one(&pool).await {
+        let value: String = match sqlx::query_scalar(
+            r#"SELECT value FROM meta WHERE module = $1 AND key1 = $2 AND key2 = $3 ORDER BY start_dt DESC;"#,
+        )
+        .bind(&module)
+        .bind(&key1)
+        .bind(&key2)
+        .fetch_one(&pool)
+        .await
+        {
             Ok(v) => v,
             Err(e) => {
                 if let sqlx::Error::RowNotFound = e {
@@ -243,6 +465,11 @@ impl super::Db for SqliteDb {
         Ok(Bytes::from(value))
     }
 
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let pool = CLIENT_RO.clone();
+        key_exists(&pool, key).await
+    }
+
     async fn put(
         &self,
         key: &str,
@@ -252,8 +479,7 @@ impl super::Db for SqliteDb {
     ) -> Result<()> {
         let (module, key1, key2) = super::parse_key(key);
         let local_start_dt = start_dt.unwrap_or_default();
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         if let Err(e) = sqlx::query(
             r#"INSERT OR IGNORE INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, $3, $4, '');"#
@@ -319,16 +545,67 @@ impl super::Db for SqliteDb {
         Ok(())
     }
 
+    async fn put_batch(&self, items: Vec<(String, Bytes, Option<i64>)>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let client = lock_rw().await;
+        let mut tx = client.begin().await?;
+        for (key, value, start_dt) in items.iter() {
+            let (module, key1, key2) = super::parse_key(key);
+            if let Err(e) = sqlx::query(
+                r#"INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (module, key1, key2, start_dt) DO UPDATE SET value = excluded.value;"#
+            )
+            .bind(&module)
+            .bind(&key1)
+            .bind(&key2)
+            .bind(start_dt.unwrap_or_default())
+            .bind(String::from_utf8(value.to_vec()).unwrap_or_default())
+            .execute(&mut *tx)
+            .await
+            {
+                if let Err(e) = tx.rollback().await {
+                    log::error!("[SQLITE] rollback put_batch meta error: {e}");
+                }
+                return Err(e.into());
+            }
+        }
+        if let Err(e) = tx.commit().await {
+            log::error!("[SQLITE] commit put_batch meta error: {e}");
+            return Err(e.into());
+        }
+
+        // release lock
+        drop(client);
+
+        // event watch
+        for (key, value, start_dt) in items {
+            if let Err(e) = CHANNEL
+                .watch_tx
+                .clone()
+                .send(Event::Put(EventData {
+                    key,
+                    value: Some(value),
+                    start_dt,
+                }))
+                .await
+            {
+                log::error!("[SQLITE] send event error: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
     async fn get_for_update(
         &self,
         key: &str,
         need_watch: bool,
         start_dt: Option<i64>,
         update_fn: Box<super::UpdateFn>,
-    ) -> Result<()> {
+    ) -> Result<super::UpdateOutcome> {
         let (module, key1, key2) = super::parse_key(key);
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         let mut need_watch_dt = 0;
         let row = if let Some(start_dt) = start_dt {
@@ -385,10 +662,11 @@ impl super::Db for SqliteDb {
                 if let Err(e) = tx.rollback().await {
                     log::error!("[SQLITE] rollback get_for_update error: {e}");
                 }
-                return Ok(());
+                return Ok(super::UpdateOutcome::unchanged(exist));
             }
             Ok(Some(v)) => v,
         };
+        let written = value.is_some() || new_value.is_some();
 
         // update value
         if let Some(value) = value.as_ref() {
@@ -470,7 +748,10 @@ impl super::Db for SqliteDb {
             }
         }
 
-        Ok(())
+        Ok(super::UpdateOutcome {
+            existed: exist,
+            written,
+        })
     }
 
     async fn delete(
@@ -516,53 +797,53 @@ impl super::Db for SqliteDb {
             });
         }
 
-        let (module, key1, key2) = super::parse_key(key);
-        // Escape ' (single quote) character with ''
-        let (key1, key2) = (key1.replace("'", "''"), key2.replace("'", "''"));
-        let sql = if with_prefix {
-            if key1.is_empty() {
-                format!(r#"DELETE FROM meta WHERE module = '{module}';"#)
-            } else if key2.is_empty() {
-                format!(r#"DELETE FROM meta WHERE module = '{module}' AND key1 = '{key1}';"#)
-            } else {
-                format!(
-                    r#"DELETE FROM meta WHERE module = '{module}' AND key1 = '{key1}' AND (key2 = '{key2}' OR key2 LIKE '{key2}/%');"#
-                )
-            }
-        } else {
-            format!(
-                r#"DELETE FROM meta WHERE module = '{module}' AND key1 = '{key1}' AND key2 = '{key2}';"#
-            )
-        };
+        let mut query = build_delete_query(key, with_prefix, start_dt);
+        let client = lock_rw().await;
+        query.build().execute(&*client).await?;
+        Ok(())
+    }
 
-        let sql = if let Some(start_dt) = start_dt {
-            sql.replace(';', &format!(" AND start_dt = {start_dt};"))
-        } else {
-            sql
-        };
+    async fn delete_org(&self, org_id: &str) -> Result<u64> {
+        let client = lock_rw().await;
+        let records: Vec<(String, String, String)> =
+            sqlx::query_as(r#"DELETE FROM meta WHERE key1 = $1 RETURNING module, key1, key2;"#)
+                .bind(org_id)
+                .fetch_all(&*client)
+                .await?;
+        drop(client);
 
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
-        sqlx::query(&sql).execute(&*client).await?;
-        Ok(())
+        let deleted = records.len() as u64;
+        for key in super::deleted_keys(records) {
+            if let Err(e) = CHANNEL
+                .watch_tx
+                .send(Event::Delete(EventData {
+                    key,
+                    value: None,
+                    start_dt: None,
+                }))
+                .await
+            {
+                log::error!("[SQLITE] send event error: {e}");
+            }
+        }
+        Ok(deleted)
+    }
+
+    async fn increment(&self, key: &str, delta: i64) -> Result<i64> {
+        let client = lock_rw().await;
+        increment(&client, key, delta).await
     }
 
     async fn list(&self, prefix: &str) -> Result<HashMap<String, Bytes>> {
-        let (module, key1, key2) = super::parse_key(prefix);
-        let mut sql = "SELECT id, module, key1, key2, start_dt, value FROM meta".to_string();
-        if !module.is_empty() {
-            sql = format!("{sql} WHERE module = '{module}'");
-        }
-        if !key1.is_empty() {
-            sql = format!("{sql} AND key1 = '{key1}'");
-        }
-        if !key2.is_empty() {
-            sql = format!("{sql} AND (key2 = '{key2}' OR key2 LIKE '{key2}/%')");
-        }
-        sql = format!("{sql} ORDER BY start_dt ASC");
+        let mut query = build_list_query(
+            "SELECT id, module, key1, key2, start_dt, value FROM meta",
+            prefix,
+        );
+        query.push(" ORDER BY start_dt ASC");
 
         let pool = CLIENT_RO.clone();
-        let ret = sqlx::query_as::<_, super::MetaRecord>(&sql)
+        let ret = query
+            .build_query_as::<super::MetaRecord>()
             .fetch_all(&pool)
             .await?;
         Ok(ret
@@ -576,22 +857,52 @@ impl super::Db for SqliteDb {
             .collect())
     }
 
+    async fn list_paginated(
+        &self,
+        prefix: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<(String, Bytes)>, bool)> {
+        let mut query = build_list_query(
+            "SELECT id, module, key1, key2, start_dt, value FROM meta",
+            prefix,
+        );
+        // fetch one more row to know whether more pages exist
+        query
+            .push(" ORDER BY start_dt ASC, id ASC LIMIT ")
+            .push_bind(limit.saturating_add(1) as i64)
+            .push(" OFFSET ")
+            .push_bind(offset as i64);
+
+        let pool = CLIENT_RO.clone();
+        let mut ret = query
+            .build_query_as::<super::MetaRecord>()
+            .fetch_all(&pool)
+            .await?;
+        let has_more = ret.len() > limit;
+        ret.truncate(limit);
+        let items = ret
+            .into_iter()
+            .map(|r| {
+                (
+                    super::build_key(&r.module, &r.key1, &r.key2, r.start_dt),
+                    Bytes::from(r.value),
+                )
+            })
+            .collect();
+        Ok((items, has_more))
+    }
+
     async fn list_keys(&self, prefix: &str) -> Result<Vec<String>> {
-        let (module, key1, key2) = super::parse_key(prefix);
-        let mut sql = "SELECT id, module, key1, key2, start_dt, '' AS value FROM meta".to_string();
-        if !module.is_empty() {
-            sql = format!("{sql} WHERE module = '{module}'");
-        }
-        if !key1.is_empty() {
-            sql = format!("{sql} AND key1 = '{key1}'");
-        }
-        if !key2.is_empty() {
-            sql = format!("{sql} AND (key2 = '{key2}' OR key2 LIKE '{key2}/%')");
-        }
+        let mut query = build_list_query(
+            "SELECT id, module, key1, key2, start_dt, '' AS value FROM meta",
+            prefix,
+        );
+        query.push(" ORDER BY start_dt ASC");
 
-        sql = format!("{sql} ORDER BY start_dt ASC");
         let pool = CLIENT_RO.clone();
-        let ret = sqlx::query_as::<_, super::MetaRecord>(&sql)
+        let ret = query
+            .build_query_as::<super::MetaRecord>()
             .fetch_all(&pool)
             .await?;
         Ok(ret
@@ -614,78 +925,161 @@ impl super::Db for SqliteDb {
         &self,
         prefix: &str,
         start_dt: Option<(i64, i64)>,
+        order: OrderBy,
     ) -> Result<Vec<(i64, Bytes)>> {
-        if start_dt.is_none() || start_dt == Some((0, 0)) {
-            let vals = self.list_values(prefix).await?;
-            return Ok(vals.into_iter().map(|v| (0, v)).collect());
-        }
-
-        let (min_dt, max_dt) = start_dt.unwrap();
-        let (module, key1, key2) = super::parse_key(prefix);
-        let mut sql = "SELECT id, module, key1, key2, start_dt, value FROM meta".to_string();
-        if !module.is_empty() {
-            sql = format!("{sql} WHERE module = '{module}'");
-        }
-        if !key1.is_empty() {
-            sql = format!("{sql} AND key1 = '{key1}'");
-        }
-        if !key2.is_empty() {
-            sql = format!("{sql} AND (key2 = '{key2}' OR key2 LIKE '{key2}/%')");
-        }
-        sql = format!("{sql} AND start_dt >= {min_dt} AND start_dt <= {max_dt}");
-        sql = format!("{sql} ORDER BY start_dt ASC");
+        // all the versions are listed with start_dt 0, but still in the requested order
+        let range = start_dt.filter(|dt| *dt != (0, 0));
+        let mut query = build_list_by_start_dt_query(
+            "SELECT id, module, key1, key2, start_dt, value FROM meta",
+            prefix,
+            range,
+            order,
+        );
 
         let pool = CLIENT_RO.clone();
-        let ret = sqlx::query_as::<_, super::MetaRecord>(&sql)
+        let ret = query
+            .build_query_as::<super::MetaRecord>()
             .fetch_all(&pool)
             .await?;
         Ok(ret
             .into_iter()
-            .map(|r| (r.start_dt, Bytes::from(r.value)))
+            .map(|r| {
+                let start_dt = if range.is_some() { r.start_dt } else { 0 };
+                (start_dt, Bytes::from(r.value))
+            })
             .collect())
     }
 
     async fn count(&self, prefix: &str) -> Result<i64> {
-        let (module, key1, key2) = super::parse_key(prefix);
-        let mut sql = "SELECT COUNT(*) AS num FROM meta".to_string();
-        if !module.is_empty() {
-            sql = format!("{sql} WHERE module = '{module}'");
-        }
-        if !key1.is_empty() {
-            sql = format!("{sql} AND key1 = '{key1}'");
-        }
-        if !key2.is_empty() {
-            sql = format!("{sql} AND (key2 = '{key2}' OR key2 LIKE '{key2}/%')");
-        }
-
+        let mut query = build_list_query("SELECT COUNT(*) AS num FROM meta", prefix);
         let pool = CLIENT_RO.clone();
-        let count: i64 = sqlx::query_scalar(&sql).fetch_one(&pool).await?;
+        let count: i64 = query.build_query_scalar().fetch_one(&pool).await?;
         Ok(count)
     }
 
     async fn watch(&self, prefix: &str) -> Result<Arc<mpsc::Receiver<Event>>> {
-        let (tx, rx) = mpsc::channel(1024);
-        WATCHERS
+        let (tx, rx) = mpsc::channel(config::get_config().limit.sql_db_watch_channel_capacity);
+        if let Some(old) = CHANNEL
+            .watchers
             .write()
             .await
-            .insert(prefix.to_string(), Arc::new(tx));
+            .insert(prefix.to_string(), Watcher::spawn(tx))
+        {
+            old.close();
+        }
         Ok(Arc::new(rx))
     }
 
     async fn close(&self) -> Result<()> {
+        CHANNEL.close(Duration::from_secs(CLOSE_TIMEOUT)).await;
         Ok(())
     }
 
     async fn add_start_dt_column(&self) -> Result<()> {
+        let pending = check_migrations(&CLIENT_RO).await?;
+        if pending.is_empty() {
+            log::info!("[SQLITE] start_dt migration already applied, skip");
+            return Ok(());
+        }
+        log::info!(
+            "[SQLITE] applying start_dt migration: {}",
+            pending.join(", ")
+        );
         create_meta_backup().await?;
         add_start_dt_column().await?;
         Ok(())
     }
+
+    async fn check_migrations(&self) -> Result<Vec<String>> {
+        check_migrations(&CLIENT_RO).await
+    }
+}
+
+/// Escapes the LIKE wildcards `%` and `_` with `\`, used with `ESCAPE '\'`
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Appends the filter of key1 and key2 to the query, the key2 matches itself and its children
+fn push_key_filter(query: &mut QueryBuilder<'_, Sqlite>, key1: String, key2: String) {
+    if !key1.is_empty() {
+        query.push(" AND key1 = ").push_bind(key1);
+    }
+    if !key2.is_empty() {
+        let pattern = format!("{}/%", escape_like(&key2));
+        query
+            .push(" AND (key2 = ")
+            .push_bind(key2)
+            .push(" OR key2 LIKE ")
+            .push_bind(pattern)
+            .push(r" ESCAPE '\')");
+    }
+}
+
+/// Builds the query to select the records with the prefix, all the keys are bound as parameters
+fn build_list_query<'a>(select: &str, prefix: &str) -> QueryBuilder<'a, Sqlite> {
+    let (module, key1, key2) = super::parse_key(prefix);
+    let mut query = QueryBuilder::new(select);
+    if !module.is_empty() {
+        query.push(" WHERE module = ").push_bind(module);
+        push_key_filter(&mut query, key1, key2);
+    }
+    query
+}
+
+/// Builds the query to list the keys with the prefix within the start_dt range, ordered by
+/// start_dt and then by key
+fn build_list_by_start_dt_query<'a>(
+    select: &str,
+    prefix: &str,
+    range: Option<(i64, i64)>,
+    order: OrderBy,
+) -> QueryBuilder<'a, Sqlite> {
+    let mut query = build_list_query(select, prefix);
+    if let Some((min_dt, max_dt)) = range {
+        query
+            .push(" AND start_dt >= ")
+            .push_bind(min_dt)
+            .push(" AND start_dt <= ")
+            .push_bind(max_dt);
+    }
+    query.push(match order {
+        OrderBy::Asc => " ORDER BY start_dt ASC, module ASC, key1 ASC, key2 ASC",
+        OrderBy::Desc => " ORDER BY start_dt DESC, module DESC, key1 DESC, key2 DESC",
+    });
+    query
+}
+
+/// Builds the query to delete the key or the keys with the prefix
+fn build_delete_query<'a>(
+    key: &str,
+    with_prefix: bool,
+    start_dt: Option<i64>,
+) -> QueryBuilder<'a, Sqlite> {
+    let (module, key1, key2) = super::parse_key(key);
+    let mut query = QueryBuilder::new("DELETE FROM meta WHERE module = ");
+    query.push_bind(module);
+    if with_prefix {
+        push_key_filter(&mut query, key1, key2);
+    } else {
+        query
+            .push(" AND key1 = ")
+            .push_bind(key1)
+            .push(" AND key2 = ")
+            .push_bind(key2);
+    }
+    if let Some(start_dt) = start_dt {
+        query.push(" AND start_dt = ").push_bind(start_dt);
+    }
+    query
 }
 
 async fn create_table() -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    start_pool_metrics_reporter();
+    let client = lock_rw().await;
     // create table
     sqlx::query(
         r#"
@@ -704,9 +1098,6 @@ CREATE TABLE IF NOT EXISTS meta
     .await?;
     drop(client);
 
-    // create start_dt column for old version <= 0.9.2
-    add_start_dt_column().await?;
-
     // create table index
     create_index(IndexStatement::new(
         "meta_module_idx",
@@ -722,20 +1113,39 @@ CREATE TABLE IF NOT EXISTS meta
         &["module", "key1"],
     ))
     .await?;
-    create_index(IndexStatement::new(
-        "meta_module_start_dt_idx",
-        "meta",
-        true,
-        &["module", "key1", "key2", "start_dt"],
-    ))
-    .await?;
+    // the start_dt column is missing for old version <= 0.9.2, the index is created by the
+    // migration then
+    if has_start_dt_column(&CLIENT_RO).await? {
+        create_index(IndexStatement::new(
+            "meta_module_start_dt_idx",
+            "meta",
+            true,
+            &["module", "key1", "key2", "start_dt"],
+        ))
+        .await?;
+    }
 
-    Ok(())
+    // check the migration after the indexes are created, so nothing is pending on a fresh db
+    let pending = check_migrations(&CLIENT_RO).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    if !config::get_config().limit.meta_auto_migration {
+        log::warn!(
+            "[SQLITE] start_dt migration is pending: {}, ZO_META_AUTO_MIGRATION is disabled, apply it explicitly",
+            pending.join(", ")
+        );
+        return Ok(());
+    }
+    log::info!(
+        "[SQLITE] applying start_dt migration: {}",
+        pending.join(", ")
+    );
+    add_start_dt_column().await
 }
 
 async fn add_start_dt_column() -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
 
     add_column(&client, "meta", "start_dt", "INTEGER NOT NULL DEFAULT 0").await?;
     drop(client);
@@ -752,9 +1162,41 @@ async fn add_start_dt_column() -> Result<()> {
     Ok(())
 }
 
+/// Reports the steps of the start_dt migration that are still pending on the meta table without
+/// applying them: the missing start_dt column, the missing unique index and the old index that
+/// has to be dropped.
+async fn check_migrations(client: &Pool<Sqlite>) -> Result<Vec<String>> {
+    let indices: HashSet<String> = sqlx::query_scalar(
+        r#"SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'meta';"#,
+    )
+    .fetch_all(client)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut pending = Vec::new();
+    if !has_start_dt_column(client).await? {
+        pending.push("add column meta.start_dt".to_string());
+    }
+    if !indices.contains("meta_module_start_dt_idx") {
+        pending.push("create index meta_module_start_dt_idx".to_string());
+    }
+    if indices.contains("meta_module_key2_idx") {
+        pending.push("drop index meta_module_key2_idx".to_string());
+    }
+    Ok(pending)
+}
+
+async fn has_start_dt_column(client: &Pool<Sqlite>) -> Result<bool> {
+    let columns: Vec<(i64, String, String, i64, Option<String>, i64)> =
+        sqlx::query_as(r#"PRAGMA table_info(meta);"#)
+            .fetch_all(client)
+            .await?;
+    Ok(columns.iter().any(|(_, name, ..)| name == "start_dt"))
+}
+
 async fn create_meta_backup() -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     let mut tx = client.begin().await?;
     if let Err(e) =
         sqlx::query(r#"CREATE TABLE IF NOT EXISTS meta_backup_20240330 AS SELECT * FROM meta;"#)
@@ -773,9 +1215,49 @@ async fn create_meta_backup() -> Result<()> {
     Ok(())
 }
 
+/// Reclaims the space of the deleted rows, truncates the WAL file and rebuilds the database
+/// file. It holds the writer lock, so the writes wait for it instead of failing, and it
+/// returns immediately if another maintenance is running.
+pub async fn maintenance() -> Result<()> {
+    let Ok(_maintenance_guard) = MAINTENANCE_LOCKER.try_lock() else {
+        log::info!("[SQLITE] maintenance is already running, skip");
+        return Ok(());
+    };
+
+    let start = std::time::Instant::now();
+    let client = lock_rw().await;
+    let size_before = db_size(&client).await?;
+    let (busy, wal_pages, checkpointed_pages): (i64, i64, i64) =
+        sqlx::query_as(r#"PRAGMA wal_checkpoint(TRUNCATE);"#)
+            .fetch_one(&*client)
+            .await?;
+    if busy != 0 {
+        log::warn!(
+            "[SQLITE] maintenance wal checkpoint blocked by readers, checkpointed {checkpointed_pages}/{wal_pages} pages"
+        );
+    }
+    sqlx::query(r#"VACUUM;"#).execute(&*client).await?;
+    let size_after = db_size(&client).await?;
+    drop(client);
+
+    log::info!(
+        "[SQLITE] maintenance done, db size before: {size_before}, after: {size_after}, took: {} ms",
+        start.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+async fn db_size(client: &Pool<Sqlite>) -> Result<i64> {
+    let size: i64 = sqlx::query_scalar(
+        r#"SELECT (page_count * page_size) as size FROM pragma_page_count(), pragma_page_size();"#,
+    )
+    .fetch_one(client)
+    .await?;
+    Ok(size)
+}
+
 pub async fn create_index(index: IndexStatement<'_>) -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     let indices = INDICES.get_or_init(cache_indices).await;
     if indices.contains(&DBIndex {
         name: index.idx_name.into(),
@@ -802,8 +1284,7 @@ pub async fn create_index(index: IndexStatement<'_>) -> Result<()> {
 }
 
 pub async fn delete_index(idx_name: &str, table: &str) -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     let indices = INDICES.get_or_init(cache_indices).await;
     if !indices.contains(&DBIndex {
         name: idx_name.into(),
@@ -853,6 +1334,52 @@ pub async fn drop_column(client: &Pool<Sqlite>, table: &str, column: &str) -> Re
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_stats_by_module() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER not null primary key autoincrement, module VARCHAR not null, key1 VARCHAR not null, key2 VARCHAR not null, start_dt INTEGER not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        for (module, key1, value) in [
+            ("schema", "default", "x".repeat(100)),
+            ("schema", "org2", "x".repeat(50)),
+            ("short_urls", "a", "x".repeat(10)),
+            ("user", "root", "é".repeat(20)),
+        ] {
+            sqlx::query(
+                r#"INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ($1, $2, '', 0, $3);"#,
+            )
+            .bind(module)
+            .bind(key1)
+            .bind(value)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert!(key_exists(&pool, "/schema/org2").await.unwrap());
+        assert!(!key_exists(&pool, "/schema/org3").await.unwrap());
+        assert!(!key_exists(&pool, "/schema/org2/logs").await.unwrap());
+
+        let stats = stats_by_module(&pool).await.unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                ("schema".to_string(), 2, 150),
+                // the size is counted in bytes
+                ("user".to_string(), 1, 40),
+                ("short_urls".to_string(), 1, 10),
+            ]
+        );
+    }
+
     #[test]
     fn test_sqlite_db_new() {
         let db = SqliteDb::new();
@@ -1139,4 +1666,331 @@ mod tests {
         assert_eq!(k1, "key1");
         assert!(k2.starts_with("key2"));
     }
+
+    #[tokio::test]
+    async fn test_increment_non_numeric_value() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER not null primary key autoincrement, module VARCHAR not null, key1 VARCHAR not null, key2 VARCHAR not null, start_dt INTEGER not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(increment(&pool, "/counter/a", 2).await.unwrap(), 2);
+        assert_eq!(increment(&pool, "/counter/a", -3).await.unwrap(), -1);
+
+        sqlx::query(
+            r#"INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ('counter', 'b', '', 0, 'abc');"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(increment(&pool, "/counter/b", 1).await.is_err());
+        // the value is left as it is
+        let value: String = sqlx::query_scalar(
+            r#"SELECT value FROM meta WHERE module = 'counter' AND key1 = 'b';"#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(value, "abc");
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() {
+        super::super::create_table().await.unwrap();
+        maintenance().await.unwrap();
+
+        // only one maintenance runs at a time
+        let _guard = MAINTENANCE_LOCKER.lock().await;
+        maintenance().await.unwrap();
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("foo"), "foo");
+        assert_eq!(escape_like("foo%bar"), "foo\\%bar");
+        assert_eq!(escape_like("foo_bar"), "foo\\_bar");
+        assert_eq!(escape_like("foo\\bar"), "foo\\\\bar");
+    }
+
+    #[tokio::test]
+    async fn test_list_and_delete_with_special_chars() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER PRIMARY KEY AUTOINCREMENT, module VARCHAR(100) not null, key1 VARCHAR(256) not null, key2 VARCHAR(256) not null, start_dt INTEGER not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        for key2 in [
+            "foo",
+            "foo/bar",
+            "foo%bar",
+            "foobar/baz",
+            "foo%/x",
+            "it's/y",
+        ] {
+            sqlx::query(
+                "INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ('mod', 'k1', $1, 0, '');",
+            )
+            .bind(key2)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let list = |prefix: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let mut query = build_list_query(
+                    "SELECT id, module, key1, key2, start_dt, '' AS value FROM meta",
+                    prefix,
+                );
+                query.push(" ORDER BY id ASC");
+                query
+                    .build_query_as::<super::super::MetaRecord>()
+                    .fetch_all(&pool)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.key2)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(list("/mod/k1/foo").await, vec!["foo", "foo/bar"]);
+        assert_eq!(list("/mod/k1/foo%").await, vec!["foo%/x"]);
+        assert_eq!(list("/mod/k1/it's").await, vec!["it's/y"]);
+        assert_eq!(list("/mod/k1").await.len(), 6);
+
+        let mut query = build_list_query("SELECT COUNT(*) AS num FROM meta", "/mod/k1/foo");
+        let count: i64 = query.build_query_scalar().fetch_one(&pool).await.unwrap();
+        assert_eq!(count, 2);
+
+        build_delete_query("/mod/k1/foo%", true, None)
+            .build()
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(list("/mod/k1").await.len(), 5);
+        assert_eq!(list("/mod/k1/foo%").await, Vec::<String>::new());
+
+        build_delete_query("/mod/k1/foo", false, Some(1))
+            .build()
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(list("/mod/k1").await.len(), 5);
+        build_delete_query("/mod/k1/foo", false, Some(0))
+            .build()
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(list("/mod/k1/foo").await, vec!["foo/bar"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_start_dt_order() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER PRIMARY KEY AUTOINCREMENT, module VARCHAR(100) not null, key1 VARCHAR(256) not null, key2 VARCHAR(256) not null, start_dt INTEGER not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // inserted out of order, the versions of a key share the key
+        for (key2, start_dt) in [("b", 20), ("a", 20), ("c", 10), ("a", 30), ("b", 5)] {
+            sqlx::query(
+                "INSERT INTO meta (module, key1, key2, start_dt, value) VALUES ('mod', 'k1', $1, $2, '');",
+            )
+            .bind(key2)
+            .bind(start_dt)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let list = |range: Option<(i64, i64)>, order: OrderBy| {
+            let pool = pool.clone();
+            async move {
+                build_list_by_start_dt_query(
+                    "SELECT id, module, key1, key2, start_dt, '' AS value FROM meta",
+                    "/mod/k1",
+                    range,
+                    order,
+                )
+                .build_query_as::<super::super::MetaRecord>()
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.start_dt, r.key2))
+                .collect::<Vec<_>>()
+            }
+        };
+        let versions = |v: &[(i64, &str)]| {
+            v.iter()
+                .map(|(dt, key)| (*dt, key.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            list(Some((10, 20)), OrderBy::Asc).await,
+            versions(&[(10, "c"), (20, "a"), (20, "b")])
+        );
+        assert_eq!(
+            list(Some((10, 20)), OrderBy::Desc).await,
+            versions(&[(20, "b"), (20, "a"), (10, "c")])
+        );
+        assert_eq!(
+            list(None, OrderBy::Asc).await,
+            versions(&[(5, "b"), (10, "c"), (20, "a"), (20, "b"), (30, "a")])
+        );
+        assert_eq!(
+            list(None, OrderBy::Desc).await,
+            versions(&[(30, "a"), (20, "b"), (20, "a"), (10, "c"), (5, "b")])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watcher_coalesces_slow_consumer() {
+        let event = |key: &str, delete: bool| {
+            let data = EventData {
+                key: key.to_string(),
+                value: None,
+                start_dt: None,
+            };
+            if delete {
+                Event::Delete(data)
+            } else {
+                Event::Put(data)
+            }
+        };
+        let (tx, mut rx) = mpsc::channel(1);
+        let watcher = Watcher::spawn(tx);
+        for i in 0..100 {
+            watcher.push("/mod/k1".to_string(), event("/mod/k1", i == 99));
+            watcher.push("/mod/k2".to_string(), event("/mod/k2", false));
+        }
+        watcher.close();
+
+        // nothing is dropped, the last event of each key is delivered
+        let mut last = HashMap::new();
+        let mut received = 0;
+        while let Some(ev) = rx.recv().await {
+            received += 1;
+            match ev {
+                Event::Put(e) => last.insert(e.key, "put"),
+                Event::Delete(e) => last.insert(e.key, "delete"),
+                Event::Empty => None,
+            };
+        }
+        assert!(received <= 200);
+        assert_eq!(last.get("/mod/k1"), Some(&"delete"));
+        assert_eq!(last.get("/mod/k2"), Some(&"put"));
+    }
+
+    #[tokio::test]
+    async fn test_channel_close_drains_events() {
+        let channel = SqliteDbChannel::new();
+        let (tx, mut rx) = mpsc::channel(10);
+        channel
+            .watchers
+            .write()
+            .await
+            .insert("/close_test/".to_string(), Watcher::spawn(tx));
+        for i in 0..3 {
+            channel
+                .watch_tx
+                .send(Event::Put(EventData {
+                    key: format!("/close_test/{i}"),
+                    value: None,
+                    start_dt: None,
+                }))
+                .await
+                .unwrap();
+        }
+        channel.close(Duration::from_secs(5)).await;
+
+        // the buffered events are delivered, then the watcher ends
+        for i in 0..3 {
+            match rx.recv().await {
+                Some(Event::Put(e)) => assert_eq!(e.key, format!("/close_test/{i}")),
+                v => panic!("unexpected event: {v:?}"),
+            }
+        }
+        assert!(rx.recv().await.is_none());
+        assert!(channel.watch_tx.is_closed());
+    }
+
+    async fn memory_pool() -> Pool<Sqlite> {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_check_migrations_old_schema() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER not null primary key autoincrement, module VARCHAR not null, key1 VARCHAR not null, key2 VARCHAR not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(r#"CREATE UNIQUE INDEX meta_module_key2_idx ON meta (module, key1, key2);"#)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(!has_start_dt_column(&pool).await.unwrap());
+
+        let expected = vec![
+            "add column meta.start_dt".to_string(),
+            "create index meta_module_start_dt_idx".to_string(),
+            "drop index meta_module_key2_idx".to_string(),
+        ];
+        assert_eq!(check_migrations(&pool).await.unwrap(), expected);
+        // checking doesn't apply anything
+        assert_eq!(check_migrations(&pool).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_check_migrations_migrated_schema() {
+        let pool = memory_pool().await;
+        sqlx::query(
+            r#"CREATE TABLE meta (id INTEGER not null primary key autoincrement, module VARCHAR not null, key1 VARCHAR not null, key2 VARCHAR not null, start_dt INTEGER not null, value TEXT not null);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(has_start_dt_column(&pool).await.unwrap());
+        assert_eq!(
+            check_migrations(&pool).await.unwrap(),
+            vec!["create index meta_module_start_dt_idx".to_string()]
+        );
+
+        sqlx::query(
+            r#"CREATE UNIQUE INDEX meta_module_start_dt_idx ON meta (module, key1, key2, start_dt);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(check_migrations(&pool).await.unwrap().is_empty());
+    }
 }
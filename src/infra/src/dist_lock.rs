@@ -64,6 +64,9 @@ pub async fn unlock_with_trace_id(trace_id: &str, locker: &Option<Locker>) -> Re
 }
 
 /// lock key in nats, wait_ttl is 0 means wait forever
+///
+/// Fails with `Error::LockTimeout` if the key is still locked by someone else after `wait_ttl`
+/// and with `Error::LockUnavailable` if the lock store failed.
 #[inline(always)]
 pub async fn lock(key: &str, wait_ttl: u64) -> Result<Option<Locker>> {
     let cfg = config::get_config();
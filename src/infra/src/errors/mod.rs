@@ -41,6 +41,12 @@ pub enum Error {
     ObjectStoreError(#[from] object_store::Error),
     #[error("WatchError# watcher is exists {0}")]
     WatcherExists(String),
+    /// The lock is held by someone else and wasn't released in time, retrying may succeed.
+    #[error("LockTimeout# {0}")]
+    LockTimeout(String),
+    /// The lock couldn't be acquired because the lock store failed.
+    #[error("LockUnavailable# {0}")]
+    LockUnavailable(String),
     #[error("StringUTF8Error# {0}")]
     StringUTF8Error(#[from] std::string::FromUtf8Error),
     #[error("SqlxError# {0}")]
@@ -121,6 +127,12 @@ pub enum DbError {
     UniqueViolation,
     #[error("SeaORMError# {0}")]
     SeaORMError(String),
+    /// The meta store is up but didn't answer the health check in time
+    #[error("health check timed out in {0} ms")]
+    HealthCheckTimeout(u64),
+    /// The meta store failed the health check
+    #[error("health check failed: {0}")]
+    HealthCheckFailed(String),
     #[error("error getting dashboard")]
     GetDashboardError(#[from] GetDashboardError),
     #[error("PutDashboard# {0}")]
@@ -399,5 +411,16 @@ mod tests {
             "DbError# key /another/shrubbery does not exist",
             &err.to_string()
         );
+
+        let err = Error::LockTimeout("nats lock for key: /a, acquire timeout in 1s".to_string());
+        assert_eq!(
+            "LockTimeout# nats lock for key: /a, acquire timeout in 1s",
+            &err.to_string()
+        );
+        let err = Error::LockUnavailable("nats lock for key: /a, error: closed".to_string());
+        assert_eq!(
+            "LockUnavailable# nats lock for key: /a, error: closed",
+            &err.to_string()
+        );
     }
 }
@@ -32,7 +32,7 @@ use sqlx::{Executor, QueryBuilder, Row, Sqlite};
 use crate::{
     db::{
         IndexStatement,
-        sqlite::{CLIENT_RO, CLIENT_RW, add_column, create_index, delete_index},
+        sqlite::{CLIENT_RO, add_column, create_index, delete_index, lock_rw},
     },
     errors::{Error, Result},
     file_list::FileRecord,
@@ -72,8 +72,7 @@ impl super::FileList for SqliteFileList {
     }
 
     async fn remove(&self, file: &str) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let pool = client.clone();
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).map_err(|e| Error::Message(e.to_string()))?;
@@ -104,8 +103,7 @@ impl super::FileList for SqliteFileList {
     }
 
     async fn update_dump_records(&self, file: &FileKey, dumped_ids: &[i64]) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
 
         // insert the dump file into file_list table
@@ -178,8 +176,7 @@ impl super::FileList for SqliteFileList {
         let chunks = files.chunks(100);
         for files in chunks {
             // we don't care the id here, because the id is from file_list table not for this table
-            let client = CLIENT_RW.clone();
-            let client = client.lock().await;
+            let client = lock_rw().await;
             let mut tx = client.begin().await?;
             let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
                 "INSERT INTO file_list_deleted (account, org, stream, date, file, index_file, flattened, created_at)",
@@ -217,8 +214,7 @@ impl super::FileList for SqliteFileList {
         let chunks = files.chunks(100);
         for files in chunks {
             // get ids of the files
-            let client = CLIENT_RW.clone();
-            let client = client.lock().await;
+            let client = lock_rw().await;
             let pool = client.clone();
             let mut ids = Vec::with_capacity(files.len());
             for file in files {
@@ -300,8 +296,7 @@ SELECT min_ts, max_ts, records, original_size, compressed_size, index_size, flat
     }
 
     async fn update_flattened(&self, file: &str, flattened: bool) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).map_err(|e| Error::Message(e.to_string()))?;
         sqlx::query(
@@ -317,8 +312,7 @@ SELECT min_ts, max_ts, records, original_size, compressed_size, index_size, flat
     }
 
     async fn update_compressed_size(&self, file: &str, size: i64) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).map_err(|e| Error::Message(e.to_string()))?;
         sqlx::query(
@@ -722,8 +716,7 @@ SELECT date
     }
 
     async fn clean_by_min_update_at(&self, val: i64) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query("DELETE FROM file_list WHERE updated_at < $1;")
             .bind(val)
             .execute(&*client)
@@ -828,8 +821,7 @@ GROUP BY stream;
         let sql = format!(
             "DELETE FROM stream_stats WHERE stream = '{org_id}/{stream_type}/{stream_name}';"
         );
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query(&sql).execute(&*client).await?;
         Ok(())
     }
@@ -843,8 +835,7 @@ GROUP BY stream;
         is_recent: bool,
     ) -> Result<()> {
         let stream_key = format!("{org_id}/{stream_type}/{stream_name}");
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         if let Err(e) = sqlx::query(
             r#"
@@ -896,8 +887,7 @@ DO UPDATE SET
         stream: &str,
         min_ts: i64,
     ) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query(r#"UPDATE stream_stats SET min_ts = $1 WHERE stream = $2;"#)
             .bind(min_ts)
             .bind(stream)
@@ -913,8 +903,7 @@ DO UPDATE SET
     }
 
     async fn reset_stream_stats(&self) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query(r#"UPDATE stream_stats SET file_num = 0, min_ts = 0, max_ts = 0, records = 0, original_size = 0, compressed_size = 0, index_size = 0;"#)
         .execute(&*client)
        .await?;
@@ -955,8 +944,7 @@ DO UPDATE SET
         offset: i64,
     ) -> Result<i64> {
         let stream_key = format!("{org_id}/{stream_type}/{stream}");
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         match sqlx::query(
             "INSERT INTO file_list_jobs (org, stream, offsets, status, node, started_at, updated_at) VALUES ($1, $2, $3, $4, '', 0, 0);",
@@ -1020,8 +1008,7 @@ DO UPDATE SET
     }
 
     async fn get_pending_jobs(&self, node: &str, limit: i64) -> Result<Vec<super::MergeJobRecord>> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         // get pending jobs group by stream and order by num desc
         let ret = match sqlx::query_as::<_, super::MergeJobPendingRecord>(
@@ -1097,8 +1084,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
     }
 
     async fn set_job_pending(&self, ids: &[i64]) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let sql = format!(
             "UPDATE file_list_jobs SET status = $1 WHERE id IN ({});",
             ids.iter()
@@ -1115,8 +1101,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
 
     async fn set_job_done(&self, ids: &[i64]) -> Result<()> {
         let cfg = get_config();
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let sql = format!(
             "UPDATE file_list_jobs SET status = $1, updated_at = $2, dumped = $3, node = '' WHERE id IN ({});",
             ids.iter()
@@ -1136,8 +1121,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
     }
 
     async fn update_running_jobs(&self, ids: &[i64]) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let sql = format!(
             r#"UPDATE file_list_jobs SET updated_at = $1 WHERE id IN ({})"#,
             ids.iter()
@@ -1153,8 +1137,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
     }
 
     async fn check_running_jobs(&self, before_date: i64) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
 
         // reset running jobs status to pending
         let ret = sqlx::query(
@@ -1189,8 +1172,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
     }
 
     async fn clean_done_jobs(&self, before_date: i64) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let ret = sqlx::query(
             r#"DELETE FROM file_list_jobs WHERE status = $1 AND dumped = $2 AND updated_at < $3;"#,
         )
@@ -1244,8 +1226,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
         node: &str,
         limit: i64,
     ) -> Result<Vec<(i64, String, i64)>> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
         // get pending dump jobs by updated_at asc
         let ret = match sqlx::query_as::<_, (i64, String, i64)>(
@@ -1301,8 +1282,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
     }
 
     async fn set_job_dumped_status(&self, ids: &[i64], dumped: bool) -> Result<()> {
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let sql = format!(
             "UPDATE file_list_jobs SET dumped = $1, node = '' WHERE id IN ({});",
             ids.iter()
@@ -1318,8 +1298,7 @@ SELECT stream, max(id) as id, COUNT(*) AS num
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).expect("parse file key failed");
         let org_id = stream_key[..stream_key.find('/').unwrap()].to_string();
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query(
             r#"
 INSERT INTO file_list_dump_stats
@@ -1355,8 +1334,7 @@ DO UPDATE SET
     async fn delete_dump_stats(&self, file: &str) -> Result<()> {
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).expect("parse file key failed");
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         sqlx::query(
             r#"DELETE FROM file_list_dump_stats WHERE stream = $1 AND date = $2 AND file = $3;"#,
         )
@@ -1437,8 +1415,7 @@ impl SqliteFileList {
         let (stream_key, date_key, file_name) =
             parse_file_key_columns(file).map_err(|e| Error::Message(e.to_string()))?;
         let org_id = stream_key[..stream_key.find('/').unwrap()].to_string();
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         if meta.min_ts == 0 || meta.max_ts == 0 {
             log::warn!("[SQLITE] min_ts or max_ts is 0 for file: {file}");
         }
@@ -1481,8 +1458,7 @@ INSERT INTO {table} (id, account, org, stream, date, file, deleted, min_ts, max_
             return Ok(());
         }
 
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let mut tx = client.begin().await?;
 
         let add_items = files.iter().filter(|f| !f.deleted).collect::<Vec<_>>();
@@ -1601,8 +1577,7 @@ INSERT INTO {table} (id, account, org, stream, date, file, deleted, min_ts, max_
 }
 
 pub async fn create_table() -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     sqlx::query(
         r#"
 CREATE TABLE IF NOT EXISTS file_list
@@ -1907,8 +1882,7 @@ pub async fn create_table_index() -> Result<()> {
         }
         // delete duplicate records
         log::warn!("[SQLITE] starting delete duplicate records");
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         let ret = sqlx::query(
                 r#"SELECT stream, date, file, min(id) as id FROM file_list GROUP BY stream, date, file HAVING COUNT(*) > 1;"#,
             ).fetch_all(&*client).await?;
@@ -1947,8 +1921,7 @@ pub async fn create_table_index() -> Result<()> {
 
     // delete trigger for old version
     // compatible for old version <= 0.6.4
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     sqlx::query(r#"DROP TRIGGER IF EXISTS update_stream_stats_delete;"#)
         .execute(&*client)
         .await?;
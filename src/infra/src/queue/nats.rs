@@ -22,7 +22,11 @@ use config::{get_cluster_name, get_config};
 use futures::TryStreamExt;
 use tokio::{sync::mpsc, task::JoinHandle};
 
-use crate::{db::nats::get_nats_client, errors::*, queue};
+use crate::{
+    db::nats::{get_nats_client, jetstream_context},
+    errors::*,
+    queue,
+};
 
 pub async fn init() -> Result<()> {
     Ok(())
@@ -98,7 +102,7 @@ impl super::Queue for NatsQueue {
         };
         let cfg = config::get_config();
         let client = get_nats_client().await.clone();
-        let jetstream = jetstream::new(client);
+        let jetstream = jetstream_context(client);
         let topic_name = format!("{}{}", self.prefix, format_key(topic));
         let config = jetstream::stream::Config {
             name: topic_name.to_string(),
@@ -117,7 +121,7 @@ impl super::Queue for NatsQueue {
     /// you can pub message with the topic or topic.* to match the topic
     async fn publish(&self, topic: &str, value: Bytes) -> Result<()> {
         let client = get_nats_client().await.clone();
-        let jetstream = jetstream::new(client);
+        let jetstream = jetstream_context(client);
         // Publish a message to the stream
         let topic_name = format!("{}{}", self.prefix, format_key(topic));
         let ack = jetstream.publish(topic_name, value).await?;
@@ -136,7 +140,7 @@ impl super::Queue for NatsQueue {
         let is_durable = self.is_durable;
         let _task: JoinHandle<Result<()>> = tokio::task::spawn(async move {
             let client = get_nats_client().await.clone();
-            let jetstream = jetstream::new(client);
+            let jetstream = jetstream_context(client);
             let stream = jetstream.get_stream(&stream_name).await.map_err(|e| {
                 log::error!("Failed to get nats stream {stream_name}: {e}");
                 Error::Message(format!("Failed to get nats stream {stream_name}: {e}"))
@@ -20,7 +20,7 @@ use datafusion::arrow::datatypes::Schema;
 use crate::{
     db::{
         IndexStatement,
-        sqlite::{CLIENT_RW, create_index},
+        sqlite::{create_index, lock_rw},
     },
     errors::{Error, Result},
 };
@@ -58,8 +58,7 @@ impl super::SchemaHistory for SqliteSchemaHistory {
         schema: Schema,
     ) -> Result<()> {
         let value = json::to_string(&schema)?;
-        let client = CLIENT_RW.clone();
-        let client = client.lock().await;
+        let client = lock_rw().await;
         match sqlx::query(
             r#"
 INSERT INTO schema_history (org, stream_type, stream_name, start_dt, value)
@@ -88,8 +87,7 @@ INSERT INTO schema_history (org, stream_type, stream_name, start_dt, value)
 }
 
 pub async fn create_table() -> Result<()> {
-    let client = CLIENT_RW.clone();
-    let client = client.lock().await;
+    let client = lock_rw().await;
     sqlx::query(
         r#"
 CREATE TABLE IF NOT EXISTS schema_history
@@ -23,7 +23,7 @@ use config::{
     get_config,
     ider::SnowflakeIdGenerator,
     meta::stream::{PartitionTimeLevel, StreamSettings, StreamType},
-    utils::{json, schema_ext::SchemaExt, time::now_micros},
+    utils::{json, schema_ext::SchemaExt, tantivy::tokenizer::O2_TOKENIZER, time::now_micros},
 };
 use datafusion::arrow::datatypes::{DataType, Field, FieldRef, Schema, SchemaRef};
 use once_cell::sync::Lazy;
@@ -354,6 +354,7 @@ pub fn get_stream_setting_fts_fields(settings: &Option<StreamSettings>) -> Vec<S
             if settings.index_all_values {
                 fields.push(ALL_VALUES_COL_NAME.to_string());
             }
+            fields.retain(|field| !settings.full_text_search_exclude_keys.contains(field));
             fields.sort();
             fields.dedup();
             fields
@@ -362,6 +363,21 @@ pub fn get_stream_setting_fts_fields(settings: &Option<StreamSettings>) -> Vec<S
     }
 }
 
+pub fn get_stream_setting_fts_exclude_fields(settings: &Option<StreamSettings>) -> Vec<String> {
+    match settings {
+        Some(settings) => settings.full_text_search_exclude_keys.clone(),
+        None => vec![],
+    }
+}
+
+/// Returns the tokenizer of the full text search fields, the o2 tokenizer by default
+pub fn get_stream_setting_fts_tokenizer(settings: &Option<StreamSettings>) -> String {
+    settings
+        .as_ref()
+        .and_then(|settings| settings.full_text_search_tokenizer.clone())
+        .unwrap_or_else(|| O2_TOKENIZER.to_string())
+}
+
 pub fn get_stream_setting_index_fields(settings: &Option<StreamSettings>) -> Vec<String> {
     let default_fields = SQL_SECONDARY_INDEX_SEARCH_FIELDS.clone();
     match settings {
@@ -1036,6 +1052,25 @@ mod tests {
         assert_eq!(unique_count, fields.len());
     }
 
+    #[test]
+    fn test_get_stream_setting_fts_fields_with_exclude_keys() {
+        let mut settings = StreamSettings::default();
+        settings.full_text_search_keys = vec!["custom_field".to_string(), "blob".to_string()];
+        settings.full_text_search_exclude_keys = vec!["blob".to_string()];
+        if let Some(default_field) = SQL_FULL_TEXT_SEARCH_FIELDS.first() {
+            settings
+                .full_text_search_exclude_keys
+                .push(default_field.to_string());
+        }
+        let settings = Some(settings);
+
+        let fields = get_stream_setting_fts_fields(&settings);
+        assert!(fields.contains(&"custom_field".to_string()));
+        for field in get_stream_setting_fts_exclude_fields(&settings) {
+            assert!(!fields.contains(&field));
+        }
+    }
+
     #[test]
     fn test_get_stream_setting_index_fields() {
         // Test with None
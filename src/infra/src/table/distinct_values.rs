@@ -13,15 +13,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use config::get_config;
 use sea_orm::{
-    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, QueryFilter, Schema, Set,
-    entity::prelude::*,
+    ColumnTrait, Condition, ConnectionTrait, DatabaseBackend, EntityTrait, FromQueryResult,
+    QueryFilter, QueryOrder, QuerySelect, Schema, Set, entity::prelude::*,
 };
 use serde::{Deserialize, Serialize};
 
 use super::get_lock;
 use crate::{
-    db::{ORM_CLIENT, ORM_CLIENT_DDL, connect_to_orm, connect_to_orm_ddl},
+    db::{
+        IndexStatement, ORM_CLIENT, ORM_CLIENT_DDL, connect_to_orm, connect_to_orm_ddl, mysql,
+        postgres, sqlite,
+    },
     errors::{self, DbError, Error},
 };
 
@@ -77,6 +81,15 @@ pub struct DistinctFieldRecord {
     pub field_name: String,
 }
 
+/// An origin of the distinct fields of a stream
+#[derive(FromQueryResult, Debug, Clone, PartialEq, Eq)]
+pub struct FieldOrigin {
+    pub origin: OriginType,
+    pub origin_id: String,
+    pub stream_name: String,
+    pub stream_type: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchDeleteMessage {
     pub origin_type: OriginType,
@@ -105,6 +118,7 @@ impl DistinctFieldRecord {
 
 pub async fn init() -> Result<(), errors::Error> {
     create_table().await?;
+    create_table_index().await?;
     Ok(())
 }
 
@@ -126,21 +140,69 @@ pub async fn create_table() -> Result<(), errors::Error> {
     Ok(())
 }
 
-pub async fn add(record: DistinctFieldRecord) -> Result<(), errors::Error> {
-    let record = ActiveModel {
+/// The primary key starts with the origin, this index is used to seek the fields of a stream
+pub async fn create_table_index() -> Result<(), errors::Error> {
+    let index = IndexStatement::new(
+        "distinct_value_fields_stream_field_idx",
+        "distinct_value_fields",
+        false,
+        &["org_name", "stream_name", "stream_type", "field_name"],
+    );
+
+    let client = ORM_CLIENT_DDL.get_or_init(connect_to_orm_ddl).await;
+    match client.get_database_backend() {
+        DatabaseBackend::MySql => mysql::create_index(index).await?,
+        DatabaseBackend::Postgres => postgres::create_index(index).await?,
+        _ => sqlite::create_index(index).await?,
+    }
+    Ok(())
+}
+
+/// The field name is part of the primary key, so `Status` and `status` are registered as two
+/// fields unless the field names are lowercased, see `ZO_DISTINCT_VALUE_FIELDS_LOWERCASE`.
+///
+/// NOTE: enabling the lowercasing doesn't rewrite the existing rows, the mixed-case rows
+/// registered before are no longer matched by [`remove`] and [`check_field_use`] and need
+/// to be removed with [`batch_remove`] or re-registered by their origin.
+fn normalize_field_name(field_name: &str, lowercase: bool) -> String {
+    if lowercase {
+        field_name.to_lowercase()
+    } else {
+        field_name.to_string()
+    }
+}
+
+fn to_active_model(record: DistinctFieldRecord, lowercase: bool) -> ActiveModel {
+    ActiveModel {
         origin: Set(record.origin),
         origin_id: Set(record.origin_id),
         org_name: Set(record.org_name),
         stream_name: Set(record.stream_name),
         stream_type: Set(record.stream_type),
-        field_name: Set(record.field_name),
-    };
+        field_name: Set(normalize_field_name(&record.field_name, lowercase)),
+    }
+}
 
+pub async fn add(record: DistinctFieldRecord) -> Result<(), errors::Error> {
     // make sure only one client is writing to the database(only for sqlite)
     let _lock = get_lock().await;
 
     let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
-    let res = Entity::insert(record).exec(client).await;
+    insert_record(
+        client,
+        record,
+        get_config().common.distinct_value_fields_lowercase,
+    )
+    .await
+}
+
+async fn insert_record<C: ConnectionTrait>(
+    db: &C,
+    record: DistinctFieldRecord,
+    lowercase: bool,
+) -> Result<(), errors::Error> {
+    let record = to_active_model(record, lowercase);
+    let res = Entity::insert(record).exec(db).await;
 
     match res {
         Ok(_) => Ok(()),
@@ -161,14 +223,7 @@ pub async fn remove(record: DistinctFieldRecord) -> Result<(), errors::Error> {
     // make sure only one client is writing to the database(only for sqlite)
     let _lock = get_lock().await;
 
-    let record = ActiveModel {
-        origin: Set(record.origin),
-        origin_id: Set(record.origin_id),
-        org_name: Set(record.org_name),
-        stream_name: Set(record.stream_name),
-        stream_type: Set(record.stream_type),
-        field_name: Set(record.field_name),
-    };
+    let record = to_active_model(record, get_config().common.distinct_value_fields_lowercase);
 
     let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
     Entity::delete(record)
@@ -185,6 +240,10 @@ pub async fn check_field_use(
     stream_type: &str,
     field_name: &str,
 ) -> Result<Vec<DistinctFieldRecord>, errors::Error> {
+    let field_name = normalize_field_name(
+        field_name,
+        get_config().common.distinct_value_fields_lowercase,
+    );
     let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
     let records = Entity::find()
         .filter(Column::OrgName.eq(org_name))
@@ -198,6 +257,96 @@ pub async fn check_field_use(
     Ok(records)
 }
 
+/// Lists the distinct fields registered for the stream ordered by field name, starting after the
+/// `after` cursor. Returns the page and the cursor of the next page, None means no more pages.
+pub async fn list_by_stream_paged(
+    org_name: &str,
+    stream_name: &str,
+    stream_type: &str,
+    after: Option<String>,
+    limit: u64,
+) -> Result<(Vec<String>, Option<String>), errors::Error> {
+    let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
+    list_fields_page(
+        client,
+        org_name,
+        stream_name,
+        stream_type,
+        after.as_deref(),
+        limit,
+    )
+    .await
+    .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
+}
+
+async fn list_fields_page<C: ConnectionTrait>(
+    db: &C,
+    org_name: &str,
+    stream_name: &str,
+    stream_type: &str,
+    after: Option<&str>,
+    limit: u64,
+) -> Result<(Vec<String>, Option<String>), DbErr> {
+    let limit = limit.max(1);
+    let mut query = Entity::find()
+        .select_only()
+        .column(Column::FieldName)
+        .distinct()
+        .filter(Column::OrgName.eq(org_name))
+        .filter(Column::StreamName.eq(stream_name))
+        .filter(Column::StreamType.eq(stream_type));
+    if let Some(after) = after {
+        query = query.filter(Column::FieldName.gt(after));
+    }
+    // fetch one more record to know if there is a next page
+    let mut fields: Vec<String> = query
+        .order_by_asc(Column::FieldName)
+        .limit(limit + 1)
+        .into_tuple()
+        .all(db)
+        .await?;
+    let cursor = if fields.len() as u64 > limit {
+        fields.truncate(limit as usize);
+        fields.last().cloned()
+    } else {
+        None
+    };
+    Ok((fields, cursor))
+}
+
+/// Counts the origins registered for each distinct field of the stream, ordered by field name.
+pub async fn count_fields_by_stream(
+    org_name: &str,
+    stream_name: &str,
+    stream_type: &str,
+) -> Result<Vec<(String, i64)>, errors::Error> {
+    let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
+    count_fields(client, org_name, stream_name, stream_type)
+        .await
+        .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
+}
+
+async fn count_fields<C: ConnectionTrait>(
+    db: &C,
+    org_name: &str,
+    stream_name: &str,
+    stream_type: &str,
+) -> Result<Vec<(String, i64)>, DbErr> {
+    // every row is a unique origin of the field, so counting the rows counts the origins
+    Entity::find()
+        .select_only()
+        .column(Column::FieldName)
+        .column_as(Column::OriginId.count(), "count")
+        .filter(Column::OrgName.eq(org_name))
+        .filter(Column::StreamName.eq(stream_name))
+        .filter(Column::StreamType.eq(stream_type))
+        .group_by(Column::FieldName)
+        .order_by_asc(Column::FieldName)
+        .into_tuple()
+        .all(db)
+        .await
+}
+
 /// This is specifically for the case when a dashboard is deleted, we can bulk remove
 /// the dependencies, without having to go through one by one
 pub async fn batch_remove(origin: OriginType, origin_id: &str) -> Result<(), errors::Error> {
@@ -215,6 +364,106 @@ pub async fn batch_remove(origin: OriginType, origin_id: &str) -> Result<(), err
     Ok(())
 }
 
+/// Lists the orgs which have distinct fields registered.
+pub async fn list_orgs() -> Result<Vec<String>, errors::Error> {
+    let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
+    find_orgs(client)
+        .await
+        .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
+}
+
+async fn find_orgs<C: ConnectionTrait>(db: &C) -> Result<Vec<String>, DbErr> {
+    Entity::find()
+        .select_only()
+        .column(Column::OrgName)
+        .distinct()
+        .order_by_asc(Column::OrgName)
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+/// Lists the origins of the distinct fields registered in the org.
+pub async fn list_origins(org_name: &str) -> Result<Vec<FieldOrigin>, errors::Error> {
+    let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
+    find_origins(client, org_name)
+        .await
+        .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
+}
+
+async fn find_origins<C: ConnectionTrait>(
+    db: &C,
+    org_name: &str,
+) -> Result<Vec<FieldOrigin>, DbErr> {
+    Entity::find()
+        .select_only()
+        .column(Column::Origin)
+        .column(Column::OriginId)
+        .column(Column::StreamName)
+        .column(Column::StreamType)
+        .distinct()
+        .filter(Column::OrgName.eq(org_name))
+        .order_by_asc(Column::Origin)
+        .order_by_asc(Column::OriginId)
+        .order_by_asc(Column::StreamName)
+        .order_by_asc(Column::StreamType)
+        .into_model::<FieldOrigin>()
+        .all(db)
+        .await
+}
+
+/// Removes the distinct fields registered by the given origins of the org and returns the
+/// number of removed rows, with `dry_run` the rows are only counted.
+///
+/// The origins are removed in a single statement, callers are expected to pass them in
+/// bounded batches.
+pub async fn remove_origins(
+    org_name: &str,
+    origins: &[FieldOrigin],
+    dry_run: bool,
+) -> Result<u64, errors::Error> {
+    if origins.is_empty() {
+        return Ok(0);
+    }
+    // make sure only one client is writing to the database(only for sqlite)
+    let _lock = if dry_run {
+        None
+    } else {
+        Some(get_lock().await)
+    };
+
+    let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
+    delete_origins(client, org_name, origins, dry_run)
+        .await
+        .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
+}
+
+async fn delete_origins<C: ConnectionTrait>(
+    db: &C,
+    org_name: &str,
+    origins: &[FieldOrigin],
+    dry_run: bool,
+) -> Result<u64, DbErr> {
+    let origins = origins.iter().fold(Condition::any(), |cond, o| {
+        cond.add(
+            Condition::all()
+                .add(Column::Origin.eq(o.origin))
+                .add(Column::OriginId.eq(o.origin_id.as_str()))
+                .add(Column::StreamName.eq(o.stream_name.as_str()))
+                .add(Column::StreamType.eq(o.stream_type.as_str())),
+        )
+    });
+    let cond = Condition::all()
+        .add(Column::OrgName.eq(org_name))
+        .add(origins);
+    if dry_run {
+        Entity::find().filter(cond).count(db).await
+    } else {
+        let res = Entity::delete_many().filter(cond).exec(db).await?;
+        Ok(res.rows_affected)
+    }
+}
+
 pub async fn len() -> Result<u64, errors::Error> {
     let _lock = get_lock().await;
     let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
@@ -223,3 +472,188 @@ pub async fn len() -> Result<u64, errors::Error> {
         .await
         .map_err(|e| Error::DbError(DbError::SeaORMError(e.to_string())))
 }
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{Database, DatabaseConnection};
+
+    use super::*;
+
+    async fn setup(num_fields: usize) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let builder = db.get_database_backend();
+        let stmt = Schema::new(builder)
+            .create_table_from_entity(Entity)
+            .if_not_exists()
+            .take();
+        db.execute(builder.build(&stmt)).await.unwrap();
+
+        let record = |origin, origin_id: &str, stream: &str, field: String| ActiveModel {
+            origin: Set(origin),
+            origin_id: Set(origin_id.to_string()),
+            org_name: Set("default".to_string()),
+            stream_name: Set(stream.to_string()),
+            stream_type: Set("logs".to_string()),
+            field_name: Set(field),
+        };
+        let mut records = (0..num_fields)
+            .map(|i| record(OriginType::Stream, "s1", "app", format!("field_{i:05}")))
+            .collect::<Vec<_>>();
+        // the same field registered by a dashboard and a field of another stream
+        records.push(record(
+            OriginType::Dashboard,
+            "d1",
+            "app",
+            "field_00001".to_string(),
+        ));
+        records.push(record(
+            OriginType::Stream,
+            "s2",
+            "other",
+            "field_00002".to_string(),
+        ));
+        for chunk in records.chunks(500) {
+            Entity::insert_many(chunk.to_vec()).exec(&db).await.unwrap();
+        }
+        db
+    }
+
+    #[tokio::test]
+    async fn test_list_fields_page() {
+        let db = setup(2000).await;
+
+        let mut fields = Vec::new();
+        let mut after = None;
+        let mut pages = 0;
+        loop {
+            let (page, cursor) =
+                list_fields_page(&db, "default", "app", "logs", after.as_deref(), 300)
+                    .await
+                    .unwrap();
+            assert!(page.len() <= 300);
+            fields.extend(page);
+            pages += 1;
+            match cursor {
+                Some(cursor) => after = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 7);
+        assert_eq!(fields.len(), 2000);
+        assert_eq!(fields.first().unwrap(), "field_00000");
+        assert_eq!(fields.last().unwrap(), "field_01999");
+        assert!(fields.windows(2).all(|w| w[0] < w[1]));
+
+        // the last page exactly fills the limit
+        let (page, cursor) = list_fields_page(&db, "default", "app", "logs", None, 2000)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 2000);
+        assert!(cursor.is_none());
+
+        let (page, cursor) = list_fields_page(&db, "default", "other", "logs", None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page, vec!["field_00002".to_string()]);
+        assert!(cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_insert_record_lowercase_field_name() {
+        let db = setup(0).await;
+        let record = |field: &str| {
+            DistinctFieldRecord::new(
+                OriginType::Stream,
+                "s3",
+                "default",
+                "web",
+                "logs".to_string(),
+                field,
+            )
+        };
+
+        for field in ["Status", "status", "STATUS"] {
+            insert_record(&db, record(field), true).await.unwrap();
+        }
+        let (page, _) = list_fields_page(&db, "default", "web", "logs", None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page, vec!["status".to_string()]);
+
+        // the field names are kept as they are by default
+        for field in ["Level", "level"] {
+            insert_record(&db, record(field), false).await.unwrap();
+        }
+        let (page, _) = list_fields_page(&db, "default", "web", "logs", None, 10)
+            .await
+            .unwrap();
+        assert_eq!(page, vec!["Level", "level", "status"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_origins() {
+        let db = setup(3).await;
+        assert_eq!(find_orgs(&db).await.unwrap(), vec!["default".to_string()]);
+
+        let origins = find_origins(&db, "default").await.unwrap();
+        let origin = |origin, origin_id: &str, stream: &str| FieldOrigin {
+            origin,
+            origin_id: origin_id.to_string(),
+            stream_name: stream.to_string(),
+            stream_type: "logs".to_string(),
+        };
+        assert_eq!(
+            origins,
+            vec![
+                origin(OriginType::Dashboard, "d1", "app"),
+                origin(OriginType::Stream, "s1", "app"),
+                origin(OriginType::Stream, "s2", "other"),
+            ]
+        );
+
+        let stale = vec![
+            origin(OriginType::Dashboard, "d1", "app"),
+            origin(OriginType::Stream, "s1", "app"),
+        ];
+        // the dry run only counts the rows
+        assert_eq!(
+            delete_origins(&db, "default", &stale, true).await.unwrap(),
+            4
+        );
+        assert_eq!(Entity::find().count(&db).await.unwrap(), 5);
+        // the origins of another org are not matched
+        assert_eq!(delete_origins(&db, "org2", &stale, false).await.unwrap(), 0);
+
+        assert_eq!(
+            delete_origins(&db, "default", &stale, false).await.unwrap(),
+            4
+        );
+        assert_eq!(
+            find_origins(&db, "default").await.unwrap(),
+            vec![origin(OriginType::Stream, "s2", "other")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_fields() {
+        let db = setup(3).await;
+
+        let counts = count_fields(&db, "default", "app", "logs").await.unwrap();
+        assert_eq!(
+            counts,
+            vec![
+                ("field_00000".to_string(), 1),
+                ("field_00001".to_string(), 2),
+                ("field_00002".to_string(), 1),
+            ]
+        );
+
+        let counts = count_fields(&db, "default", "other", "logs").await.unwrap();
+        assert_eq!(counts, vec![("field_00002".to_string(), 1)]);
+
+        let counts = count_fields(&db, "default", "app", "metrics")
+            .await
+            .unwrap();
+        assert!(counts.is_empty());
+    }
+}
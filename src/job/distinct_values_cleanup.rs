@@ -0,0 +1,94 @@
+// Copyright 2025 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use config::{cluster::LOCAL_NODE, get_config, spawn_pausable_job};
+use infra::cluster::get_cached_online_ingester_nodes;
+
+use crate::service::db;
+
+/// Runs the periodic distinct value fields cleanup job.
+///
+/// This job removes the distinct value fields registered by dashboards and streams which
+/// were deleted without unregistering them. With ZO_DISTINCT_VALUES_CLEANUP_DRY_RUN the
+/// stale rows are only counted and logged.
+///
+/// Only runs on ingester nodes with leader election to ensure a single node in the
+/// cluster handles cleanup.
+pub fn run() {
+    // Only run on ingester nodes to avoid duplicate cleanup by multiple nodes
+    if !LOCAL_NODE.is_ingester() {
+        log::debug!("[DISTINCT_VALUES_CLEANUP] Not running on ingester node, skipping");
+        return;
+    }
+
+    log::info!("[DISTINCT_VALUES_CLEANUP] Job initialized on ingester node");
+
+    spawn_pausable_job!(
+        "distinct_values_cleanup",
+        get_config().limit.distinct_values_cleanup_interval,
+        {
+            log::debug!("[DISTINCT_VALUES_CLEANUP] Job kicked off");
+
+            // Leader election: only the ingester with the smallest UUID runs cleanup
+            let is_leader = match get_cached_online_ingester_nodes().await {
+                Some(mut nodes) => {
+                    if nodes.is_empty() {
+                        log::warn!("[DISTINCT_VALUES_CLEANUP] No online ingester nodes found");
+                        false
+                    } else {
+                        nodes.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+                        nodes[0].uuid == LOCAL_NODE.uuid
+                    }
+                }
+                // If we can't get cached nodes, assume single node and run cleanup
+                None => true,
+            };
+
+            if !is_leader {
+                log::debug!("[DISTINCT_VALUES_CLEANUP] Not leader, skipping cleanup");
+                continue; // Skip this iteration if not the leader
+            }
+
+            let cfg = get_config();
+            let dry_run = cfg.limit.distinct_values_cleanup_dry_run;
+            match db::distinct_values::cleanup_orphans(
+                dry_run,
+                cfg.limit.distinct_values_cleanup_batch_size,
+            )
+            .await
+            {
+                Ok(rows) => {
+                    if rows > 0 {
+                        log::info!(
+                            "[DISTINCT_VALUES_CLEANUP] {} {} stale distinct value field(s)",
+                            if dry_run { "Found" } else { "Removed" },
+                            rows
+                        );
+                    } else {
+                        log::debug!(
+                            "[DISTINCT_VALUES_CLEANUP] No stale distinct value fields found"
+                        );
+                    }
+                }
+                Err(e) => {
+                    log::error!(
+                        "[DISTINCT_VALUES_CLEANUP] Failed to cleanup distinct value fields: {}",
+                        e
+                    );
+                }
+            }
+        }
+    );
+}
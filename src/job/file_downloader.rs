@@ -13,12 +13,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::{collections::VecDeque, sync::Arc};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 
 use config::{
     cluster::LOCAL_NODE,
     get_config,
-    meta::cluster::{Role, RoleGroup, get_internal_grpc_token},
+    meta::{
+        cluster::{Role, RoleGroup, get_internal_grpc_token},
+        search::ScanStats,
+    },
     metrics,
     utils::time::now_micros,
 };
@@ -58,6 +61,142 @@ mod processing_files {
     }
 }
 
+/// The files queued or being downloaded, the queries can wait for them instead of downloading
+/// the same files again.
+mod pending_downloads {
+    use hashbrown::HashMap;
+    use parking_lot::RwLock;
+    use tokio::sync::watch;
+
+    use super::*;
+
+    static PENDING_DOWNLOADS: Lazy<RwLock<HashMap<String, watch::Sender<bool>>>> =
+        Lazy::new(|| RwLock::new(HashMap::new()));
+
+    /// Returns false if the file is already pending
+    pub fn add(file_name: &str) -> bool {
+        let mut w = PENDING_DOWNLOADS.write();
+        if w.contains_key(file_name) {
+            return false;
+        }
+        let (tx, _) = watch::channel(false);
+        w.insert(file_name.to_string(), tx);
+        true
+    }
+
+    pub fn is_pending(file_name: &str) -> bool {
+        PENDING_DOWNLOADS.read().contains_key(file_name)
+    }
+
+    pub fn subscribe(file_name: &str) -> Option<watch::Receiver<bool>> {
+        PENDING_DOWNLOADS
+            .read()
+            .get(file_name)
+            .map(|tx| tx.subscribe())
+    }
+
+    /// Marks the download finished, no matter it succeeded or not, and wakes up the waiters
+    pub fn done(file_name: &str) {
+        let tx = PENDING_DOWNLOADS.write().remove(file_name);
+        if let Some(tx) = tx {
+            let _ = tx.send(true);
+        }
+    }
+
+    /// Marks the download finished when dropped, so the file isn't left pending forever if the
+    /// worker fails or panics before it finishes
+    pub struct DoneGuard(String);
+
+    impl DoneGuard {
+        pub fn new(file_name: &str) -> Self {
+            Self(file_name.to_string())
+        }
+    }
+
+    impl Drop for DoneGuard {
+        fn drop(&mut self) {
+            done(&self.0);
+        }
+    }
+}
+
+/// The bytes downloaded or skipped by the file downloader for each query, the search takes them
+/// by trace_id to report the real transfer cost in the scan stats.
+mod download_stats {
+    use hashbrown::HashMap;
+    use parking_lot::RwLock;
+
+    use super::*;
+
+    /// the entries of the queries which never take their stats are dropped after this time
+    const STATS_TTL: Duration = Duration::from_secs(3600);
+
+    #[derive(Debug)]
+    struct Stats {
+        downloaded_size: i64,
+        skipped_size: i64,
+        updated_at: std::time::Instant,
+    }
+
+    static DOWNLOAD_STATS: Lazy<RwLock<HashMap<String, Stats>>> =
+        Lazy::new(|| RwLock::new(HashMap::new()));
+
+    fn record(trace_id: &str, downloaded_size: usize, skipped_size: usize) {
+        let mut w = DOWNLOAD_STATS.write();
+        let stats = w.entry_ref(trace_id).or_insert_with(|| Stats {
+            downloaded_size: 0,
+            skipped_size: 0,
+            updated_at: std::time::Instant::now(),
+        });
+        stats.downloaded_size += downloaded_size as i64;
+        stats.skipped_size += skipped_size as i64;
+        stats.updated_at = std::time::Instant::now();
+    }
+
+    pub fn downloaded(trace_id: &str, size: usize) {
+        record(trace_id, size, 0);
+    }
+
+    pub fn skipped(trace_id: &str, size: usize) {
+        record(trace_id, 0, size);
+    }
+
+    /// Returns (downloaded_size, skipped_size) of the query and removes it
+    pub fn take(trace_id: &str) -> Option<(i64, i64)> {
+        let mut w = DOWNLOAD_STATS.write();
+        w.retain(|_, stats| stats.updated_at.elapsed() < STATS_TTL);
+        w.remove(trace_id)
+            .map(|stats| (stats.downloaded_size, stats.skipped_size))
+    }
+}
+
+/// Returns the scan stats of the bytes downloaded and skipped because of the cache by the file
+/// downloader for the query so far, the downloads finished after this call are not included.
+pub fn take_download_stats(trace_id: &str) -> Option<ScanStats> {
+    download_stats::take(trace_id).map(|(downloaded_size, download_skipped_size)| ScanStats {
+        downloaded_size,
+        download_skipped_size,
+        ..Default::default()
+    })
+}
+
+/// Returns true if the file is queued or being downloaded by the file downloader
+pub fn is_download_pending(file_name: &str) -> bool {
+    pending_downloads::is_pending(file_name)
+}
+
+/// Waits for the pending download of the file to finish, returns false if it is still pending
+/// after the timeout. The caller needs to check the cache again, the download may have failed.
+pub async fn wait_for_download(file_name: &str, timeout: Duration) -> bool {
+    let Some(mut rx) = pending_downloads::subscribe(file_name) else {
+        return true;
+    };
+    match tokio::time::timeout(timeout, rx.wait_for(|done| *done)).await {
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
 struct DownloadQueue {
     sender: Sender<FileInfo>,
     receiver: Arc<Mutex<Receiver<FileInfo>>>,
@@ -124,6 +263,7 @@ pub async fn run() -> Result<(), anyhow::Error> {
                             log::warn!(
                                 "[trace_id {trace_id}] [thread {thread}] search->storage: file {file} is already being downloaded, will skip it"
                             );
+                            download_stats::skipped(&trace_id, file_size);
                             // update metrics
                             metrics::FILE_DOWNLOADER_NORMAL_QUEUE_SIZE
                                 .with_label_values::<&str>(&[])
@@ -133,6 +273,7 @@ pub async fn run() -> Result<(), anyhow::Error> {
 
                         // add the file to processing set
                         processing_files::add(&file);
+                        let _pending = pending_downloads::DoneGuard::new(&file);
 
                         // download the file
                         match download_file(
@@ -141,6 +282,7 @@ pub async fn run() -> Result<(), anyhow::Error> {
                         .await
                         {
                             Ok(data_len) => {
+                                record_download(&trace_id, data_len, file_size);
                                 if data_len > 0 && data_len != file_size {
                                     log::warn!(
                                         "[FILE_CACHE_DOWNLOAD:JOB:NORMAL] download file {file} found size mismatch, expected: {file_size}, actual: {data_len}, will skip it",
@@ -214,6 +356,7 @@ pub async fn run() -> Result<(), anyhow::Error> {
                                     log::warn!(
                                         "[trace_id {trace_id}] [thread {thread}] search->storage: file {file} is already being downloaded, will skip it"
                                     );
+                                    download_stats::skipped(&trace_id, file_size);
                                     // update metrics
                                     metrics::FILE_DOWNLOADER_PRIORITY_QUEUE_SIZE
                                         .with_label_values::<&str>(&[])
@@ -223,10 +366,12 @@ pub async fn run() -> Result<(), anyhow::Error> {
 
                                 // add the file to processing set
                                 processing_files::add(&file);
+                                let _pending = pending_downloads::DoneGuard::new(&file);
 
                                 // download the file
                                 match download_file(thread, &trace_id, id, &account, &file, file_size, cache).await {
                                     Ok(data_len) => {
+                                        record_download(&trace_id, data_len, file_size);
                                         if data_len > 0 && data_len != file_size {
                                             log::warn!(
                                                 "[FILE_CACHE_DOWNLOAD:JOB:PRIORITY] download file {file} found size mismatch, expected: {file_size}, actual: {data_len}, will skip it",
@@ -260,6 +405,15 @@ pub async fn run() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// The downloaded size is 0 if the file was already in the cache
+fn record_download(trace_id: &str, data_len: usize, file_size: usize) {
+    if data_len > 0 {
+        download_stats::downloaded(trace_id, data_len);
+    } else {
+        download_stats::skipped(trace_id, file_size);
+    }
+}
+
 async fn download_file(
     thread: usize,
     trace_id: &str,
@@ -489,9 +643,18 @@ pub async fn queue_download(
     ts: i64,
     cache_type: file_data::CacheType,
 ) -> Result<(), anyhow::Error> {
+    // the file is already queued by another query, wait for it instead of downloading it again
+    if !pending_downloads::add(&file) {
+        log::debug!(
+            "[FILE_CACHE_DOWNLOAD:JOB] [trace_id {trace_id}] file: {file} is already queued, will skip it"
+        );
+        download_stats::skipped(&trace_id, size as usize);
+        return Ok(());
+    }
     log::debug!(
         "[FILE_CACHE_DOWNLOAD:JOB] [trace_id {trace_id}] enqueue file: {file}, size: {size}, ts: {ts}"
     );
+    let file_name = file.clone();
     let cfg = get_config();
     if cfg.limit.file_download_enable_priority_queue
         && should_prioritize_file(ts, cfg.limit.file_download_priority_queue_window_secs)
@@ -499,7 +662,8 @@ pub async fn queue_download(
         PRIORITY_FILE_DOWNLOAD_CHANNEL
             .sender
             .send((trace_id, id, account, file, size as usize, cache_type))
-            .await?;
+            .await
+            .inspect_err(|_| pending_downloads::done(&file_name))?;
 
         // update metrics
         metrics::FILE_DOWNLOADER_PRIORITY_QUEUE_SIZE
@@ -516,7 +680,8 @@ pub async fn queue_download(
                 size as usize,
                 cache_type,
             ))
-            .await?;
+            .await
+            .inspect_err(|_| pending_downloads::done(&file_name))?;
 
         // update metrics
         metrics::FILE_DOWNLOADER_NORMAL_QUEUE_SIZE
@@ -532,3 +697,85 @@ fn should_prioritize_file(ts: i64, window_secs: i64) -> bool {
     let now = now_micros();
     ts > now - window_micros
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_download_shared_by_concurrent_searches() {
+        let file = "files/default/logs/test_shared_download/2025/01/01/00/1.parquet".to_string();
+        let tasks = (0..2)
+            .map(|i| {
+                let file = file.clone();
+                tokio::spawn(queue_download(
+                    format!("trace_{i}"),
+                    1,
+                    String::new(),
+                    file,
+                    1024,
+                    0,
+                    file_data::CacheType::Disk,
+                ))
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // only one download is queued
+        let mut queued = 0;
+        let mut rx = FILE_DOWNLOAD_CHANNEL.receiver.lock().await;
+        while let Ok((_, _, _, f, ..)) = rx.try_recv() {
+            if f == file {
+                queued += 1;
+            }
+        }
+        drop(rx);
+        assert_eq!(queued, 1);
+        assert!(is_download_pending(&file));
+
+        // the other search waits for the shared download
+        assert!(!wait_for_download(&file, Duration::from_millis(10)).await);
+        let waiter = {
+            let file = file.clone();
+            tokio::spawn(async move { wait_for_download(&file, Duration::from_secs(10)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        pending_downloads::done(&file);
+        assert!(waiter.await.unwrap());
+        assert!(!is_download_pending(&file));
+        assert!(wait_for_download(&file, Duration::ZERO).await);
+    }
+
+    #[test]
+    fn test_pending_download_done_when_worker_panics() {
+        let file = "files/default/logs/test_worker_panic/2025/01/01/00/1.parquet";
+        assert!(pending_downloads::add(file));
+        assert!(is_download_pending(file));
+
+        let ret = std::panic::catch_unwind(|| {
+            let _pending = pending_downloads::DoneGuard::new(file);
+            panic!("worker died");
+        });
+        assert!(ret.is_err());
+        assert!(!is_download_pending(file));
+    }
+
+    #[test]
+    fn test_take_download_stats() {
+        let trace_id = "trace_download_stats";
+        assert!(take_download_stats(trace_id).is_none());
+
+        record_download(trace_id, 1000, 1024);
+        record_download(trace_id, 0, 2048);
+        download_stats::skipped(trace_id, 512);
+        let stats = take_download_stats(trace_id).unwrap();
+        assert_eq!(stats.downloaded_size, 1000);
+        assert_eq!(stats.download_skipped_size, 2560);
+        assert_eq!(stats.files, 0);
+
+        // the stats are taken only once
+        assert!(take_download_stats(trace_id).is_none());
+    }
+}
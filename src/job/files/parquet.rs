@@ -38,7 +38,7 @@ use hashbrown::HashSet;
 use infra::{
     schema::{
         SchemaCache, get_stream_setting_bloom_filter_fields, get_stream_setting_fts_fields,
-        get_stream_setting_index_fields,
+        get_stream_setting_fts_tokenizer, get_stream_setting_index_fields,
     },
     storage,
 };
@@ -739,6 +739,7 @@ async fn merge_files(
         infra::schema::get_stream_setting_log_patterns_enabled(&stream_settings);
     let bloom_filter_fields = get_stream_setting_bloom_filter_fields(&stream_settings);
     let full_text_search_fields = get_stream_setting_fts_fields(&stream_settings);
+    let fts_tokenizer = get_stream_setting_fts_tokenizer(&stream_settings);
     let index_fields = get_stream_setting_index_fields(&stream_settings);
     let (defined_schema_fields, need_original, index_original_data, index_all_values) =
         match stream_settings {
@@ -972,6 +973,7 @@ async fn merge_files(
         "INGESTER",
         &new_file_key,
         &full_text_search_fields,
+        &fts_tokenizer,
         &index_fields,
         latest_schema.clone(), // Use stream schema to include all configured fields
         reader,
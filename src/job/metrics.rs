@@ -23,10 +23,11 @@ use config::{
 };
 use hashbrown::HashMap;
 use infra::{cache, cluster::get_cached_online_nodes, db::get_db};
+use strum::IntoEnumIterator;
 use tokio::time;
 
 use crate::{
-    common::infra::config::{ORG_USERS, USERS},
+    common::infra::config::{CacheKind, ORG_USERS, USERS, cache_entries, cache_refreshed_at},
     service::db,
 };
 
@@ -52,6 +53,28 @@ pub async fn run() -> Result<(), anyhow::Error> {
         if let Err(e) = update_parquet_metadata_cache_metrics().await {
             log::error!("Error update parquet metadata cache metrics: {e}");
         }
+        update_meta_cache_metrics().await;
+        if config::cluster::LOCAL_NODE.is_compactor()
+            && let Err(e) = crate::service::metrics::idempotency::delete_expired().await
+        {
+            log::error!("Error delete expired metrics idempotency keys: {e}");
+        }
+    }
+}
+
+async fn update_meta_cache_metrics() {
+    for (cache, entries) in cache_entries().await {
+        metrics::META_CACHE_ENTRIES
+            .with_label_values(&[cache])
+            .set(entries as i64);
+    }
+    let now = chrono::Utc::now().timestamp();
+    for kind in CacheKind::iter() {
+        if let Some(refreshed_at) = cache_refreshed_at(kind) {
+            metrics::META_CACHE_REFRESH_AGE_SECONDS
+                .with_label_values(&[kind.to_string().as_str()])
+                .set(now - refreshed_at);
+        }
     }
 }
 
@@ -120,6 +120,9 @@ async fn run_download_files() {
         if download_city_files {
             log::info!("New city file found, updating client");
             update_maxmind_table(&city_fname).await;
+        }
+
+        if download_asn_files || download_city_files {
             update_maxmind_client().await;
         }
     }
@@ -144,7 +147,11 @@ async fn update_maxmind_client() {
         city_fname
     };
     match MaxmindClient::new_with_path(&city_fname) {
-        Ok(maxminddb_client) => {
+        Ok(mut maxminddb_client) => {
+            let asn_fname = format!("{}{}", &cfg.common.mmdb_data_dir, MMDB_ASN_FILE_NAME);
+            if let Err(e) = maxminddb_client.set_asn_database(&asn_fname) {
+                log::warn!("Failed to load asn database with path: {asn_fname}, {e}");
+            }
             let mut client = MAXMIND_DB_CLIENT.write().await;
             *client = Some(maxminddb_client);
         }
@@ -21,9 +21,12 @@ use o2_openfga::config::get_config as get_openfga_config;
 use regex::Regex;
 
 use crate::{
-    common::meta::{
-        organization::DEFAULT_ORG,
-        user::{UserOrgRole, UserRequest},
+    common::{
+        infra::config::{CacheKind, refresh_cache},
+        meta::{
+            organization::DEFAULT_ORG,
+            user::{UserOrgRole, UserRequest},
+        },
     },
     service::{db, self_reporting, users},
 };
@@ -37,6 +40,7 @@ mod cipher;
 mod cloud;
 mod compactor;
 pub mod config_watcher;
+mod distinct_values_cleanup;
 mod file_downloader;
 mod file_list_dump;
 pub(crate) mod files;
@@ -55,7 +59,9 @@ mod service_graph;
 mod session_cleanup;
 mod stats;
 
-pub use file_downloader::{download_from_node, queue_download};
+pub use file_downloader::{
+    download_from_node, is_download_pending, queue_download, take_download_stats, wait_for_download,
+};
 pub use mmdb_downloader::MMDB_INIT_NOTIFIER;
 
 #[cfg(feature = "enterprise")]
@@ -137,11 +143,15 @@ pub async fn init() -> Result<(), anyhow::Error> {
         crate::service::enrichment_table::init_url_processor();
     }
 
-    db::user::cache().await.expect("user cache failed");
-    db::organization::cache()
+    refresh_cache(CacheKind::User)
+        .await
+        .expect("user cache failed");
+    refresh_cache(CacheKind::Organization)
         .await
         .expect("organizations cache failed");
-    db::org_users::cache().await.expect("org user cache failed");
+    refresh_cache(CacheKind::OrgUsers)
+        .await
+        .expect("org user cache failed");
 
     db::organization::org_settings_cache()
         .await
@@ -220,7 +230,7 @@ pub async fn init() -> Result<(), anyhow::Error> {
 
     // cache short_urls
     tokio::task::spawn(db::short_url::watch());
-    db::short_url::cache()
+    refresh_cache(CacheKind::ShortUrl)
         .await
         .expect("short url cache failed");
 
@@ -263,11 +273,13 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::yield_now().await;
 
     // cache core metadata
-    db::schema::cache().await.expect("stream cache failed");
-    db::functions::cache()
+    refresh_cache(CacheKind::Schema)
+        .await
+        .expect("stream cache failed");
+    refresh_cache(CacheKind::Functions)
         .await
         .expect("functions cache failed");
-    db::compact::retention::cache()
+    refresh_cache(CacheKind::CompactRetention)
         .await
         .expect("compact delete cache failed");
     db::metrics::cache_prom_cluster_leader()
@@ -280,16 +292,16 @@ pub async fn init() -> Result<(), anyhow::Error> {
         .expect("system settings cache failed");
 
     // cache alerts
-    db::alerts::templates::cache()
+    refresh_cache(CacheKind::Templates)
         .await
         .expect("alerts templates cache failed");
-    db::alerts::destinations::cache()
+    refresh_cache(CacheKind::Destinations)
         .await
         .expect("alerts destinations cache failed");
-    db::alerts::realtime_triggers::cache()
+    refresh_cache(CacheKind::RealtimeTriggers)
         .await
         .expect("alerts realtime triggers cache failed");
-    db::alerts::alert::cache()
+    refresh_cache(CacheKind::Alerts)
         .await
         .expect("alerts cache failed");
     #[cfg(feature = "enterprise")]
@@ -310,7 +322,7 @@ pub async fn init() -> Result<(), anyhow::Error> {
 
     #[cfg(feature = "enterprise")]
     if LOCAL_NODE.is_ingester() || LOCAL_NODE.is_querier() || LOCAL_NODE.is_alert_manager() {
-        db::session::cache()
+        refresh_cache(CacheKind::Session)
             .await
             .expect("user session cache failed");
     }
@@ -361,6 +373,7 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(pipeline::run());
     pipeline_error_cleanup::run();
     session_cleanup::run();
+    distinct_values_cleanup::run();
 
     if LOCAL_NODE.is_compactor() {
         tokio::task::spawn(file_list_dump::run());
@@ -438,7 +451,9 @@ pub async fn init_deferred() -> Result<(), anyhow::Error> {
         .await
         .expect("EnrichmentTables cache failed");
     // pipelines can potentially depend on enrichment tables, so cached afterwards
-    db::pipeline::cache().await.expect("Pipeline cache failed");
+    refresh_cache(CacheKind::Pipeline)
+        .await
+        .expect("Pipeline cache failed");
 
     Ok(())
 }
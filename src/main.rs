@@ -328,6 +328,17 @@ async fn main() -> Result<(), anyhow::Error> {
             // flush db
             let db = infra::db::get_db().await;
             _ = db.close().await;
+            // release the locks held in the cluster coordinator
+            let coordinator = infra::db::get_coordinator().await;
+            _ = coordinator.close().await;
+            #[cfg(feature = "enterprise")]
+            if o2_enterprise::enterprise::common::config::get_config()
+                .super_cluster
+                .enabled
+            {
+                let super_cluster = infra::db::get_super_cluster().await;
+                _ = super_cluster.close().await;
+            }
         });
     });
 
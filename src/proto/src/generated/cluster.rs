@@ -68,6 +68,24 @@ pub struct ScanStats {
     /// unit: bytes
     #[prost(int64, tag = "12")]
     pub peak_memory_usage: i64,
+    #[prost(int64, tag = "13")]
+    pub parquet_cache_hits: i64,
+    #[prost(int64, tag = "14")]
+    pub parquet_cache_misses: i64,
+    #[prost(int64, tag = "15")]
+    pub index_cache_hits: i64,
+    #[prost(int64, tag = "16")]
+    pub index_cache_misses: i64,
+    /// unit: bytes
+    #[prost(int64, tag = "17")]
+    pub downloaded_size: i64,
+    /// unit: bytes
+    #[prost(int64, tag = "18")]
+    pub download_skipped_size: i64,
+    #[prost(int64, tag = "19")]
+    pub file_stat_cache_hits: i64,
+    #[prost(int64, tag = "20")]
+    pub file_stat_cache_misses: i64,
 }
 #[derive(serde::Serialize)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -2884,6 +2902,10 @@ pub struct SearchInfo {
     pub sampling_config: ::core::option::Option<SamplingConfig>,
     #[prost(bool, tag = "11")]
     pub clear_cache: bool,
+    #[prost(bool, tag = "12")]
+    pub disable_index_skip_threshold: bool,
+    #[prost(bool, tag = "13")]
+    pub strict_index_only: bool,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct IndexInfo {
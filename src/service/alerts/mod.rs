@@ -426,6 +426,8 @@ impl QueryConditionExt for QueryCondition {
                 use_cache: false,
                 clear_cache: false,
                 local_mode: None,
+                disable_index_skip_threshold: false,
+                strict_index_only: false,
             };
             log::debug!(
                 "evaluate_scheduled trace_id: {trace_id}, begin to call SearchService::search, {req:?}"
@@ -47,8 +47,8 @@ use infra::{
     runtime::DATAFUSION_RUNTIME,
     schema::{
         SchemaCache, get_stream_setting_bloom_filter_fields, get_stream_setting_fts_fields,
-        get_stream_setting_index_fields, unwrap_partition_time_level, unwrap_stream_created_at,
-        unwrap_stream_settings,
+        get_stream_setting_fts_tokenizer, get_stream_setting_index_fields,
+        unwrap_partition_time_level, unwrap_stream_created_at, unwrap_stream_settings,
     },
     storage,
 };
@@ -769,6 +769,7 @@ pub async fn merge_files(
     let stream_settings = infra::schema::unwrap_stream_settings(&latest_schema);
     let bloom_filter_fields = get_stream_setting_bloom_filter_fields(&stream_settings);
     let full_text_search_fields = get_stream_setting_fts_fields(&stream_settings);
+    let fts_tokenizer = get_stream_setting_fts_tokenizer(&stream_settings);
     let index_fields = get_stream_setting_index_fields(&stream_settings);
     let (defined_schema_fields, need_original, index_original_data, index_all_values) =
         match stream_settings {
@@ -976,6 +977,7 @@ pub async fn merge_files(
                 generate_inverted_index(
                     &new_file_key,
                     &full_text_search_fields,
+                    &fts_tokenizer,
                     &index_fields,
                     &retain_file_list,
                     &mut new_file_meta,
@@ -1017,6 +1019,7 @@ pub async fn merge_files(
                     generate_inverted_index(
                         &new_file_key,
                         &full_text_search_fields,
+                        &fts_tokenizer,
                         &index_fields,
                         &retain_file_list,
                         &mut new_file_meta,
@@ -1048,6 +1051,7 @@ pub async fn merge_files(
 async fn generate_inverted_index(
     new_file_key: &str,
     full_text_search_fields: &[String],
+    fts_tokenizer: &str,
     index_fields: &[String],
     retain_file_list: &[FileKey],
     new_file_meta: &mut FileMeta,
@@ -1059,6 +1063,7 @@ async fn generate_inverted_index(
         "COMPACTOR",
         new_file_key,
         full_text_search_fields,
+        fts_tokenizer,
         index_fields,
         latest_schema, // Use stream schema to include all configured fields
         reader,
@@ -31,7 +31,7 @@ use sea_orm::{ConnectionTrait, TransactionTrait};
 use svix_ksuid::Ksuid;
 
 use crate::{
-    common::infra::config::{ALERTS, STREAM_ALERTS},
+    common::infra::config::{ALERTS, CacheKind, STREAM_ALERTS, mark_cache_refreshed},
     service::{alerts::alert::get_folder_alert_by_id_db, db},
 };
 
@@ -357,6 +357,7 @@ async fn put_into_cache(
     alert_id: String,
     _folder_id: Option<String>,
 ) -> Result<(), anyhow::Error> {
+    mark_cache_refreshed(CacheKind::Alerts);
     let client = ORM_CLIENT.get_or_init(connect_to_orm).await;
     let Ok(alert_id_ksuid) = svix_ksuid::Ksuid::from_str(&alert_id) else {
         log::error!("Error parsing alert id into Ksuid while putting the alert into cache");
@@ -398,6 +399,7 @@ async fn put_into_cache(
 }
 
 async fn delete_from_cache(org: String, alert_id: String) -> Result<(), anyhow::Error> {
+    mark_cache_refreshed(CacheKind::Alerts);
     // First delete from the alerts cache and then from stream_alerts cache if required
     let mut alerts_cacher = ALERTS.write().await;
     let alert_cache_key = cache_alert_key(&org, &alert_id);
@@ -19,7 +19,10 @@ use config::meta::destinations::Destination;
 use infra::table;
 use itertools::Itertools;
 
-use crate::{common::infra::config::DESTINATIONS, service::db};
+use crate::{
+    common::infra::config::{CacheKind, DESTINATIONS, mark_cache_refreshed},
+    service::db,
+};
 
 // db cache watcher prefix
 const DESTINATION_WATCHER_PREFIX: &str = "/destinations/";
@@ -174,6 +177,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::Destinations);
         match ev {
             db::Event::Put(ev) => {
                 let (org_id, name) = match parse_event_key(DESTINATION_WATCHER_PREFIX, &ev.key) {
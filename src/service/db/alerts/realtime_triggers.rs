@@ -17,7 +17,10 @@ use std::sync::Arc;
 
 use config::utils::json;
 
-use crate::{common::infra::config::REALTIME_ALERT_TRIGGERS, service::db};
+use crate::{
+    common::infra::config::{CacheKind, REALTIME_ALERT_TRIGGERS, mark_cache_refreshed},
+    service::db,
+};
 
 // Parses the item key from the event key and extracts org_id and module_key
 fn parse_item_key(key_prefix: &str, event_key: &str) -> (String, String, String) {
@@ -83,6 +86,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::RealtimeTriggers);
         match ev {
             // Cluster coordinator sends put events only for realtime alerts
             db::Event::Put(ev) => {
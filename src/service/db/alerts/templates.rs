@@ -21,7 +21,7 @@ use itertools::Itertools;
 
 use crate::{
     common::{
-        infra::config::{ALERTS, ALERTS_TEMPLATES, DESTINATIONS},
+        infra::config::{ALERTS, ALERTS_TEMPLATES, CacheKind, DESTINATIONS, mark_cache_refreshed},
         meta::organization::DEFAULT_ORG,
     },
     service::db,
@@ -180,6 +180,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::Templates);
         match ev {
             db::Event::Put(ev) => {
                 let (org_id, name) =
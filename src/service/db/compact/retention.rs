@@ -22,7 +22,10 @@ use config::{
 };
 use once_cell::sync::Lazy;
 
-use crate::service::db;
+use crate::{
+    common::infra::config::{CacheKind, mark_cache_refreshed},
+    service::db,
+};
 
 static CACHE: Lazy<RwHashMap<String, i64>> = Lazy::new(Default::default);
 
@@ -160,6 +163,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::CompactRetention);
         match ev {
             db::Event::Put(ev) => {
                 let item_key = ev.key.strip_prefix(key).unwrap();
@@ -13,13 +13,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use config::meta::stream::StreamType;
 #[cfg(feature = "enterprise")]
 use config::utils::json;
+use hashbrown::HashMap;
 #[cfg(feature = "enterprise")]
 use infra::table::distinct_values::BatchDeleteMessage;
 use infra::{
     errors,
-    table::distinct_values::{DistinctFieldRecord, OriginType},
+    table::distinct_values::{DistinctFieldRecord, FieldOrigin, OriginType},
 };
 #[cfg(feature = "enterprise")]
 use o2_enterprise::enterprise::common::config::get_config;
@@ -42,6 +44,63 @@ pub async fn batch_remove(origin: OriginType, origin_id: &str) -> Result<(), err
     infra::table::distinct_values::batch_remove(origin, origin_id).await
 }
 
+/// Removes the distinct fields registered by origins which no longer exist, i.e. deleted
+/// dashboards and streams. The orgs are processed one by one and the rows are removed in
+/// batches of `batch_size` origins, with `dry_run` the rows are only counted.
+///
+/// Returns the number of removed, or counted, rows.
+pub async fn cleanup_orphans(dry_run: bool, batch_size: usize) -> Result<u64, errors::Error> {
+    let action = if dry_run { "found" } else { "removed" };
+    let mut total = 0;
+    for org in infra::table::distinct_values::list_orgs().await? {
+        let mut dashboards = HashMap::new();
+        let mut stale = Vec::new();
+        for origin in infra::table::distinct_values::list_origins(&org).await? {
+            if is_orphan(&org, &origin, &mut dashboards).await? {
+                stale.push(origin);
+            }
+        }
+        let mut rows = 0;
+        for batch in stale.chunks(batch_size.max(1)) {
+            rows += infra::table::distinct_values::remove_origins(&org, batch, dry_run).await?;
+        }
+        if rows > 0 {
+            log::info!(
+                "[DISTINCT_VALUES_CLEANUP] org {org}: {action} {rows} stale distinct value field(s) of {} origin(s)",
+                stale.len()
+            );
+        }
+        total += rows;
+    }
+    Ok(total)
+}
+
+async fn is_orphan(
+    org: &str,
+    origin: &FieldOrigin,
+    dashboards: &mut HashMap<String, bool>,
+) -> Result<bool, errors::Error> {
+    match origin.origin {
+        OriginType::Dashboard => {
+            if let Some(exists) = dashboards.get(&origin.origin_id) {
+                return Ok(!exists);
+            }
+            let exists = infra::table::dashboards::get_by_id(org, &origin.origin_id)
+                .await?
+                .is_some();
+            dashboards.insert(origin.origin_id.clone(), exists);
+            Ok(!exists)
+        }
+        OriginType::Stream => {
+            let stream_type = StreamType::from(origin.stream_type.as_str());
+            let schema = infra::schema::get(org, &origin.stream_name, stream_type).await?;
+            Ok(schema.fields().is_empty())
+        }
+        // reports don't register distinct fields yet, keep them
+        OriginType::Report => Ok(false),
+    }
+}
+
 /// Sends event to super cluster queue for a new distinct values entry.
 #[cfg(feature = "enterprise")]
 pub async fn emit_put_event(record: &DistinctFieldRecord) -> Result<(), errors::Error> {
@@ -17,7 +17,10 @@ use std::sync::Arc;
 
 use config::{meta::function::Transform, utils::json};
 
-use crate::{common::infra::config::QUERY_FUNCTIONS, service::db};
+use crate::{
+    common::infra::config::{CacheKind, QUERY_FUNCTIONS, mark_cache_refreshed},
+    service::db,
+};
 
 pub async fn set(org_id: &str, name: &str, js_func: &Transform) -> Result<(), anyhow::Error> {
     let key = format!("/function/{org_id}/{name}");
@@ -78,6 +81,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::Functions);
         match ev {
             db::Event::Put(ev) => {
                 let item_key = ev.key.strip_prefix(key).unwrap();
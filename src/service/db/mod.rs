@@ -14,6 +14,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use bytes::Bytes;
+use config::meta::sql::OrderBy;
 use hashbrown::HashMap;
 use infra::{db as infra_db, errors::Result};
 #[cfg(feature = "enterprise")]
@@ -153,9 +154,10 @@ pub(crate) async fn list_values(prefix: &str) -> Result<Vec<Bytes>> {
 pub(crate) async fn list_values_by_start_dt(
     prefix: &str,
     start_dt: Option<(i64, i64)>,
+    order: OrderBy,
 ) -> Result<Vec<(i64, Bytes)>> {
     let db = infra_db::get_db().await;
-    db.list_values_by_start_dt(prefix, start_dt).await
+    db.list_values_by_start_dt(prefix, start_dt, order).await
 }
 
 #[cfg(test)]
@@ -25,7 +25,9 @@ use infra::{
 };
 
 use crate::common::{
-    infra::config::{ORG_USERS, ROOT_USER, USERS, USERS_RUM_TOKEN},
+    infra::config::{
+        CacheKind, ORG_USERS, ROOT_USER, USERS, USERS_RUM_TOKEN, mark_cache_refreshed,
+    },
     utils::auth::is_root_user,
 };
 
@@ -334,6 +336,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::OrgUsers);
         match ev {
             db::Event::Put(ev) => {
                 let item_key = ev.key.strip_prefix(key).unwrap();
@@ -26,7 +26,7 @@ use infra::{
 // use o2_enterprise::enterprise::cloud::org_usage::{self, OrgUsageRecord};
 use crate::{
     common::{
-        infra::config::{ORGANIZATION_SETTING, ORGANIZATIONS},
+        infra::config::{CacheKind, ORGANIZATION_SETTING, ORGANIZATIONS, mark_cache_refreshed},
         meta::organization::{Organization, OrganizationSetting},
     },
     service::db,
@@ -223,6 +223,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 return Ok(());
             }
         };
+        mark_cache_refreshed(CacheKind::Organization);
 
         if let db::Event::Put(ev) = ev {
             let item_key = ev.key.strip_prefix(key).unwrap();
@@ -28,7 +28,8 @@ use once_cell::sync::Lazy;
 
 use crate::{
     common::infra::config::{
-        PIPELINE_STREAM_MAPPING, SCHEDULED_PIPELINES, STREAM_EXECUTABLE_PIPELINES,
+        CacheKind, PIPELINE_STREAM_MAPPING, SCHEDULED_PIPELINES, STREAM_EXECUTABLE_PIPELINES,
+        mark_cache_refreshed,
     },
     service::pipeline::batch_execution::ExecutablePipeline,
 };
@@ -317,6 +318,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::Pipeline);
         match ev {
             db::Event::Put(ev) => {
                 let pipeline_id = ev.key.strip_prefix(PIPELINES_WATCH_PREFIX).unwrap();
@@ -25,7 +25,7 @@ use config::{
     get_config,
     ider::SnowflakeIdGenerator,
     is_local_disk_storage,
-    meta::{cluster::RoleGroup, stream::StreamType},
+    meta::{cluster::RoleGroup, sql::OrderBy, stream::StreamType},
     utils::{json, time::now_micros},
 };
 use hashbrown::{HashMap, HashSet};
@@ -45,7 +45,7 @@ use {
 
 use crate::{
     common::{
-        infra::config::{ENRICHMENT_TABLES, ORGANIZATIONS},
+        infra::config::{CacheKind, ENRICHMENT_TABLES, ORGANIZATIONS, mark_cache_refreshed},
         meta::stream::StreamSchema,
     },
     service::{db, enrichment::StreamTable, organization::check_and_create_org},
@@ -297,6 +297,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::Schema);
         log::debug!("[Schema:watch] Received event: {ev:?}");
         match ev {
             db::Event::Put(ev) => {
@@ -334,14 +335,19 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                     Some((ev_start_dt, now_micros()))
                 };
 
-                let mut schema_versions =
-                    match db::list_values_by_start_dt(&format!("{ev_key}/"), ts_range).await {
-                        Ok(val) => val,
-                        Err(e) => {
-                            log::error!("[Schema:watch] Error getting value: {e}");
-                            continue;
-                        }
-                    };
+                let mut schema_versions = match db::list_values_by_start_dt(
+                    &format!("{ev_key}/"),
+                    ts_range,
+                    OrderBy::Asc,
+                )
+                .await
+                {
+                    Ok(val) => val,
+                    Err(e) => {
+                        log::error!("[Schema:watch] Error getting value: {e}");
+                        continue;
+                    }
+                };
                 if schema_versions.is_empty() {
                     log::warn!("[Schema:watch] No schema versions found, skip");
                     continue;
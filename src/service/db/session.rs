@@ -20,7 +20,9 @@ use infra::db::{delete_from_db_coordinator, put_into_db_coordinator};
 #[cfg(feature = "enterprise")]
 use o2_enterprise::enterprise::common::config::get_config as get_o2_config;
 
-use crate::common::infra::config::{USER_SESSIONS, USER_SESSIONS_EXPIRY};
+use crate::common::infra::config::{
+    CacheKind, USER_SESSIONS, USER_SESSIONS_EXPIRY, mark_cache_refreshed,
+};
 
 // Key prefix for session events in coordinator
 pub const USER_SESSION_KEY: &str = "/user_sessions/";
@@ -186,6 +188,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 return Ok(());
             }
         };
+        mark_cache_refreshed(CacheKind::Session);
         match ev {
             infra::db::Event::Put(ev) => {
                 let session_id = ev.key.strip_prefix(key).unwrap();
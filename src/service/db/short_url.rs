@@ -20,7 +20,10 @@ use chrono::Utc;
 use config::get_config;
 use infra::{db::Event, table::short_urls};
 
-use crate::{common::infra::config::SHORT_URLS, service::db};
+use crate::{
+    common::infra::config::{CacheKind, SHORT_URLS, mark_cache_refreshed},
+    service::db,
+};
 
 // DBKey to set short URL's
 pub const SHORT_URL_KEY: &str = "/short_urls/";
@@ -78,6 +81,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 return Ok(());
             }
         };
+        mark_cache_refreshed(CacheKind::ShortUrl);
 
         match ev {
             Event::Put(ev) => {
@@ -29,7 +29,7 @@ use o2_enterprise::enterprise::cloud::{InvitationRecord, org_invites};
 use super::org_users::{self, get_cached_user_org};
 use crate::{
     common::{
-        infra::config::{ROOT_USER, USERS, USERS_RUM_TOKEN},
+        infra::config::{CacheKind, ROOT_USER, USERS, USERS_RUM_TOKEN, mark_cache_refreshed},
         utils::auth::is_root_user,
     },
     service::db,
@@ -264,6 +264,7 @@ pub async fn watch() -> Result<(), anyhow::Error> {
                 break;
             }
         };
+        mark_cache_refreshed(CacheKind::User);
         match ev {
             db::Event::Put(ev) => {
                 let item_key = ev.key.strip_prefix(key).unwrap();
@@ -193,6 +193,7 @@ pub async fn exec(
             .collect_vec(),
         &mut scan_stats,
         "parquet",
+        None,
     )
     .await;
 
@@ -207,6 +207,8 @@ impl TraceListIndex {
                 partition_time_level: None,
                 partition_keys: vec![],
                 full_text_search_keys: vec![],
+                full_text_search_exclude_keys: vec![],
+                full_text_search_tokenizer: None,
                 index_fields: vec![],
                 bloom_filter_fields: vec!["trace_id".to_string()],
                 data_retention: 0,
@@ -222,6 +224,7 @@ impl TraceListIndex {
                 index_original_data: false,
                 enable_distinct_fields: true,
                 enable_log_patterns_extraction: false,
+                cache_policy: None,
             };
 
             stream::save_stream_settings(org_id, STREAM_NAME, StreamType::Metadata, settings)
@@ -0,0 +1,202 @@
+// Copyright 2026 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Idempotency keys of the metrics `_json` ingestion.
+//!
+//! A collector retrying a timed out request sends the same batch again, the
+//! `X-Idempotency-Key` header lets the second request be ignored instead of
+//! ingesting the samples twice. The key is recorded per org in the meta store
+//! with its expiry time as the value.
+
+use bytes::Bytes;
+use config::{
+    get_config,
+    utils::{
+        hash::{Sum64, gxhash},
+        time::now_micros,
+    },
+};
+use infra::db::{Db, NO_NEED_WATCH, get_db};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "x-idempotency-key";
+
+const IDEMPOTENCY_KEY_PREFIX: &str = "/metrics_idempotency";
+
+// the key is hashed so any header value fits in the key of the meta store
+fn record_key(org_id: &str, key: &str) -> String {
+    format!(
+        "{IDEMPOTENCY_KEY_PREFIX}/{org_id}/{:x}",
+        gxhash::new().sum64(key)
+    )
+}
+
+/// Records the idempotency key of the org, returns `false` if the key was already recorded and
+/// isn't expired, then the request is a duplicate and must not be ingested.
+///
+/// The key is recorded before the ingestion so a retry arriving while the first request is
+/// still in progress is ignored as well, call [`release`] if the ingestion fails.
+pub async fn reserve(org_id: &str, key: &str) -> Result<bool, anyhow::Error> {
+    let ttl = get_config().limit.metrics_idempotency_ttl * 1_000_000;
+    reserve_with(get_db().await.as_ref(), org_id, key, now_micros(), ttl).await
+}
+
+async fn reserve_with(
+    db: &dyn Db,
+    org_id: &str,
+    key: &str,
+    now: i64,
+    ttl: i64,
+) -> Result<bool, anyhow::Error> {
+    // the check and the record are done under the lock of the key, so only one of the
+    // concurrent retries records it
+    let outcome = db
+        .get_for_update(
+            &record_key(org_id, key),
+            NO_NEED_WATCH,
+            None,
+            Box::new(move |value| {
+                if let Some(value) = value {
+                    let expires_at: i64 =
+                        String::from_utf8_lossy(&value).parse().unwrap_or_default();
+                    if expires_at > now {
+                        return Ok(None);
+                    }
+                }
+                Ok(Some((Some(Bytes::from((now + ttl).to_string())), None)))
+            }),
+        )
+        .await?;
+    Ok(outcome.written)
+}
+
+/// Removes the idempotency key of the org so the client can retry the batch.
+pub async fn release(org_id: &str, key: &str) -> Result<(), anyhow::Error> {
+    get_db()
+        .await
+        .delete(&record_key(org_id, key), false, NO_NEED_WATCH, None)
+        .await?;
+    Ok(())
+}
+
+/// Deletes the expired idempotency keys of all the orgs, returns the number of deleted keys.
+///
+/// An expired key is overwritten when it's sent again, this only keeps the keys which are never
+/// sent again from piling up.
+pub async fn delete_expired() -> Result<usize, anyhow::Error> {
+    delete_expired_with(
+        get_db().await.as_ref(),
+        IDEMPOTENCY_KEY_PREFIX,
+        now_micros(),
+    )
+    .await
+}
+
+async fn delete_expired_with(db: &dyn Db, prefix: &str, now: i64) -> Result<usize, anyhow::Error> {
+    let mut deleted = 0;
+    for (key, value) in db.list(prefix).await? {
+        let expires_at: i64 = String::from_utf8_lossy(&value).parse().unwrap_or_default();
+        if expires_at <= now {
+            db.delete(&key, false, NO_NEED_WATCH, None).await?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_prefix(org_id: &str) -> String {
+        format!("{IDEMPOTENCY_KEY_PREFIX}/{org_id}")
+    }
+
+    #[tokio::test]
+    async fn test_reserve_idempotency_key() {
+        infra::db::create_table().await.unwrap();
+        let db = get_db().await;
+        let (now, ttl) = (1_000_000, 300_000_000);
+        // the orgs are unique so the keys of the other tests and runs aren't touched
+        let org1 = format!("org_reserve_{}", config::ider::uuid());
+        let org2 = format!("org_reserve_{}", config::ider::uuid());
+
+        // the first request is ingested, the retry within the ttl is ignored
+        assert!(
+            reserve_with(db.as_ref(), &org1, "batch-1", now, ttl)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !reserve_with(db.as_ref(), &org1, "batch-1", now + 1, ttl)
+                .await
+                .unwrap()
+        );
+
+        // the keys are namespaced per org
+        assert!(
+            reserve_with(db.as_ref(), &org2, "batch-1", now, ttl)
+                .await
+                .unwrap()
+        );
+
+        // an expired key is recorded again
+        assert!(
+            reserve_with(db.as_ref(), &org1, "batch-1", now + ttl, ttl)
+                .await
+                .unwrap()
+        );
+
+        // only the expired keys are deleted, the key of org2 expired and the keys of org1 were
+        // recorded again
+        assert!(
+            reserve_with(db.as_ref(), &org1, "batch-2", now + ttl, ttl)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            delete_expired_with(db.as_ref(), &record_prefix(&org1), now + ttl + 1)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            delete_expired_with(db.as_ref(), &record_prefix(&org2), now + ttl + 1)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(
+            !reserve_with(db.as_ref(), &org1, "batch-2", now + ttl, ttl)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reserve_idempotency_key_concurrent() {
+        infra::db::create_table().await.unwrap();
+        let db = get_db().await;
+        let (now, ttl) = (1_000_000, 300_000_000);
+        let org_id = format!("org_concurrent_{}", config::ider::uuid());
+
+        // only one of the concurrent retries of the batch is ingested
+        let ret = futures::future::join_all(
+            (0..8).map(|_| reserve_with(db.as_ref(), &org_id, "batch-1", now, ttl)),
+        )
+        .await;
+        let reserved = ret.into_iter().filter(|r| *r.as_ref().unwrap()).count();
+        assert_eq!(reserved, 1);
+    }
+}
@@ -39,6 +39,7 @@ use config::{
     },
 };
 use datafusion::arrow::datatypes::Schema;
+use futures::{Stream, StreamExt};
 use infra::schema::{SchemaCache, unwrap_partition_time_level};
 
 use super::get_exclude_labels;
@@ -63,11 +64,243 @@ use crate::{
 
 const VALID_METRICS_TYPES: &[&str] = &["counter", "gauge", "histogram", "summary"];
 
+/// The lines of a multi-line request ingested together, the request is ingested in batches so
+/// only one batch of parsed records is held at a time
+const MULTI_LINES_BATCH_SIZE: usize = 1000;
+
+/// The status name of the lines of a multi-line request which failed to be parsed or ingested,
+/// the errors carry the line numbers
+pub const MULTI_FAILED_LINES_STATUS: &str = "_failed_lines";
+
 pub async fn ingest(
     org_id: &str,
     stream_name: Option<&str>,
     body: Bytes,
     user: crate::common::meta::ingestion::IngestUser,
+) -> Result<IngestionResponse> {
+    let records: Vec<json::Value> = json::from_slice(&body)?;
+    ingest_records(org_id, stream_name, records, user).await
+}
+
+/// Ingests a body with one metric object per line as it arrives, the malformed lines and the
+/// lines longer than `max_line_size` are counted as failed with their line number instead of
+/// failing the request.
+///
+/// If a batch is rejected, e.g. by the ingestion limits, or the body fails to be read, the rest
+/// of the body is not read and the response carries the code of the failure with the status of
+/// the lines ingested before.
+pub async fn ingest_multi<S, E>(
+    org_id: &str,
+    mut body: S,
+    max_line_size: usize,
+    user: crate::common::meta::ingestion::IngestUser,
+) -> Result<IngestionResponse>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut lines = MultiLines::new(org_id, user);
+    let mut buf = Vec::new();
+    let mut line_no = 0;
+    // the current line is too long and is dropped up to the next newline
+    let mut skip_line = false;
+    while let Some(chunk) = body.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                // the complete lines read before are still ingested
+                if let Some(rejected) = lines.flush(line_no).await {
+                    return Ok(lines.into_response(rejected.code, rejected.error));
+                }
+                let error = format!("read body error: {e}");
+                lines.push_error(
+                    line_no + 1,
+                    format!("{error}, the following lines are not ingested"),
+                );
+                return Ok(lines.into_response(http::StatusCode::BAD_REQUEST.into(), Some(error)));
+            }
+        };
+        let mut rest = chunk.as_ref();
+        while let Some(pos) = rest.iter().position(|b| *b == b'\n') {
+            line_no += 1;
+            if skip_line {
+                skip_line = false;
+            } else if buf.len() + pos > max_line_size {
+                lines.push_too_long(line_no, max_line_size);
+                buf.clear();
+            } else if buf.is_empty() {
+                lines.push_line(line_no, &rest[..pos]);
+            } else {
+                buf.extend_from_slice(&rest[..pos]);
+                lines.push_line(line_no, &buf);
+                buf.clear();
+            }
+            rest = &rest[pos + 1..];
+            if lines.batch.len() >= MULTI_LINES_BATCH_SIZE
+                && let Some(rejected) = lines.flush(line_no).await
+            {
+                return Ok(lines.into_response(rejected.code, rejected.error));
+            }
+        }
+        if skip_line {
+            continue;
+        }
+        buf.extend_from_slice(rest);
+        if buf.len() > max_line_size {
+            lines.push_too_long(line_no + 1, max_line_size);
+            buf.clear();
+            skip_line = true;
+        }
+    }
+    line_no += 1;
+    if !skip_line {
+        lines.push_line(line_no, &buf);
+    }
+    if let Some(rejected) = lines.flush(line_no).await {
+        return Ok(lines.into_response(rejected.code, rejected.error));
+    }
+    Ok(lines.into_response(http::StatusCode::OK.into(), None))
+}
+
+/// The state of a multi-line request, only the current batch of parsed records is held
+struct MultiLines<'a> {
+    org_id: &'a str,
+    user: crate::common::meta::ingestion::IngestUser,
+    stream_status_map: HashMap<String, StreamStatus>,
+    failed_lines: StreamStatus,
+    line_errors: Vec<String>,
+    batch: Vec<json::Value>,
+    batch_start: usize,
+}
+
+impl<'a> MultiLines<'a> {
+    fn new(org_id: &'a str, user: crate::common::meta::ingestion::IngestUser) -> Self {
+        Self {
+            org_id,
+            user,
+            stream_status_map: HashMap::new(),
+            failed_lines: StreamStatus::new(MULTI_FAILED_LINES_STATUS),
+            line_errors: Vec::new(),
+            batch: Vec::with_capacity(MULTI_LINES_BATCH_SIZE),
+            batch_start: 1,
+        }
+    }
+
+    fn push_line(&mut self, line_no: usize, line: &[u8]) {
+        if line.trim_ascii().is_empty() {
+            return;
+        }
+        match json::from_slice::<json::Value>(line) {
+            Ok(record) if record.is_object() => match validate_record(&record) {
+                Ok(()) => self.batch.push(record),
+                Err(e) => self.push_error(line_no, e.to_string()),
+            },
+            Ok(_) => self.push_error(line_no, "need to be a json object".to_string()),
+            Err(e) => self.push_error(line_no, e.to_string()),
+        }
+    }
+
+    fn push_error(&mut self, line_no: usize, error: String) {
+        self.failed_lines.status.failed += 1;
+        self.line_errors.push(format!("line {line_no}: {error}"));
+    }
+
+    fn push_too_long(&mut self, line_no: usize, max_line_size: usize) {
+        self.push_error(
+            line_no,
+            format!("longer than the limit of {max_line_size} bytes"),
+        );
+    }
+
+    /// Ingests the batch of the lines up to `line_no`, returns the response of the batch if it is
+    /// rejected and the request should stop.
+    async fn flush(&mut self, line_no: usize) -> Option<IngestionResponse> {
+        let batch_start = std::mem::replace(&mut self.batch_start, line_no + 1);
+        if self.batch.is_empty() {
+            return None;
+        }
+
+        let count = self.batch.len() as u32;
+        let records =
+            std::mem::replace(&mut self.batch, Vec::with_capacity(MULTI_LINES_BATCH_SIZE));
+        match ingest_records(self.org_id, None, records, self.user.clone()).await {
+            Ok(resp) if resp.code != http::StatusCode::OK.as_u16() => {
+                self.failed_lines.status.failed += count;
+                self.line_errors.push(format!(
+                    "lines {batch_start}-{line_no}: {}, the following lines are not ingested",
+                    resp.error.as_deref().unwrap_or("rejected")
+                ));
+                return Some(resp);
+            }
+            Ok(resp) => {
+                for status in resp.status {
+                    let entry = self
+                        .stream_status_map
+                        .entry(status.name.clone())
+                        .or_insert_with(|| StreamStatus::new(&status.name));
+                    entry.status.successful += status.status.successful;
+                    entry.status.failed += status.status.failed;
+                    if !status.status.error.is_empty() {
+                        entry.status.error = status.status.error;
+                    }
+                }
+            }
+            Err(e) => {
+                self.failed_lines.status.failed += count;
+                self.line_errors
+                    .push(format!("lines {batch_start}-{line_no}: {e}"));
+            }
+        }
+        None
+    }
+
+    fn into_response(self, code: u16, error: Option<String>) -> IngestionResponse {
+        let mut status = self.stream_status_map.into_values().collect::<Vec<_>>();
+        let mut failed_lines = self.failed_lines;
+        if failed_lines.status.failed > 0 {
+            failed_lines.status.error = self.line_errors.join("; ");
+            status.push(failed_lines);
+        }
+        IngestionResponse {
+            code,
+            status,
+            error,
+        }
+    }
+}
+
+/// Checks the fields [`ingest_records`] fails the whole batch for, so the invalid lines of a
+/// multi-line request are counted as failed on their own
+fn validate_record(record: &json::Value) -> Result<()> {
+    match record.get(NAME_LABEL) {
+        Some(json::Value::String(_)) => {}
+        Some(_) => return Err(anyhow!("invalid __name__, need to be string")),
+        None => return Err(anyhow!("missing __name__")),
+    }
+    let metrics_type = record
+        .get(TYPE_LABEL)
+        .and_then(|v| v.as_str())
+        .unwrap_or("gauge");
+    if !VALID_METRICS_TYPES.contains(&metrics_type.to_lowercase().as_str()) {
+        return Err(anyhow!(
+            "invalid metrics type, need to be one of: {}",
+            VALID_METRICS_TYPES.join(", ")
+        ));
+    }
+    if record
+        .get(TIMESTAMP_COL_NAME)
+        .is_some_and(|v| !v.is_number())
+    {
+        return Err(anyhow!("invalid _timestamp, need to be number"));
+    }
+    Ok(())
+}
+
+async fn ingest_records(
+    org_id: &str,
+    stream_name: Option<&str>,
+    records: Vec<json::Value>,
+    user: crate::common::meta::ingestion::IngestUser,
 ) -> Result<IngestionResponse> {
     // check system resource
     if let Err(e) = check_ingestion_allowed(org_id, StreamType::Metrics, stream_name).await {
@@ -114,8 +347,7 @@ pub async fn ingest(
     // records buffer
     let mut json_data_by_stream: HashMap<String, Vec<_>> = HashMap::new();
 
-    let reader: Vec<json::Value> = json::from_slice(&body)?;
-    for record in reader.into_iter() {
+    for record in records.into_iter() {
         // JSON Flattening
         let mut record = flatten::flatten(record)?;
         // check data type
@@ -582,6 +814,7 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::common::meta::ingestion::IngestUser;
 
     fn create_test_metric_record(
         name: &str,
@@ -606,6 +839,105 @@ mod tests {
         record
     }
 
+    #[tokio::test]
+    async fn test_ingest_multi_malformed_lines() {
+        let body = Bytes::from("{\"__name__\": \n\n  \nnot json\r\n[1]");
+        let resp = ingest_multi(
+            "default",
+            futures::stream::iter([Ok::<_, std::io::Error>(body)]),
+            1024,
+            IngestUser::from_user_email("root@example.com"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.code, 200);
+        assert_eq!(resp.status.len(), 1);
+        let status = &resp.status[0];
+        assert_eq!(status.name, MULTI_FAILED_LINES_STATUS);
+        assert_eq!(status.status.successful, 0);
+        assert_eq!(status.status.failed, 3);
+        let lines = status
+            .status
+            .error
+            .split("; ")
+            .map(|e| e.split(':').next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec!["line 1", "line 4", "line 5"]);
+    }
+
+    #[tokio::test]
+    async fn test_ingest_multi_chunked_lines() {
+        // a line split across chunks and a line longer than the limit
+        let chunks = ["not js", "on\n0123456789", "0123\n[1", "]\n"];
+        let resp = ingest_multi(
+            "default",
+            futures::stream::iter(chunks.map(|c| Ok::<_, std::io::Error>(Bytes::from(c)))),
+            10,
+            IngestUser::from_user_email("root@example.com"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.code, 200);
+        assert_eq!(resp.status.len(), 1);
+        let status = &resp.status[0];
+        assert_eq!(status.status.failed, 3);
+        let errors = status.status.error.split("; ").collect::<Vec<_>>();
+        assert!(errors[0].starts_with("line 1: expected"));
+        assert_eq!(errors[1], "line 2: longer than the limit of 10 bytes");
+        assert_eq!(errors[2], "line 3: need to be a json object");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_multi_invalid_records() {
+        let body = Bytes::from(
+            "{\"value\": 1}\n{\"__name__\": 1}\n{\"__name__\": \"up\", \"__type__\": \"set\"}\n{\"__name__\": \"up\", \"_timestamp\": \"now\"}",
+        );
+        let resp = ingest_multi(
+            "default",
+            futures::stream::iter([Ok::<_, std::io::Error>(body)]),
+            1024,
+            IngestUser::from_user_email("root@example.com"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.code, 200);
+        assert_eq!(resp.status.len(), 1);
+        let status = &resp.status[0];
+        assert_eq!(status.status.failed, 4);
+        let errors = status.status.error.split("; ").collect::<Vec<_>>();
+        assert_eq!(errors[0], "line 1: missing __name__");
+        assert_eq!(errors[1], "line 2: invalid __name__, need to be string");
+        assert!(errors[2].starts_with("line 3: invalid metrics type"));
+        assert_eq!(errors[3], "line 4: invalid _timestamp, need to be number");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_multi_body_error_keeps_status() {
+        let chunks = [
+            Ok(Bytes::from("not json\n[1]\n")),
+            Err(std::io::Error::other("connection reset")),
+        ];
+        let resp = ingest_multi(
+            "default",
+            futures::stream::iter(chunks),
+            1024,
+            IngestUser::from_user_email("root@example.com"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.code, 400);
+        assert_eq!(
+            resp.error.as_deref(),
+            Some("read body error: connection reset")
+        );
+        let status = &resp.status[0];
+        assert_eq!(status.status.failed, 3);
+        let errors = status.status.error.split("; ").collect::<Vec<_>>();
+        assert!(errors[0].starts_with("line 1: "));
+        assert_eq!(errors[1], "line 2: need to be a json object");
+        assert!(errors[2].starts_with("line 3: read body error: connection reset"));
+    }
+
     #[test]
     fn test_create_test_metric_record() {
         let record = create_test_metric_record(
@@ -19,6 +19,7 @@ use config::{
 };
 use datafusion::arrow::datatypes::Schema;
 
+pub mod idempotency;
 pub mod json;
 pub mod otlp;
 pub mod prom;
@@ -14,7 +14,7 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     io::Error,
     sync::Arc,
 };
@@ -170,7 +170,7 @@ pub async fn handle_otlp_request(
     let mut stream_alerts_map: HashMap<String, Vec<alert::Alert>> = HashMap::new();
     let mut stream_trigger_map: HashMap<String, Option<TriggerAlertData>> = HashMap::new();
 
-    let mut partial_success = ExportMetricsPartialSuccess::default();
+    let mut data_points_status = DataPointsStatus::default();
 
     // records buffer
     let mut json_data_by_stream: HashMap<String, Vec<_>> = HashMap::new();
@@ -302,7 +302,13 @@ pub async fn handle_otlp_request(
                 // process data points
                 for mut rec in records {
                     // flattening
-                    rec = flatten::flatten(rec)?;
+                    rec = match flatten::flatten(rec) {
+                        Ok(rec) => rec,
+                        Err(e) => {
+                            data_points_status.reject(&metric_name, 1, e);
+                            continue;
+                        }
+                    };
 
                     let local_metric_name = format_stream_name(
                         rec.get(NAME_LABEL).unwrap().as_str().unwrap().to_string(),
@@ -401,7 +407,7 @@ pub async fn handle_otlp_request(
                     "[Ingestion]: Stream {stream_name} has pipeline, but inputs failed to be buffered. BUG",
                 );
                 log::error!("{err_msg}");
-                partial_success.error_message = err_msg;
+                data_points_status.reject(stream_name, 0, err_msg);
                 continue;
             };
             let count = pipeline_inputs.len();
@@ -415,8 +421,7 @@ pub async fn handle_otlp_request(
                     );
                     log::error!("{err_msg}");
                     // update status
-                    partial_success.rejected_data_points += count as i64;
-                    partial_success.error_message = err_msg;
+                    data_points_status.reject(stream_name, count, err_msg);
                     continue;
                 }
                 Ok(pl_results) => {
@@ -499,7 +504,9 @@ pub async fn handle_otlp_request(
             }
             drop(schema_fields);
             if need_schema_check {
-                let (schema_evolution, _infer_schema) = check_for_schema(
+                // a data point that fails the schema validation is rejected alone, the others
+                // of the request are still ingested
+                let schema_evolution = match check_for_schema(
                     org_id,
                     &local_metric_name,
                     StreamType::Metrics,
@@ -508,7 +515,14 @@ pub async fn handle_otlp_request(
                     timestamp,
                     false, // is_derived is false for metrics
                 )
-                .await?;
+                .await
+                {
+                    Ok((schema_evolution, _infer_schema)) => schema_evolution,
+                    Err(e) => {
+                        data_points_status.reject(&local_metric_name, 1, e);
+                        continue;
+                    }
+                };
                 if schema_evolution.is_schema_changed {
                     schema_evolved.insert(local_metric_name.to_owned(), true);
                 }
@@ -543,6 +557,7 @@ pub async fn handle_otlp_request(
                 .records
                 .push(Arc::new(json::Value::Object(val_map.to_owned())));
             hour_buf.records_size += value_str.len();
+            data_points_status.accepted += 1;
 
             // real time alert
             let need_trigger = !stream_trigger_map.contains_key(&local_metric_name);
@@ -646,7 +661,7 @@ pub async fn handle_otlp_request(
         }
     }
 
-    format_response(partial_success, req_type)
+    format_response(data_points_status.partial_success(), req_type)
 }
 
 fn process_gauge(
@@ -999,8 +1014,49 @@ fn process_aggregation_temporality(rec: &mut json::Value, val: i32) {
     .into();
 }
 
+/// The data points of a request that were accepted, and the ones rejected per metric. The
+/// rejected ones are reported in the partial success of the response, so the clients can drop
+/// them instead of retrying the whole request.
+#[derive(Debug, Default)]
+struct DataPointsStatus {
+    accepted: i64,
+    // metric -> (rejected data points, first error)
+    rejected: BTreeMap<String, (i64, String)>,
+}
+
+impl DataPointsStatus {
+    fn reject(&mut self, metric: &str, count: usize, error: impl ToString) {
+        let entry = self
+            .rejected
+            .entry(metric.to_string())
+            .or_insert_with(|| (0, error.to_string()));
+        entry.0 += count as i64;
+    }
+
+    fn partial_success(&self) -> ExportMetricsPartialSuccess {
+        let rejected_data_points = self.rejected.values().map(|(count, _)| count).sum::<i64>();
+        if rejected_data_points == 0 {
+            return ExportMetricsPartialSuccess::default();
+        }
+        let errors = self
+            .rejected
+            .iter()
+            .filter(|(_, (count, _))| *count > 0)
+            .map(|(metric, (count, error))| format!("{metric}: {count} rejected, {error}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        ExportMetricsPartialSuccess {
+            rejected_data_points,
+            error_message: format!(
+                "accepted {} data points, rejected {rejected_data_points} data points: {errors}",
+                self.accepted
+            ),
+        }
+    }
+}
+
 fn format_response(
-    mut partial_success: ExportMetricsPartialSuccess,
+    partial_success: ExportMetricsPartialSuccess,
     req_type: OtlpRequestType,
 ) -> Result<HttpResponse, anyhow::Error> {
     let partial = partial_success.rejected_data_points > 0;
@@ -1011,9 +1067,6 @@ fn format_response(
             partial_success.rejected_data_points,
             partial_success.error_message
         );
-        partial_success.error_message =
-            "Some data points were rejected due to exceeding the allowed retention period"
-                .to_string();
         ExportMetricsServiceResponse {
             partial_success: Some(partial_success),
         }
@@ -1021,12 +1074,10 @@ fn format_response(
         ExportMetricsServiceResponse::default()
     };
 
+    // a partial success is still a success for OTLP, the rejected data points are described in
+    // the body and must not be retried
     match req_type {
-        OtlpRequestType::HttpJson => Ok(if partial {
-            (http::StatusCode::PARTIAL_CONTENT, Json(res)).into_response()
-        } else {
-            MetaHttpResponse::json(res)
-        }),
+        OtlpRequestType::HttpJson => Ok(MetaHttpResponse::json(res)),
         _ => {
             let mut out = BytesMut::with_capacity(res.encoded_len());
             res.encode(&mut out).expect("Out of memory");
@@ -1561,7 +1612,27 @@ mod tests {
         assert!(response.is_ok());
 
         let http_response = response.unwrap();
-        assert_eq!(http_response.status(), http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(http_response.status(), http::StatusCode::OK);
+    }
+
+    #[test]
+    fn test_data_points_status() {
+        let mut status = DataPointsStatus::default();
+        assert_eq!(
+            status.partial_success(),
+            ExportMetricsPartialSuccess::default()
+        );
+
+        status.accepted = 8;
+        status.reject("cpu_usage", 1, "too many columns");
+        status.reject("cpu_usage", 2, "other error");
+        status.reject("mem_usage", 0, "pipeline inputs missing");
+        let partial_success = status.partial_success();
+        assert_eq!(partial_success.rejected_data_points, 3);
+        assert_eq!(
+            partial_success.error_message,
+            "accepted 8 data points, rejected 3 data points: cpu_usage: 3 rejected, too many columns"
+        );
     }
 
     #[test]
@@ -2335,7 +2406,7 @@ mod tests {
             assert!(response.is_ok());
 
             let http_response = response.unwrap();
-            assert_eq!(http_response.status(), http::StatusCode::PARTIAL_CONTENT);
+            assert_eq!(http_response.status(), http::StatusCode::OK);
         }
 
         #[test]
@@ -905,6 +905,8 @@ pub(crate) async fn get_series(
         use_cache: default_use_cache(),
         clear_cache: false,
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
     let series = match search_service::search("", org_id, StreamType::Metrics, None, &req).await {
         Err(err) => {
@@ -1085,6 +1087,8 @@ pub(crate) async fn get_label_values(
         use_cache: default_use_cache(),
         clear_cache: false,
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
     let mut label_values = match search_service::search("", org_id, stream_type, None, &req).await {
         Ok(resp) => resp
@@ -127,10 +127,10 @@ pub fn calculate_trend(
 
 pub fn linear_regression(samples: &[Sample], intercept_time: i64) -> Option<(f64, f64)> {
     let mut num_samples = 0.0;
-    let mut sum_x = 0.0;
-    let mut sum_y = 0.0;
-    let mut sum_xy = 0.0;
-    let mut sum_x2 = 0.0;
+    let (mut sum_x, mut c_x) = (0.0, 0.0);
+    let (mut sum_y, mut c_y) = (0.0, 0.0);
+    let (mut sum_xy, mut c_xy) = (0.0, 0.0);
+    let (mut sum_x2, mut c_x2) = (0.0, 0.0);
     let initial_y = samples.first()?.value;
     let mut constant_y = true;
 
@@ -140,10 +140,10 @@ pub fn linear_regression(samples: &[Sample], intercept_time: i64) -> Option<(f64
         }
         num_samples += 1.0;
         let x = (sample.timestamp / 1000 - intercept_time) as f64 / 1e3;
-        sum_x += x;
-        sum_y += sample.value;
-        sum_xy += x * sample.value;
-        sum_x2 += x * x;
+        (sum_x, c_x) = kahan_sum_increment(x, sum_x, c_x);
+        (sum_y, c_y) = kahan_sum_increment(sample.value, sum_y, c_y);
+        (sum_xy, c_xy) = kahan_sum_increment(x * sample.value, sum_xy, c_xy);
+        (sum_x2, c_x2) = kahan_sum_increment(x * x, sum_x2, c_x2);
     }
 
     if constant_y {
@@ -152,6 +152,10 @@ pub fn linear_regression(samples: &[Sample], intercept_time: i64) -> Option<(f64
         }
         return Some((0.0, initial_y));
     }
+    sum_x += c_x;
+    sum_y += c_y;
+    sum_xy += c_xy;
+    sum_x2 += c_x2;
 
     let cov_xy = sum_xy - (sum_x * sum_y) / num_samples;
     let var_x = sum_x2 - (sum_x * sum_x) / num_samples;
@@ -1040,12 +1040,7 @@ impl Engine {
                 let max = self.call_expr_third_arg(args).await?;
 
                 let (min_f, max_f) = match (min, max) {
-                    (Value::Float(min), Value::Float(max)) => {
-                        if min > max {
-                            return Ok(Value::Matrix(vec![]));
-                        }
-                        (min, max)
-                    }
+                    (Value::Float(min), Value::Float(max)) => (min, max),
                     _ => {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
@@ -1064,7 +1059,7 @@ impl Engine {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
                 };
-                functions::clamp(input, f64::MIN, max_f)?
+                functions::clamp_max(input, max_f)?
             }
             Func::ClampMin => {
                 let err = "Invalid args, expected \"clamp(v instant-vector, min scalar)\"";
@@ -1078,7 +1073,7 @@ impl Engine {
                         return Err(DataFusionError::NotImplemented(err.into()));
                     }
                 };
-                functions::clamp(input, min_f, f64::MAX)?
+                functions::clamp_min(input, min_f)?
             }
             Func::CountOverTime => functions::count_over_time(input, &self.eval_ctx)?,
             Func::DayOfMonth => functions::day_of_month(input)?,
@@ -1095,9 +1090,18 @@ impl Engine {
                 )));
             }
             Func::HistogramFraction => {
-                return Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported Function: {func_name:?}"
-                )));
+                let err = "Invalid args, expected \"histogram_fraction(lower scalar, upper scalar, b instant-vector)\"";
+                self.ensure_three_args(args, err)?;
+
+                let lower = self.call_expr_first_arg(args).await?;
+                let upper = self.call_expr_second_arg(args).await?;
+                let (lower, upper) = match (lower, upper) {
+                    (Value::Float(lower), Value::Float(upper)) => (lower, upper),
+                    _ => {
+                        return Err(DataFusionError::NotImplemented(err.into()));
+                    }
+                };
+                functions::histogram_fraction(lower, upper, input, &self.eval_ctx)?
             }
             Func::HistogramQuantile => {
                 let args = &args.args;
@@ -1198,6 +1202,7 @@ impl Engine {
             Func::Ln => functions::ln(input)?,
             Func::Log10 => functions::log10(input)?,
             Func::Log2 => functions::log2(input)?,
+            Func::MadOverTime => functions::mad_over_time(input, &self.eval_ctx)?,
             Func::MaxOverTime => functions::max_over_time(input, &self.eval_ctx)?,
             Func::MinOverTime => functions::min_over_time(input, &self.eval_ctx)?,
             Func::Minute => functions::minute(input)?,
@@ -1235,16 +1240,29 @@ impl Engine {
             Func::Round => functions::round(input)?,
             Func::Scalar => functions::scalar(input, &self.eval_ctx)?,
             Func::Sgn => functions::sgn(input)?,
-            Func::Sort => {
-                return Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported Function: {func_name:?}"
-                )));
-            }
-            Func::SortDesc => {
-                return Err(DataFusionError::NotImplemented(format!(
-                    "Unsupported Function: {func_name:?}"
-                )));
+            Func::Sort => functions::SortOrder::Value { desc: false }.apply(input)?,
+            Func::SortByLabel | Func::SortByLabelDesc => {
+                let err =
+                    "Invalid args, expected \"sort_by_label(v instant-vector, label string, ...)\"";
+                if args.len() < 2 {
+                    return Err(DataFusionError::NotImplemented(err.into()));
+                }
+
+                let input = self.call_expr_first_arg(args).await?;
+                let mut labels = vec![];
+                for each_label in args.args[1..].iter() {
+                    match self.exec_expr(each_label).await? {
+                        Value::String(label) => labels.push(label),
+                        _ => return Err(DataFusionError::NotImplemented(err.into())),
+                    }
+                }
+                functions::SortOrder::Labels {
+                    labels,
+                    desc: func_name == Func::SortByLabelDesc,
+                }
+                .apply(input)?
             }
+            Func::SortDesc => functions::SortOrder::Value { desc: true }.apply(input)?,
             Func::Sqrt => functions::sqrt(input)?,
             Func::StddevOverTime => functions::stddev_over_time(input, &self.eval_ctx)?,
             Func::StdvarOverTime => functions::stdvar_over_time(input, &self.eval_ctx)?,
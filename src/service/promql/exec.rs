@@ -26,7 +26,7 @@ use tokio::sync::{RwLock, Semaphore};
 
 use super::Engine;
 use crate::service::promql::{
-    DEFAULT_LOOKBACK, TableProvider, micros, micros_since_epoch,
+    DEFAULT_LOOKBACK, TableProvider, functions, micros, micros_since_epoch,
     selector_visitor::MetricSelectorVisitor,
 };
 
@@ -144,8 +144,15 @@ impl PromqlContext {
             }
         };
 
-        let mut sorted_value = final_value;
-        sorted_value.sort();
+        // keep the ordering of the outermost sort function for instant vectors
+        let sorted_value = match functions::SortOrder::from_expr(&expr) {
+            Some(order) if matches!(final_value, Value::Vector(_)) => order.apply(final_value)?,
+            _ => {
+                let mut sorted_value = final_value;
+                sorted_value.sort();
+                sorted_value
+            }
+        };
         Ok((
             sorted_value,
             final_result_type,
@@ -39,12 +39,18 @@ impl RangeFunc for ChangesFunc {
     }
 
     fn exec(&self, samples: &[Sample], _eval_ts: i64, _range: &Duration) -> Option<f64> {
+        if samples.is_empty() {
+            return None;
+        }
+        // NaN to NaN is not a change
         let changes = samples
             .iter()
             .zip(samples.iter().skip(1))
-            .map(|(current, next)| (!current.value.eq(&next.value) as u32) as f64)
-            .sum();
-        Some(changes)
+            .filter(|(current, next)| {
+                current.value != next.value && !(current.value.is_nan() && next.value.is_nan())
+            })
+            .count();
+        Some(changes as f64)
     }
 }
 
@@ -93,4 +99,41 @@ mod tests {
             _ => panic!("Expected Matrix result"),
         }
     }
+
+    fn samples(values: &[f64]) -> Vec<Sample> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Sample::new(i as i64 * 1000, *v))
+            .collect()
+    }
+
+    #[test]
+    fn test_exec_changes() {
+        let func = ChangesFunc::new();
+        let range = Duration::ZERO;
+
+        // Monotonically increasing - every step is a change
+        let data = samples(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(func.exec(&data, 1000, &range), Some(4.0));
+
+        // Flat series - no change
+        let data = samples(&[5.0, 5.0, 5.0, 5.0]);
+        assert_eq!(func.exec(&data, 1000, &range), Some(0.0));
+
+        // Resetting series - the reset is a change as well
+        let data = samples(&[100.0, 150.0, 150.0, 20.0, 40.0]);
+        assert_eq!(func.exec(&data, 1000, &range), Some(3.0));
+
+        // NaN to NaN is not a change, NaN to a number is
+        let data = samples(&[f64::NAN, f64::NAN, 1.0, f64::NAN]);
+        assert_eq!(func.exec(&data, 1000, &range), Some(2.0));
+
+        // Single sample - no pairs to compare
+        let data = samples(&[42.0]);
+        assert_eq!(func.exec(&data, 1000, &range), Some(0.0));
+
+        // Empty range - no result
+        assert_eq!(func.exec(&[], 1000, &range), None);
+    }
 }
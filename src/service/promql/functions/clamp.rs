@@ -13,12 +13,52 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use config::meta::promql::value::{LabelsExt, RangeValue, Sample, Value};
+use config::meta::promql::value::{InstantValue, LabelsExt, RangeValue, Sample, Value};
 use datafusion::error::{DataFusionError, Result};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 /// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp
 pub(crate) fn clamp(data: Value, min: f64, max: f64) -> Result<Value> {
+    // the result is empty if min > max, a NaN bound makes all the values NaN instead
+    if min > max {
+        match data {
+            Value::Matrix(_) => return Ok(Value::Matrix(vec![])),
+            Value::Vector(_) => return Ok(Value::Vector(vec![])),
+            _ => {}
+        }
+    }
+    exec(data, "clamp", |value| go_max(min, go_min(max, value)))
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp_max
+pub(crate) fn clamp_max(data: Value, max: f64) -> Result<Value> {
+    exec(data, "clamp_max", |value| go_min(max, value))
+}
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#clamp_min
+pub(crate) fn clamp_min(data: Value, min: f64) -> Result<Value> {
+    exec(data, "clamp_min", |value| go_max(min, value))
+}
+
+// Prometheus uses math.Min and math.Max of Go, which return NaN if any argument is NaN, unlike
+// f64::min and f64::max
+fn go_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        a.min(b)
+    }
+}
+
+fn go_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        a.max(b)
+    }
+}
+
+fn exec(data: Value, name: &str, f: impl Fn(f64) -> f64 + Send + Sync) -> Result<Value> {
     match data {
         Value::Matrix(matrix) => {
             let out: Vec<RangeValue> = matrix
@@ -28,10 +68,7 @@ pub(crate) fn clamp(data: Value, min: f64, max: f64) -> Result<Value> {
                     let samples: Vec<Sample> = range_value
                         .samples
                         .into_iter()
-                        .map(|sample| {
-                            let val = sample.value.clamp(min, max);
-                            Sample::new(sample.timestamp, val)
-                        })
+                        .map(|sample| Sample::new(sample.timestamp, f(sample.value)))
                         .collect();
 
                     RangeValue {
@@ -44,9 +81,19 @@ pub(crate) fn clamp(data: Value, min: f64, max: f64) -> Result<Value> {
                 .collect();
             Ok(Value::Matrix(out))
         }
+        Value::Vector(vector) => {
+            let out: Vec<InstantValue> = vector
+                .into_iter()
+                .map(|instant| InstantValue {
+                    labels: instant.labels.without_metric_name(),
+                    sample: Sample::new(instant.sample.timestamp, f(instant.sample.value)),
+                })
+                .collect();
+            Ok(Value::Vector(out))
+        }
         Value::None => Ok(Value::None),
         _ => Err(DataFusionError::Plan(format!(
-            "Invalid input for clamp, expected matrix but got: {:?}",
+            "Invalid input for {name}, expected instant vector but got: {:?}",
             data.get_type()
         ))),
     }
@@ -96,4 +143,78 @@ mod tests {
             _ => panic!("Expected Matrix result"),
         }
     }
+
+    // Helper function to create an instant vector from sample values
+    fn create_vector(eval_ts: i64, values: Vec<f64>) -> Value {
+        let instant_values: Vec<InstantValue> = values
+            .into_iter()
+            .map(|val| InstantValue {
+                labels: Labels::default(),
+                sample: Sample::new(eval_ts, val),
+            })
+            .collect();
+        Value::Vector(instant_values)
+    }
+
+    fn vector_values(value: Value) -> Vec<f64> {
+        match value {
+            Value::Vector(v) => v.iter().map(|s| s.sample.value).collect(),
+            _ => panic!("Expected Vector result"),
+        }
+    }
+
+    #[test]
+    fn test_clamp_instant_vector() {
+        let eval_ts = 1000;
+        // the bounds themselves are kept
+        let vector = create_vector(eval_ts, vec![9.0, 10.0, 15.0, 20.0, 21.0]);
+        let result = clamp(vector, 10.0, 20.0).unwrap();
+        assert_eq!(vector_values(result), vec![10.0, 10.0, 15.0, 20.0, 20.0]);
+
+        // min == max clamps every value to it
+        let vector = create_vector(eval_ts, vec![5.0, 25.0]);
+        let result = clamp(vector, 10.0, 10.0).unwrap();
+        assert_eq!(vector_values(result), vec![10.0, 10.0]);
+
+        let vector = create_vector(eval_ts, vec![5.0, 15.0, 25.0]);
+        let result = clamp_min(vector, 10.0).unwrap();
+        assert_eq!(vector_values(result), vec![10.0, 15.0, 25.0]);
+
+        let vector = create_vector(eval_ts, vec![5.0, 15.0, 25.0]);
+        let result = clamp_max(vector, 10.0).unwrap();
+        assert_eq!(vector_values(result), vec![5.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_clamp_min_greater_than_max() {
+        let eval_ts = 1000;
+        let vector = create_vector(eval_ts, vec![5.0, 15.0, 25.0]);
+        let result = clamp(vector, 20.0, 10.0).unwrap();
+        assert!(vector_values(result).is_empty());
+
+        let matrix = create_matrix(eval_ts, vec![5.0, 15.0, 25.0]);
+        match clamp(matrix, 20.0, 10.0).unwrap() {
+            Value::Matrix(m) => assert!(m.is_empty()),
+            _ => panic!("Expected Matrix result"),
+        }
+    }
+
+    #[test]
+    fn test_clamp_nan() {
+        let eval_ts = 1000;
+        // a NaN sample stays NaN
+        let vector = create_vector(eval_ts, vec![f64::NAN, 15.0]);
+        let values = vector_values(clamp(vector, 10.0, 20.0).unwrap());
+        assert!(values[0].is_nan());
+        assert_eq!(values[1], 15.0);
+
+        // a NaN bound makes every sample NaN instead of panicking
+        let vector = create_vector(eval_ts, vec![5.0, 15.0]);
+        let values = vector_values(clamp(vector, f64::NAN, 20.0).unwrap());
+        assert!(values.iter().all(|v| v.is_nan()));
+
+        let vector = create_vector(eval_ts, vec![5.0, 15.0]);
+        let values = vector_values(clamp_max(vector, f64::NAN).unwrap());
+        assert!(values.iter().all(|v| v.is_nan()));
+    }
 }
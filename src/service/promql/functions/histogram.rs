@@ -38,6 +38,29 @@ impl Bucket {
 
 /// Enhanced version that processes all timestamps at once for range queries
 pub(crate) fn histogram_quantile(phi: f64, data: Value, eval_ctx: &EvalContext) -> Result<Value> {
+    eval_buckets("histogram_quantile", data, eval_ctx, |buckets| {
+        bucket_quantile(phi, buckets)
+    })
+}
+
+/// Estimates the fraction of observations between `lower` and `upper` for all timestamps
+pub(crate) fn histogram_fraction(
+    lower: f64,
+    upper: f64,
+    data: Value,
+    eval_ctx: &EvalContext,
+) -> Result<Value> {
+    eval_buckets("histogram_fraction", data, eval_ctx, |buckets| {
+        bucket_fraction(lower, upper, buckets)
+    })
+}
+
+/// Groups the bucket series by their signature (without bucket label) and computes a value
+/// from the buckets at each timestamp
+fn eval_buckets<F>(fn_name: &str, data: Value, eval_ctx: &EvalContext, f: F) -> Result<Value>
+where
+    F: Fn(Vec<Bucket>) -> f64,
+{
     // Handle input data - convert to matrix format if needed
     let in_matrix = match data {
         Value::Matrix(m) => m,
@@ -45,9 +68,9 @@ pub(crate) fn histogram_quantile(phi: f64, data: Value, eval_ctx: &EvalContext)
             return Ok(Value::None);
         }
         _ => {
-            return Err(DataFusionError::Plan(
-                "histogram_quantile: vector or matrix argument expected".to_owned(),
-            ));
+            return Err(DataFusionError::Plan(format!(
+                "{fn_name}: vector or matrix argument expected"
+            )));
         }
     };
 
@@ -77,7 +100,7 @@ pub(crate) fn histogram_quantile(phi: f64, data: Value, eval_ctx: &EvalContext)
 
         let mut samples = Vec::with_capacity(timestamps.len());
 
-        // For each timestamp, compute histogram_quantile
+        // For each timestamp, compute the value from the buckets
         for &eval_ts in &timestamps {
             let mut buckets = Vec::new();
 
@@ -100,8 +123,7 @@ pub(crate) fn histogram_quantile(phi: f64, data: Value, eval_ctx: &EvalContext)
             }
 
             if !buckets.is_empty() {
-                let quantile_value = bucket_quantile(phi, buckets);
-                samples.push(Sample::new(eval_ts, quantile_value));
+                samples.push(Sample::new(eval_ts, f(buckets)));
             }
         }
 
@@ -173,6 +195,79 @@ fn bucket_quantile(phi: f64, mut buckets: Vec<Bucket>) -> f64 {
     bucket_start + (bucket_end - bucket_start) * (rank / count)
 }
 
+// cf. https://github.com/prometheus/prometheus/blob/v3.5.0/promql/quantile.go#L465
+fn bucket_fraction(lower: f64, upper: f64, mut buckets: Vec<Bucket>) -> f64 {
+    if lower.is_nan() || upper.is_nan() || buckets.is_empty() {
+        return f64::NAN;
+    }
+    buckets.sort_by(|a, b| sort_float(&a.upper_bound, &b.upper_bound));
+    let highest_bucket = &buckets[buckets.len() - 1];
+    if !(highest_bucket.upper_bound.is_infinite() && highest_bucket.upper_bound.is_sign_positive())
+    {
+        return f64::NAN;
+    }
+    let mut buckets = coalesce_buckets(buckets);
+    ensure_monotonic(&mut buckets);
+    let count = buckets[buckets.len() - 1].count;
+    if count == 0.0 {
+        return f64::NAN;
+    }
+    if lower >= upper {
+        return 0.0;
+    }
+
+    let mut rank = 0.0;
+    let mut lower_rank = None;
+    let mut upper_rank = None;
+    for (i, b) in buckets.iter().enumerate() {
+        // same as bucket_quantile, the first bucket starts at 0 if its upper bound is positive
+        let lower_bound = if i > 0 {
+            buckets[i - 1].upper_bound
+        } else if b.upper_bound > 0.0 {
+            0.0
+        } else {
+            f64::NEG_INFINITY
+        };
+        let upper_bound = b.upper_bound;
+        let interpolate_linearly = |v: f64| {
+            if lower_bound.is_infinite() {
+                // all observations are assumed to be at the upper bound
+                rank
+            } else if upper_bound.is_infinite() {
+                // all observations are assumed to be at the lower bound
+                b.count
+            } else {
+                rank + (b.count - rank) * (v - lower_bound) / (upper_bound - lower_bound)
+            }
+        };
+
+        // the value is at the lower boundary of the bucket
+        if lower_rank.is_none() && lower_bound >= lower {
+            lower_rank = Some(rank);
+        }
+        if upper_rank.is_none() && lower_bound >= upper {
+            upper_rank = Some(rank);
+        }
+        if lower_rank.is_some() && upper_rank.is_some() {
+            break;
+        }
+        // the value is inside the bucket
+        if lower_rank.is_none() && lower < upper_bound {
+            lower_rank = Some(interpolate_linearly(lower));
+        }
+        if upper_rank.is_none() && upper < upper_bound {
+            upper_rank = Some(interpolate_linearly(upper));
+        }
+        if lower_rank.is_some() && upper_rank.is_some() {
+            break;
+        }
+        rank = b.count;
+    }
+    let lower_rank = lower_rank.unwrap_or(count).min(count);
+    let upper_rank = upper_rank.unwrap_or(count).min(count);
+    ((upper_rank - lower_rank) / count).clamp(0.0, 1.0)
+}
+
 /// `coalesce_buckets` merges buckets with the same upper bound.
 /// The input buckets must be sorted.
 fn coalesce_buckets(buckets: Vec<Bucket>) -> Vec<Bucket> {
@@ -309,6 +404,57 @@ mod tests {
         assert_eq!(result, expected_result);
     }
 
+    fn fraction_buckets() -> Vec<Bucket> {
+        vec![
+            Bucket::new(1.0, 10.0),
+            Bucket::new(2.0, 20.0),
+            Bucket::new(4.0, 40.0),
+            Bucket::new(f64::INFINITY, 40.0),
+        ]
+    }
+
+    #[test]
+    fn test_bucket_fraction() {
+        let inf = f64::INFINITY;
+        let cases = [
+            // bucket boundaries
+            (0.0, 1.0, 0.25),
+            (1.0, 2.0, 0.25),
+            (0.0, 4.0, 1.0),
+            // interpolate within buckets
+            (1.5, 3.0, 0.375),
+            (0.0, 0.5, 0.125),
+            // the +Inf bucket has no observations
+            (3.0, 10.0, 0.25),
+            (5.0, inf, 0.0),
+            // open-ended ranges
+            (-inf, inf, 1.0),
+            (-inf, 2.0, 0.5),
+            (2.0, inf, 0.5),
+            (-1.0, 1.0, 0.25),
+            // empty ranges
+            (2.0, 2.0, 0.0),
+            (3.0, 1.0, 0.0),
+        ];
+        for (lower, upper, expected) in cases {
+            assert_eq!(
+                bucket_fraction(lower, upper, fraction_buckets()),
+                expected,
+                "lower: {lower}, upper: {upper}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bucket_fraction_invalid() {
+        assert!(bucket_fraction(f64::NAN, 1.0, fraction_buckets()).is_nan());
+        assert!(bucket_fraction(0.0, 1.0, vec![]).is_nan());
+        // no +Inf bucket
+        assert!(bucket_fraction(0.0, 1.0, vec![Bucket::new(1.0, 10.0)]).is_nan());
+        // no observations
+        assert!(bucket_fraction(0.0, 1.0, vec![Bucket::new(f64::INFINITY, 0.0)]).is_nan());
+    }
+
     #[test]
     fn test_ensure_monotonic() {
         let mut buckets = vec![
@@ -16,17 +16,30 @@
 use std::time::Duration;
 
 use config::meta::promql::value::{EvalContext, Sample, Value};
-use datafusion::error::Result;
+use datafusion::error::{DataFusionError, Result};
 
 use crate::service::promql::{common::calculate_trend, functions::RangeFunc};
 
-/// https://prometheus.io/docs/prometheus/latest/querying/functions/#holt_winters
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#double_exponential_smoothing
+///
+/// Also known as `holt_winters`, both factors must be in (0, 1) and the series with less than
+/// two samples are dropped.
 pub(crate) fn holt_winters(
     data: Value,
     scaling_factor: f64,
     trend_factor: f64,
     eval_ctx: &EvalContext,
 ) -> Result<Value> {
+    if scaling_factor <= 0.0 || scaling_factor >= 1.0 {
+        return Err(DataFusionError::Plan(format!(
+            "invalid smoothing factor. Expected: 0 < sf < 1, got: {scaling_factor}"
+        )));
+    }
+    if trend_factor <= 0.0 || trend_factor >= 1.0 {
+        return Err(DataFusionError::Plan(format!(
+            "invalid trend factor. Expected: 0 < tf < 1, got: {trend_factor}"
+        )));
+    }
     super::eval_range(
         data,
         HoltWintersFunc::new(scaling_factor, trend_factor),
@@ -140,4 +153,42 @@ mod tests {
             _ => panic!("Expected Matrix result"),
         }
     }
+
+    #[test]
+    fn test_holt_winters_trending_series() {
+        let series = |values: &[f64]| RangeValue {
+            labels: Labels::default(),
+            samples: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| Sample::new(1000 * (i as i64 + 1), *v))
+                .collect(),
+            exemplars: None,
+            time_window: Some(TimeWindow {
+                range: Duration::from_secs(5),
+                offset: Duration::ZERO,
+            }),
+        };
+        let eval_ctx = EvalContext::new(4000, 4000, 0, "test".to_string());
+        let data = Value::Matrix(vec![series(&[10.0, 20.0, 25.0, 40.0]), series(&[10.0])]);
+
+        // s = 10, b = 10
+        // 20: b = 10, s = 0.5 * 20 + 0.5 * (10 + 10) = 20
+        // 25: b = 0.3 * (20 - 10) + 0.7 * 10 = 10, s = 0.5 * 25 + 0.5 * (20 + 10) = 27.5
+        // 40: b = 0.3 * (27.5 - 20) + 0.7 * 10 = 9.25, s = 0.5 * 40 + 0.5 * (27.5 + 9.25)
+        match holt_winters(data, 0.5, 0.3, &eval_ctx).unwrap() {
+            Value::Matrix(m) => {
+                // the series with one sample is dropped
+                assert_eq!(m.len(), 1);
+                assert_eq!(m[0].samples.len(), 1);
+                assert!((m[0].samples[0].value - 38.375).abs() < 1e-9);
+            }
+            _ => panic!("Expected Matrix result"),
+        }
+
+        for (sf, tf) in [(0.0, 0.5), (1.0, 0.5), (0.5, 0.0), (0.5, 1.5)] {
+            let data = Value::Matrix(vec![series(&[10.0, 20.0])]);
+            assert!(holt_winters(data, sf, tf, &eval_ctx).is_err());
+        }
+    }
 }
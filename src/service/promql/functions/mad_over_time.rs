@@ -0,0 +1,99 @@
+// Copyright 2025 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use config::meta::promql::value::{EvalContext, Sample, Value};
+use datafusion::error::Result;
+
+use crate::service::promql::{common::quantile, functions::RangeFunc};
+
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#mad_over_time
+pub(crate) fn mad_over_time(data: Value, eval_ctx: &EvalContext) -> Result<Value> {
+    super::eval_range(data, MadOverTimeFunc::new(), eval_ctx)
+}
+
+pub struct MadOverTimeFunc;
+
+impl MadOverTimeFunc {
+    pub fn new() -> Self {
+        MadOverTimeFunc {}
+    }
+}
+
+impl RangeFunc for MadOverTimeFunc {
+    fn name(&self) -> &'static str {
+        "mad_over_time"
+    }
+
+    /// The median of the absolute deviations from the median of the samples
+    fn exec(&self, samples: &[Sample], _eval_ts: i64, _range: &Duration) -> Option<f64> {
+        if samples.is_empty() {
+            return None;
+        }
+        let values: Vec<f64> = samples.iter().map(|s| s.value).collect();
+        let median = quantile(&values, 0.5)?;
+        let deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+        quantile(&deviations, 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::meta::promql::value::{Labels, RangeValue, TimeWindow};
+
+    use super::*;
+
+    // Test helper
+    fn mad_over_time_test_helper(data: Value) -> Result<Value> {
+        let eval_ctx = EvalContext::new(7000, 7000, 0, "test".to_string());
+        mad_over_time(data, &eval_ctx)
+    }
+
+    #[test]
+    fn test_mad_over_time_function() {
+        let samples = [1.0, 1.0, 2.0, 2.0, 4.0, 6.0, 9.0]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Sample::new((i as i64 + 1) * 1000, *v))
+            .collect();
+
+        let range_value = RangeValue {
+            labels: Labels::default(),
+            samples,
+            exemplars: None,
+            time_window: Some(TimeWindow {
+                range: Duration::from_secs(10),
+                offset: Duration::ZERO,
+            }),
+        };
+
+        let matrix = Value::Matrix(vec![range_value]);
+        let result = mad_over_time_test_helper(matrix).unwrap();
+
+        // median is 2, the deviations are 1,1,0,0,2,4,7 and their median is 1
+        match result {
+            Value::Matrix(m) => {
+                assert_eq!(m.len(), 1);
+                assert_eq!(m[0].samples.len(), 1);
+                assert!((m[0].samples[0].value - 1.0).abs() < 0.001);
+                assert_eq!(m[0].samples[0].timestamp, 7000);
+            }
+            _ => panic!("Expected Matrix result"),
+        }
+
+        assert_eq!(MadOverTimeFunc::new().exec(&[], 0, &Duration::ZERO), None);
+    }
+}
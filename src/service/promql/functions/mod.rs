@@ -39,6 +39,7 @@ mod irate;
 mod label_join;
 mod label_replace;
 mod last_over_time;
+mod mad_over_time;
 mod math_operations;
 mod max_over_time;
 mod min_over_time;
@@ -47,6 +48,7 @@ mod quantile_over_time;
 mod rate;
 mod resets;
 mod scalar;
+mod sort;
 mod stddev_over_time;
 mod stdvar_over_time;
 mod sum_over_time;
@@ -57,11 +59,11 @@ pub(crate) use absent::absent;
 pub(crate) use absent_over_time::absent_over_time;
 pub(crate) use avg_over_time::avg_over_time;
 pub(crate) use changes::changes;
-pub(crate) use clamp::clamp;
+pub(crate) use clamp::{clamp, clamp_max, clamp_min};
 pub(crate) use count_over_time::count_over_time;
 pub(crate) use delta::delta;
 pub(crate) use deriv::deriv;
-pub(crate) use histogram::histogram_quantile;
+pub(crate) use histogram::{histogram_fraction, histogram_quantile};
 pub(crate) use holt_winters::holt_winters;
 pub(crate) use idelta::idelta;
 pub(crate) use increase::increase;
@@ -69,6 +71,7 @@ pub(crate) use irate::irate;
 pub(crate) use label_join::label_join;
 pub(crate) use label_replace::label_replace;
 pub(crate) use last_over_time::last_over_time;
+pub(crate) use mad_over_time::mad_over_time;
 pub(crate) use math_operations::*;
 pub(crate) use max_over_time::max_over_time;
 pub(crate) use min_over_time::min_over_time;
@@ -77,6 +80,7 @@ pub(crate) use quantile_over_time::quantile_over_time;
 pub(crate) use rate::rate;
 pub(crate) use resets::resets;
 pub(crate) use scalar::scalar;
+pub(crate) use sort::SortOrder;
 pub(crate) use stddev_over_time::stddev_over_time;
 pub(crate) use stdvar_over_time::stdvar_over_time;
 pub(crate) use sum_over_time::sum_over_time;
@@ -109,6 +113,7 @@ pub(crate) enum Func {
     HistogramFraction,
     HistogramQuantile,
     HistogramSum,
+    #[strum(serialize = "holt_winters", serialize = "double_exponential_smoothing")]
     HoltWinters,
     Hour,
     Idelta,
@@ -120,6 +125,7 @@ pub(crate) enum Func {
     Ln,
     Log10,
     Log2,
+    MadOverTime,
     MaxOverTime,
     MinOverTime,
     Minute,
@@ -132,6 +138,8 @@ pub(crate) enum Func {
     Scalar,
     Sgn,
     Sort,
+    SortByLabel,
+    SortByLabelDesc,
     SortDesc,
     Sqrt,
     StddevOverTime,
@@ -41,6 +41,9 @@ impl RangeFunc for PredictLinearFunc {
     }
 
     fn exec(&self, samples: &[Sample], eval_ts: i64, _range: &Duration) -> Option<f64> {
+        if samples.len() < 2 {
+            return None;
+        }
         let (slope, intercept) = linear_regression(samples, eval_ts / 1000)?;
         Some(slope * self.duration + intercept)
     }
@@ -91,4 +94,36 @@ mod tests {
             _ => panic!("Expected Matrix result"),
         }
     }
+
+    #[test]
+    fn test_predict_linear_known_value() {
+        // one sample per minute growing by 1 per second
+        let samples = (0..4)
+            .map(|i| Sample::new(i * 60_000_000, 100.0 + i as f64 * 60.0))
+            .collect::<Vec<_>>();
+        let range_value = |samples: Vec<Sample>| RangeValue {
+            labels: Labels::default(),
+            samples,
+            exemplars: None,
+            time_window: Some(TimeWindow {
+                range: Duration::from_secs(300),
+                offset: Duration::ZERO,
+            }),
+        };
+        let eval_ctx = EvalContext::new(180_000_000, 180_000_000, 0, "test".to_string());
+
+        let matrix = Value::Matrix(vec![range_value(samples.clone())]);
+        let result = predict_linear(matrix, 600.0, &eval_ctx).unwrap();
+        let Value::Matrix(m) = result else {
+            panic!("Expected Matrix result");
+        };
+        assert_eq!(m.len(), 1);
+        assert_eq!(m[0].samples[0].timestamp, 180_000_000);
+        assert!((m[0].samples[0].value - 880.0).abs() < 1e-9);
+
+        // a single sample can't be extrapolated, the series is dropped
+        let matrix = Value::Matrix(vec![range_value(samples[3..].to_vec())]);
+        let result = predict_linear(matrix, 600.0, &eval_ctx).unwrap();
+        assert!(matches!(result, Value::Matrix(m) if m.is_empty()));
+    }
 }
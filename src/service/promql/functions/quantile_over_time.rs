@@ -44,8 +44,78 @@ impl RangeFunc for QuantileOverTimeFunc {
         "quantile_over_time"
     }
 
+    /// The phi-quantile of the samples, linearly interpolated between the closest ranks. A phi
+    /// below 0 returns -Inf and above 1 returns +Inf, as Prometheus does, an empty range drops
+    /// the series whatever phi is.
     fn exec(&self, samples: &[Sample], _eval_ts: i64, _range: &Duration) -> Option<f64> {
+        if samples.is_empty() {
+            return None;
+        }
         let input: Vec<f64> = samples.iter().map(|x| x.value).collect();
         quantile(&input, self.phi_quantile)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use config::meta::promql::value::{Labels, RangeValue, TimeWindow};
+
+    use super::*;
+
+    fn samples(values: &[f64]) -> Vec<Sample> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| Sample::new((i as i64 + 1) * 1000, *v))
+            .collect()
+    }
+
+    #[test]
+    fn test_quantile_over_time_function() {
+        let range_value = RangeValue {
+            labels: Labels::default(),
+            samples: samples(&[4.0, 1.0, 3.0, 2.0]),
+            exemplars: None,
+            time_window: Some(TimeWindow {
+                range: Duration::from_secs(10),
+                offset: Duration::ZERO,
+            }),
+        };
+        let eval_ctx = EvalContext::new(5000, 5000, 0, "test".to_string());
+        let result = quantile_over_time(0.5, Value::Matrix(vec![range_value]), &eval_ctx).unwrap();
+
+        // the samples are sorted to 1,2,3,4 and the median is between 2 and 3
+        match result {
+            Value::Matrix(m) => {
+                assert_eq!(m.len(), 1);
+                assert_eq!(m[0].samples.len(), 1);
+                assert!((m[0].samples[0].value - 2.5).abs() < 0.001);
+                assert_eq!(m[0].samples[0].timestamp, 5000);
+            }
+            _ => panic!("Expected Matrix result"),
+        }
+    }
+
+    #[test]
+    fn test_quantile_over_time_phi() {
+        let data = samples(&[10.0, 40.0, 20.0, 30.0]);
+        let exec = |phi| QuantileOverTimeFunc::new(phi).exec(&data, 0, &Duration::ZERO);
+
+        assert_eq!(exec(0.0), Some(10.0));
+        assert_eq!(exec(1.0), Some(40.0));
+        assert_eq!(exec(0.5), Some(25.0));
+        // 0.9 * 3 = 2.7, between 30 and 40
+        assert!((exec(0.9).unwrap() - 37.0).abs() < 0.001);
+
+        // out of range phi
+        assert_eq!(exec(-0.5), Some(f64::NEG_INFINITY));
+        assert_eq!(exec(1.5), Some(f64::INFINITY));
+        assert!(exec(f64::NAN).unwrap().is_nan());
+
+        // empty ranges drop the series
+        assert_eq!(
+            QuantileOverTimeFunc::new(1.5).exec(&[], 0, &Duration::ZERO),
+            None
+        );
+    }
+}
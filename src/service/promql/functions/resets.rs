@@ -39,6 +39,9 @@ impl RangeFunc for ResetsFunc {
     }
 
     fn exec(&self, samples: &[Sample], _eval_ts: i64, _range: &Duration) -> Option<f64> {
+        if samples.is_empty() {
+            return None;
+        }
         let resets = samples
             .iter()
             .zip(samples.iter().skip(1))
@@ -136,9 +139,9 @@ mod tests {
         let data = create_range_value(vec![2.0, 1.0]);
         assert_eq!(func.exec(&data.samples, 1000, &Duration::ZERO), Some(1.0));
 
-        // Empty samples (should not happen in practice, but test for safety)
+        // Empty samples - no result
         let data = create_range_value(vec![]);
-        assert_eq!(func.exec(&data.samples, 1000, &Duration::ZERO), Some(0.0));
+        assert_eq!(func.exec(&data.samples, 1000, &Duration::ZERO), None);
     }
 
     #[test]
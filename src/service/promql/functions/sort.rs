@@ -0,0 +1,200 @@
+// Copyright 2025 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+
+use config::meta::promql::value::{Labels, LabelsExt, Value};
+use datafusion::error::{DataFusionError, Result};
+use promql_parser::parser::Expr;
+
+/// The ordering of `sort`, `sort_desc`, `sort_by_label` and `sort_by_label_desc`.
+///
+/// https://prometheus.io/docs/prometheus/latest/querying/functions/#sort
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SortOrder {
+    Value { desc: bool },
+    Labels { labels: Vec<String>, desc: bool },
+}
+
+impl SortOrder {
+    /// Returns the ordering of the outermost sort function of the expression. The final
+    /// result of the query needs to keep this ordering instead of the default one.
+    pub(crate) fn from_expr(expr: &Expr) -> Option<Self> {
+        match expr {
+            Expr::Paren(paren) => Self::from_expr(&paren.expr),
+            Expr::Call(call) => match call.func.name {
+                "sort" => Some(SortOrder::Value { desc: false }),
+                "sort_desc" => Some(SortOrder::Value { desc: true }),
+                "sort_by_label" | "sort_by_label_desc" => {
+                    let labels = call
+                        .args
+                        .args
+                        .iter()
+                        .skip(1)
+                        .filter_map(|arg| match arg.as_ref() {
+                            Expr::StringLiteral(s) => Some(s.val.clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    Some(SortOrder::Labels {
+                        labels,
+                        desc: call.func.name == "sort_by_label_desc",
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Sorts the samples of the instant vector, the samples with the same sort key are
+    /// ordered by their labels to keep the result stable.
+    pub(crate) fn apply(&self, data: Value) -> Result<Value> {
+        match data {
+            Value::Vector(mut vector) => {
+                vector.sort_by(|a, b| {
+                    self.compare(&a.labels, a.sample.value, &b.labels, b.sample.value)
+                });
+                Ok(Value::Vector(vector))
+            }
+            // instant queries are evaluated as a matrix with one sample per series
+            Value::Matrix(mut matrix) => {
+                matrix.sort_by(|a, b| {
+                    let a_value = a.samples.last().map(|s| s.value).unwrap_or(f64::NAN);
+                    let b_value = b.samples.last().map(|s| s.value).unwrap_or(f64::NAN);
+                    self.compare(&a.labels, a_value, &b.labels, b_value)
+                });
+                Ok(Value::Matrix(matrix))
+            }
+            Value::None => Ok(Value::None),
+            _ => Err(DataFusionError::Plan(
+                "sort: instant vector argument expected".into(),
+            )),
+        }
+    }
+
+    fn compare(
+        &self,
+        a_labels: &Labels,
+        a_value: f64,
+        b_labels: &Labels,
+        b_value: f64,
+    ) -> Ordering {
+        let by_labels = || a_labels.partial_cmp(b_labels).unwrap_or(Ordering::Equal);
+        match self {
+            SortOrder::Value { desc } => {
+                // NaN is always at the end
+                let ord = match (a_value.is_nan(), b_value.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    _ if *desc => b_value.partial_cmp(&a_value).unwrap_or(Ordering::Equal),
+                    _ => a_value.partial_cmp(&b_value).unwrap_or(Ordering::Equal),
+                };
+                ord.then_with(by_labels)
+            }
+            SortOrder::Labels { labels, desc } => {
+                let ord = labels
+                    .iter()
+                    .map(|name| a_labels.get_value(name).cmp(&b_labels.get_value(name)))
+                    .find(|ord| ord.is_ne())
+                    .unwrap_or_else(by_labels);
+                if *desc { ord.reverse() } else { ord }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use config::meta::promql::value::{InstantValue, Label, Sample};
+    use promql_parser::parser;
+
+    use super::*;
+
+    fn instant(host: &str, value: f64) -> InstantValue {
+        InstantValue {
+            labels: vec![Arc::new(Label {
+                name: "host".to_string(),
+                value: host.to_string(),
+            })],
+            sample: Sample::new(1000, value),
+        }
+    }
+
+    fn hosts(value: Value) -> Vec<String> {
+        match value {
+            Value::Vector(v) => v.iter().map(|s| s.labels.get_value("host")).collect(),
+            _ => panic!("vector expected"),
+        }
+    }
+
+    fn data() -> Value {
+        Value::Vector(vec![
+            instant("c", 2.0),
+            instant("a", 1.0),
+            instant("d", f64::NAN),
+            instant("b", 2.0),
+            instant("e", 3.0),
+        ])
+    }
+
+    #[test]
+    fn test_sort_by_value() {
+        let ret = SortOrder::Value { desc: false }.apply(data()).unwrap();
+        assert_eq!(hosts(ret), vec!["a", "b", "c", "e", "d"]);
+
+        let ret = SortOrder::Value { desc: true }.apply(data()).unwrap();
+        assert_eq!(hosts(ret), vec!["e", "b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn test_sort_by_label() {
+        let labels = vec!["host".to_string()];
+        let ret = SortOrder::Labels {
+            labels: labels.clone(),
+            desc: false,
+        }
+        .apply(data())
+        .unwrap();
+        assert_eq!(hosts(ret), vec!["a", "b", "c", "d", "e"]);
+
+        let ret = SortOrder::Labels { labels, desc: true }
+            .apply(data())
+            .unwrap();
+        assert_eq!(hosts(ret), vec!["e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_sort_order_from_expr() {
+        let expr = parser::parse("(sort_desc(up))").unwrap();
+        assert_eq!(
+            SortOrder::from_expr(&expr),
+            Some(SortOrder::Value { desc: true })
+        );
+        let expr = parser::parse(r#"sort_by_label(up, "job", "instance")"#).unwrap();
+        assert_eq!(
+            SortOrder::from_expr(&expr),
+            Some(SortOrder::Labels {
+                labels: vec!["job".to_string(), "instance".to_string()],
+                desc: false
+            })
+        );
+        let expr = parser::parse("sum(sort(up))").unwrap();
+        assert_eq!(SortOrder::from_expr(&expr), None);
+    }
+}
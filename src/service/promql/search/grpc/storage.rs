@@ -164,6 +164,7 @@ pub(crate) async fn create_context(
             .collect_vec(),
         &mut scan_stats,
         "parquet",
+        Some(stream_type),
     )
     .instrument(enter_span.clone())
     .await;
@@ -176,6 +177,8 @@ pub(crate) async fn create_context(
         .with_label_values(&[org_id, &stream_type.to_string(), "parquet"])
         .inc_by(cache_misses);
 
+    scan_stats.parquet_cache_hits = cache_hits as i64;
+    scan_stats.parquet_cache_misses = cache_misses as i64;
     scan_stats.querier_files = scan_stats.files;
     let cached_ratio = (scan_stats.querier_memory_cached_files
         + scan_stats.querier_disk_cached_files) as f64
@@ -220,6 +223,11 @@ pub(crate) async fn create_context(
         time_range,
         work_group: None,
         use_inverted_index: true,
+        disable_index_skip_threshold: false,
+        explain: false,
+        explain_index: false,
+        strict_index_only: false,
+        referenced_fields: None,
     });
 
     // search tantivy index
@@ -228,7 +236,8 @@ pub(crate) async fn create_context(
     let (index_condition, is_full_convert) =
         convert_matchers_to_index_condition(&matchers, &schema, &index_fields)?;
     if !index_condition.conditions.is_empty() && cfg.common.inverted_index_enabled {
-        (idx_took, is_add_filter_back,..) =
+        let (filter_back, idx_scan_stats);
+        (idx_took, filter_back, _, idx_scan_stats, _) =
             tantivy_search(query.clone(), &mut files, Some(index_condition), None)
                 .await
                 .map_err(|e| {
@@ -237,9 +246,13 @@ pub(crate) async fn create_context(
                     );
                     DataFusionError::Execution(e.to_string())
                 })?;
+        // all the files are in one table, a single file needing the filter adds it to all
+        is_add_filter_back = filter_back.is_any();
         log::info!(
             "[trace_id {trace_id}] promql->search->storage: filter file list by tantivy index took: {idx_took} ms, is_add_filter_back: {is_add_filter_back}, is_full_convert: {is_full_convert}",
         );
+        scan_stats.index_cache_hits = idx_scan_stats.index_cache_hits;
+        scan_stats.index_cache_misses = idx_scan_stats.index_cache_misses;
     }
     scan_stats.idx_took = idx_took as i64;
 
@@ -189,6 +189,8 @@ async fn get_wal_batches(
             is_analyze: false,     // not needed for wal
             sampling_config: None, // not needed for wal
             clear_cache: false,    // not needed for wal
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         },
         index_info: IndexInfo::default(), // not needed for wal
         super_cluster_info: cluster_rpc::SuperClusterInfo::default(), // current not needed for wal
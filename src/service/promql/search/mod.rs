@@ -51,7 +51,7 @@ use {
 use crate::service::{
     promql::{
         DEFAULT_LOOKBACK, DEFAULT_MAX_POINTS_PER_SERIES, MetricsQueryRequest, adjust_start_end,
-        micros,
+        functions, micros,
     },
     search::server_internal_error,
     self_reporting::report_request_usage_stats,
@@ -446,7 +446,17 @@ async fn search_in_cluster(
     let values = if result_type == "matrix" {
         merge_matrix_query(&series_data, &req.org_id).await?
     } else if result_type == "vector" {
-        merge_vector_query(&series_data, &req.org_id).await?
+        let values = merge_vector_query(&series_data, &req.org_id).await?;
+        // the merge loses the ordering of the outermost sort function, apply it again
+        match promql_parser::parser::parse(query)
+            .ok()
+            .and_then(|expr| functions::SortOrder::from_expr(&expr))
+        {
+            Some(order) => order
+                .apply(values)
+                .map_err(|e| server_internal_error(e.to_string()))?,
+            None => values,
+        }
     } else if result_type == "scalar" {
         merge_scalar_query(&series_data)
     } else if result_type == "exemplars" {
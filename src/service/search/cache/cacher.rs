@@ -1252,6 +1252,8 @@ mod tests {
             use_cache: true,
             clear_cache: false,
             local_mode: None,
+            disable_index_skip_threshold: false,
+            strict_index_only: false,
         };
         let mut origin_sql = req.query.sql.clone();
         let file_path = "test_org/logs/test_stream".to_string();
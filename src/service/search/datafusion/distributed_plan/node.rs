@@ -81,6 +81,8 @@ impl RemoteScanNodes {
             is_analyze: false, // set in distribute Analyze
             sampling_config: self.sampling_config.clone(),
             clear_cache: self.req.overwrite_cache,
+            disable_index_skip_threshold: self.req.disable_index_skip_threshold,
+            strict_index_only: self.req.strict_index_only,
         };
 
         let index_info = IndexInfo {
@@ -180,6 +182,8 @@ pub struct SearchInfos {
     pub is_analyze: bool,
     pub sampling_config: Option<proto::cluster_rpc::SamplingConfig>,
     pub clear_cache: bool,
+    pub disable_index_skip_threshold: bool,
+    pub strict_index_only: bool,
 }
 
 impl SearchInfos {
@@ -200,6 +204,8 @@ impl SearchInfos {
             is_analyze: self.is_analyze,
             sampling_config: self.sampling_config.clone(),
             clear_cache: self.clear_cache,
+            disable_index_skip_threshold: self.disable_index_skip_threshold,
+            strict_index_only: self.strict_index_only,
         }
     }
 }
@@ -47,8 +47,8 @@ use crate::service::search::{
             get_column_name, is_column, is_only_timestamp_filter, is_value,
         },
         udf::{
-            MATCH_FIELD_IGNORE_CASE_UDF_NAME, MATCH_FIELD_UDF_NAME, STR_MATCH_UDF_IGNORE_CASE_NAME,
-            STR_MATCH_UDF_NAME,
+            MATCH_FIELD_IGNORE_CASE_UDF_NAME, MATCH_FIELD_UDF_NAME, MATCH_PHRASE_UDF_NAME,
+            STR_MATCH_UDF_IGNORE_CASE_NAME, STR_MATCH_UDF_NAME,
             match_all_udf::{FUZZY_MATCH_ALL_UDF_NAME, MATCH_ALL_UDF_NAME},
         },
     },
@@ -58,6 +58,7 @@ use crate::service::search::{
 #[derive(Default, Debug)]
 pub struct IndexRule {
     index_fields: HashSet<String>,
+    fts_fields: HashSet<String>,
     index_condition: Arc<Mutex<Option<IndexCondition>>>,
     // this set to true when all filter can be extract to
     // index condition(except _timestamp filter)
@@ -67,10 +68,12 @@ pub struct IndexRule {
 impl IndexRule {
     pub fn new(
         index_fields: HashSet<String>,
+        fts_fields: HashSet<String>,
         index_condition: Arc<Mutex<Option<IndexCondition>>>,
     ) -> Self {
         Self {
             index_fields,
+            fts_fields,
             index_condition,
             can_optimize: Arc::new(AtomicBool::new(false)),
         }
@@ -91,8 +94,11 @@ impl PhysicalOptimizerRule for IndexRule {
             return Ok(plan);
         }
 
-        let mut rewriter =
-            IndexOptimizer::new(self.index_fields.clone(), self.index_condition.clone());
+        let mut rewriter = IndexOptimizer::new(
+            self.index_fields.clone(),
+            self.fts_fields.clone(),
+            self.index_condition.clone(),
+        );
         let plan = plan.rewrite(&mut rewriter).data()?;
 
         // if all filter can be used in index, we can
@@ -121,6 +127,7 @@ impl PhysicalOptimizerRule for IndexRule {
 
 struct IndexOptimizer {
     index_fields: HashSet<String>,
+    fts_fields: HashSet<String>,
     index_condition: Arc<Mutex<Option<IndexCondition>>>,
     // set to true when the filter only have _timestamp filter
     can_optimize: bool,
@@ -131,10 +138,12 @@ struct IndexOptimizer {
 impl IndexOptimizer {
     pub fn new(
         index_fields: HashSet<String>,
+        fts_fields: HashSet<String>,
         index_condition: Arc<Mutex<Option<IndexCondition>>>,
     ) -> Self {
         Self {
             index_fields,
+            fts_fields,
             index_condition,
             can_optimize: false,
             is_remove_filter: config::get_config()
@@ -149,12 +158,14 @@ impl IndexOptimizer {
     #[cfg(test)]
     fn new_with_config(
         index_fields: HashSet<String>,
+        fts_fields: HashSet<String>,
         index_condition: Arc<Mutex<Option<IndexCondition>>>,
         is_remove_filter: bool,
         optimizer_enabled: bool,
     ) -> Self {
         Self {
             index_fields,
+            fts_fields,
             index_condition,
             can_optimize: false,
             is_remove_filter,
@@ -174,6 +185,12 @@ impl TreeNodeRewriter for IndexOptimizer {
                 if is_expr_valid_for_index(expr, &self.index_fields) {
                     let condition = Condition::from_physical_expr(expr);
                     index_conditions.add_condition(condition);
+                } else if is_fts_match_phrase(expr, &self.fts_fields) {
+                    // the index only prunes the files by the terms of the phrase, datafusion
+                    // still needs to filter the phrase
+                    let condition = Condition::from_physical_expr(expr);
+                    index_conditions.add_condition(condition);
+                    other_conditions.push(expr.clone());
                 } else {
                     other_conditions.push(expr.clone());
                 }
@@ -239,7 +256,10 @@ fn construct_filter_exec(
 }
 
 // Check if the expression is valid for the index.
-fn is_expr_valid_for_index(expr: &Arc<dyn PhysicalExpr>, index_fields: &HashSet<String>) -> bool {
+pub(crate) fn is_expr_valid_for_index(
+    expr: &Arc<dyn PhysicalExpr>,
+    index_fields: &HashSet<String>,
+) -> bool {
     if let Some(expr) = expr.as_any().downcast_ref::<BinaryExpr>() {
         match expr.op() {
             Operator::Eq | Operator::NotEq => {
@@ -292,6 +312,18 @@ fn is_expr_valid_for_index(expr: &Arc<dyn PhysicalExpr>, index_fields: &HashSet<
     true
 }
 
+// Check if the expression is a match_phrase() of a full text search field, the secondary index
+// fields are indexed as a whole value so a phrase can't be searched in them.
+fn is_fts_match_phrase(expr: &Arc<dyn PhysicalExpr>, fts_fields: &HashSet<String>) -> bool {
+    expr.as_any()
+        .downcast_ref::<ScalarFunctionExpr>()
+        .is_some_and(|expr| {
+            expr.name() == MATCH_PHRASE_UDF_NAME
+                && expr.args().len() == 3
+                && fts_fields.contains(get_column_name(&expr.args()[0]))
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::{collections::HashSet, sync::Arc};
@@ -305,6 +337,7 @@ mod tests {
             PhysicalExpr,
             expressions::{BinaryExpr, Column, Literal},
         },
+        physical_plan::displayable,
         prelude::SessionContext,
         scalar::ScalarValue,
     };
@@ -315,6 +348,7 @@ mod tests {
             optimizer::physical_optimizer::utils::is_only_timestamp_filter,
             udf::{
                 match_all_udf::{self, MATCH_ALL_UDF},
+                match_phrase_udf,
                 str_match_udf::{self, STR_MATCH_UDF},
             },
         },
@@ -577,6 +611,7 @@ mod tests {
             let optimizer_enabled = true;
             let mut rewriter = IndexOptimizer::new_with_config(
                 index_fields,
+                HashSet::new(),
                 index_condition.clone(),
                 is_remove_filter,
                 optimizer_enabled,
@@ -662,6 +697,7 @@ mod tests {
             let optimizer_enabled = true;
             let mut rewriter = IndexOptimizer::new_with_config(
                 index_fields,
+                HashSet::new(),
                 index_condition.clone(),
                 is_remove_filter,
                 optimizer_enabled,
@@ -672,4 +708,71 @@ mod tests {
             assert_eq!(rewriter.can_optimize, can_optimizer);
         }
     }
+
+    #[tokio::test]
+    async fn test_index_optimizer_match_phrase() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("_timestamp", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, false),
+            Field::new("message", DataType::Utf8, false),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["openobserve"])),
+                Arc::new(StringArray::from(vec!["connection was finally refused"])),
+            ],
+        )
+        .unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_udf(match_phrase_udf::MATCH_PHRASE_UDF.clone());
+        let provider = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        // sql, except_condition
+        let cases = vec![
+            (
+                "SELECT count(*) from t where match_phrase(message, 'connection refused', 2) and _timestamp > 1715395200000",
+                Some(IndexCondition {
+                    conditions: vec![Condition::MatchPhrase(
+                        "message".to_string(),
+                        "connection refused".to_string(),
+                        2,
+                    )],
+                }),
+            ),
+            // the secondary index field can't search a phrase
+            (
+                "SELECT count(*) from t where match_phrase(name, 'open observe', 0) and _timestamp > 1715395200000",
+                None,
+            ),
+        ];
+
+        for (sql, except_condition) in cases {
+            let plan = ctx.state().create_logical_plan(sql).await.unwrap();
+            let physical_plan = ctx.state().create_physical_plan(&plan).await.unwrap();
+            let index_fields = HashSet::from(["name".to_string()]);
+            let fts_fields = HashSet::from(["message".to_string()]);
+            let index_condition = Arc::new(Mutex::new(None));
+            let mut rewriter = IndexOptimizer::new_with_config(
+                index_fields,
+                fts_fields,
+                index_condition.clone(),
+                true,
+                true,
+            );
+            let physical_plan = physical_plan.rewrite(&mut rewriter).unwrap().data;
+
+            assert_eq!(index_condition.lock().clone(), except_condition);
+            assert!(!rewriter.can_optimize);
+            // the phrase is still filtered by datafusion
+            let plan = displayable(physical_plan.as_ref())
+                .indent(false)
+                .to_string();
+            assert!(plan.contains("match_phrase"));
+        }
+    }
 }
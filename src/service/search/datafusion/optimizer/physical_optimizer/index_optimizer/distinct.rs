@@ -29,6 +29,7 @@ use datafusion::{
 };
 
 use crate::service::search::datafusion::optimizer::physical_optimizer::{
+    index::is_expr_valid_for_index,
     index_optimizer::utils::is_complex_plan,
     utils::{get_column_name, is_column, is_only_timestamp_filter},
 };
@@ -37,7 +38,8 @@ use crate::service::search::datafusion::optimizer::physical_optimizer::{
 /// SimpleDistinct(String, usize, bool):
 /// the sql can like: select name from table where str_match(name, 'a') group by name order by name asc limit 10;
 ///                   or select name as key from table where str_match(name, 'a') group by key order by key desc limit 10;
-/// condition：name is index field, group by name (or alias), order by name (or alias), have limit, and where only the str_match()(expect _timestamp)
+/// condition：name is index field, group by name (or alias), order by name (or alias), have limit, and where only the filters the index can answer (expect _timestamp),
+///            the distinct terms are intersected with the matched docs when the filter isn't a str_match() of name, which needs order by asc
 /// example plan:
 ///   SortPreservingMergeExec: [kubernetes_namespace_name@0 ASC NULLS LAST], fetch=10
 ///     SortExec: TopK(fetch=10), expr=[kubernetes_namespace_name@0 ASC NULLS LAST], preserve_partitioning=[true]
@@ -134,14 +136,26 @@ impl<'n> TreeNodeVisitor<'n> for SimpleDistinctVisitor {
             return Ok(TreeNodeRecursion::Stop);
         } else if let Some(filter) = node.as_any().downcast_ref::<FilterExec>() {
             let predicate = filter.predicate();
-            let exprs = split_conjunction(predicate);
-            if exprs.len() == 2 && is_only_timestamp_filter(&exprs) {
-                return Ok(TreeNodeRecursion::Continue);
-            }
-            if exprs.len() == 3
-                && is_only_timestamp_filter(&exprs[1..])
-                && let Some(column_name) = is_simple_str_match(exprs[0])
-                && self.index_fields.contains(&column_name)
+            let (timestamp_exprs, other_exprs): (Vec<_>, Vec<_>) = split_conjunction(predicate)
+                .into_iter()
+                .partition(|expr| is_only_timestamp_filter(&[*expr]));
+            // the other filters intersect the terms with the matched docs, which is only bounded
+            // by the limit for ascending order
+            let (field, ascend) = self
+                .simple_distinct
+                .as_ref()
+                .map(|(field, _, ascend)| (field.as_str(), *ascend))
+                .unwrap_or_default();
+            let supported = match other_exprs.as_slice() {
+                [] => true,
+                [expr] if is_simple_str_match(expr).is_some_and(|column| column == field) => true,
+                _ => ascend,
+            };
+            if timestamp_exprs.len() == 2
+                && supported
+                && other_exprs
+                    .iter()
+                    .all(|expr| is_expr_valid_for_index(expr, &self.index_fields))
             {
                 return Ok(TreeNodeRecursion::Continue);
             }
@@ -238,6 +252,22 @@ mod tests {
                     true,
                 )),
             ),
+            (
+                "select name from t where id = '1' and _timestamp >= 175256100000000 and _timestamp < 17525610000000000 group by name order by name asc limit 10",
+                Some(IndexOptimizeMode::SimpleDistinct(
+                    "name".to_string(),
+                    10,
+                    true,
+                )),
+            ),
+            (
+                "select name from t where status = 'success' and _timestamp >= 175256100000000 and _timestamp < 17525610000000000 group by name order by name asc limit 10",
+                None,
+            ),
+            (
+                "select name from t where id = '1' and _timestamp >= 175256100000000 and _timestamp < 17525610000000000 group by name order by name desc limit 10",
+                None,
+            ),
             ("SELECT count(*) from t", None),
         ];
 
@@ -20,7 +20,10 @@ use arrow::array::{
 };
 use config::{
     PARQUET_BATCH_SIZE,
-    meta::{inverted_index::IndexOptimizeMode, stream::FileKey},
+    meta::{
+        inverted_index::{IndexOptimizeMode, check_histogram_boundaries},
+        stream::FileKey,
+    },
 };
 use datafusion::{
     arrow::{array::RecordBatch, datatypes::SchemaRef},
@@ -175,7 +178,7 @@ async fn adapt_tantivy_result(
     metrics: BaselineMetrics,
 ) -> Result<SendableRecordBatchStream> {
     let timer = metrics.elapsed_compute().timer();
-    let (idx_took, error, result) = tantivy_search(
+    let (idx_took, filter_back, result, ..) = tantivy_search(
         query.clone(),
         &mut file_list,
         index_condition,
@@ -184,7 +187,8 @@ async fn adapt_tantivy_result(
     .await
     .map_err(|e| DataFusionError::External(Box::new(e)))?;
 
-    if error {
+    // the result must come from the index only
+    if filter_back.is_any() {
         return internal_err!("Error while filtering file list by Tantivy index");
     }
 
@@ -214,6 +218,13 @@ async fn adapt_tantivy_result(
                 num_buckets,
             )?]
         }
+        IndexOptimizeMode::SimpleHistogramBuckets(boundaries) => {
+            vec![create_histogram_buckets_arrow_array(
+                &schema,
+                result.histogram(),
+                &boundaries,
+            )?]
+        }
         IndexOptimizeMode::SimpleTopN(field, limit, _ascend) => {
             create_top_n_arrow_array(&schema, result.top_n(), &field, limit)?
         }
@@ -314,6 +325,45 @@ fn create_histogram_arrow_array(
         }
     }
 
+    create_histogram_arrays(schema, timestamp_values, count_values)
+}
+
+/// Creates the histogram arrays of buckets with arbitrary boundaries, each bucket is
+/// timestamped with its lower boundary
+///
+/// Parameters:
+/// - schema: The expected schema for the result
+/// - histogram_counts: Vector of counts for each histogram bucket
+/// - boundaries: The bucket boundaries, one more than the number of buckets
+fn create_histogram_buckets_arrow_array(
+    schema: &SchemaRef,
+    histogram_counts: Vec<u64>,
+    boundaries: &[i64],
+) -> Result<Vec<Arc<dyn arrow::array::Array>>, DataFusionError> {
+    check_histogram_boundaries(boundaries).map_err(|e| DataFusionError::Internal(e.to_string()))?;
+
+    // Verify schema has expected structure
+    if schema.fields().len() != 2 {
+        return Err(DataFusionError::Internal(format!(
+            "Expected schema with 2 fields for histogram, got {}",
+            schema.fields().len()
+        )));
+    }
+
+    let (timestamp_values, count_values) = boundaries
+        .iter()
+        .zip(histogram_counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(start, count)| (*start, count as i64))
+        .unzip();
+    create_histogram_arrays(schema, timestamp_values, count_values)
+}
+
+fn create_histogram_arrays(
+    schema: &SchemaRef,
+    timestamp_values: Vec<i64>,
+    count_values: Vec<i64>,
+) -> Result<Vec<Arc<dyn arrow::array::Array>>, DataFusionError> {
     // Get field data types from schema to ensure we create the right array types
     let timestamp_field = &schema.fields()[0];
     let count_field = &schema.fields()[1];
@@ -806,6 +856,31 @@ mod tests {
         assert_eq!(count_array.value(2), 30);
     }
 
+    #[test]
+    fn test_create_histogram_buckets_arrow_array() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("count", DataType::Int64, false),
+        ]));
+        let boundaries = vec![0, 10, 100, 1000, 10000];
+        let result =
+            create_histogram_buckets_arrow_array(&schema, vec![5, 0, 7, 1], &boundaries).unwrap();
+        let timestamp_array = result[0]
+            .as_any()
+            .downcast_ref::<TimestampMicrosecondArray>()
+            .unwrap();
+        let count_array = result[1].as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(timestamp_array.values().to_vec(), vec![0, 100, 1000]);
+        assert_eq!(count_array.values().to_vec(), vec![5, 7, 1]);
+
+        // unsorted boundaries are rejected
+        assert!(create_histogram_buckets_arrow_array(&schema, vec![1, 1], &[0, 100, 10]).is_err());
+    }
+
     #[test]
     fn test_create_histogram_arrow_array_nanosecond() {
         let schema = Arc::new(Schema::new(vec![
@@ -975,6 +1050,11 @@ mod tests {
             time_range: (0, 1000),
             work_group: None,
             use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
         });
         let schema = Arc::new(Schema::new(vec![Field::new(
             "field",
@@ -1016,6 +1096,11 @@ mod tests {
             time_range: (0, 1000),
             work_group: None,
             use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
         });
         let schema = Arc::new(Schema::new(vec![Field::new(
             "field",
@@ -1105,6 +1190,11 @@ mod tests {
             time_range: (0, 1000),
             work_group: None,
             use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
         });
         let schema = Arc::new(Schema::new(vec![Field::new(
             "field",
@@ -1146,6 +1236,11 @@ mod tests {
             time_range: (0, 1000),
             work_group: None,
             use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
         });
         let schema = Arc::new(Schema::new(vec![Field::new(
             "field",
@@ -1188,6 +1283,11 @@ mod tests {
             time_range: (0, 1000),
             work_group: None,
             use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
         });
         let schema = Arc::new(Schema::new(vec![Field::new(
             "field",
@@ -1221,4 +1321,122 @@ mod tests {
             datafusion::common::stats::Precision::Absent
         ));
     }
+
+    // writes an index file with the timestamps 100, 110, ..., 190 into the disk cache
+    async fn write_timestamp_index(key: &str, ts_opts: tantivy::schema::NumericOptions) -> FileKey {
+        use crate::service::tantivy::puffin_directory::writer::PuffinDirWriter;
+
+        let dir = PuffinDirWriter::new();
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let ts = schema_builder.add_i64_field(config::TIMESTAMP_COL_NAME, ts_opts);
+        let mut index_writer = tantivy::IndexBuilder::new()
+            .schema(schema_builder.build())
+            .single_segment_index_writer(dir.clone(), 50_000_000)
+            .unwrap();
+        for i in 0..10i64 {
+            index_writer
+                .add_document(tantivy::doc!(ts => 100 + i * 10))
+                .unwrap();
+        }
+        index_writer.finalize().unwrap();
+        let puffin_bytes = dir.to_puffin_bytes().unwrap();
+        let ttv_file =
+            config::utils::inverted_index::convert_parquet_file_name_to_tantivy_file(key).unwrap();
+        infra::cache::file_data::disk::set(&ttv_file, puffin_bytes.clone().into())
+            .await
+            .unwrap();
+        FileKey {
+            key: key.to_string(),
+            meta: FileMeta {
+                min_ts: 100,
+                max_ts: 199,
+                records: 10,
+                index_size: puffin_bytes.len() as i64,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tantivy_optimize_exec_histogram_without_timestamp_fast_field() {
+        use tantivy::schema::NumericOptions;
+
+        use crate::service::search::{
+            grpc::storage::split_missing_timestamp_fast_field, index::Condition,
+        };
+
+        let fast = write_timestamp_index(
+            "files/default/logs/histogram/2024/01/01/00/7000000000000000011.parquet",
+            NumericOptions::default().set_indexed().set_fast(),
+        )
+        .await;
+        let not_fast = write_timestamp_index(
+            "files/default/logs/histogram/2024/01/01/00/7000000000000000012.parquet",
+            NumericOptions::default().set_indexed(),
+        )
+        .await;
+
+        // the file without the _timestamp fast field is computed by datafusion
+        let (tantivy_files, datafusion_files) =
+            split_missing_timestamp_fast_field("test", vec![fast.clone(), not_fast.clone()]).await;
+        assert_eq!(
+            tantivy_files.iter().map(|f| &f.key).collect::<Vec<_>>(),
+            vec![&fast.key]
+        );
+        assert_eq!(
+            datafusion_files.iter().map(|f| &f.key).collect::<Vec<_>>(),
+            vec![&not_fast.key]
+        );
+
+        let query = Arc::new(QueryParams {
+            trace_id: "test".to_string(),
+            org_id: "default".to_string(),
+            stream: TableReference::from("histogram"),
+            stream_type: StreamType::Logs,
+            stream_name: "histogram".to_string(),
+            time_range: (0, 1000),
+            work_group: None,
+            use_inverted_index: true,
+            disable_index_skip_threshold: true,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
+        });
+        let schema = Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Microsecond, None),
+                false,
+            ),
+            Field::new("count", DataType::Int64, false),
+        ]));
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::All());
+        let mode = IndexOptimizeMode::SimpleHistogram(100, 50, 2);
+        let execute = |files: Vec<FileKey>| {
+            let exec = TantivyOptimizeExec::new(
+                query.clone(),
+                schema.clone(),
+                files,
+                Some(condition.clone()),
+                mode.clone(),
+            );
+            datafusion::physical_plan::collect(Arc::new(exec), Arc::new(TaskContext::default()))
+        };
+
+        let batches = execute(tantivy_files).await.unwrap();
+        let counts = batches
+            .iter()
+            .flat_map(|batch| {
+                let counts = batch.column(1).as_any().downcast_ref::<Int64Array>();
+                counts.unwrap().values().to_vec()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![5, 5]);
+
+        // the exec can't add the file back, it fails if the file isn't routed to datafusion
+        assert!(execute(vec![fast, not_fast]).await.is_err());
+    }
 }
@@ -15,7 +15,10 @@
 
 use std::{
     collections::{HashMap, VecDeque},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
 };
 
 use dashmap::DashMap;
@@ -213,6 +216,102 @@ impl CacheAccessor<Path, Arc<Statistics>> for FileStatisticsCache {
     }
 }
 
+/// The file statistics cache used by one query, it counts the cache hits and misses of the
+/// query and only stores the collected statistics in the shared cache when `update` is set.
+pub struct QueryFileStatisticsCache {
+    inner: Arc<dyn datafusion::execution::cache::cache_manager::FileStatisticsCache>,
+    update: bool,
+    hits: AtomicI64,
+    misses: AtomicI64,
+}
+
+impl QueryFileStatisticsCache {
+    pub fn new(
+        inner: Arc<dyn datafusion::execution::cache::cache_manager::FileStatisticsCache>,
+        update: bool,
+    ) -> Self {
+        Self {
+            inner,
+            update,
+            hits: AtomicI64::new(0),
+            misses: AtomicI64::new(0),
+        }
+    }
+
+    /// Returns the number of cache hits and misses
+    pub fn stats(&self) -> (i64, i64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl CacheAccessor<Path, Arc<Statistics>> for QueryFileStatisticsCache {
+    type Extra = ObjectMeta;
+
+    fn get(&self, k: &Path) -> Option<Arc<Statistics>> {
+        self.inner.get(k)
+    }
+
+    fn get_with_extra(&self, k: &Path, e: &Self::Extra) -> Option<Arc<Statistics>> {
+        let ret = self.inner.get_with_extra(k, e);
+        if ret.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        ret
+    }
+
+    fn put(&self, k: &Path, value: Arc<Statistics>) -> Option<Arc<Statistics>> {
+        if self.update {
+            self.inner.put(k, value)
+        } else {
+            None
+        }
+    }
+
+    fn put_with_extra(
+        &self,
+        k: &Path,
+        value: Arc<Statistics>,
+        e: &Self::Extra,
+    ) -> Option<Arc<Statistics>> {
+        if self.update {
+            self.inner.put_with_extra(k, value, e)
+        } else {
+            None
+        }
+    }
+
+    fn remove(&self, k: &Path) -> Option<Arc<Statistics>> {
+        self.inner.remove(k)
+    }
+
+    fn contains_key(&self, k: &Path) -> bool {
+        self.inner.contains_key(k)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn clear(&self) {
+        self.inner.clear()
+    }
+
+    fn name(&self) -> String {
+        "QueryFileStatisticsCache".to_string()
+    }
+}
+
+impl datafusion::execution::cache::cache_manager::FileStatisticsCache for QueryFileStatisticsCache {
+    fn list_entries(&self) -> HashMap<Path, FileStatisticsCacheEntry> {
+        self.inner.list_entries()
+    }
+}
+
 impl datafusion::execution::cache::cache_manager::FileStatisticsCache for FileStatisticsCache {
     fn list_entries(&self) -> HashMap<Path, FileStatisticsCacheEntry> {
         let mut entries = HashMap::<Path, FileStatisticsCacheEntry>::new();
@@ -287,6 +386,43 @@ mod tests {
         assert!(cache.get_with_extra(&meta2.location, &meta2).is_none());
     }
 
+    #[test]
+    fn test_query_file_statistics_cache() {
+        let meta = |location: &str| ObjectMeta {
+            location: Path::from(location),
+            last_modified: DateTime::parse_from_rfc3339("2022-09-27T22:36:00+02:00")
+                .unwrap()
+                .into(),
+            size: 1024,
+            e_tag: None,
+            version: None,
+        };
+        let stats = || -> Arc<Statistics> {
+            Statistics::new_unknown(&Schema::new(vec![Field::new(
+                "test_column",
+                DataType::Int64,
+                false,
+            )]))
+            .into()
+        };
+        let shared = Arc::new(FileStatisticsCache::default());
+
+        // a query which doesn't update the cache only reads it
+        let cache = QueryFileStatisticsCache::new(shared.clone(), false);
+        let m1 = meta("test1");
+        assert!(cache.get_with_extra(&m1.location, &m1).is_none());
+        cache.put_with_extra(&m1.location, stats(), &m1);
+        assert!(shared.get_with_extra(&m1.location, &m1).is_none());
+        assert_eq!(cache.stats(), (0, 1));
+
+        let cache = QueryFileStatisticsCache::new(shared.clone(), true);
+        assert!(cache.get_with_extra(&m1.location, &m1).is_none());
+        cache.put_with_extra(&m1.location, stats(), &m1);
+        assert!(cache.get_with_extra(&m1.location, &m1).is_some());
+        assert!(shared.get_with_extra(&m1.location, &m1).is_some());
+        assert_eq!(cache.stats(), (1, 1));
+    }
+
     #[test]
     fn test_memory_size_calculation() {
         let cache = FileStatisticsCache::new();
@@ -0,0 +1,192 @@
+// Copyright 2025 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{iter::zip, sync::Arc};
+
+use config::utils::tantivy::tokenizer::{
+    CollectType, o2_collect_search_tokens, o2_tokenizer_build,
+};
+use datafusion::{
+    arrow::{
+        array::{ArrayRef, BooleanArray},
+        datatypes::DataType,
+    },
+    common::cast::{as_int64_array, as_string_array},
+    error::DataFusionError,
+    logical_expr::{ColumnarValue, ScalarFunctionImplementation, ScalarUDF, Volatility},
+    prelude::create_udf,
+    sql::sqlparser::parser::ParserError,
+};
+use hashbrown::{HashMap, HashSet};
+use once_cell::sync::Lazy;
+
+/// Implementation of match_phrase
+pub(crate) static MATCH_PHRASE_UDF: Lazy<ScalarUDF> = Lazy::new(|| {
+    create_udf(
+        super::MATCH_PHRASE_UDF_NAME,
+        // expects the field, the phrase and the slop
+        vec![DataType::Utf8, DataType::Utf8, DataType::Int64],
+        // returns boolean
+        DataType::Boolean,
+        Volatility::Stable,
+        match_phrase_expr_impl(),
+    )
+});
+
+/// match_phrase function for datafusion
+pub fn match_phrase_expr_impl() -> ScalarFunctionImplementation {
+    Arc::new(move |args: &[ColumnarValue]| {
+        if args.len() != 3 {
+            return Err(DataFusionError::SQL(
+                Box::new(ParserError::ParserError(
+                    "match_phrase UDF expects two string and one integer".to_string(),
+                )),
+                None,
+            ));
+        }
+        let args = ColumnarValue::values_to_arrays(args)?;
+
+        // 1. cast the arguments to be aligned with the signature
+        let haystack = as_string_array(&args[0])?;
+        let phrase = as_string_array(&args[1])?;
+        let slop = as_int64_array(&args[2])?;
+
+        // 2. perform the computation
+        let array = zip(haystack.iter(), zip(phrase.iter(), slop.iter()))
+            .map(
+                |(haystack, (phrase, slop))| match (haystack, phrase, slop) {
+                    (Some(haystack), Some(phrase), Some(slop)) => Some(match_phrase(
+                        haystack,
+                        &o2_collect_search_tokens(phrase),
+                        slop.max(0) as u32,
+                    )),
+                    _ => None,
+                },
+            )
+            .collect::<BooleanArray>();
+
+        Ok(ColumnarValue::from(Arc::new(array) as ArrayRef))
+    })
+}
+
+/// Checks if the tokens of the phrase appear in the text in order, each token can be at most
+/// `slop` positions away from where it follows the previous one, which is the same as the slop
+/// of [`tantivy::query::PhraseQuery`].
+///
+/// The text is tokenized like the ingestion, so the positions are the same as in the index.
+pub(crate) fn match_phrase(text: &str, phrase: &[String], slop: u32) -> bool {
+    let Some((first, rest)) = phrase.split_first() else {
+        return false;
+    };
+
+    let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut analyzer = o2_tokenizer_build(CollectType::Ingest);
+    let mut token_stream = analyzer.token_stream(text);
+    token_stream.process(&mut |token| {
+        positions
+            .entry(token.text.to_lowercase())
+            .or_default()
+            .push(token.position);
+    });
+
+    // the positions where the phrase read so far ends
+    let mut ends: HashSet<usize> = match positions.get(first) {
+        Some(p) => p.iter().copied().collect(),
+        None => return false,
+    };
+    for token in rest {
+        let Some(candidates) = positions.get(token) else {
+            return false;
+        };
+        ends = candidates
+            .iter()
+            .copied()
+            .filter(|pos| {
+                ends.iter()
+                    .any(|end| *pos > *end && *pos - *end <= slop as usize + 1)
+            })
+            .collect();
+        if ends.is_empty() {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::StringArray;
+    use datafusion::{
+        arrow::{
+            array::Int64Array,
+            datatypes::{Field, Schema},
+            record_batch::RecordBatch,
+        },
+        datasource::MemTable,
+        prelude::SessionContext,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_match_phrase() {
+        let phrase = o2_collect_search_tokens("connection refused");
+        let text = "Connection was finally refused";
+        assert!(!match_phrase(text, &phrase, 0));
+        assert!(!match_phrase(text, &phrase, 1));
+        assert!(match_phrase(text, &phrase, 2));
+        assert!(match_phrase("connection refused", &phrase, 0));
+        // the tokens must be in order
+        assert!(!match_phrase("refused connection", &phrase, 2));
+        assert!(!match_phrase(text, &[], 2));
+    }
+
+    #[tokio::test]
+    async fn test_match_phrase_udf() {
+        let sql = "select * from t where match_phrase(log, 'connection refused', 2)";
+
+        // define a schema.
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("log", DataType::Utf8, false),
+        ]));
+
+        // define data.
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2, 3, 4])),
+                Arc::new(StringArray::from(vec![
+                    "connection refused",
+                    "connection was finally refused",
+                    "connection to the remote server was refused",
+                    "refused connection",
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let ctx = SessionContext::new();
+        ctx.register_udf(MATCH_PHRASE_UDF.clone());
+
+        let provider = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let df = ctx.sql(sql).await.unwrap();
+        let result = df.collect().await.unwrap();
+        let count = result.iter().map(|batch| batch.num_rows()).sum::<usize>();
+        assert_eq!(count, 2);
+    }
+}
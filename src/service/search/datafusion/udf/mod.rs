@@ -30,6 +30,7 @@ pub(crate) mod fuzzy_match_udf;
 pub(crate) mod histogram_udf;
 pub(crate) mod match_all_hash_udf;
 pub(crate) mod match_all_udf;
+pub(crate) mod match_phrase_udf;
 pub(crate) mod regexp_matches_udf;
 pub(crate) mod regexp_udf;
 pub(crate) mod spath_udf;
@@ -49,6 +50,8 @@ pub(crate) const MATCH_FIELD_UDF_NAME: &str = "match_field";
 pub(crate) const MATCH_FIELD_IGNORE_CASE_UDF_NAME: &str = "match_field_ignore_case";
 /// The name of the fuzzy_match UDF given to DataFusion.
 pub(crate) const FUZZY_MATCH_UDF_NAME: &str = "fuzzy_match";
+/// The name of the match_phrase UDF given to DataFusion.
+pub(crate) const MATCH_PHRASE_UDF_NAME: &str = "match_phrase";
 /// The name of the regex_match UDF given to DataFusion.
 pub(crate) const REGEX_MATCH_UDF_NAME: &str = "re_match";
 /// The name of the not_regex_match UDF given to DataFusion.
@@ -56,7 +59,7 @@ pub(crate) const REGEX_NOT_MATCH_UDF_NAME: &str = "re_not_match";
 /// The name of the regex_matches UDF given to DataFusion.
 pub(crate) const REGEX_MATCHES_UDF_NAME: &str = "re_matches";
 
-pub(crate) const DEFAULT_FUNCTIONS: [ZoFunction; 11] = [
+pub(crate) const DEFAULT_FUNCTIONS: [ZoFunction; 12] = [
     ZoFunction {
         name: "match_all",
         text: "match_all('v')",
@@ -85,6 +88,10 @@ pub(crate) const DEFAULT_FUNCTIONS: [ZoFunction; 11] = [
         name: FUZZY_MATCH_UDF_NAME,
         text: "fuzzy_match(field, 'v', 1)",
     },
+    ZoFunction {
+        name: MATCH_PHRASE_UDF_NAME,
+        text: "match_phrase(field, 'v', 0)",
+    },
     ZoFunction {
         name: REGEX_MATCH_UDF_NAME,
         text: "re_match(field, 'pattern')",
@@ -33,6 +33,7 @@ use config::{
 };
 use datafusion::{
     common::TableReference,
+    execution::cache::cache_manager::FileStatisticsCache,
     physical_optimizer::{PhysicalOptimizerRule, filter_pushdown::FilterPushdown},
 };
 use datafusion_proto::bytes::physical_plan_from_bytes_with_extension_codec;
@@ -54,27 +55,33 @@ use crate::service::{
         datafusion::{
             distributed_plan::{
                 NewEmptyExecVisitor, ReplaceTableScanExec, codec::get_physical_extension_codec,
-                rewrite::tantivy_optimize_rewrite,
+                empty_exec::NewEmptyExec, rewrite::tantivy_optimize_rewrite,
             },
             exec::{DataFusionContextBuilder, register_udf},
             optimizer::physical_optimizer::{
                 index::IndexRule, index_optimizer::FollowerIndexOptimizerRule,
                 rewrite_match::RewriteMatchPhysical,
             },
+            storage::file_statistics_cache::QueryFileStatisticsCache,
             table_provider::{enrich_table::EnrichTable, uniontable::NewUnionTable},
         },
-        grpc::QueryParams,
+        grpc::{QueryParams, storage::StoragePlan},
         index::IndexCondition,
         inspector::{SearchInspectorFieldsBuilder, search_inspector_fields},
         match_file,
     },
 };
 
+/// The context and physical plan of the search, the storage plan is built for EXPLAIN ANALYZE
+pub type SearchPlan = (
+    SessionContext,
+    Arc<dyn ExecutionPlan>,
+    ScanStats,
+    Option<StoragePlan>,
+);
+
 #[tracing::instrument(name = "service:search:grpc:flight:do_get::search", skip_all, fields(org_id = req.query_identifier.org_id))]
-pub async fn search(
-    trace_id: &str,
-    req: &FlightSearchRequest,
-) -> Result<(SessionContext, Arc<dyn ExecutionPlan>, ScanStats), Error> {
+pub async fn search(trace_id: &str, req: &FlightSearchRequest) -> Result<SearchPlan, Error> {
     let cfg = get_config();
 
     let org_id = req.query_identifier.org_id.to_string();
@@ -174,7 +181,21 @@ pub async fn search(
     // get all tables
     let mut tables = Vec::new();
     let mut scan_stats = ScanStats::new();
-    let file_stats_cache = ctx.runtime_env().cache_manager.get_file_statistic_cache();
+    // the statistics collected by the ad-hoc queries are only stored when configured, the
+    // queries using the result cache are likely to be repeated
+    let query_stats_cache = ctx
+        .runtime_env()
+        .cache_manager
+        .get_file_statistic_cache()
+        .map(|cache| {
+            Arc::new(QueryFileStatisticsCache::new(
+                cache,
+                cfg.limit.datafusion_file_stat_cache_update_all || req.search_info.use_cache,
+            ))
+        });
+    let file_stats_cache = query_stats_cache
+        .clone()
+        .map(|cache| cache as Arc<dyn FileStatisticsCache>);
 
     // optimize physical plan, current for tantivy index optimize
     let index_optimize_mode = req.index_info.index_optimize_mode.clone();
@@ -193,6 +214,18 @@ pub async fn search(
     let index_condition = { index_condition_ref.lock().clone() };
     let idx_optimize_rule = { index_optimizer_rule_ref.lock().clone() };
 
+    // the columns read by the optimized plan, the enrichment table of the enrich mode is
+    // loaded with all the columns
+    let mut plan_visitor = NewEmptyExecVisitor::default();
+    let referenced_fields =
+        if stream_type == StreamType::EnrichmentTables && req.query_identifier.enrich_mode {
+            None
+        } else if physical_plan.visit(&mut plan_visitor).is_ok() && plan_visitor.has_empty_exec() {
+            referenced_fields(plan_visitor.plan())
+        } else {
+            None
+        };
+
     let query_params = Arc::new(QueryParams {
         trace_id: trace_id.to_string(),
         org_id: org_id.clone(),
@@ -205,7 +238,15 @@ pub async fn search(
             && cfg.common.inverted_index_enabled
             && (!index_condition.as_ref().unwrap().is_condition_all()
                 || idx_optimize_rule.is_some()),
+        disable_index_skip_threshold: req.search_info.disable_index_skip_threshold,
+        // EXPLAIN ANALYZE returns the storage plan with the metrics of the node
+        explain: req.search_info.is_analyze,
+        explain_index: cfg.common.inverted_index_explain,
+        strict_index_only: req.search_info.strict_index_only,
+        referenced_fields,
     });
+    let table_schema =
+        query_params.project_schema(&latest_schema, index_condition.as_ref(), &fst_fields);
 
     log::info!(
         "[trace_id {trace_id}] flight->search: use_inverted_index: {}, index_condition: {index_condition:?}, index_optimizer_rule: {idx_optimize_rule:?}",
@@ -214,6 +255,7 @@ pub async fn search(
 
     // search in object storage
     let mut tantivy_file_list = Vec::new();
+    let mut storage_plan = None;
     if !req.search_info.file_id_list.is_empty() {
         let (mut file_list, file_list_took) = get_file_list_by_ids(
             &trace_id,
@@ -301,9 +343,9 @@ pub async fn search(
         );
 
         let storage_search_start = std::time::Instant::now();
-        let (tbls, stats, _) = match super::storage::search(
+        let (tbls, stats, plan) = match super::storage::search(
             query_params.clone(),
-            latest_schema.clone(),
+            table_schema.clone(),
             &file_list,
             empty_exec.sorted_by_time(),
             file_stats_cache.clone(),
@@ -338,6 +380,7 @@ pub async fn search(
                     .build()
             )
         );
+        storage_plan = plan;
         tables.extend(tbls);
         scan_stats.add(&stats);
     }
@@ -349,7 +392,7 @@ pub async fn search(
     if LOCAL_NODE.is_ingester() {
         let (tbls, stats, ids) = match super::wal::search_memtable(
             query_params.clone(),
-            latest_schema.clone(),
+            table_schema.clone(),
             &search_partition_keys,
             empty_exec.sorted_by_time(),
             index_condition.clone(),
@@ -374,7 +417,7 @@ pub async fn search(
     if LOCAL_NODE.is_ingester() {
         let (tbls, stats, _) = match super::wal::search_parquet(
             query_params.clone(),
-            latest_schema.clone(),
+            table_schema.clone(),
             &search_partition_keys,
             empty_exec.sorted_by_time(),
             file_stats_cache.clone(),
@@ -435,15 +478,27 @@ pub async fn search(
         )
     );
 
+    // the tables are created with the table schema, the projection is on the full schema
+    let projection = super::remap_projection(
+        empty_exec.projection(),
+        &empty_exec.full_schema(),
+        &table_schema,
+    )?;
     let scan_start = std::time::Instant::now();
     let union_exec = union_table
         .scan(
             &ctx.state(),
-            empty_exec.projection(),
+            projection.as_ref(),
             empty_exec.filters(),
             empty_exec.limit(),
         )
         .await?;
+    // the file statistics are collected when the tables are scanned
+    if let Some(cache) = &query_stats_cache {
+        let (hits, misses) = cache.stats();
+        scan_stats.file_stat_cache_hits += hits;
+        scan_stats.file_stat_cache_misses += misses;
+    }
     log::info!(
         "{}",
         search_inspector_fields(
@@ -523,7 +578,22 @@ pub async fn search(
         )
     );
 
-    Ok((ctx, physical_plan, scan_stats))
+    Ok((ctx, physical_plan, scan_stats, storage_plan))
+}
+
+/// The columns read by the scan of the empty exec, its projection and the columns of its
+/// filters. None when it has no projection, all the columns are read.
+fn referenced_fields(empty_exec: &NewEmptyExec) -> Option<HashSet<String>> {
+    let full_schema = empty_exec.full_schema();
+    let mut fields = empty_exec
+        .projection()?
+        .iter()
+        .map(|i| full_schema.field(*i).name().to_string())
+        .collect::<HashSet<_>>();
+    for filter in empty_exec.filters() {
+        fields.extend(filter.column_refs().into_iter().map(|c| c.name.clone()));
+    }
+    Some(fields)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -538,7 +608,11 @@ fn optimizer_physical_plan(
     index_optimizer_rule_ref: Arc<Mutex<Option<IndexOptimizeMode>>>,
 ) -> Result<Arc<dyn ExecutionPlan>, Error> {
     let index_fields: HashSet<String> = index_fields.iter().cloned().collect();
-    let index_rule = IndexRule::new(index_fields.clone(), index_condition_ref.clone());
+    let index_rule = IndexRule::new(
+        index_fields.clone(),
+        fst_fields.iter().cloned().collect(),
+        index_condition_ref.clone(),
+    );
     let mut plan = index_rule.optimize(plan, ctx.state().config_options())?;
 
     // if the index rule can't optimize, we should take the index optimizer rule
@@ -642,6 +716,7 @@ async fn handle_tantivy_optimize(
         idx_optimize_rule,
         Some(IndexOptimizeMode::SimpleCount)
             | Some(IndexOptimizeMode::SimpleHistogram(..))
+            | Some(IndexOptimizeMode::SimpleHistogramBuckets(..))
             | Some(IndexOptimizeMode::SimpleTopN(..))
             | Some(IndexOptimizeMode::SimpleDistinct(..))
     ) {
@@ -650,12 +725,24 @@ async fn handle_tantivy_optimize(
 
     let index_updated_at = update_index_updated_at(idx_optimize_rule, index_updated_at).await;
 
-    let (tantivy_files, datafusion_files) = split_file_list_by_time_range(
+    let (mut tantivy_files, mut datafusion_files) = split_file_list_by_time_range(
         file_list,
         req.search_info.start_time,
         req.search_info.end_time,
         index_updated_at,
     );
+    // TantivyOptimizeExec can't add a file back, the files whose index can't compute the
+    // histogram are computed by datafusion
+    if matches!(
+        idx_optimize_rule,
+        Some(IndexOptimizeMode::SimpleHistogram(..))
+            | Some(IndexOptimizeMode::SimpleHistogramBuckets(..))
+    ) {
+        let missing_fast_field;
+        (tantivy_files, missing_fast_field) =
+            super::storage::split_missing_timestamp_fast_field(trace_id, tantivy_files).await;
+        datafusion_files.extend(missing_fast_field);
+    }
     // set optimize rule to None, because datafusion should not use it
     *idx_optimize_rule = None;
 
@@ -678,6 +765,7 @@ async fn update_index_updated_at(
     if matches!(
         idx_optimize_rule,
         Some(IndexOptimizeMode::SimpleHistogram(..))
+            | Some(IndexOptimizeMode::SimpleHistogramBuckets(..))
     ) {
         let ttv_timestamp_updated_at =
             db::metas::tantivy_index::get_ttv_timestamp_updated_at().await;
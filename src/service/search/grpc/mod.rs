@@ -16,20 +16,24 @@
 use std::{collections::HashSet, sync::Arc};
 
 use arrow_schema::Schema;
-use config::meta::{
-    search::ScanStats,
-    stream::{FileKey, StreamType},
+use config::{
+    TIMESTAMP_COL_NAME,
+    meta::{
+        search::ScanStats,
+        stream::{FileKey, StreamType},
+    },
 };
 use datafusion::{
     datasource::TableProvider, execution::cache::cache_manager::FileStatisticsCache,
     sql::TableReference,
 };
-use infra::errors::Result;
+use infra::errors::{Error, Result};
 
 use super::{datafusion::exec::TableBuilder, index::IndexCondition};
 
 pub mod flight;
 pub mod storage;
+pub(crate) mod storage_result_cache;
 pub(crate) mod tantivy_result;
 pub(crate) mod tantivy_result_cache;
 pub mod wal;
@@ -46,6 +50,77 @@ pub struct QueryParams {
     pub time_range: (i64, i64),
     pub work_group: Option<String>,
     pub use_inverted_index: bool,
+    /// always build the bitvec for the matched docs even if the matched docs exceed
+    /// `inverted_index_skip_threshold`, used by recall-sensitive queries
+    pub disable_index_skip_threshold: bool,
+    /// build a [`storage::StoragePlan`] with the file pruning and partition decisions, set by
+    /// EXPLAIN ANALYZE which returns it with the metrics of the node
+    pub explain: bool,
+    /// record why the inverted index search kept or removed each file, see
+    /// [`storage::IndexFileExplain`]
+    pub explain_index: bool,
+    /// treat a file without an index file as no match instead of searching it by datafusion,
+    /// used by the queries which only need the indexed data
+    pub strict_index_only: bool,
+    /// the columns read by the query, the tables are created with these and the required
+    /// columns only, see [`QueryParams::project_schema`]. None means all the columns
+    pub referenced_fields: Option<HashSet<String>>,
+}
+
+impl QueryParams {
+    /// Restricts the schema of the tables to the referenced columns plus the columns the
+    /// search always needs: `_timestamp`, the full text search fields and the fields of the
+    /// index condition, which are read when the filter is added back. The schema is returned
+    /// as is when the referenced columns are unknown.
+    pub fn project_schema(
+        &self,
+        schema: &Arc<Schema>,
+        index_condition: Option<&IndexCondition>,
+        fst_fields: &[String],
+    ) -> Arc<Schema> {
+        let Some(referenced_fields) = self.referenced_fields.as_ref() else {
+            return schema.clone();
+        };
+        let mut required_fields = index_condition
+            .map(|condition| condition.get_schema_fields(fst_fields))
+            .unwrap_or_default();
+        required_fields.extend(fst_fields.iter().cloned());
+        required_fields.insert(TIMESTAMP_COL_NAME.to_string());
+        let fields = schema
+            .fields()
+            .iter()
+            .filter(|f| referenced_fields.contains(f.name()) || required_fields.contains(f.name()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if fields.len() == schema.fields().len() {
+            return schema.clone();
+        }
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+}
+
+/// Maps the projection on the full schema to the same columns of the projected schema, the
+/// projected schema must contain all the projected columns.
+pub(crate) fn remap_projection(
+    projection: Option<&Vec<usize>>,
+    full_schema: &Schema,
+    projected_schema: &Schema,
+) -> Result<Option<Vec<usize>>> {
+    let Some(projection) = projection else {
+        return Ok(None);
+    };
+    projection
+        .iter()
+        .map(|i| {
+            let name = full_schema.field(*i).name();
+            projected_schema.index_of(name).map_err(|_| {
+                Error::Message(format!(
+                    "projected column {name} is not in the table schema"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
 }
 
 /// Create tables from files, automatically splitting them based on time range overlap:
@@ -119,17 +194,86 @@ where
         .into_iter()
         .partition(|file| file.meta.min_ts >= start_time && file.meta.max_ts < end_time);
 
-    // Create table for files without timestamp filter
-    if !files_without_filter.is_empty() {
-        let table = create_table(files_without_filter, None).await?;
-        tables.push(table);
+    // Create the tables for files without and with timestamp filter concurrently, the table
+    // without filter always comes first
+    match (
+        files_without_filter.is_empty(),
+        files_with_filter.is_empty(),
+    ) {
+        (false, false) => {
+            let (table_without_filter, table_with_filter) = futures::future::try_join(
+                create_table(files_without_filter, None),
+                create_table(files_with_filter, Some(query.time_range)),
+            )
+            .await?;
+            tables.push(table_without_filter);
+            tables.push(table_with_filter);
+        }
+        (false, true) => tables.push(create_table(files_without_filter, None).await?),
+        (true, false) => {
+            tables.push(create_table(files_with_filter, Some(query.time_range)).await?)
+        }
+        (true, true) => {}
     }
 
-    // Create table for files with timestamp filter
-    if !files_with_filter.is_empty() {
-        let table = create_table(files_with_filter, Some(query.time_range)).await?;
-        tables.push(table);
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_schema::{DataType, Field};
+
+    use super::*;
+    use crate::service::search::index::Condition;
+
+    fn query_params(referenced_fields: Option<HashSet<String>>) -> QueryParams {
+        QueryParams {
+            trace_id: "test".to_string(),
+            org_id: "org".to_string(),
+            stream: TableReference::from("test"),
+            stream_type: StreamType::Logs,
+            stream_name: "test".to_string(),
+            time_range: (0, 1000),
+            work_group: None,
+            use_inverted_index: true,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields,
+        }
     }
 
-    Ok(tables)
+    #[test]
+    fn test_project_schema() {
+        let schema = Arc::new(Schema::new(
+            [TIMESTAMP_COL_NAME, "a", "b", "c", "log", "level", "unused"]
+                .into_iter()
+                .map(|name| Field::new(name, DataType::Utf8, true))
+                .collect::<Vec<_>>(),
+        ));
+        let mut index_condition = IndexCondition::new();
+        index_condition.add_condition(Condition::Equal("level".to_string(), "error".to_string()));
+        let fst_fields = vec!["log".to_string()];
+
+        // the unreferenced columns are dropped, the required ones are kept
+        let query = query_params(Some(HashSet::from(["b".to_string(), "a".to_string()])));
+        let projected = query.project_schema(&schema, Some(&index_condition), &fst_fields);
+        let names = projected
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![TIMESTAMP_COL_NAME, "a", "b", "log", "level"]);
+
+        // the projection is mapped to the same columns of the projected schema
+        let projection = remap_projection(Some(&vec![2, 1]), &schema, &projected).unwrap();
+        assert_eq!(projection, Some(vec![2, 1]));
+        assert!(remap_projection(Some(&vec![3]), &schema, &projected).is_err());
+
+        // all the columns are kept when the referenced columns are unknown
+        let query = query_params(None);
+        let projected = query.project_schema(&schema, Some(&index_condition), &fst_fields);
+        assert_eq!(projected.fields().len(), schema.fields().len());
+    }
 }
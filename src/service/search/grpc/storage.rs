@@ -18,24 +18,27 @@ use std::{collections::HashSet, sync::Arc};
 use anyhow::Context;
 use arrow_schema::Schema;
 use config::{
-    INDEX_FIELD_NAME_FOR_ALL, TIMESTAMP_COL_NAME,
+    Config, INDEX_FIELD_NAME_FOR_ALL, TIMESTAMP_COL_NAME,
     cluster::LOCAL_NODE,
     get_config, is_local_disk_storage,
     meta::{
         bitvec::BitVec,
         inverted_index::IndexOptimizeMode,
         search::{ScanStats, StorageType},
-        stream::{FileKey, StreamType},
+        stream::{FileKey, StreamCachePolicy, StreamCacheType, StreamType},
     },
     metrics::{self, QUERY_PARQUET_CACHE_RATIO_NODE},
     utils::{
         inverted_index::convert_parquet_file_name_to_tantivy_file,
+        json,
+        parquet::parse_file_key_columns,
         size::bytes_to_human_readable,
-        tantivy::tokenizer::{CollectType, O2_TOKENIZER, o2_tokenizer_build},
+        tantivy::tokenizer::{CollectType, register_fts_tokenizers},
         time::BASE_TIME,
     },
 };
-use datafusion::execution::cache::cache_manager::FileStatisticsCache;
+use dashmap::DashSet;
+use datafusion::{datasource::TableProvider, execution::cache::cache_manager::FileStatisticsCache};
 use futures::{StreamExt, stream};
 use hashbrown::HashMap;
 use infra::{
@@ -43,7 +46,9 @@ use infra::{
     errors::{Error, ErrorCodes},
 };
 use itertools::Itertools;
+use once_cell::sync::Lazy;
 use roaring::RoaringBitmap;
+use serde::Serialize;
 use tantivy::Directory;
 use tokio::sync::Semaphore;
 use tokio_stream::StreamExt as _;
@@ -53,10 +58,11 @@ use crate::service::{
     file_list,
     search::{
         grpc::{
+            storage_result_cache,
             tantivy_result::{TantivyMultiResult, TantivyMultiResultBuilder, TantivyResult},
             tantivy_result_cache::{self, CacheEntry},
         },
-        index::IndexCondition,
+        index::{IndexCondition, get_fts_fields},
         inspector::{SearchInspectorFieldsBuilder, search_inspector_fields},
     },
     tantivy::puffin_directory::{
@@ -66,6 +72,173 @@ use crate::service::{
     },
 };
 
+pub type StorageSearchTable =
+    Result<(Vec<Arc<dyn TableProvider>>, ScanStats, Option<StoragePlan>), Error>;
+
+/// The file pruning and partition decisions of a storage search, built when
+/// [`super::QueryParams::explain`] is set
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StoragePlan {
+    /// files in the file list
+    pub files: usize,
+    /// files left to scan after the inverted index search
+    pub files_loaded: usize,
+    pub files_pruned_by_index: usize,
+    /// loaded files outside the query time range, the timestamp filter skips them
+    pub files_pruned_by_time: usize,
+    /// loaded files partially overlapping the query time range, scanned with the timestamp
+    /// filter
+    pub files_with_time_filter: usize,
+    pub target_partitions: usize,
+    pub cache_type: String,
+    pub is_add_filter_back: bool,
+}
+
+impl StoragePlan {
+    fn new(
+        time_range: (i64, i64),
+        original_files_len: usize,
+        files: &[FileKey],
+        target_partitions: usize,
+        cache_type: file_data::CacheType,
+        is_add_filter_back: bool,
+    ) -> Self {
+        let (start_time, end_time) = time_range;
+        let mut plan = StoragePlan {
+            files: original_files_len,
+            files_loaded: files.len(),
+            files_pruned_by_index: original_files_len.saturating_sub(files.len()),
+            target_partitions,
+            cache_type: format!("{cache_type:?}"),
+            is_add_filter_back,
+            ..Default::default()
+        };
+        for file in files {
+            if file.meta.max_ts < start_time || file.meta.min_ts >= end_time {
+                plan.files_pruned_by_time += 1;
+            } else if file.meta.min_ts < start_time || file.meta.max_ts >= end_time {
+                plan.files_with_time_filter += 1;
+            }
+        }
+        plan
+    }
+}
+
+/// The files the inverted index couldn't filter on its own, datafusion evaluates the index
+/// condition on them again
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FilterBack {
+    /// the index filtered all the files
+    #[default]
+    None,
+    /// only the files with these keys, the others are filtered by the index
+    Files(HashSet<String>),
+    /// all the files, used when the failure can't be attributed to specific files
+    All,
+}
+
+impl FilterBack {
+    pub fn add_file(&mut self, key: &str) {
+        match self {
+            FilterBack::None => *self = FilterBack::Files(HashSet::from([key.to_string()])),
+            FilterBack::Files(keys) => {
+                keys.insert(key.to_string());
+            }
+            FilterBack::All => {}
+        }
+    }
+
+    /// Whether any file needs the filter
+    pub fn is_any(&self) -> bool {
+        !matches!(self, FilterBack::None)
+    }
+
+    /// Splits the files into the ones which need the filter and the ones which don't
+    pub fn partition(&self, files: Vec<FileKey>) -> (Vec<FileKey>, Vec<FileKey>) {
+        match self {
+            FilterBack::None => (vec![], files),
+            FilterBack::Files(keys) => files.into_iter().partition(|f| keys.contains(&f.key)),
+            FilterBack::All => (files, vec![]),
+        }
+    }
+}
+
+impl std::fmt::Display for FilterBack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterBack::None => write!(f, "none"),
+            FilterBack::Files(keys) => write!(f, "{} files", keys.len()),
+            FilterBack::All => write!(f, "all"),
+        }
+    }
+}
+
+/// Why the inverted index search kept or removed a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexFileDecision {
+    /// kept, the file has no index file
+    NoIndexFile,
+    /// removed, the file has no index file and the query only searches the indexed files
+    NoIndexFileSkipped,
+    /// kept with the matched rows
+    Matched,
+    /// removed, no rows matched
+    NotMatched,
+    /// removed, the result is computed from the index only
+    AnsweredByIndex,
+    /// kept without the matched rows, the index didn't reduce the rows enough
+    AddedBack,
+    /// kept, searching the index failed
+    Error,
+    /// removed, the time range group of the file already reached the query limit
+    LimitReached,
+    /// removed, the time range of the file is entirely outside the query time range
+    OutOfTimeRange,
+}
+
+/// The decision of the inverted index search for a file, recorded when
+/// [`super::QueryParams::explain_index`] is set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexFileExplain {
+    pub file: String,
+    pub decision: IndexFileDecision,
+}
+
+fn explain_file(
+    explain: &mut Option<Vec<IndexFileExplain>>,
+    file: &str,
+    decision: IndexFileDecision,
+) {
+    if let Some(explain) = explain {
+        explain.push(IndexFileExplain {
+            file: file.to_string(),
+            decision,
+        });
+    }
+}
+
+/// Records the decision for the files without one yet, used when the index search stops early
+/// and the files left are all kept
+fn explain_undecided_files<'a>(
+    explain: &mut Option<Vec<IndexFileExplain>>,
+    files: impl Iterator<Item = &'a String>,
+    decision: IndexFileDecision,
+) {
+    if let Some(explain) = explain {
+        let decided = explain
+            .iter()
+            .map(|e| e.file.clone())
+            .collect::<HashSet<_>>();
+        for file in files.filter(|f| !decided.contains(*f)) {
+            explain.push(IndexFileExplain {
+                file: file.to_string(),
+                decision,
+            });
+        }
+    }
+}
+
 /// search in remote object storage
 #[tracing::instrument(name = "service:search:grpc:storage", skip_all, fields(org_id = query.org_id, stream_name = query.stream_name))]
 #[allow(clippy::too_many_arguments)]
@@ -75,24 +248,25 @@ pub async fn search(
     file_list: &[FileKey],
     sorted_by_time: bool,
     file_stat_cache: Option<Arc<dyn FileStatisticsCache>>,
-    mut index_condition: Option<IndexCondition>,
-    mut fst_fields: Vec<String>,
+    index_condition: Option<IndexCondition>,
+    fst_fields: Vec<String>,
     idx_optimize_rule: Option<IndexOptimizeMode>,
-) -> super::SearchTable {
+) -> StorageSearchTable {
     let super::QueryParams {
         trace_id,
         org_id,
         stream_type,
         stream_name,
-        use_inverted_index,
         work_group,
+        explain,
         ..
     } = query.as_ref();
     let enter_span = tracing::span::Span::current();
     log::info!("[trace_id {trace_id}] search->storage: enter");
-    let mut files = file_list.to_vec();
+    let files = file_list.to_vec();
     if files.is_empty() {
-        return Ok((vec![], ScanStats::default(), HashSet::new()));
+        let plan = explain.then(StoragePlan::default);
+        return Ok((vec![], ScanStats::default(), plan));
     }
     let original_files_len = files.len();
     log::info!(
@@ -100,10 +274,176 @@ pub async fn search(
         files.len(),
     );
 
+    // the inverted index explain is only recorded when the files are loaded
+    let cache_key = if get_config().common.storage_result_cache_enabled && !query.explain_index {
+        let stream_settings = infra::schema::get_settings(org_id, stream_name, *stream_type).await;
+        let fts_exclude_fields =
+            infra::schema::get_stream_setting_fts_exclude_fields(&stream_settings);
+        Some(storage_result_cache::generate_cache_key(
+            &query,
+            &index_condition,
+            &fst_fields,
+            &fts_exclude_fields,
+            &idx_optimize_rule,
+        ))
+    } else {
+        None
+    };
+    let files_hash = cache_key
+        .as_ref()
+        .map(|_| storage_result_cache::files_hash(&files))
+        .unwrap_or_default();
+    let cached = cache_key
+        .as_ref()
+        .and_then(|key| storage_result_cache::GLOBAL_CACHE.get(key, files_hash));
+    let storage_result_cache::CacheEntry {
+        files,
+        mut scan_stats,
+        filter_back,
+    } = match cached {
+        Some(mut entry) => {
+            log::info!(
+                "[trace_id {trace_id}] search->storage: stream {org_id}/{stream_type}/{stream_name}, hit result cache, load files {}",
+                entry.files.len(),
+            );
+            // the inverted index isn't searched again
+            entry.scan_stats.idx_took = 0;
+            entry.scan_stats.index_cache_hits = 0;
+            entry.scan_stats.index_cache_misses = 0;
+            entry
+        }
+        None => {
+            let entry = load_files(
+                query.clone(),
+                files,
+                index_condition.clone(),
+                idx_optimize_rule,
+            )
+            .await?;
+            if let Some(key) = cache_key {
+                storage_result_cache::GLOBAL_CACHE.put(key, files_hash, entry.clone());
+            }
+            entry
+        }
+    };
+
+    // the cached files may be evicted since the entry was cached, so they are always loaded
+    let cache_type = load_files_to_cache(&query, &files, &mut scan_stats)
+        .instrument(enter_span.clone())
+        .await?;
+
+    let cfg = get_config();
+
+    // set target partitions based on cache type
+    let target_partitions = if cache_type == file_data::CacheType::None {
+        cfg.limit.query_thread_num
+    } else {
+        cfg.limit.cpu_num
+    };
+    let target_partitions = clamp_target_partitions(
+        target_partitions,
+        cfg.limit.min_query_partitions,
+        files.len(),
+    );
+
+    log::debug!("search->storage: session target_partitions: {target_partitions}");
+
+    let plan = explain.then(|| {
+        StoragePlan::new(
+            query.time_range,
+            original_files_len,
+            &files,
+            target_partitions,
+            cache_type,
+            filter_back.is_any(),
+        )
+    });
+
+    let session = config::meta::search::Session {
+        id: format!("{trace_id}-storage"),
+        storage_type: StorageType::Memory,
+        work_group: work_group.clone(),
+        target_partitions,
+    };
+
+    // only the files the index couldn't filter get the filter added back, the others are
+    // read without it
+    let (files_with_filter, files_without_filter) = filter_back.partition(files);
+
+    let start = std::time::Instant::now();
+    let mut tables = super::create_tables_from_files(
+        files_without_filter,
+        session.clone(),
+        query.clone(),
+        schema.clone(),
+        sorted_by_time,
+        file_stat_cache.clone(),
+        None,
+        vec![],
+        || {},
+    )
+    .await?;
+    if !files_with_filter.is_empty() {
+        let session = config::meta::search::Session {
+            id: format!("{}-filter", session.id),
+            ..session
+        };
+        tables.extend(
+            super::create_tables_from_files(
+                files_with_filter,
+                session,
+                query.clone(),
+                schema,
+                sorted_by_time,
+                file_stat_cache,
+                index_condition,
+                fst_fields,
+                || {},
+            )
+            .await?,
+        );
+    }
+
+    log::info!(
+        "{}",
+        search_inspector_fields(
+            format!(
+                "[trace_id {trace_id}] search->storage: create tables took: {} ms",
+                start.elapsed().as_millis()
+            ),
+            SearchInspectorFieldsBuilder::new()
+                .node_name(LOCAL_NODE.name.clone())
+                .component("storage create tables".to_string())
+                .search_role("follower".to_string())
+                .duration(start.elapsed().as_millis() as usize)
+                .build()
+        )
+    );
+    Ok((tables, scan_stats, plan))
+}
+
+/// Prunes the file list by the inverted index and computes the scan stats of the files left
+async fn load_files(
+    query: Arc<super::QueryParams>,
+    mut files: Vec<FileKey>,
+    index_condition: Option<IndexCondition>,
+    idx_optimize_rule: Option<IndexOptimizeMode>,
+) -> Result<storage_result_cache::CacheEntry, Error> {
+    let super::QueryParams {
+        trace_id,
+        org_id,
+        stream_type,
+        stream_name,
+        use_inverted_index,
+        ..
+    } = query.as_ref();
+    let original_files_len = files.len();
     let mut idx_took = 0;
-    let mut is_add_filter_back = false;
+    let mut filter_back = FilterBack::None;
+    let mut idx_scan_stats = ScanStats::default();
     if *use_inverted_index && !index_condition.as_ref().unwrap().is_condition_all() {
-        (idx_took, is_add_filter_back, ..) = tantivy_search(
+        let idx_explain;
+        (idx_took, filter_back, _, idx_scan_stats, idx_explain) = tantivy_search(
             query.clone(),
             &mut files,
             index_condition.clone(),
@@ -111,6 +451,24 @@ pub async fn search(
         )
         .await?;
 
+        if !idx_explain.is_empty() {
+            let decisions = idx_explain.iter().map(|e| e.decision).counts();
+            log::info!(
+                "{}",
+                search_inspector_fields(
+                    format!(
+                        "[trace_id {trace_id}] search->storage: stream {org_id}/{stream_type}/{stream_name}, inverted index explain: {decisions:?}",
+                    ),
+                    SearchInspectorFieldsBuilder::new()
+                        .node_name(LOCAL_NODE.name.clone())
+                        .component("storage inverted index explain".to_string())
+                        .search_role("follower".to_string())
+                        .desc(json::to_string(&idx_explain).unwrap_or_default())
+                        .build()
+                )
+            );
+        }
+
         log::info!(
             "{}",
             search_inspector_fields(
@@ -132,13 +490,6 @@ pub async fn search(
         );
     }
 
-    // set index_condition to None, means we do not need to add filter back
-    if !is_add_filter_back {
-        index_condition = None;
-        fst_fields = vec![];
-    }
-
-    let cfg = get_config();
     let mut scan_stats = match file_list::calculate_files_size(&files).await {
         Ok(size) => size,
         Err(err) => {
@@ -155,6 +506,30 @@ pub async fn search(
         scan_stats.original_size,
         scan_stats.compressed_size
     );
+    scan_stats.index_cache_hits = idx_scan_stats.index_cache_hits;
+    scan_stats.index_cache_misses = idx_scan_stats.index_cache_misses;
+    scan_stats.idx_took = idx_took as i64;
+
+    Ok(storage_result_cache::CacheEntry {
+        files,
+        scan_stats,
+        filter_back,
+    })
+}
+
+/// Loads the files into the local cache, returns the cache type the files are read from
+async fn load_files_to_cache(
+    query: &super::QueryParams,
+    files: &[FileKey],
+    scan_stats: &mut ScanStats,
+) -> Result<file_data::CacheType, Error> {
+    let super::QueryParams {
+        trace_id,
+        org_id,
+        stream_type,
+        stream_name,
+        ..
+    } = query;
 
     // check memory circuit breaker
     ingester::check_memory_circuit_breaker().map_err(|e| Error::ResourceError(e.to_string()))?;
@@ -175,10 +550,10 @@ pub async fn search(
                 )
             })
             .collect_vec(),
-        &mut scan_stats,
+        scan_stats,
         "parquet",
+        Some(query.stream_type),
     )
-    .instrument(enter_span.clone())
     .await;
 
     // report cache hit and miss metrics
@@ -189,7 +564,8 @@ pub async fn search(
         .with_label_values(&[org_id.as_str(), stream_type.as_str(), "parquet"])
         .inc_by(cache_misses);
 
-    scan_stats.idx_took = idx_took as i64;
+    scan_stats.parquet_cache_hits = cache_hits as i64;
+    scan_stats.parquet_cache_misses = cache_misses as i64;
     scan_stats.querier_files = scan_stats.files;
     let cached_ratio = (scan_stats.querier_memory_cached_files
         + scan_stats.querier_disk_cached_files) as f64
@@ -234,52 +610,7 @@ pub async fn search(
             .observe(cached_ratio);
     }
 
-    // set target partitions based on cache type
-    let target_partitions = if cache_type == file_data::CacheType::None {
-        cfg.limit.query_thread_num
-    } else {
-        cfg.limit.cpu_num
-    };
-
-    log::debug!("search->storage: session target_partitions: {target_partitions}");
-
-    let session = config::meta::search::Session {
-        id: format!("{trace_id}-storage"),
-        storage_type: StorageType::Memory,
-        work_group: work_group.clone(),
-        target_partitions,
-    };
-
-    let start = std::time::Instant::now();
-    let tables = super::create_tables_from_files(
-        files,
-        session,
-        query.clone(),
-        schema,
-        sorted_by_time,
-        file_stat_cache,
-        index_condition,
-        fst_fields,
-        || {},
-    )
-    .await?;
-
-    log::info!(
-        "{}",
-        search_inspector_fields(
-            format!(
-                "[trace_id {trace_id}] search->storage: create tables took: {} ms",
-                start.elapsed().as_millis()
-            ),
-            SearchInspectorFieldsBuilder::new()
-                .node_name(LOCAL_NODE.name.clone())
-                .component("storage create tables".to_string())
-                .search_role("follower".to_string())
-                .duration(start.elapsed().as_millis() as usize)
-                .build()
-        )
-    );
-    Ok((tables, scan_stats, HashSet::new()))
+    Ok(cache_type)
 }
 
 #[tracing::instrument(name = "service:search:grpc:storage:cache_files", skip_all)]
@@ -288,13 +619,15 @@ pub async fn cache_files(
     files: &[(i64, &String, &String, i64, i64)],
     scan_stats: &mut ScanStats,
     file_type: &str,
+    stream_type: Option<StreamType>,
 ) -> (file_data::CacheType, u64, u64) {
     // check how many files already cached
     let mut cached_files = HashSet::with_capacity(files.len());
+    let mut pending_files = Vec::new();
     let (mut cache_hits, mut cache_misses) = (0, 0);
 
     let start = std::time::Instant::now();
-    for (_id, _account, file, _size, max_ts) in files.iter() {
+    for (_id, _account, file, size, max_ts) in files.iter() {
         if file_data::memory::exist(file).await {
             scan_stats.querier_memory_cached_files += 1;
             cached_files.insert(file);
@@ -303,24 +636,18 @@ pub async fn cache_files(
             scan_stats.querier_disk_cached_files += 1;
             cached_files.insert(file);
             cache_hits += 1;
+        } else if crate::job::is_download_pending(file) {
+            pending_files.push((file, *size));
         } else {
             cache_misses += 1;
         };
 
-        // Record file access metrics
-        let stream_type = if file_type == "index" {
-            config::meta::stream::StreamType::Index
-        } else {
-            // Determine stream type from the file path
-            if file.contains("/logs/") {
-                config::meta::stream::StreamType::Logs
-            } else if file.contains("/metrics/") {
-                config::meta::stream::StreamType::Metrics
-            } else if file.contains("/traces/") {
-                config::meta::stream::StreamType::Traces
-            } else {
-                config::meta::stream::StreamType::Logs // Default
-            }
+        // Record file access metrics, the stream type is inferred from the file path only
+        // when the caller doesn't know it
+        let stream_type = match stream_type {
+            Some(stream_type) => stream_type,
+            None if file_type == "index" => StreamType::Index,
+            None => stream_type_from_file(file),
         };
 
         let current_time = chrono::Utc::now().timestamp_micros();
@@ -334,6 +661,31 @@ pub async fn cache_files(
         }
     }
 
+    // wait for the files which are being downloaded by other queries
+    if !pending_files.is_empty() {
+        let timeout =
+            std::time::Duration::from_millis(get_config().limit.file_download_wait_timeout_ms);
+        futures::future::join_all(
+            pending_files
+                .iter()
+                .map(|(file, _)| crate::job::wait_for_download(file, timeout)),
+        )
+        .await;
+        for (file, _) in pending_files {
+            if file_data::memory::exist(file).await {
+                scan_stats.querier_memory_cached_files += 1;
+                cached_files.insert(file);
+                cache_hits += 1;
+            } else if file_data::disk::exist(file).await {
+                scan_stats.querier_disk_cached_files += 1;
+                cached_files.insert(file);
+                cache_hits += 1;
+            } else {
+                cache_misses += 1;
+            }
+        }
+    }
+
     let check_cache_took = start.elapsed().as_millis() as usize;
     if check_cache_took > 1000 {
         log::warn!(
@@ -347,23 +699,23 @@ pub async fn cache_files(
         return (file_data::CacheType::Disk, cache_hits, cache_misses);
     }
 
-    // check cache size
+    // check cache size, the cache policy of the stream overrides the global config
     let cfg = get_config();
-    let cache_type = if cfg.memory_cache.enabled
-        && scan_stats.compressed_size < cfg.memory_cache.skip_size as i64
-    {
-        // if scan_compressed_size < ZO_MEMORY_CACHE_SKIP_SIZE, use memory cache
-        file_data::CacheType::Memory
-    } else if !is_local_disk_storage()
-        && cfg.disk_cache.enabled
-        && scan_stats.compressed_size < cfg.disk_cache.skip_size as i64
-    {
-        // if scan_compressed_size < ZO_DISK_CACHE_SKIP_SIZE, use disk cache
-        file_data::CacheType::Disk
-    } else {
+    let cache_policy = files
+        .first()
+        .and_then(|(_, _, file, ..)| parse_file_key_columns(file).ok())
+        .and_then(|(stream_key, ..)| infra::schema::get_stream_settings_atomic(&stream_key))
+        .and_then(|settings| settings.cache_policy);
+    let cache_type = select_cache_type(
+        &cfg,
+        cache_policy.as_ref(),
+        scan_stats.compressed_size,
+        is_local_disk_storage(),
+    );
+    if cache_type == file_data::CacheType::None {
         // no cache, the files are too big than cache size
         return (file_data::CacheType::None, cache_hits, cache_misses);
-    };
+    }
 
     let trace_id = trace_id.to_string();
     let files = files
@@ -379,6 +731,7 @@ pub async fn cache_files(
     let file_type = file_type.to_string();
     tokio::spawn(async move {
         let files_num = files.len();
+        let files_size = files.iter().map(|(_, _, _, size, _)| *size).sum::<i64>();
         for (id, account, file, size, ts) in files {
             if let Err(e) = crate::job::queue_download(
                 trace_id.clone(),
@@ -397,34 +750,129 @@ pub async fn cache_files(
             }
         }
         log::info!(
-            "[trace_id {trace_id}] search->storage: successfully enqueued {files_num} files of {file_type} for background download into {cache_type:?}",
+            "[trace_id {trace_id}] search->storage: successfully enqueued {files_num} files of {file_type}, size: {}, for background download into {cache_type:?}",
+            bytes_to_human_readable(files_size as f64)
         );
     });
 
-    // if cached file less than 50% of the total files, return None
-    if scan_stats.querier_memory_cached_files + scan_stats.querier_disk_cached_files < files_num / 2
-    {
+    // if cached file less than the download ratio threshold of the total files, return None
+    let cached_files_num =
+        scan_stats.querier_memory_cached_files + scan_stats.querier_disk_cached_files;
+    if is_cached_ratio_too_low(
+        cached_files_num,
+        files_num,
+        cfg.disk_cache.download_ratio_threshold,
+    ) {
         (file_data::CacheType::None, cache_hits, cache_misses)
     } else {
         (cache_type, cache_hits, cache_misses)
     }
 }
 
+/// Returns the stream type of the file path `files/{org}/{stream_type}/{stream}/...`
+fn stream_type_from_file(file: &str) -> StreamType {
+    file.split('/')
+        .nth(2)
+        .map(StreamType::from)
+        .unwrap_or_default()
+}
+
+/// Selects the cache for downloading the files by the scan size. The skip sizes of the stream
+/// cache policy replace `ZO_MEMORY_CACHE_SKIP_SIZE` and `ZO_DISK_CACHE_SKIP_SIZE`, a forced
+/// cache type is used regardless of the scan size as long as that cache is enabled.
+fn select_cache_type(
+    cfg: &Config,
+    cache_policy: Option<&StreamCachePolicy>,
+    scan_size: i64,
+    local_disk: bool,
+) -> file_data::CacheType {
+    let memory_enabled = cfg.memory_cache.enabled;
+    let disk_enabled = !local_disk && cfg.disk_cache.enabled;
+    match cache_policy.and_then(|p| p.cache_type) {
+        Some(StreamCacheType::Memory) if memory_enabled => return file_data::CacheType::Memory,
+        Some(StreamCacheType::Disk) if disk_enabled => return file_data::CacheType::Disk,
+        Some(_) => return file_data::CacheType::None,
+        None => {}
+    }
+
+    let memory_skip_size = cache_policy
+        .and_then(|p| p.memory_skip_size)
+        .unwrap_or(cfg.memory_cache.skip_size);
+    let disk_skip_size = cache_policy
+        .and_then(|p| p.disk_skip_size)
+        .unwrap_or(cfg.disk_cache.skip_size);
+    if memory_enabled && scan_size < memory_skip_size as i64 {
+        file_data::CacheType::Memory
+    } else if disk_enabled && scan_size < disk_skip_size as i64 {
+        file_data::CacheType::Disk
+    } else {
+        file_data::CacheType::None
+    }
+}
+
+/// Returns true if the percent of the cached files is less than the threshold, threshold 0 means
+/// always use the cache and 100 means use the cache only when all the files are cached.
+fn is_cached_ratio_too_low(cached_files_num: i64, files_num: i64, threshold: usize) -> bool {
+    let threshold = threshold.min(100) as i64;
+    cached_files_num * 100 < files_num * threshold
+}
+
+/// Handles the files which have no index file, they are kept and filtered by datafusion, or
+/// removed if the query only searches the indexed files. Returns the number of removed files.
+fn handle_no_index_files(
+    file_list_map: &mut HashMap<String, FileKey>,
+    indexed_files: &HashSet<String>,
+    strict_index_only: bool,
+    filter_back: &mut FilterBack,
+    explain: &mut Option<Vec<IndexFileExplain>>,
+) -> usize {
+    let no_index_files = file_list_map
+        .keys()
+        .filter(|key| !indexed_files.contains(*key))
+        .cloned()
+        .collect_vec();
+    for key in no_index_files.iter() {
+        if strict_index_only {
+            explain_file(explain, key, IndexFileDecision::NoIndexFileSkipped);
+            file_list_map.remove(key);
+        } else {
+            explain_file(explain, key, IndexFileDecision::NoIndexFile);
+            filter_back.add_file(key);
+        }
+    }
+    if strict_index_only {
+        no_index_files.len()
+    } else {
+        0
+    }
+}
+
 /// Filter file list using inverted index
 /// This function will load the index file corresponding to each file in the file list.
 /// FSTs in those files are used to match the incoming query in `SearchRequest`.
 /// If the query does not match any FST in the index file, the file will be filtered out.
 /// If the query does match then the segment IDs for the file will be updated.
-/// If the query not find corresponding index file, the file will *not* be filtered out.
+/// If the query not find corresponding index file, the file will *not* be filtered out, unless
+/// [`super::QueryParams::strict_index_only`] is set.
 #[tracing::instrument(name = "service:search:grpc:storage:tantivy_search", skip_all)]
 pub async fn tantivy_search(
     query: Arc<super::QueryParams>,
     file_list: &mut Vec<FileKey>,
     index_condition: Option<IndexCondition>,
     idx_optimize_mode: Option<IndexOptimizeMode>,
-) -> Result<(usize, bool, TantivyMultiResult), Error> {
+) -> Result<
+    (
+        usize,
+        FilterBack,
+        TantivyMultiResult,
+        ScanStats,
+        Vec<IndexFileExplain>,
+    ),
+    Error,
+> {
     let start = std::time::Instant::now();
     let cfg = get_config();
+    let mut explain = query.explain_index.then(Vec::new);
 
     // Cache the corresponding Index files
     let mut scan_stats = ScanStats::new();
@@ -445,6 +893,27 @@ pub async fn tantivy_search(
         })
         .collect_vec();
     scan_stats.querier_files = index_file_names.len() as i64;
+    // the files without an index file are filtered by datafusion
+    let mut filter_back = FilterBack::None;
+    if file_list_map.len() != index_file_names.len() {
+        let indexed_files = index_file_names
+            .iter()
+            .map(|(_, f)| f.key.clone())
+            .collect::<HashSet<_>>();
+        let skipped = handle_no_index_files(
+            &mut file_list_map,
+            &indexed_files,
+            query.strict_index_only,
+            &mut filter_back,
+            &mut explain,
+        );
+        if skipped > 0 {
+            log::warn!(
+                "[trace_id {}] search->tantivy: strict index only, skipped {skipped} files without index file, their data is not searched",
+                query.trace_id,
+            );
+        }
+    }
     let (cache_type, cache_hits, cache_misses) = cache_files(
         &query.trace_id,
         &index_file_names
@@ -453,6 +922,8 @@ pub async fn tantivy_search(
             .collect_vec(),
         &mut scan_stats,
         "index",
+        // the index files are counted apart from the data files of the stream
+        Some(StreamType::Index),
     )
     .await;
 
@@ -463,6 +934,8 @@ pub async fn tantivy_search(
     metrics::QUERY_DISK_CACHE_MISS_COUNT
         .with_label_values(&[query.org_id.as_str(), query.stream_type.as_str(), "index"])
         .inc_by(cache_misses);
+    scan_stats.index_cache_hits = cache_hits as i64;
+    scan_stats.index_cache_misses = cache_misses as i64;
 
     let cached_ratio = (scan_stats.querier_memory_cached_files
         + scan_stats.querier_disk_cached_files) as f64
@@ -516,71 +989,113 @@ pub async fn tantivy_search(
     } else {
         cfg.limit.query_index_thread_num
     };
+    let target_partitions = clamp_target_partitions(
+        target_partitions,
+        cfg.limit.min_query_partitions,
+        index_file_names.len(),
+    );
+
+    // 0 means never skip the tantivy result even if the matched docs are too many
+    let skip_threshold = if query.disable_index_skip_threshold {
+        log::info!(
+            "[trace_id {}] search->tantivy: inverted index skip threshold is disabled for this query",
+            query.trace_id
+        );
+        0
+    } else {
+        cfg.limit.inverted_index_skip_threshold
+    };
 
     let search_start = std::time::Instant::now();
-    let mut is_add_filter_back = file_list_map.len() != index_file_names.len();
     let time_range = query.time_range;
+    let stream_settings =
+        infra::schema::get_settings(&query.org_id, &query.stream_name, query.stream_type).await;
+    let fts_exclude_fields = Arc::new(infra::schema::get_stream_setting_fts_exclude_fields(
+        &stream_settings,
+    ));
     let index_parquet_files = index_file_names.into_iter().map(|(_, f)| f).collect_vec();
-    let (index_parquet_files, query_limit) =
-        partition_tantivy_files(index_parquet_files, &idx_optimize_mode, target_partitions);
+    let mut out_of_range_files = index_parquet_files
+        .iter()
+        .map(|f| f.key.clone())
+        .collect::<HashSet<_>>();
+    // the index files are fetched from the storage, so the concurrency can be tuned apart from
+    // the cpu bound target partitions, the semaphore still bounds the open tantivy readers
+    let search_concurrency = index_search_concurrency(
+        cfg.limit.inverted_index_search_concurrency,
+        target_partitions,
+    );
+    // the files of a group are searched at the same time, so the groups are sized by the
+    // search concurrency
+    let (index_parquet_files, query_limit, file_group_ids) = partition_tantivy_files(
+        index_parquet_files,
+        &idx_optimize_mode,
+        search_concurrency,
+        time_range,
+    );
+    // the files dropped by the partition are outside the query time range and can't match
+    for file in index_parquet_files.iter().flatten() {
+        out_of_range_files.remove(&file.key);
+    }
+    for key in out_of_range_files {
+        explain_file(&mut explain, &key, IndexFileDecision::OutOfTimeRange);
+        file_list_map.remove(&key);
+    }
 
-    let mut no_more_files = false;
+    // the hits of each time range group, a group stops searching once it alone reached the limit
+    let mut group_hits: HashMap<usize, usize> = HashMap::new();
     let mut tantivy_result_builder = TantivyMultiResultBuilder::new(&idx_optimize_mode);
+    let mut prune_stats = IndexPruneStats::default();
     let group_num = index_parquet_files.first().unwrap_or(&vec![]).len();
     let max_group_len = index_parquet_files.len();
 
     log::info!(
-        "[trace_id {}] search->tantivy: target_partitions: {target_partitions}, group_num: {group_num}, max_group_len: {max_group_len}",
+        "[trace_id {}] search->tantivy: target_partitions: {target_partitions}, search_concurrency: {search_concurrency}, group_num: {group_num}, max_group_len: {max_group_len}",
         query.trace_id,
     );
 
     for file_group in index_parquet_files {
-        if no_more_files {
-            // delete the rest of the files
-            for file in file_group {
-                file_list_map.remove(&file.key);
-            }
+        let (file_group, skipped_files) =
+            skip_satisfied_groups(file_group, &file_group_ids, &group_hits, query_limit);
+        // delete the rest of the files of the groups which already reached the limit
+        for file in skipped_files {
+            explain_file(&mut explain, &file.key, IndexFileDecision::LimitReached);
+            file_list_map.remove(&file.key);
+        }
+        if file_group.is_empty() {
             continue;
         }
 
-        // Spawn a task for each group of files get row_id from index
-        let mut tasks = Vec::new();
-        let semaphore = std::sync::Arc::new(Semaphore::new(target_partitions));
-        for file in file_group {
+        // Spawn a task for each file of the group to get row_id from index
+        let mut tasks = spawn_index_searches(file_group, search_concurrency, |file| {
             let trace_id = query.trace_id.to_string();
             let index_condition_clone = index_condition.clone();
             let idx_optimize_rule_clone = idx_optimize_mode.clone();
-            let semaphore_clone = semaphore.clone();
-            let task = tokio::task::spawn(async move {
-                let permit = semaphore_clone.acquire_owned().await.unwrap();
+            let fts_exclude_fields = fts_exclude_fields.clone();
+            async move {
                 let ret = search_tantivy_index(
                     &trace_id,
                     time_range,
                     index_condition_clone,
                     idx_optimize_rule_clone,
+                    fts_exclude_fields,
+                    skip_threshold,
                     &file,
                 )
                 .await;
-                drop(permit);
-                match ret {
-                    Ok(ret) => Ok(ret),
-                    Err(e) => {
-                        log::error!(
-                            "[trace_id {trace_id}] search->tantivy: error filtering via index: {}, index_size: {}, error: {e:?}",
-                            file.key,
-                            file.meta.index_size,
-                        );
-                        Err(e)
-                    }
+                if let Err(e) = &ret {
+                    log::error!(
+                        "[trace_id {trace_id}] search->tantivy: error filtering via index: {}, index_size: {}, error: {e:?}",
+                        file.key,
+                        file.meta.index_size,
+                    );
                 }
-            });
-            tasks.push(task)
-        }
+                (file.key, ret)
+            }
+        });
 
         // if more than cpu_num's file returned many row_ids, we skip tantivy search
         let mut threshold_num = cfg.limit.cpu_num;
         let mut total_row_ids_percent = 0;
-        let mut tasks = stream::iter(tasks).buffer_unordered(target_partitions);
         while let Some(result) = match tasks.try_next().await {
             Err(e) => {
                 let took = start.elapsed().as_millis() as usize;
@@ -588,16 +1103,41 @@ pub async fn tantivy_search(
                     "[trace_id {}] search->tantivy: error filtering via index, error: {e:?}, took: {took} ms",
                     query.trace_id,
                 );
-                // search error, need add filter back
-                return Ok((took, true, TantivyMultiResult::RowNums(0)));
+                // the failed task can't be attributed to a file, add filter back to all
+                explain_undecided_files(
+                    &mut explain,
+                    file_list_map.keys(),
+                    IndexFileDecision::Error,
+                );
+                file_list.extend(file_list_map.into_values());
+                return Ok((
+                    took,
+                    FilterBack::All,
+                    TantivyMultiResult::RowNums(0),
+                    scan_stats,
+                    explain.unwrap_or_default(),
+                ));
             }
             Ok(result) => result,
         } {
             // Each result corresponds to a file in the file list
+            let (file_key, result) = result;
             match result {
-                Ok((file_name, result)) => {
+                Ok((file_name, result, is_partial, origin)) => {
+                    // the residual condition of the partial search needs to be filtered by
+                    // datafusion
+                    if is_partial {
+                        filter_back.add_file(&file_key);
+                    }
                     if file_name.is_empty() {
                         // no need inverted index for this file, need add filter back
+                        explain_file(&mut explain, &file_key, IndexFileDecision::AddedBack);
+                        // the index can't compute the optimized result of this file, it doesn't
+                        // count towards the skip threshold
+                        if result.percent() == 0 {
+                            filter_back.add_file(&file_key);
+                            continue;
+                        }
                         let took = start.elapsed().as_millis() as usize;
                         threshold_num -= 1;
                         total_row_ids_percent += result.percent();
@@ -607,37 +1147,79 @@ pub async fn tantivy_search(
                                 query.trace_id,
                                 total_row_ids_percent as f64 / cfg.limit.cpu_num as f64,
                             );
+                            // the files not searched yet are kept without the index as well
+                            explain_undecided_files(
+                                &mut explain,
+                                file_list_map.keys(),
+                                IndexFileDecision::AddedBack,
+                            );
                             file_list.extend(file_list_map.into_values());
-                            return Ok((took, true, TantivyMultiResult::RowNums(0)));
+                            return Ok((
+                                took,
+                                FilterBack::All,
+                                TantivyMultiResult::RowNums(0),
+                                scan_stats,
+                                explain.unwrap_or_default(),
+                            ));
                         }
-                        is_add_filter_back = true;
+                        filter_back.add_file(&file_key);
                         continue;
                     }
                     match result {
                         TantivyResult::RowIdsBitVec(num_rows, bitvec) => {
+                            prune_stats.add(origin, num_rows > 0);
                             if num_rows == 0 {
                                 // if the bitmap is empty then we remove the file from the list
+                                explain_file(
+                                    &mut explain,
+                                    &file_key,
+                                    IndexFileDecision::NotMatched,
+                                );
                                 file_list_map.remove(&file_name);
                             } else {
+                                explain_file(&mut explain, &file_key, IndexFileDecision::Matched);
                                 // Replace the segment IDs in the existing `FileKey` with the found
                                 tantivy_result_builder.add_row_nums(num_rows as u64);
+                                if let Some(group_id) = file_group_ids.get(&file_name) {
+                                    *group_hits.entry(*group_id).or_default() += num_rows;
+                                }
                                 let file = file_list_map.get_mut(&file_name).unwrap();
                                 file.with_segment_ids(bitvec);
                             }
                         }
                         TantivyResult::Count(count) => {
+                            explain_file(
+                                &mut explain,
+                                &file_key,
+                                IndexFileDecision::AnsweredByIndex,
+                            );
                             tantivy_result_builder.add_row_nums(count as u64);
                             file_list_map.remove(&file_name); // maybe we do not need to remove it?
                         }
                         TantivyResult::Histogram(histogram) => {
+                            explain_file(
+                                &mut explain,
+                                &file_key,
+                                IndexFileDecision::AnsweredByIndex,
+                            );
                             tantivy_result_builder.add_histogram(histogram);
                             file_list_map.remove(&file_name);
                         }
                         TantivyResult::TopN(top_n) => {
+                            explain_file(
+                                &mut explain,
+                                &file_key,
+                                IndexFileDecision::AnsweredByIndex,
+                            );
                             tantivy_result_builder.add_top_n(top_n);
                             file_list_map.remove(&file_name);
                         }
                         TantivyResult::Distinct(distinct) => {
+                            explain_file(
+                                &mut explain,
+                                &file_key,
+                                IndexFileDecision::AnsweredByIndex,
+                            );
                             tantivy_result_builder.add_distinct(distinct);
                             file_list_map.remove(&file_name);
                         }
@@ -651,15 +1233,13 @@ pub async fn tantivy_search(
                         "[trace_id {}] search->tantivy: error filtering via index. Keep file to search, error: {e}",
                         query.trace_id,
                     );
-                    is_add_filter_back = true;
+                    // only this file is filtered by datafusion, the others keep the index result
+                    explain_file(&mut explain, &file_key, IndexFileDecision::Error);
+                    filter_back.add_file(&file_key);
                     continue;
                 }
             }
         }
-        // if limit is set and total hits exceed the limit, we stop searching
-        if query_limit > 0 && tantivy_result_builder.num_rows() > query_limit {
-            no_more_files = true;
-        }
     }
 
     // get the result
@@ -669,12 +1249,13 @@ pub async fn tantivy_search(
         "{}",
         search_inspector_fields(
             format!(
-                "[trace_id {}] search->tantivy: total hits for index_condition: {:?} found {}, is_add_filter_back: {}, file_num: {}, took: {} ms",
+                "[trace_id {}] search->tantivy: total hits for index_condition: {:?} found {}, filter_back: {}, file_num: {}, index_prune: {}, took: {} ms",
                 query.trace_id,
                 index_condition,
                 tantivy_result,
-                is_add_filter_back,
+                filter_back,
                 file_list_map.len(),
+                prune_stats,
                 search_start.elapsed().as_millis()
             ),
             SearchInspectorFieldsBuilder::new()
@@ -683,10 +1264,11 @@ pub async fn tantivy_search(
                 .search_role("follower".to_string())
                 .duration(search_start.elapsed().as_millis() as usize)
                 .desc(format!(
-                    "found {}, is_add_filter_back: {}, file_num: {}",
+                    "found {}, filter_back: {}, file_num: {}, index_prune: {}",
                     tantivy_result,
-                    is_add_filter_back,
+                    filter_back,
                     file_list_map.len(),
+                    prune_stats,
                 ))
                 .build()
         )
@@ -695,8 +1277,10 @@ pub async fn tantivy_search(
     file_list.extend(file_list_map.into_values());
     Ok((
         start.elapsed().as_millis() as usize,
-        is_add_filter_back,
+        filter_back,
         tantivy_result,
+        scan_stats,
+        explain.unwrap_or_default(),
     ))
 }
 
@@ -717,13 +1301,70 @@ pub async fn get_tantivy_directory(
     Ok(PuffinDirReader::from_path(file_account, source).await?)
 }
 
+/// A file flagged with an index whose tantivy file is missing or can't be opened
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexFileMismatch {
+    pub file: String,
+    /// empty if the tantivy file name can't be derived from the parquet file name
+    pub index_file: String,
+    pub error: String,
+}
+
+/// Checks that the tantivy file of every file with `index_size > 0` can be opened and returns
+/// the files where it can't, these files fall back to datafusion on every search. The file
+/// list and the index files aren't changed.
+pub async fn check_tantivy_index_files(
+    trace_id: &str,
+    files: &[FileKey],
+) -> Vec<IndexFileMismatch> {
+    let cfg = get_config();
+    let checked = stream::iter(files.iter().filter(|f| f.meta.index_size > 0).map(
+        |file| async move {
+            let Some(index_file) = convert_parquet_file_name_to_tantivy_file(&file.key) else {
+                return Some(IndexFileMismatch {
+                    file: file.key.clone(),
+                    index_file: String::new(),
+                    error: "can't convert the file name to a tantivy file name".to_string(),
+                });
+            };
+            match get_tantivy_directory(trace_id, &file.account, &index_file, file.meta.index_size)
+                .await
+            {
+                Ok(_) => None,
+                Err(e) => Some(IndexFileMismatch {
+                    file: file.key.clone(),
+                    index_file,
+                    error: e.to_string(),
+                }),
+            }
+        },
+    ))
+    .buffer_unordered(cfg.limit.cpu_num);
+    // tokio_stream::StreamExt is in scope too
+    let mismatches = StreamExt::collect::<Vec<_>>(checked)
+        .await
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if !mismatches.is_empty() {
+        log::warn!(
+            "[trace_id {trace_id}] search->tantivy: {} of {} files have a broken index file",
+            mismatches.len(),
+            files.len()
+        );
+    }
+    mismatches
+}
+
 async fn search_tantivy_index(
     trace_id: &str,
     time_range: (i64, i64),
     index_condition: Option<IndexCondition>,
     idx_optimize_rule: Option<IndexOptimizeMode>,
+    fts_exclude_fields: Arc<Vec<String>>,
+    skip_threshold: usize,
     parquet_file: &FileKey,
-) -> anyhow::Result<(String, TantivyResult)> {
+) -> anyhow::Result<(String, TantivyResult, bool, Option<IndexFieldOrigin>)> {
     let file_account = parquet_file.account.clone();
     let Some(ttv_file_name) = convert_parquet_file_name_to_tantivy_file(&parquet_file.key) else {
         return Err(anyhow::anyhow!(
@@ -738,12 +1379,21 @@ async fn search_tantivy_index(
         metrics::TANTIVY_RESULT_CACHE_REQUESTS_TOTAL
             .with_label_values::<&str>(&[])
             .inc();
-        cache_key = generate_cache_key(&index_condition, &idx_optimize_rule, parquet_file);
+        cache_key = generate_cache_key(
+            &index_condition,
+            &idx_optimize_rule,
+            &fts_exclude_fields,
+            parquet_file,
+        );
         if let Some(result) = tantivy_result_cache::GLOBAL_CACHE.get(&cache_key) {
             metrics::TANTIVY_RESULT_CACHE_HITS_TOTAL
                 .with_label_values::<&str>(&[])
                 .inc();
-            return Ok((parquet_file.key.to_string(), result));
+            // only the full condition is cached
+            let origin = index_condition
+                .as_ref()
+                .and_then(IndexFieldOrigin::from_condition);
+            return Ok((parquet_file.key.to_string(), result, false, origin));
         }
     }
 
@@ -764,9 +1414,26 @@ async fn search_tantivy_index(
     let reader_directory: Box<dyn Directory> = Box::new(cache_dir);
 
     let index = tantivy::Index::open(reader_directory)?;
-    index
-        .tokenizers()
-        .register(O2_TOKENIZER, o2_tokenizer_build(CollectType::Search));
+    let tantivy_schema = index.schema();
+
+    // check the condition against the index schema before searching, the optimized modes need
+    // the full condition to compute the result, so the filter is added back if any field is
+    // missing, otherwise only the conditions indexed in this file are searched
+    let condition: IndexCondition =
+        index_condition.ok_or(anyhow::anyhow!("IndexCondition not found"))?;
+    if let Err(missing_fields) = condition.validate_against_schema(&tantivy_schema) {
+        if idx_optimize_rule.is_some() {
+            return Err(anyhow::anyhow!(
+                "condition {condition:?} can't be searched in tantivy file: {ttv_file_name}, missing fields: {missing_fields:?}"
+            ));
+        }
+        log::debug!(
+            "[trace_id {trace_id}] search->tantivy: tantivy file: {ttv_file_name} misses fields: {missing_fields:?}"
+        );
+    }
+
+    // the full text search fields are tokenized by the tokenizer in the index schema
+    register_fts_tokenizers(index.tokenizers(), CollectType::Search);
     let reader = index
         .reader_builder()
         .reload_policy(tantivy::ReloadPolicy::Manual)
@@ -776,8 +1443,7 @@ async fn search_tantivy_index(
     let tantivy_reader = Arc::new(reader);
 
     let searcher = tantivy_reader.searcher();
-    let tantivy_schema = tantivy_index.schema();
-    let fts_field = tantivy_schema.get_field(INDEX_FIELD_NAME_FOR_ALL).ok();
+    let fts_fields = get_fts_fields(&tantivy_schema, &fts_exclude_fields);
 
     // check if the index has multiple segments
     let seg_metas = tantivy_index
@@ -789,28 +1455,40 @@ async fn search_tantivy_index(
         ));
     }
 
-    // generate the tantivy query
-    let condition: IndexCondition =
-        index_condition.ok_or(anyhow::anyhow!("IndexCondition not found"))?;
-    let query = condition.to_tantivy_query(tantivy_schema.clone(), fts_field)?;
-    let need_all_term_fields = condition
-        .need_all_term_fields()
-        .into_iter()
-        .chain(get_simple_distinct_field(&idx_optimize_rule).into_iter())
-        .filter_map(|filed| tantivy_schema.get_field(&filed).ok())
-        .collect::<HashSet<_>>();
+    // generate the tantivy query, only search the conditions indexed in this file, the residual
+    // conditions are filtered by datafusion, so the file can still be pruned by the indexable
+    // conditions
+    let (condition, residual) = condition.split_indexable(&tantivy_schema, &fts_fields);
+    let is_partial = !residual.is_empty();
+    let origin = IndexFieldOrigin::from_condition(&condition);
+    if is_partial {
+        // the optimized modes need the full condition to compute the result
+        if condition.is_empty() || idx_optimize_rule.is_some() {
+            return Err(anyhow::anyhow!(
+                "condition {residual:?} can't be searched in tantivy file: {ttv_file_name}"
+            ));
+        }
+        log::debug!(
+            "[trace_id {trace_id}] search->tantivy: partial search tantivy file: {ttv_file_name}, residual condition: {residual:?}"
+        );
+    }
+    let query = condition.to_tantivy_query(tantivy_schema.clone(), &fts_fields)?;
+    let need_all_term_fields = resolve_need_all_term_fields(
+        &tantivy_schema,
+        &fts_fields,
+        condition
+            .need_all_term_fields()
+            .into_iter()
+            .chain(get_simple_distinct_field(&idx_optimize_rule).into_iter()),
+    );
 
     // warm up the terms in the query
-    let mut warm_terms: HashMap<tantivy::schema::Field, HashMap<tantivy::Term, bool>> =
-        HashMap::new();
-    query.query_terms(&mut |term, need_position| {
-        let field = term.field();
-        let entry = warm_terms.entry(field).or_default();
-        entry.insert(term.clone(), need_position);
-    });
+    let warm_terms = collect_warm_terms(&*query);
 
     let need_fast_field = idx_optimize_rule.as_ref().and_then(|rule| match rule {
-        IndexOptimizeMode::SimpleHistogram(..) => Some(TIMESTAMP_COL_NAME.to_string()),
+        IndexOptimizeMode::SimpleHistogram(..) | IndexOptimizeMode::SimpleHistogramBuckets(..) => {
+            Some(TIMESTAMP_COL_NAME.to_string())
+        }
         IndexOptimizeMode::SimpleTopN(field, ..) => Some(field.to_string()),
         _ => None,
     });
@@ -825,6 +1503,16 @@ async fn search_tantivy_index(
     // search the index
     let file_in_range =
         parquet_file.meta.min_ts >= time_range.0 && parquet_file.meta.max_ts < time_range.1;
+    if file_in_range
+        && histogram_needs_add_back(&tantivy_schema, &idx_optimize_rule, &parquet_file.key)
+    {
+        return Ok((
+            "".to_string(),
+            TantivyResult::RowIdsBitVec(0, BitVec::EMPTY),
+            is_partial,
+            origin,
+        ));
+    }
     let res = tokio::task::spawn_blocking(move || match (file_in_range, idx_optimize_rule) {
         (false, _) | (true, None) => TantivyResult::handle_matched_docs(&searcher, query),
         (true, Some(IndexOptimizeMode::SimpleSelect(limit, ascend))) => {
@@ -834,11 +1522,6 @@ async fn search_tantivy_index(
             TantivyResult::handle_simple_count(&searcher, query)
         }
         (true, Some(IndexOptimizeMode::SimpleHistogram(min_value, bucket_width, num_buckets))) => {
-            // fail the function if field not in tantivy schema
-            if tantivy_schema.get_field(TIMESTAMP_COL_NAME).is_err() {
-                log::warn!("_timestamp not index in tantivy file: {ttv_file_name}");
-                return Ok(TantivyResult::Histogram(vec![]));
-            }
             TantivyResult::handle_simple_histogram(
                 &searcher,
                 query,
@@ -847,7 +1530,19 @@ async fn search_tantivy_index(
                 num_buckets,
             )
         }
+        (true, Some(IndexOptimizeMode::SimpleHistogramBuckets(boundaries))) => {
+            TantivyResult::handle_simple_histogram_buckets(&searcher, query, boundaries)
+        }
         (true, Some(IndexOptimizeMode::SimpleTopN(field, limit, ascend))) => {
+            // the terms aggregation needs a fast field, otherwise need add filter back
+            let is_fast = tantivy_schema
+                .get_field(&field)
+                .is_ok_and(|f| tantivy_schema.get_field_entry(f).is_fast());
+            if !is_fast {
+                return Err(anyhow::anyhow!(
+                    "search->tantivy: {field} is not a fast field in tantivy file: {ttv_file_name}"
+                ));
+            }
             TantivyResult::handle_simple_top_n(&searcher, query, &field, limit, ascend)
         }
         (true, Some(IndexOptimizeMode::SimpleDistinct(field, limit, ascend))) => {
@@ -855,7 +1550,9 @@ async fn search_tantivy_index(
                 log::warn!("search->tantivy: {field} not index in tantivy file: {ttv_file_name}");
                 Ok(TantivyResult::Distinct(HashSet::new()))
             } else {
-                TantivyResult::handle_simple_distinct(&searcher, &condition, &field, limit, ascend)
+                TantivyResult::handle_simple_distinct(
+                    &searcher, query, &condition, &field, limit, ascend,
+                )
             }
         }
     })
@@ -870,12 +1567,17 @@ async fn search_tantivy_index(
         TantivyResult::Distinct(distinct) => TantivyResult::Distinct(distinct),
         TantivyResult::RowIds(row_ids) => {
             if row_ids.is_empty() || parquet_file.meta.records == 0 {
-                return Ok((key, TantivyResult::RowIdsBitVec(0, BitVec::EMPTY)));
+                return Ok((
+                    key,
+                    TantivyResult::RowIdsBitVec(0, BitVec::EMPTY),
+                    is_partial,
+                    origin,
+                ));
             }
             // return early if the number of matched docs is too large
-            let skip_threshold = cfg.limit.inverted_index_skip_threshold;
-            let row_ids_percent = row_ids.len() as f64 / parquet_file.meta.records as f64 * 100.0;
-            if skip_threshold > 0 && row_ids_percent > skip_threshold as f64 {
+            let (row_ids_percent, need_skip) =
+                check_skip_threshold(row_ids.len(), parquet_file.meta.records, skip_threshold);
+            if need_skip {
                 // return empty file name means we need to add filter back and skip tantivy search
                 log::info!(
                     "search->tantivy: file: {}, result percent {row_ids_percent}% is too large, back to datafusion",
@@ -884,40 +1586,331 @@ async fn search_tantivy_index(
                 return Ok((
                     "".to_string(),
                     TantivyResult::RowIdsBitVec(row_ids_percent as usize, BitVec::EMPTY),
+                    is_partial,
+                    origin,
                 ));
             }
             percent = row_ids_percent;
-            let max_doc_id = *row_ids.iter().max().unwrap_or(&0) as i64;
-            if max_doc_id >= parquet_file.meta.records {
-                return Err(anyhow::anyhow!(
-                    "doc_id {} is out of range, records {}",
-                    max_doc_id,
-                    parquet_file.meta.records,
-                ));
-            }
-            // NOTE: the BitVec's length should equal to the number of records in the parquet file
-            let mut res = BitVec::repeat(false, parquet_file.meta.records as usize);
-            let num_rows = row_ids.len();
-            for id in row_ids {
-                res.set(id as usize, true);
-            }
-            TantivyResult::RowIdsBitVec(num_rows, res)
+            row_ids_to_bitvec(row_ids, parquet_file.meta.records)?
         }
         TantivyResult::RowIdsBitVec(..) => {
             unreachable!("unsupported tantivy search result in search_tantivy_index")
         }
     };
 
-    // cache the result if the memory size is less than the limit
+    // cache the result if the memory size is less than the limit, the partial result can't be
+    // cached because the cache key is generated from the full condition
     if cfg.common.inverted_index_result_cache_enabled
         && !cache_key.is_empty()
+        && !is_partial
         && (result.get_memory_size() < cfg.limit.inverted_index_result_cache_max_entry_size
             || percent < 1.0)
     {
         let entry = get_cache_entry(result.clone(), percent, parquet_file.meta.records as usize);
         tantivy_result_cache::GLOBAL_CACHE.put(cache_key, entry);
     }
-    Ok((key, result))
+    Ok((key, result, is_partial, origin))
+}
+
+/// The kind of index fields which searched an index file, `match_all()` searches the full text
+/// search fields and the conditions on a named field, e.g. `field = 'v'`, search the secondary
+/// index fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexFieldOrigin {
+    FullText,
+    Secondary,
+    Mixed,
+}
+
+impl IndexFieldOrigin {
+    /// Returns `None` if the condition doesn't search any field, e.g. `match_all('*')`
+    fn from_condition(condition: &IndexCondition) -> Option<Self> {
+        let (mut full_text, mut secondary) = (false, false);
+        for field in condition
+            .conditions
+            .iter()
+            .flat_map(|condition| condition.get_tantivy_fields())
+        {
+            if field == INDEX_FIELD_NAME_FOR_ALL {
+                full_text = true;
+            } else {
+                secondary = true;
+            }
+        }
+        match (full_text, secondary) {
+            (true, true) => Some(Self::Mixed),
+            (true, false) => Some(Self::FullText),
+            (false, true) => Some(Self::Secondary),
+            (false, false) => None,
+        }
+    }
+}
+
+/// The index files retained and pruned by the row ids search, grouped by the kind of index
+/// fields which searched them, so a secondary index which doesn't prune can be told apart from
+/// the full text search.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct IndexPruneStats {
+    // (retained, pruned)
+    full_text: (usize, usize),
+    secondary: (usize, usize),
+    mixed: (usize, usize),
+}
+
+impl IndexPruneStats {
+    fn add(&mut self, origin: Option<IndexFieldOrigin>, retained: bool) {
+        let counter = match origin {
+            Some(IndexFieldOrigin::FullText) => &mut self.full_text,
+            Some(IndexFieldOrigin::Secondary) => &mut self.secondary,
+            Some(IndexFieldOrigin::Mixed) => &mut self.mixed,
+            None => return,
+        };
+        if retained {
+            counter.0 += 1;
+        } else {
+            counter.1 += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for IndexPruneStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fts retained/pruned {}/{}, secondary retained/pruned {}/{}, mixed retained/pruned {}/{}",
+            self.full_text.0,
+            self.full_text.1,
+            self.secondary.0,
+            self.secondary.1,
+            self.mixed.0,
+            self.mixed.1
+        )
+    }
+}
+
+/// The streams already reported by [`histogram_needs_add_back`], older index files don't have
+/// the `_timestamp` fast field and warning for every file floods the log.
+static MISSING_TIMESTAMP_FAST_FIELD: Lazy<DashSet<String>> = Lazy::new(DashSet::new);
+
+/// Returns true when the optimized histogram can't be computed from the index file because
+/// `_timestamp` isn't a fast field in it, the file is filtered by datafusion instead so the
+/// histogram still counts its rows.
+fn histogram_needs_add_back(
+    tantivy_schema: &tantivy::schema::Schema,
+    idx_optimize_rule: &Option<IndexOptimizeMode>,
+    file_key: &str,
+) -> bool {
+    if !matches!(
+        idx_optimize_rule,
+        Some(
+            IndexOptimizeMode::SimpleHistogram(..) | IndexOptimizeMode::SimpleHistogramBuckets(..)
+        )
+    ) {
+        return false;
+    }
+    if has_timestamp_fast_field(tantivy_schema) {
+        return false;
+    }
+    warn_missing_timestamp_fast_field(file_key);
+    true
+}
+
+fn has_timestamp_fast_field(tantivy_schema: &tantivy::schema::Schema) -> bool {
+    tantivy_schema
+        .get_field(TIMESTAMP_COL_NAME)
+        .is_ok_and(|f| tantivy_schema.get_field_entry(f).is_fast())
+}
+
+fn warn_missing_timestamp_fast_field(file_key: &str) {
+    let stream_key = parse_file_key_columns(file_key)
+        .map(|(stream_key, ..)| stream_key)
+        .unwrap_or_else(|_| file_key.to_string());
+    if MISSING_TIMESTAMP_FAST_FIELD.insert(stream_key.clone()) {
+        log::warn!(
+            "search->tantivy: _timestamp is not a fast field in the index files of {stream_key}, the histogram of these files is computed by datafusion"
+        );
+    }
+}
+
+/// Splits the files of an optimized histogram into the files whose index has a `_timestamp`
+/// fast field and the others, the histogram of the others can't be computed from the index and
+/// is computed by datafusion. A file whose index can't be opened is computed by datafusion too.
+pub async fn split_missing_timestamp_fast_field(
+    trace_id: &str,
+    files: Vec<FileKey>,
+) -> (Vec<FileKey>, Vec<FileKey>) {
+    let cfg = get_config();
+    let checked = stream::iter(
+        files
+            .into_iter()
+            .map(|file| check_timestamp_fast_field(trace_id, file)),
+    )
+    .buffered(cfg.limit.cpu_num);
+    // tokio_stream::StreamExt is in scope too
+    let checked: Vec<_> = StreamExt::collect(checked).await;
+    let (tantivy_files, datafusion_files): (Vec<_>, Vec<_>) = checked
+        .into_iter()
+        .partition(|(has_fast_field, _)| *has_fast_field);
+    (
+        tantivy_files.into_iter().map(|(_, f)| f).collect(),
+        datafusion_files.into_iter().map(|(_, f)| f).collect(),
+    )
+}
+
+async fn check_timestamp_fast_field(trace_id: &str, file: FileKey) -> (bool, FileKey) {
+    let has_fast_field = match index_has_timestamp_fast_field(trace_id, &file).await {
+        Ok(has_fast_field) => has_fast_field,
+        Err(e) => {
+            log::warn!(
+                "[trace_id {trace_id}] search->tantivy: can't open the index of {} to check the _timestamp fast field, computed by datafusion, error: {e}",
+                file.key
+            );
+            false
+        }
+    };
+    if !has_fast_field {
+        warn_missing_timestamp_fast_field(&file.key);
+    }
+    (has_fast_field, file)
+}
+
+async fn index_has_timestamp_fast_field(trace_id: &str, file: &FileKey) -> anyhow::Result<bool> {
+    let Some(ttv_file_name) = convert_parquet_file_name_to_tantivy_file(&file.key) else {
+        return Err(anyhow::anyhow!(
+            "can't convert the file name to a tantivy file name"
+        ));
+    };
+    let puffin_dir = Arc::new(
+        get_tantivy_directory(
+            trace_id,
+            &file.account,
+            &ttv_file_name,
+            file.meta.index_size,
+        )
+        .await?,
+    );
+    let footer_cache = FooterCache::from_directory(puffin_dir.clone()).await?;
+    let cache_dir = CachingDirectory::new_with_cacher(puffin_dir, Arc::new(footer_cache));
+    let reader_directory: Box<dyn Directory> = Box::new(cache_dir);
+    let index = tantivy::Index::open(reader_directory)?;
+    Ok(has_timestamp_fast_field(&index.schema()))
+}
+
+/// Raises the target partitions to at least `min_partitions`, the raised value is capped at the
+/// number of files because a partition without a file has nothing to scan.
+fn clamp_target_partitions(
+    target_partitions: usize,
+    min_partitions: usize,
+    num_files: usize,
+) -> usize {
+    target_partitions.max(min_partitions.min(num_files)).max(1)
+}
+
+/// The number of index files searched at the same time, `configured` 0 falls back to
+/// `target_partitions`.
+fn index_search_concurrency(configured: usize, target_partitions: usize) -> usize {
+    if configured > 0 {
+        configured
+    } else {
+        target_partitions.max(1)
+    }
+}
+
+/// Spawns a task for each index file, at most `concurrency` of them search at the same time, the
+/// permits bound the open tantivy readers. The results are yielded as the tasks finish.
+fn spawn_index_searches<F, Fut>(
+    files: Vec<FileKey>,
+    concurrency: usize,
+    search: F,
+) -> impl futures::Stream<Item = Result<Fut::Output, tokio::task::JoinError>> + Unpin
+where
+    F: Fn(FileKey) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let tasks = files
+        .into_iter()
+        .map(|file| {
+            let semaphore = semaphore.clone();
+            let search = search(file);
+            tokio::task::spawn(async move {
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                search.await
+            })
+        })
+        .collect::<Vec<_>>();
+    stream::iter(tasks).buffer_unordered(concurrency)
+}
+
+/// Returns the percent of the matched docs in the parquet file and whether the percent exceeds
+/// the skip threshold, `skip_threshold` 0 means never skip.
+///
+/// The file is skipped only if strictly more than `skip_threshold` percent of the records
+/// matched, e.g. with 1000 records and a threshold of 10, 100 matched docs are kept and 101 are
+/// skipped. The comparison is done in integers so the boundary is exact and can't overflow, a
+/// file without records is never skipped.
+fn check_skip_threshold(matched_docs: usize, records: i64, skip_threshold: usize) -> (f64, bool) {
+    if records <= 0 {
+        return (0.0, false);
+    }
+    let percent = matched_docs as f64 / records as f64 * 100.0;
+    let need_skip =
+        skip_threshold > 0 && matched_docs as u128 * 100 > skip_threshold as u128 * records as u128;
+    (percent, need_skip)
+}
+
+/// Converts the matched doc ids to a bitvec, the length of the bitvec equals to the number of
+/// records in the parquet file.
+fn row_ids_to_bitvec(row_ids: HashSet<u32>, records: i64) -> anyhow::Result<TantivyResult> {
+    let max_doc_id = *row_ids.iter().max().unwrap_or(&0) as i64;
+    if max_doc_id >= records {
+        return Err(anyhow::anyhow!(
+            "doc_id {max_doc_id} is out of range, records {records}",
+        ));
+    }
+    let mut res = BitVec::repeat(false, records as usize);
+    let num_rows = row_ids.len();
+    for id in row_ids {
+        res.set(id as usize, true);
+    }
+    Ok(TantivyResult::RowIdsBitVec(num_rows, res))
+}
+
+/// Resolves the field names which need all the terms to the tantivy fields, the catch-all field
+/// name `_all` stands for all the full text search fields.
+fn resolve_need_all_term_fields(
+    tantivy_schema: &tantivy::schema::Schema,
+    fts_fields: &[tantivy::schema::Field],
+    field_names: impl Iterator<Item = String>,
+) -> HashSet<tantivy::schema::Field> {
+    let mut fields = HashSet::new();
+    for name in field_names {
+        if name == INDEX_FIELD_NAME_FOR_ALL {
+            fields.extend(fts_fields.iter().copied());
+        } else if let Ok(field) = tantivy_schema.get_field(&name) {
+            fields.insert(field);
+        }
+    }
+    fields
+}
+
+/// Groups the terms of the query by field, the positions of a term are only needed if it is
+/// reported with positions for its own field, i.e. it's part of a phrase query on the field.
+fn collect_warm_terms(
+    query: &dyn tantivy::query::Query,
+) -> HashMap<tantivy::schema::Field, HashMap<tantivy::Term, bool>> {
+    let mut warm_terms: HashMap<tantivy::schema::Field, HashMap<tantivy::Term, bool>> =
+        HashMap::new();
+    query.query_terms(&mut |term, need_position| {
+        // the same term can be reported by a term query and a phrase query
+        *warm_terms
+            .entry(term.field())
+            .or_default()
+            .entry(term.clone())
+            .or_default() |= need_position;
+    });
+    warm_terms
 }
 
 /// if simple distinct without filter, we need to warm up the field
@@ -931,36 +1924,61 @@ fn get_simple_distinct_field(idx_optimize_rule: &Option<IndexOptimizeMode>) -> V
 
 // partition the tantivy files by time range
 // the return file groups should execte one by one
+// returns the file groups, the limit and the time range group id of each file, the group id is
+// only set for simple select with limit
 fn partition_tantivy_files(
     index_parquet_files: Vec<FileKey>,
     idx_optimize_mode: &Option<IndexOptimizeMode>,
-    target_partitions: usize,
-) -> (Vec<Vec<FileKey>>, usize) {
-    let (file_groups, limit) = if let Some(IndexOptimizeMode::SimpleSelect(limit, _ascend)) =
-        idx_optimize_mode
+    partition_num: usize,
+    time_range: (i64, i64),
+) -> (Vec<Vec<FileKey>>, usize, HashMap<String, usize>) {
+    if let Some(IndexOptimizeMode::SimpleSelect(limit, ascend)) = idx_optimize_mode
         && *limit > 0
     {
-        let file_groups = group_files_by_time_range(index_parquet_files, target_partitions);
-        (file_groups, *limit)
+        let file_groups = group_files_by_time_range(index_parquet_files, partition_num, time_range);
+        let file_group_ids = file_groups
+            .iter()
+            .enumerate()
+            .flat_map(|(id, group)| group.iter().map(move |f| (f.key.clone(), id)))
+            .collect();
+        (
+            regroup_tantivy_files(file_groups, *ascend),
+            *limit,
+            file_group_ids,
+        )
     } else {
         // splite the filter groups by target partitions
-        let file_groups = into_chunks(index_parquet_files, target_partitions);
-        (file_groups, 0)
-    };
+        let file_groups = into_chunks(index_parquet_files, partition_num);
+        (file_groups, 0, HashMap::new())
+    }
+}
 
+// split the files into the files need to search and the files of the groups which already reached
+// the limit, the files in a group are not overlapped and searched by the sort direction, so the
+// rest files of a group can't contribute to the result once the group alone reached the limit
+fn skip_satisfied_groups(
+    file_group: Vec<FileKey>,
+    file_group_ids: &HashMap<String, usize>,
+    group_hits: &HashMap<usize, usize>,
+    limit: usize,
+) -> (Vec<FileKey>, Vec<FileKey>) {
     if limit == 0 {
-        (file_groups, limit)
-    } else {
-        (regroup_tantivy_files(file_groups), limit)
+        return (file_group, vec![]);
     }
+    file_group.into_iter().partition(|file| {
+        file_group_ids
+            .get(&file.key)
+            .and_then(|id| group_hits.get(id))
+            .is_none_or(|hits| *hits < limit)
+    })
 }
 
 // regroup the tantivy for better performance
-// after [`partition_tantivy_files`] we get multiple groups that order by time range desc and each
-// group's time range not overlap, when execute the tantivy search, we get the last file in each
-// group and do the tantivy search.
+// after [`partition_tantivy_files`] we get multiple groups that order by time range asc and each
+// group's time range not overlap, when execute the tantivy search, we get the last file (or the
+// first file for ascending order) in each group and do the tantivy search.
 // so in this function, we recursive collect the last file in each group
-fn regroup_tantivy_files(file_groups: Vec<Vec<FileKey>>) -> Vec<Vec<FileKey>> {
+fn regroup_tantivy_files(file_groups: Vec<Vec<FileKey>>, ascend: bool) -> Vec<Vec<FileKey>> {
     let group_num = file_groups.len();
     let max_group_len = file_groups.iter().map(|g| g.len()).max().unwrap_or(0);
     let mut new_file_groups: Vec<Vec<FileKey>> = vec![Vec::new(); max_group_len];
@@ -968,7 +1986,9 @@ fn regroup_tantivy_files(file_groups: Vec<Vec<FileKey>>) -> Vec<Vec<FileKey>> {
     let mut file_groups: Vec<_> = file_groups
         .into_iter()
         .map(|mut group| {
-            group.reverse();
+            if !ascend {
+                group.reverse();
+            }
             group.into_iter()
         })
         .collect();
@@ -1000,8 +2020,16 @@ fn into_chunks<T>(mut v: Vec<T>, chunk_size: usize) -> Vec<Vec<T>> {
 
 // Group files by time range
 // use the min_ts & max_ts of the file.meta to group files and each group can't contains crossing
-// time range files
-fn group_files_by_time_range(mut files: Vec<FileKey>, partition_num: usize) -> Vec<Vec<FileKey>> {
+// time range files, the files entirely outside the query time range are dropped, the time range
+// (0, 0) means unbounded
+fn group_files_by_time_range(
+    mut files: Vec<FileKey>,
+    partition_num: usize,
+    time_range: (i64, i64),
+) -> Vec<Vec<FileKey>> {
+    if time_range != (0, 0) {
+        files.retain(|f| !(f.meta.max_ts < time_range.0 || f.meta.min_ts >= time_range.1));
+    }
     let expect_group_elements = files.len().div_ceil(partition_num);
     // sort files by max_ts in ascending order
     files.sort_unstable_by(|a, b| a.meta.max_ts.cmp(&b.meta.max_ts));
@@ -1089,6 +2117,7 @@ fn get_cache_entry(tantivy_result: TantivyResult, percent: f64, parquet_rows: us
 fn generate_cache_key(
     index_condition: &Option<IndexCondition>,
     idx_optimize_rule: &Option<IndexOptimizeMode>,
+    fts_exclude_fields: &[String],
     parquet_file: &FileKey,
 ) -> String {
     let condition = match index_condition {
@@ -1099,12 +2128,22 @@ fn generate_cache_key(
         Some(rule) => rule.to_rule_string(),
         None => return String::new(),
     };
-    format!("{}_{}_{}", condition, rule, parquet_file.key)
+    if fts_exclude_fields.is_empty() {
+        format!("{}_{}_{}", condition, rule, parquet_file.key)
+    } else {
+        format!(
+            "{}_{}_{}_{}",
+            condition,
+            rule,
+            fts_exclude_fields.join(","),
+            parquet_file.key
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use config::meta::stream::FileMeta;
+    use config::{meta::stream::FileMeta, utils::tantivy::tokenizer::O2_TOKENIZER};
 
     use super::*;
     use crate::service::search::{
@@ -1124,6 +2163,63 @@ mod tests {
         }
     }
 
+    // builds an in-RAM tantivy index with a text field per `(name, options)` and a document per
+    // row of `docs`, the values of a row are in the order of the fields
+    fn build_ram_index(
+        fields: &[(&str, tantivy::schema::TextOptions)],
+        docs: &[&[&str]],
+    ) -> (
+        tantivy::schema::Schema,
+        Vec<tantivy::schema::Field>,
+        tantivy::Searcher,
+    ) {
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let fields = fields
+            .iter()
+            .map(|(name, opts)| schema_builder.add_text_field(name, opts.clone()))
+            .collect::<Vec<_>>();
+        let tantivy_schema = schema_builder.build();
+
+        let index = tantivy::Index::create_in_ram(tantivy_schema.clone());
+        register_fts_tokenizers(index.tokenizers(), CollectType::Ingest);
+        let mut writer = index.writer(50_000_000).unwrap();
+        for values in docs {
+            let mut doc = tantivy::TantivyDocument::default();
+            for (field, value) in fields.iter().zip(values.iter()) {
+                doc.add_text(*field, value);
+            }
+            writer.add_document(doc).unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        (tantivy_schema, fields, searcher)
+    }
+
+    #[test]
+    fn test_storage_plan() {
+        let files = vec![
+            create_file_key(110, 150),
+            create_file_key(50, 120),
+            create_file_key(150, 250),
+            create_file_key(10, 50),
+            create_file_key(200, 300),
+        ];
+        let plan = StoragePlan::new((100, 200), 6, &files, 4, file_data::CacheType::Disk, true);
+        assert_eq!(
+            plan,
+            StoragePlan {
+                files: 6,
+                files_loaded: 5,
+                files_pruned_by_index: 1,
+                files_pruned_by_time: 2,
+                files_with_time_filter: 2,
+                target_partitions: 4,
+                cache_type: "Disk".to_string(),
+                is_add_filter_back: true,
+            }
+        );
+    }
+
     #[test]
     fn test_group_files_by_time_range() {
         let files = vec![
@@ -1134,7 +2230,7 @@ mod tests {
             create_file_key(41, 50),
         ];
         let partition_num = 3;
-        let groups = group_files_by_time_range(files, partition_num);
+        let groups = group_files_by_time_range(files, partition_num, (0, 0));
         assert!(groups.len() >= 3);
     }
 
@@ -1149,7 +2245,7 @@ mod tests {
             create_file_key(41, 50),
         ];
         let partition_num = 2;
-        let groups = group_files_by_time_range(files, partition_num);
+        let groups = group_files_by_time_range(files, partition_num, (0, 0));
         assert!(groups.len() >= 2);
     }
 
@@ -1157,10 +2253,33 @@ mod tests {
     fn test_group_files_by_time_range_with_less_partitions() {
         let files = vec![create_file_key(1, 10), create_file_key(11, 20)];
         let partition_num = 3;
-        let groups = group_files_by_time_range(files, partition_num);
+        let groups = group_files_by_time_range(files, partition_num, (0, 0));
         assert!(groups.len() >= 2);
     }
 
+    #[test]
+    fn test_group_files_by_time_range_drops_out_of_range_files() {
+        let files = vec![
+            create_file_key(1, 10),
+            create_file_key(11, 20),
+            create_file_key(21, 30),
+            create_file_key(31, 40),
+            create_file_key(41, 50),
+        ];
+        let groups = group_files_by_time_range(files.clone(), 2, (15, 41));
+        let keys = groups
+            .iter()
+            .flatten()
+            .map(|f| f.key.as_str())
+            .sorted()
+            .collect_vec();
+        assert_eq!(keys, vec!["file_11_20", "file_21_30", "file_31_40"]);
+
+        // unbounded time range keeps all the files
+        let groups = group_files_by_time_range(files, 2, (0, 0));
+        assert_eq!(groups.iter().flatten().count(), 5);
+    }
+
     #[test]
     fn test_repartition_sorted_groups() {
         let groups = vec![
@@ -1186,6 +2305,439 @@ mod tests {
         assert!(repartitioned_groups.len() >= 3);
     }
 
+    #[test]
+    fn test_handle_no_index_files() {
+        let files = (0..4)
+            .map(|i| create_file_key(i * 10, i * 10 + 9))
+            .map(|f| (f.key.clone(), f))
+            .collect::<HashMap<_, _>>();
+        let indexed_files = HashSet::from(["file_0_9".to_string(), "file_10_19".to_string()]);
+
+        // lenient: the files without index file are kept and filtered by datafusion
+        let mut file_list_map = files.clone();
+        let mut filter_back = FilterBack::None;
+        let mut explain = Some(Vec::new());
+        let skipped = handle_no_index_files(
+            &mut file_list_map,
+            &indexed_files,
+            false,
+            &mut filter_back,
+            &mut explain,
+        );
+        assert_eq!(skipped, 0);
+        assert_eq!(file_list_map.len(), 4);
+        assert_eq!(filter_back.to_string(), "2 files");
+        assert!(
+            explain
+                .unwrap()
+                .iter()
+                .all(|e| e.decision == IndexFileDecision::NoIndexFile)
+        );
+
+        // strict: the files without index file are removed
+        let mut file_list_map = files;
+        let mut filter_back = FilterBack::None;
+        let mut explain = Some(Vec::new());
+        let skipped = handle_no_index_files(
+            &mut file_list_map,
+            &indexed_files,
+            true,
+            &mut filter_back,
+            &mut explain,
+        );
+        assert_eq!(skipped, 2);
+        assert_eq!(
+            file_list_map.keys().sorted().collect_vec(),
+            vec!["file_0_9", "file_10_19"]
+        );
+        assert!(!filter_back.is_any());
+        assert!(
+            explain
+                .unwrap()
+                .iter()
+                .all(|e| e.decision == IndexFileDecision::NoIndexFileSkipped)
+        );
+    }
+
+    #[test]
+    fn test_filter_back_one_failing_file() {
+        let files = (0..10)
+            .map(|i| create_file_key(i * 10, i * 10 + 9))
+            .collect::<Vec<_>>();
+
+        // one file failed in the index search, only that one is filtered by datafusion
+        let mut filter_back = FilterBack::None;
+        assert!(!filter_back.is_any());
+        filter_back.add_file("file_30_39");
+        assert!(filter_back.is_any());
+        assert_eq!(filter_back.to_string(), "1 files");
+        let (with_filter, without_filter) = filter_back.partition(files.clone());
+        assert_eq!(
+            with_filter.iter().map(|f| f.key.as_str()).collect_vec(),
+            vec!["file_30_39"]
+        );
+        assert_eq!(without_filter.len(), 9);
+
+        // a failure which can't be attributed keeps the filter on all the files
+        let mut filter_back = FilterBack::All;
+        filter_back.add_file("file_30_39");
+        assert_eq!(filter_back, FilterBack::All);
+        let (with_filter, without_filter) = filter_back.partition(files.clone());
+        assert_eq!(with_filter.len(), 10);
+        assert!(without_filter.is_empty());
+
+        let (with_filter, without_filter) = FilterBack::None.partition(files);
+        assert!(with_filter.is_empty());
+        assert_eq!(without_filter.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_tantivy_search_one_failing_index_file() {
+        use crate::service::tantivy::puffin_directory::writer::PuffinDirWriter;
+
+        // an index file with 10 docs where one matches the condition
+        let dir = PuffinDirWriter::new();
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let name = schema_builder.add_text_field("name", tantivy::schema::STRING);
+        let mut index_writer = tantivy::IndexBuilder::new()
+            .schema(schema_builder.build())
+            .single_segment_index_writer(dir.clone(), 50_000_000)
+            .unwrap();
+        for i in 0..10 {
+            let value = if i == 0 {
+                "a".to_string()
+            } else {
+                format!("b{i}")
+            };
+            index_writer
+                .add_document(tantivy::doc!(name => value))
+                .unwrap();
+        }
+        index_writer.finalize().unwrap();
+        let puffin_bytes = dir.to_puffin_bytes().unwrap();
+
+        let file_key = |key: &str, index_size: i64| FileKey {
+            key: key.to_string(),
+            meta: FileMeta {
+                min_ts: 100,
+                max_ts: 199,
+                records: 10,
+                index_size,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let good_key = "files/default/logs/test/2024/01/01/00/7000000000000000001.parquet";
+        let bad_key = "files/default/logs/test/2024/01/01/00/7000000000000000002.parquet";
+        let ttv_file = convert_parquet_file_name_to_tantivy_file(good_key).unwrap();
+        file_data::disk::set(&ttv_file, bytes::Bytes::from(puffin_bytes.clone()))
+            .await
+            .unwrap();
+        // the index of the bad file was never written, opening it fails
+        let mut files = vec![
+            file_key(good_key, puffin_bytes.len() as i64),
+            file_key(bad_key, puffin_bytes.len() as i64),
+        ];
+
+        let query = Arc::new(super::super::QueryParams {
+            trace_id: "test_tantivy_search_one_failing_index_file".to_string(),
+            org_id: "default".to_string(),
+            stream: datafusion::sql::TableReference::from("test"),
+            stream_type: StreamType::Logs,
+            stream_name: "test".to_string(),
+            time_range: (0, 1000),
+            work_group: None,
+            use_inverted_index: true,
+            disable_index_skip_threshold: true,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
+        });
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::Equal("name".to_string(), "a".to_string()));
+        let (_, filter_back, ..) = tantivy_search(query, &mut files, Some(condition), None)
+            .await
+            .unwrap();
+
+        // only the file whose index failed is filtered by datafusion
+        assert_eq!(
+            filter_back,
+            FilterBack::Files(HashSet::from([bad_key.to_string()]))
+        );
+        assert_eq!(files.len(), 2);
+        let good = files.iter().find(|f| f.key == good_key).unwrap();
+        assert_eq!(good.segment_ids.as_ref().unwrap().count_ones(), 1);
+        let bad = files.iter().find(|f| f.key == bad_key).unwrap();
+        assert!(bad.segment_ids.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tantivy_search_explain_filter_back_all() {
+        use crate::service::tantivy::puffin_directory::writer::PuffinDirWriter;
+
+        // every doc matches, so every file is above the skip threshold
+        let dir = PuffinDirWriter::new();
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let name = schema_builder.add_text_field("name", tantivy::schema::STRING);
+        let mut index_writer = tantivy::IndexBuilder::new()
+            .schema(schema_builder.build())
+            .single_segment_index_writer(dir.clone(), 50_000_000)
+            .unwrap();
+        for _ in 0..10 {
+            index_writer
+                .add_document(tantivy::doc!(name => "a"))
+                .unwrap();
+        }
+        index_writer.finalize().unwrap();
+        let puffin_bytes = dir.to_puffin_bytes().unwrap();
+
+        // more files than the skip threshold counts, the search stops before the last ones
+        let file_num = get_config().limit.cpu_num + 2;
+        let mut files = Vec::with_capacity(file_num);
+        for i in 0..file_num {
+            let key = format!(
+                "files/default/logs/explain_all/2024/01/01/00/70000000000000{i:05}.parquet"
+            );
+            let ttv_file = convert_parquet_file_name_to_tantivy_file(&key).unwrap();
+            file_data::disk::set(&ttv_file, bytes::Bytes::from(puffin_bytes.clone()))
+                .await
+                .unwrap();
+            files.push(FileKey {
+                key,
+                meta: FileMeta {
+                    min_ts: 100,
+                    max_ts: 199,
+                    records: 10,
+                    index_size: puffin_bytes.len() as i64,
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+        }
+
+        let query = Arc::new(super::super::QueryParams {
+            trace_id: "test_tantivy_search_explain_filter_back_all".to_string(),
+            org_id: "default".to_string(),
+            stream: datafusion::sql::TableReference::from("explain_all"),
+            stream_type: StreamType::Logs,
+            stream_name: "explain_all".to_string(),
+            time_range: (0, 1000),
+            work_group: None,
+            use_inverted_index: true,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: true,
+            strict_index_only: false,
+            referenced_fields: None,
+        });
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::Equal("name".to_string(), "a".to_string()));
+        let (_, filter_back, _, _, explain) =
+            tantivy_search(query, &mut files, Some(condition), None)
+                .await
+                .unwrap();
+
+        // every file is kept and has exactly one decision
+        assert_eq!(filter_back, FilterBack::All);
+        assert_eq!(files.len(), file_num);
+        assert_eq!(explain.len(), file_num);
+        let explained = explain.iter().map(|e| &e.file).collect::<HashSet<_>>();
+        assert!(files.iter().all(|f| explained.contains(&f.key)));
+        assert!(
+            explain
+                .iter()
+                .all(|e| e.decision == IndexFileDecision::AddedBack)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_result_cache_hit_loads_files() {
+        unsafe {
+            std::env::set_var("ZO_STORAGE_RESULT_CACHE_ENABLED", "true");
+        }
+        config::refresh_config().unwrap();
+
+        let key = "files/default/logs/result_cache/2024/01/01/00/7000000000000000001.parquet";
+        let files = vec![FileKey {
+            key: key.to_string(),
+            meta: FileMeta {
+                min_ts: 100,
+                max_ts: 199,
+                records: 10,
+                original_size: 100,
+                compressed_size: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+        let query = Arc::new(super::super::QueryParams {
+            trace_id: "test_search_result_cache_hit_loads_files".to_string(),
+            org_id: "default".to_string(),
+            stream: datafusion::sql::TableReference::from("result_cache"),
+            stream_type: StreamType::Logs,
+            stream_name: "result_cache".to_string(),
+            time_range: (0, 1000),
+            work_group: None,
+            use_inverted_index: false,
+            disable_index_skip_threshold: false,
+            explain: false,
+            explain_index: false,
+            strict_index_only: false,
+            referenced_fields: None,
+        });
+        let schema = Arc::new(Schema::new(vec![arrow_schema::Field::new(
+            TIMESTAMP_COL_NAME,
+            arrow_schema::DataType::Int64,
+            false,
+        )]));
+        let search = || {
+            super::search(
+                query.clone(),
+                schema.clone(),
+                &files,
+                true,
+                None,
+                None,
+                vec![],
+                None,
+            )
+        };
+
+        let (_, scan_stats, _) = search().await.unwrap();
+        assert_eq!(scan_stats.querier_disk_cached_files, 0);
+        let cache_key = storage_result_cache::generate_cache_key(&query, &None, &[], &[], &None);
+        let files_hash = storage_result_cache::files_hash(&files);
+        assert!(
+            storage_result_cache::GLOBAL_CACHE
+                .get(&cache_key, files_hash)
+                .is_some()
+        );
+
+        // the file is cached after the result was cached, the hit still checks the local cache
+        file_data::disk::set(key, bytes::Bytes::from("0123456789"))
+            .await
+            .unwrap();
+        let (_, scan_stats, _) = search().await.unwrap();
+        assert_eq!(scan_stats.files, 1);
+        assert_eq!(scan_stats.querier_disk_cached_files, 1);
+        assert_eq!(scan_stats.parquet_cache_hits, 1);
+    }
+
+    #[test]
+    fn test_index_search_concurrency() {
+        assert_eq!(index_search_concurrency(0, 8), 8);
+        assert_eq!(index_search_concurrency(64, 8), 64);
+        assert_eq!(index_search_concurrency(0, 0), 1);
+    }
+
+    #[tokio::test]
+    async fn test_index_searches_above_target_partitions() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let target_partitions = 2;
+        let concurrency = index_search_concurrency(8, target_partitions);
+        let files = (0..16)
+            .map(|i| FileKey {
+                key: format!("files/default/logs/test/{i}.parquet"),
+                ..Default::default()
+            })
+            .collect_vec();
+        // the groups are sized by the search concurrency, not the target partitions
+        let (groups, ..) = partition_tantivy_files(files, &None, concurrency, (0, 0));
+        assert_eq!(groups.len(), 2);
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        for group in groups {
+            let mut tasks = spawn_index_searches(group, concurrency, |file| {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                async move {
+                    let num = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_running.fetch_max(num, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    file.key
+                }
+            });
+            let mut searched = 0;
+            while tasks.try_next().await.unwrap().is_some() {
+                searched += 1;
+            }
+            assert_eq!(searched, concurrency);
+        }
+        assert_eq!(max_running.load(Ordering::SeqCst), concurrency);
+        assert!(concurrency > target_partitions);
+    }
+
+    #[tokio::test]
+    async fn test_check_tantivy_index_files_skips_unindexed() {
+        let mut indexed = create_file_key(1, 10);
+        indexed.key = "bad_name.parquet".to_string();
+        indexed.meta.index_size = 100;
+        let mut unindexed = create_file_key(11, 20);
+        unindexed.meta.index_size = 0;
+
+        let mismatches = check_tantivy_index_files("trace", &[indexed, unindexed]).await;
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].file, "bad_name.parquet");
+        assert!(mismatches[0].index_file.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_tantivy_index_files_missing_index() {
+        let mut missing = create_file_key(1, 10);
+        missing.key = "files/default/logs/check_missing/2024/01/01/00/7000000000000000001.parquet"
+            .to_string();
+        missing.meta.index_size = 100;
+
+        let mismatches = check_tantivy_index_files("trace", &[missing.clone()]).await;
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].file, missing.key);
+        assert_eq!(
+            mismatches[0].index_file,
+            convert_parquet_file_name_to_tantivy_file(&missing.key).unwrap()
+        );
+        assert!(!mismatches[0].error.is_empty());
+    }
+
+    #[test]
+    fn test_histogram_without_timestamp_fast_field() {
+        let rule = Some(IndexOptimizeMode::SimpleHistogram(0, 10, 10));
+        let file_key = "files/default/logs/old/2024/01/01/00/7000000000000000000.parquet";
+
+        let mut schema_builder = tantivy::schema::SchemaBuilder::new();
+        schema_builder.add_text_field("message", tantivy::schema::TEXT);
+        let schema = schema_builder.build();
+        // the file is added back instead of returning an empty histogram, every time
+        assert!(histogram_needs_add_back(&schema, &rule, file_key));
+        assert!(histogram_needs_add_back(&schema, &rule, file_key));
+        assert!(MISSING_TIMESTAMP_FAST_FIELD.contains("default/logs/old"));
+        assert!(!histogram_needs_add_back(
+            &schema,
+            &Some(IndexOptimizeMode::SimpleCount),
+            file_key
+        ));
+
+        // an indexed but not fast _timestamp can't be used by the histogram either
+        let mut schema_builder = tantivy::schema::SchemaBuilder::new();
+        schema_builder.add_i64_field(TIMESTAMP_COL_NAME, tantivy::schema::INDEXED);
+        assert!(histogram_needs_add_back(
+            &schema_builder.build(),
+            &rule,
+            file_key
+        ));
+
+        let mut schema_builder = tantivy::schema::SchemaBuilder::new();
+        schema_builder.add_i64_field(TIMESTAMP_COL_NAME, tantivy::schema::FAST);
+        assert!(!histogram_needs_add_back(
+            &schema_builder.build(),
+            &rule,
+            file_key
+        ));
+    }
+
     #[test]
     fn test_histogram_i64() {
         const MARGIN_IN_BYTES: usize = 1_000_000;
@@ -1230,7 +2782,7 @@ mod tests {
             vec![create_file_key(1, 10), create_file_key(11, 20)],
             vec![create_file_key(21, 30), create_file_key(31, 40)],
         ];
-        let result = regroup_tantivy_files(file_groups);
+        let result = regroup_tantivy_files(file_groups, false);
 
         // Should have 2 groups (max length of input groups)
         assert_eq!(result.len(), 2);
@@ -1256,7 +2808,7 @@ mod tests {
             ],
             vec![create_file_key(31, 40)],
         ];
-        let result = regroup_tantivy_files(file_groups);
+        let result = regroup_tantivy_files(file_groups, false);
 
         // Should have 3 groups (max length of input groups)
         assert_eq!(result.len(), 3);
@@ -1275,10 +2827,61 @@ mod tests {
         assert_eq!(result[2][0].key, "file_1_10"); // First file from first group
     }
 
+    #[test]
+    fn test_regroup_tantivy_files_ascend() {
+        let file_groups = vec![
+            vec![create_file_key(1, 10), create_file_key(11, 20)],
+            vec![create_file_key(21, 30)],
+        ];
+        let result = regroup_tantivy_files(file_groups, true);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0][0].key, "file_1_10"); // First file from first group
+        assert_eq!(result[0][1].key, "file_21_30");
+        assert_eq!(result[1][0].key, "file_11_20");
+    }
+
+    #[test]
+    fn test_skip_satisfied_groups_per_group_limit() {
+        // group 0: [90, 99], [100, 110], group 1: [50, 105] overlaps with both files of group 0
+        let files = vec![
+            create_file_key(90, 99),
+            create_file_key(50, 105),
+            create_file_key(100, 110),
+        ];
+        let idx_optimize_mode = Some(IndexOptimizeMode::SimpleSelect(5, false));
+        let (file_groups, limit, file_group_ids) =
+            partition_tantivy_files(files, &idx_optimize_mode, 2, (0, 0));
+        assert_eq!(limit, 5);
+        assert_eq!(file_groups.len(), 2);
+        assert_eq!(
+            file_groups[0].iter().map(|f| f.key.as_str()).collect_vec(),
+            vec!["file_100_110", "file_50_105"]
+        );
+        assert_eq!(file_groups[1][0].key, "file_90_99");
+
+        // the newest file of group 0 hits 3 rows, the file of group 1 hits 5 rows, the total hits
+        // exceed the limit but group 0 alone doesn't, its older file should be searched
+        let mut group_hits = HashMap::new();
+        group_hits.insert(file_group_ids["file_100_110"], 3);
+        group_hits.insert(file_group_ids["file_50_105"], 5);
+        let (search_files, skipped_files) =
+            skip_satisfied_groups(file_groups[1].clone(), &file_group_ids, &group_hits, limit);
+        assert_eq!(search_files.len(), 1);
+        assert_eq!(search_files[0].key, "file_90_99");
+        assert!(skipped_files.is_empty());
+
+        // once group 0 alone reached the limit, its rest files are skipped
+        group_hits.insert(file_group_ids["file_100_110"], 5);
+        let (search_files, skipped_files) =
+            skip_satisfied_groups(file_groups[1].clone(), &file_group_ids, &group_hits, limit);
+        assert!(search_files.is_empty());
+        assert_eq!(skipped_files[0].key, "file_90_99");
+    }
+
     #[test]
     fn test_regroup_tantivy_files_empty_groups() {
         let file_groups: Vec<Vec<FileKey>> = vec![];
-        let result = regroup_tantivy_files(file_groups);
+        let result = regroup_tantivy_files(file_groups, false);
         assert_eq!(result.len(), 0);
     }
 
@@ -1289,7 +2892,7 @@ mod tests {
             create_file_key(11, 20),
             create_file_key(21, 30),
         ]];
-        let result = regroup_tantivy_files(file_groups);
+        let result = regroup_tantivy_files(file_groups, false);
 
         // Should have 3 groups (length of the single input group)
         assert_eq!(result.len(), 3);
@@ -1316,6 +2919,457 @@ mod tests {
         assert_eq!(chunks[2], vec![7, 8]);
     }
 
+    #[tokio::test]
+    async fn test_search_multiple_fts_fields() {
+        use crate::service::search::index::Condition;
+
+        let fts_opts = tantivy::schema::TextOptions::default().set_indexing_options(
+            tantivy::schema::TextFieldIndexing::default()
+                .set_index_option(tantivy::schema::IndexRecordOption::Basic)
+                .set_tokenizer(O2_TOKENIZER),
+        );
+        let (tantivy_schema, fields, searcher) = build_ram_index(
+            &[("message", fts_opts.clone()), ("log", fts_opts)],
+            &[
+                &["connection error", "ok"],
+                &["ok", "disk error"],
+                &["ok", "ok"],
+            ],
+        );
+        let (message, log) = (fields[0], fields[1]);
+
+        // both full text search fields are resolved
+        let fts_fields = get_fts_fields(&tantivy_schema, &[]);
+        assert_eq!(fts_fields, vec![message, log]);
+
+        // the terms of both fields are warmed
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::MatchAll("error".to_string()));
+        let query = condition
+            .to_tantivy_query(tantivy_schema.clone(), &fts_fields)
+            .unwrap();
+        let warm_terms = collect_warm_terms(&*query);
+        assert!(warm_terms.contains_key(&message));
+        assert!(warm_terms.contains_key(&log));
+
+        // the prefix search needs the term dictionaries of both fields
+        let need_all_term_fields = resolve_need_all_term_fields(
+            &tantivy_schema,
+            &fts_fields,
+            vec![INDEX_FIELD_NAME_FOR_ALL.to_string()].into_iter(),
+        );
+        assert_eq!(need_all_term_fields, HashSet::from([message, log]));
+        warm_up_terms(&searcher, &warm_terms, need_all_term_fields, None)
+            .await
+            .unwrap();
+
+        // both fields are queried
+        let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_search_with_fts_tokenizer() {
+        use config::utils::tantivy::tokenizer::NGRAM_TOKENIZER;
+
+        use crate::service::search::index::Condition;
+
+        let fts_opts = tantivy::schema::TextOptions::default().set_indexing_options(
+            tantivy::schema::TextFieldIndexing::default()
+                .set_index_option(tantivy::schema::IndexRecordOption::Basic)
+                .set_tokenizer(NGRAM_TOKENIZER),
+        );
+        let (tantivy_schema, fields, searcher) = build_ram_index(
+            &[(INDEX_FIELD_NAME_FOR_ALL, fts_opts)],
+            &[&["东京都的天气"], &["京都的天气"]],
+        );
+        let all = fields[0];
+
+        // the field tokenized by the ngram tokenizer is a full text search field
+        let fts_fields = get_fts_fields(&tantivy_schema, &[]);
+        assert_eq!(fts_fields, vec![all]);
+
+        // the query is tokenized by the same tokenizer as the index
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::MatchAll("东京".to_string()));
+        let query = condition
+            .to_tantivy_query(tantivy_schema.clone(), &fts_fields)
+            .unwrap();
+        let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+        assert_eq!(count, 1);
+
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::MatchAll("京都".to_string()));
+        let query = condition
+            .to_tantivy_query(tantivy_schema, &fts_fields)
+            .unwrap();
+        let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_index_prune_stats_by_field_origin() {
+        use crate::service::search::index::Condition;
+
+        let condition = |conditions: Vec<Condition>| IndexCondition { conditions };
+        let fts = Condition::MatchAll("error".to_string());
+        let secondary = Condition::Equal("status".to_string(), "500".to_string());
+        assert_eq!(
+            IndexFieldOrigin::from_condition(&condition(vec![fts.clone()])),
+            Some(IndexFieldOrigin::FullText)
+        );
+        assert_eq!(
+            IndexFieldOrigin::from_condition(&condition(vec![secondary.clone()])),
+            Some(IndexFieldOrigin::Secondary)
+        );
+        assert_eq!(
+            IndexFieldOrigin::from_condition(&condition(vec![Condition::Or(
+                Box::new(fts),
+                Box::new(secondary)
+            )])),
+            Some(IndexFieldOrigin::Mixed)
+        );
+        assert_eq!(
+            IndexFieldOrigin::from_condition(&condition(vec![Condition::All()])),
+            None
+        );
+
+        let mut stats = IndexPruneStats::default();
+        stats.add(Some(IndexFieldOrigin::FullText), false);
+        stats.add(Some(IndexFieldOrigin::Secondary), true);
+        stats.add(Some(IndexFieldOrigin::Secondary), true);
+        stats.add(Some(IndexFieldOrigin::Mixed), false);
+        stats.add(None, true);
+        assert_eq!(
+            stats.to_string(),
+            "fts retained/pruned 0/1, secondary retained/pruned 2/0, mixed retained/pruned 0/1"
+        );
+    }
+
+    #[test]
+    fn test_clamp_target_partitions() {
+        // the minimum raises the partitions of a small node
+        assert_eq!(clamp_target_partitions(1, 4, 10), 4);
+        // but not above the number of files
+        assert_eq!(clamp_target_partitions(1, 4, 2), 2);
+        // the configured partitions are never lowered
+        assert_eq!(clamp_target_partitions(8, 4, 2), 8);
+        assert_eq!(clamp_target_partitions(8, 1, 10), 8);
+        assert_eq!(clamp_target_partitions(1, 4, 0), 1);
+    }
+
+    #[test]
+    fn test_explain_file() {
+        let mut explain = None;
+        explain_file(&mut explain, "a.parquet", IndexFileDecision::Matched);
+        assert!(explain.is_none());
+
+        let mut explain = Some(Vec::new());
+        explain_file(&mut explain, "a.parquet", IndexFileDecision::NoIndexFile);
+        explain_file(&mut explain, "b.parquet", IndexFileDecision::AddedBack);
+        assert_eq!(
+            json::to_string(&explain.unwrap()).unwrap(),
+            r#"[{"file":"a.parquet","decision":"no_index_file"},{"file":"b.parquet","decision":"added_back"}]"#
+        );
+    }
+
+    #[test]
+    fn test_collect_warm_terms_positions_per_field() {
+        use tantivy::query::{BooleanQuery, Occur, PhraseQuery, Query, TermQuery};
+
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        let message = schema_builder.add_text_field("message", tantivy::schema::TEXT);
+        let status = schema_builder.add_text_field("status", tantivy::schema::STRING);
+        let _ = schema_builder.build();
+
+        let term = |field, text: &str| tantivy::Term::from_field_text(field, text);
+        let term_query = |t| -> Box<dyn Query> {
+            Box::new(TermQuery::new(t, tantivy::schema::IndexRecordOption::Basic))
+        };
+        // status:error AND message:"connection refused" AND message:timeout AND
+        // message:connection
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, term_query(term(status, "error"))),
+            (
+                Occur::Must,
+                Box::new(PhraseQuery::new(vec![
+                    term(message, "connection"),
+                    term(message, "refused"),
+                ])),
+            ),
+            (Occur::Must, term_query(term(message, "timeout"))),
+            (Occur::Must, term_query(term(message, "connection"))),
+        ]);
+
+        let warm_terms = collect_warm_terms(&query);
+        assert_eq!(
+            warm_terms[&status],
+            HashMap::from([(term(status, "error"), false)])
+        );
+        assert_eq!(
+            warm_terms[&message],
+            HashMap::from([
+                (term(message, "connection"), true),
+                (term(message, "refused"), true),
+                (term(message, "timeout"), false),
+            ])
+        );
+        // only the terms of the phrase warm up their positions
+        let position_warm_ups = warm_terms
+            .values()
+            .flat_map(|terms| terms.values())
+            .filter(|need_position| **need_position)
+            .count();
+        assert_eq!(position_warm_ups, 2);
+    }
+
+    #[tokio::test]
+    async fn test_search_match_phrase_with_slop() {
+        use crate::service::search::index::Condition;
+
+        let fts_opts = tantivy::schema::TextOptions::default().set_indexing_options(
+            tantivy::schema::TextFieldIndexing::default()
+                .set_index_option(tantivy::schema::IndexRecordOption::WithFreqsAndPositions)
+                .set_tokenizer(O2_TOKENIZER),
+        );
+        let (tantivy_schema, fields, searcher) = build_ram_index(
+            &[("message", fts_opts)],
+            &[&["connection was finally refused"]],
+        );
+        let message = fields[0];
+
+        let search = |slop| {
+            let mut condition = IndexCondition::new();
+            condition.add_condition(Condition::MatchPhrase(
+                "message".to_string(),
+                "connection refused".to_string(),
+                slop,
+            ));
+            condition
+                .to_tantivy_query(tantivy_schema.clone(), &[message])
+                .unwrap()
+        };
+
+        // the phrase query warms up the positions of its terms
+        let query = search(2);
+        let warm_terms = collect_warm_terms(&*query);
+        assert!(
+            warm_terms[&message]
+                .values()
+                .all(|need_position| *need_position)
+        );
+        warm_up_terms(&searcher, &warm_terms, HashSet::new(), None)
+            .await
+            .unwrap();
+        let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+        assert_eq!(count, 1);
+
+        // two tokens between the phrase terms need a slop of 2
+        let count = searcher
+            .search(&search(0), &tantivy::collector::Count)
+            .unwrap();
+        assert_eq!(count, 0);
+        let count = searcher
+            .search(&search(1), &tantivy::collector::Count)
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_search_excluded_fts_fields() {
+        use crate::service::search::index::Condition;
+
+        let fts_opts = tantivy::schema::TextOptions::default().set_indexing_options(
+            tantivy::schema::TextFieldIndexing::default()
+                .set_index_option(tantivy::schema::IndexRecordOption::Basic)
+                .set_tokenizer(O2_TOKENIZER),
+        );
+        let (tantivy_schema, fields, searcher) = build_ram_index(
+            &[("message", fts_opts.clone()), ("blob", fts_opts)],
+            &[&["connection error", "aGVsbG8"], &["ok", "error"]],
+        );
+        let (message, blob) = (fields[0], fields[1]);
+
+        let exclude_fields = vec!["blob".to_string()];
+        let fts_fields = get_fts_fields(&tantivy_schema, &exclude_fields);
+        assert_eq!(fts_fields, vec![message]);
+
+        // the terms of the excluded field don't match
+        for (value, expected) in [("aGVsbG8", 0), ("error", 1)] {
+            let mut condition = IndexCondition::new();
+            condition.add_condition(Condition::MatchAll(value.to_string()));
+            let query = condition
+                .to_tantivy_query(tantivy_schema.clone(), &fts_fields)
+                .unwrap();
+            let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+            assert_eq!(count, expected, "match_all({value})");
+        }
+
+        // the field is searched when it isn't excluded
+        let fts_fields = get_fts_fields(&tantivy_schema, &[]);
+        assert_eq!(fts_fields, vec![message, blob]);
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::MatchAll("aGVsbG8".to_string()));
+        let query = condition
+            .to_tantivy_query(tantivy_schema.clone(), &fts_fields)
+            .unwrap();
+        let count = searcher.search(&query, &tantivy::collector::Count).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_regex_condition_matched_docs() {
+        use crate::service::search::index::Condition;
+
+        let (tantivy_schema, _, searcher) = build_ram_index(
+            &[("path", tantivy::schema::STRING)],
+            &[
+                &["/api/v1/users"],
+                &["/api/v2/orders"],
+                &["/api/v12/users"],
+                &["/health"],
+            ],
+        );
+
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::Regex(
+            "path".to_string(),
+            "/api/v[0-9]+/users".to_string(),
+        ));
+        // regex can't be warmed by exact terms, need the whole term dictionary
+        assert_eq!(condition.need_all_term_fields(), vec!["path".to_string()]);
+
+        let query = condition.to_tantivy_query(tantivy_schema, &[]).unwrap();
+        let TantivyResult::RowIds(row_ids) =
+            TantivyResult::handle_matched_docs(&searcher, query).unwrap()
+        else {
+            panic!("Expected RowIds result");
+        };
+        let TantivyResult::RowIdsBitVec(num_rows, bitvec) = row_ids_to_bitvec(row_ids, 4).unwrap()
+        else {
+            panic!("Expected RowIdsBitVec result");
+        };
+        assert_eq!(num_rows, 2);
+        assert_eq!(
+            bitvec.iter().by_vals().collect::<Vec<_>>(),
+            vec![true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_regex_condition_field_not_indexed() {
+        use crate::service::search::index::Condition;
+
+        let mut schema_builder = tantivy::schema::Schema::builder();
+        schema_builder.add_text_field("path", tantivy::schema::STRING);
+        let tantivy_schema = schema_builder.build();
+
+        // the error makes the file be added back to datafusion
+        let mut condition = IndexCondition::new();
+        condition.add_condition(Condition::Regex(
+            "method".to_string(),
+            "GET|POST".to_string(),
+        ));
+        assert!(condition.to_tantivy_query(tantivy_schema, &[]).is_err());
+    }
+
+    #[test]
+    fn test_partial_indexable_condition_prunes_files() {
+        use crate::service::search::index::Condition;
+
+        let (tantivy_schema, _, searcher) = build_ram_index(
+            &[("status", tantivy::schema::STRING)],
+            &[&["200"], &["500"], &["200"]],
+        );
+
+        let search = |status: &str| {
+            // `method` is not indexed, it's the residual condition filtered by datafusion
+            let mut condition = IndexCondition::new();
+            condition.add_condition(Condition::Equal("status".to_string(), status.to_string()));
+            condition.add_condition(Condition::Equal("method".to_string(), "GET".to_string()));
+            let (condition, residual) = condition.split_indexable(&tantivy_schema, &[]);
+            assert_eq!(residual.to_query(), "method=GET");
+            let query = condition
+                .to_tantivy_query(tantivy_schema.clone(), &[])
+                .unwrap();
+            let TantivyResult::RowIds(row_ids) =
+                TantivyResult::handle_matched_docs(&searcher, query).unwrap()
+            else {
+                panic!("Expected RowIds result");
+            };
+            row_ids
+        };
+
+        // the rows are still pruned by the indexable condition
+        assert_eq!(search("500"), HashSet::from([1]));
+        // no rows matched, the file is pruned
+        assert!(search("404").is_empty());
+    }
+
+    #[test]
+    fn test_is_cached_ratio_too_low() {
+        // default 50%
+        assert!(is_cached_ratio_too_low(4, 10, 50));
+        assert!(!is_cached_ratio_too_low(5, 10, 50));
+        // lower threshold for fast disk cache
+        assert!(!is_cached_ratio_too_low(2, 10, 20));
+        assert!(is_cached_ratio_too_low(1, 10, 20));
+        // 0 means always use the cache
+        assert!(!is_cached_ratio_too_low(0, 10, 0));
+        // 100 means use the cache only when all the files are cached
+        assert!(is_cached_ratio_too_low(9, 10, 100));
+        assert!(!is_cached_ratio_too_low(10, 10, 100));
+        // out of range threshold is treated as 100
+        assert!(is_cached_ratio_too_low(9, 10, 200));
+    }
+
+    #[test]
+    fn test_check_skip_threshold() {
+        // 90 of 100 records matched, exceed the threshold
+        let (percent, need_skip) = check_skip_threshold(90, 100, 35);
+        assert_eq!(percent, 90.0);
+        assert!(need_skip);
+        // under the threshold
+        let (_, need_skip) = check_skip_threshold(10, 100, 35);
+        assert!(!need_skip);
+
+        // exactly at the threshold is kept, one more matched doc skips the file
+        let (percent, need_skip) = check_skip_threshold(100, 1000, 10);
+        assert!((percent - 10.0).abs() < 1e-9);
+        assert!(!need_skip);
+        let (_, need_skip) = check_skip_threshold(101, 1000, 10);
+        assert!(need_skip);
+
+        // no records and huge files
+        assert_eq!(check_skip_threshold(10, 0, 10), (0.0, false));
+        let (_, need_skip) = check_skip_threshold(usize::MAX, i64::MAX, usize::MAX);
+        assert!(!need_skip);
+    }
+
+    #[test]
+    fn test_disabled_skip_threshold_builds_bitvec() {
+        let row_ids: HashSet<u32> = (0..90).collect();
+        // threshold 0 means the skip is disabled for the query
+        let (_, need_skip) = check_skip_threshold(row_ids.len(), 100, 0);
+        assert!(!need_skip);
+        match row_ids_to_bitvec(row_ids, 100).unwrap() {
+            TantivyResult::RowIdsBitVec(num_rows, bitvec) => {
+                assert_eq!(num_rows, 90);
+                assert_eq!(bitvec.len(), 100);
+                assert_eq!(bitvec.count_ones(), 90);
+                assert!(bitvec[89]);
+                assert!(!bitvec[90]);
+            }
+            _ => panic!("Expected RowIdsBitVec result"),
+        }
+    }
+
+    #[test]
+    fn test_row_ids_to_bitvec_out_of_range() {
+        let row_ids: HashSet<u32> = [1, 10].into_iter().collect();
+        assert!(row_ids_to_bitvec(row_ids, 10).is_err());
+    }
+
     #[test]
     fn test_get_simple_distinct_field_none() {
         let idx_optimize_rule = None;
@@ -1355,8 +3409,8 @@ mod tests {
             Some(config::meta::inverted_index::IndexOptimizeMode::SimpleSelect(100, false));
         let target_partitions = 2;
 
-        let (file_groups, limit) =
-            partition_tantivy_files(files, &idx_optimize_mode, target_partitions);
+        let (file_groups, limit, _) =
+            partition_tantivy_files(files, &idx_optimize_mode, target_partitions, (0, 0));
         assert_eq!(limit, 100);
         assert!(!file_groups.is_empty());
     }
@@ -1368,8 +3422,8 @@ mod tests {
             Some(config::meta::inverted_index::IndexOptimizeMode::SimpleSelect(0, false));
         let target_partitions = 2;
 
-        let (file_groups, limit) =
-            partition_tantivy_files(files, &idx_optimize_mode, target_partitions);
+        let (file_groups, limit, _) =
+            partition_tantivy_files(files, &idx_optimize_mode, target_partitions, (0, 0));
         assert_eq!(limit, 0);
         assert_eq!(file_groups.len(), 1);
     }
@@ -1385,8 +3439,8 @@ mod tests {
         let idx_optimize_mode = Some(config::meta::inverted_index::IndexOptimizeMode::SimpleCount);
         let target_partitions = 2;
 
-        let (file_groups, limit) =
-            partition_tantivy_files(files, &idx_optimize_mode, target_partitions);
+        let (file_groups, limit, _) =
+            partition_tantivy_files(files, &idx_optimize_mode, target_partitions, (0, 0));
         assert_eq!(limit, 0);
         assert!(file_groups.len() <= 2);
     }
@@ -1397,7 +3451,7 @@ mod tests {
         let idx_optimize_rule = Some(config::meta::inverted_index::IndexOptimizeMode::SimpleCount);
         let parquet_file = &create_file_key(1, 10);
 
-        let result = generate_cache_key(&index_condition, &idx_optimize_rule, parquet_file);
+        let result = generate_cache_key(&index_condition, &idx_optimize_rule, &[], parquet_file);
         assert_eq!(result, String::new());
     }
 
@@ -1410,7 +3464,12 @@ mod tests {
         let idx_optimize_rule = None;
         let parquet_file = &create_file_key(1, 10);
 
-        let result = generate_cache_key(&Some(index_condition), &idx_optimize_rule, parquet_file);
+        let result = generate_cache_key(
+            &Some(index_condition),
+            &idx_optimize_rule,
+            &[],
+            parquet_file,
+        );
         assert_eq!(result, String::new());
     }
 
@@ -1421,7 +3480,12 @@ mod tests {
         let idx_optimize_rule = Some(config::meta::inverted_index::IndexOptimizeMode::SimpleCount);
         let parquet_file = &create_file_key(1, 10);
 
-        let result = generate_cache_key(&Some(index_condition), &idx_optimize_rule, parquet_file);
+        let result = generate_cache_key(
+            &Some(index_condition),
+            &idx_optimize_rule,
+            &[],
+            parquet_file,
+        );
         assert!(!result.is_empty());
         assert!(result.contains("file_1_10"));
     }
@@ -1570,7 +3634,7 @@ mod tests {
     fn test_group_files_by_time_range_single_file() {
         let files = vec![create_file_key(1, 10)];
         let partition_num = 3;
-        let groups = group_files_by_time_range(files, partition_num);
+        let groups = group_files_by_time_range(files, partition_num, (0, 0));
 
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].len(), 1);
@@ -1585,7 +3649,7 @@ mod tests {
             create_file_key(21, 30),
         ];
         let partition_num = 10; // More partitions than files
-        let groups = group_files_by_time_range(files, partition_num);
+        let groups = group_files_by_time_range(files, partition_num, (0, 0));
 
         // Should create separate groups for non-overlapping files
         assert_eq!(groups.len(), 3);
@@ -1601,7 +3665,7 @@ mod tests {
             vec![create_file_key(11, 20)],
             vec![create_file_key(21, 30)],
         ];
-        let result = regroup_tantivy_files(file_groups);
+        let result = regroup_tantivy_files(file_groups, false);
 
         assert_eq!(result.len(), 1); // Max group length is 1
         assert_eq!(result[0].len(), 3); // Should contain all files
@@ -1609,4 +3673,85 @@ mod tests {
         assert_eq!(result[0][1].key, "file_11_20");
         assert_eq!(result[0][2].key, "file_21_30");
     }
+
+    #[test]
+    fn test_select_cache_type_with_stream_policy() {
+        let mut cfg = Config::default();
+        cfg.memory_cache.enabled = true;
+        cfg.memory_cache.skip_size = 100;
+        cfg.disk_cache.enabled = true;
+        cfg.disk_cache.skip_size = 1000;
+
+        // the global skip sizes
+        assert_eq!(
+            select_cache_type(&cfg, None, 50, false),
+            file_data::CacheType::Memory
+        );
+        assert_eq!(
+            select_cache_type(&cfg, None, 500, false),
+            file_data::CacheType::Disk
+        );
+        assert_eq!(
+            select_cache_type(&cfg, None, 5000, false),
+            file_data::CacheType::None
+        );
+        assert_eq!(
+            select_cache_type(&cfg, None, 500, true),
+            file_data::CacheType::None
+        );
+
+        // the skip sizes of the stream replace the global ones
+        let hot = StreamCachePolicy {
+            memory_skip_size: Some(1000),
+            disk_skip_size: Some(10000),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_cache_type(&cfg, Some(&hot), 500, false),
+            file_data::CacheType::Memory
+        );
+        assert_eq!(
+            select_cache_type(&cfg, Some(&hot), 5000, false),
+            file_data::CacheType::Disk
+        );
+
+        // a forced cache type ignores the scan size, but not a disabled cache
+        let cold = StreamCachePolicy {
+            cache_type: Some(StreamCacheType::None),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_cache_type(&cfg, Some(&cold), 50, false),
+            file_data::CacheType::None
+        );
+        let disk = StreamCachePolicy {
+            cache_type: Some(StreamCacheType::Disk),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_cache_type(&cfg, Some(&disk), 50000, false),
+            file_data::CacheType::Disk
+        );
+        assert_eq!(
+            select_cache_type(&cfg, Some(&disk), 50, true),
+            file_data::CacheType::None
+        );
+    }
+
+    #[test]
+    fn test_stream_type_from_file() {
+        assert_eq!(
+            stream_type_from_file("files/default/logs/olympics/2022/10/03/10/1.parquet"),
+            StreamType::Logs
+        );
+        assert_eq!(
+            stream_type_from_file("files/default/enrichment_tables/ip/2022/10/03/10/1.parquet"),
+            StreamType::EnrichmentTables
+        );
+        // the stream name doesn't affect the stream type
+        assert_eq!(
+            stream_type_from_file("files/default/traces/app_metrics/2022/10/03/10/1.parquet"),
+            StreamType::Traces
+        );
+    }
 }
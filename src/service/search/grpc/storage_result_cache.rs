@@ -0,0 +1,271 @@
+// Copyright 2025 OpenObserve Inc.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use config::{
+    get_config,
+    meta::{inverted_index::IndexOptimizeMode, search::ScanStats, stream::FileKey},
+    utils::hash::gxhash,
+};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+
+use crate::service::search::{
+    grpc::{QueryParams, storage::FilterBack},
+    index::IndexCondition,
+};
+
+pub static GLOBAL_CACHE: Lazy<StorageResultCache> = Lazy::new(StorageResultCache::default);
+
+/// The result of loading the file list of a storage search, the tables are created from it.
+///
+/// The tables themselves can't be cached, they read the files registered under the trace_id
+/// of the query and those are cleared when the query finishes.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// files left to scan after the inverted index search
+    pub files: Vec<FileKey>,
+    /// the scan stats of the files before they are loaded into the local cache
+    pub scan_stats: ScanStats,
+    pub filter_back: FilterBack,
+}
+
+struct CachedEntry {
+    files_hash: u64,
+    expires_at: Instant,
+    entry: CacheEntry,
+}
+
+/// Cache created for storing the loaded file list of the storage search
+pub struct StorageResultCache {
+    entries: DashMap<String, CachedEntry>,
+    cacher: parking_lot::Mutex<VecDeque<String>>,
+    max_entries: usize,
+    ttl: Duration,
+}
+
+impl StorageResultCache {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
+        Self {
+            entries: DashMap::new(),
+            cacher: parking_lot::Mutex::new(VecDeque::new()),
+            max_entries,
+            ttl,
+        }
+    }
+
+    /// Returns the cached entry if it isn't expired and it was loaded from the same file list,
+    /// otherwise the stale entry is removed.
+    pub fn get(&self, key: &str, files_hash: u64) -> Option<CacheEntry> {
+        let entry = self.entries.get(key)?;
+        if entry.files_hash == files_hash && entry.expires_at > Instant::now() {
+            return Some(entry.entry.clone());
+        }
+        drop(entry);
+        let mut w = self.cacher.lock();
+        self.entries.remove(key);
+        w.retain(|k| k != key);
+        None
+    }
+
+    pub fn put(&self, key: String, files_hash: u64, entry: CacheEntry) {
+        let mut w = self.cacher.lock();
+        if !self.entries.contains_key(&key) {
+            if w.len() >= self.max_entries {
+                // release 10% of the cache
+                for _ in 0..(std::cmp::max(1, self.max_entries / 10)) {
+                    match w.pop_front() {
+                        Some(k) => {
+                            self.entries.remove(&k);
+                        }
+                        None => break,
+                    }
+                }
+            }
+            w.push_back(key.clone());
+        }
+        drop(w);
+        self.entries.insert(
+            key,
+            CachedEntry {
+                files_hash,
+                expires_at: Instant::now() + self.ttl,
+                entry,
+            },
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for StorageResultCache {
+    fn default() -> Self {
+        let cfg = get_config();
+        Self::new(
+            cfg.limit.storage_result_cache_max_entries,
+            Duration::from_secs(cfg.limit.storage_result_cache_ttl),
+        )
+    }
+}
+
+/// Generates the cache key from the inputs of the storage search except the file list, the
+/// file list is compared by [`files_hash`] so a changed file list replaces the old entry.
+/// The full text search fields excluded by the stream settings change the files the index
+/// matches, so they are part of the key.
+pub fn generate_cache_key(
+    query: &QueryParams,
+    index_condition: &Option<IndexCondition>,
+    fst_fields: &[String],
+    fts_exclude_fields: &[String],
+    idx_optimize_rule: &Option<IndexOptimizeMode>,
+) -> String {
+    let mut h = gxhash::new_hasher();
+    query.use_inverted_index.hash(&mut h);
+    query.disable_index_skip_threshold.hash(&mut h);
+    query.strict_index_only.hash(&mut h);
+    index_condition.hash(&mut h);
+    fst_fields.hash(&mut h);
+    fts_exclude_fields.hash(&mut h);
+    idx_optimize_rule.hash(&mut h);
+    format!(
+        "{}/{}/{}/{}_{}/{}",
+        query.org_id,
+        query.stream_type,
+        query.stream_name,
+        query.time_range.0,
+        query.time_range.1,
+        h.finish()
+    )
+}
+
+pub fn files_hash(files: &[FileKey]) -> u64 {
+    let mut h = gxhash::new_hasher();
+    for file in files {
+        file.id.hash(&mut h);
+        file.account.hash(&mut h);
+        file.key.hash(&mut h);
+        file.deleted.hash(&mut h);
+        file.meta.min_ts.hash(&mut h);
+        file.meta.max_ts.hash(&mut h);
+        file.meta.records.hash(&mut h);
+        file.meta.original_size.hash(&mut h);
+        file.meta.compressed_size.hash(&mut h);
+        file.meta.index_size.hash(&mut h);
+        file.segment_ids
+            .as_ref()
+            .map(|ids| ids.count_ones())
+            .hash(&mut h);
+    }
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use config::meta::stream::FileMeta;
+
+    use super::*;
+
+    fn file_key(key: &str, records: i64) -> FileKey {
+        FileKey {
+            key: key.to_string(),
+            meta: FileMeta {
+                min_ts: 1000,
+                max_ts: 2000,
+                records,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn entry(files: Vec<FileKey>) -> CacheEntry {
+        CacheEntry {
+            scan_stats: ScanStats {
+                files: files.len() as i64,
+                ..Default::default()
+            },
+            files,
+            filter_back: FilterBack::None,
+        }
+    }
+
+    #[test]
+    fn test_storage_result_cache_hit_and_miss() {
+        let cache = StorageResultCache::new(10, Duration::from_secs(60));
+        let files = vec![file_key("a.parquet", 10), file_key("b.parquet", 20)];
+        let hash = files_hash(&files);
+        cache.put("key".to_string(), hash, entry(files.clone()));
+
+        // identical query and file list hits the cache
+        let hit = cache.get("key", files_hash(&files)).unwrap();
+        assert_eq!(hit.files.len(), 2);
+        assert_eq!(hit.scan_stats.files, 2);
+
+        // a changed file list misses and invalidates the entry
+        let mut changed = files.clone();
+        changed.push(file_key("c.parquet", 30));
+        assert!(cache.get("key", files_hash(&changed)).is_none());
+        assert!(cache.is_empty());
+
+        // a changed file in place misses as well
+        cache.put("key".to_string(), hash, entry(files.clone()));
+        let compacted = vec![file_key("a.parquet", 10), file_key("b.parquet", 25)];
+        assert!(cache.get("key", files_hash(&compacted)).is_none());
+    }
+
+    #[test]
+    fn test_storage_result_cache_expire_and_evict() {
+        let cache = StorageResultCache::new(10, Duration::ZERO);
+        let files = vec![file_key("a.parquet", 10)];
+        let hash = files_hash(&files);
+        cache.put("key".to_string(), hash, entry(files.clone()));
+        assert!(cache.get("key", hash).is_none());
+
+        let cache = StorageResultCache::new(2, Duration::from_secs(60));
+        for i in 0..3 {
+            cache.put(format!("key{i}"), hash, entry(files.clone()));
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("key0", hash).is_none());
+        assert!(cache.get("key2", hash).is_some());
+    }
+
+    #[test]
+    fn test_storage_result_cache_stale_entry_not_evicting_live() {
+        let cache = StorageResultCache::new(2, Duration::from_secs(60));
+        let files = vec![file_key("a.parquet", 10)];
+        let hash = files_hash(&files);
+        cache.put("key0".to_string(), hash, entry(files.clone()));
+        // the stale entry is removed from the eviction order as well
+        assert!(cache.get("key0", hash + 1).is_none());
+        assert_eq!(cache.cacher.lock().len(), 0);
+
+        cache.put("key0".to_string(), hash, entry(files.clone()));
+        cache.put("key1".to_string(), hash, entry(files.clone()));
+        assert!(cache.get("key0", hash).is_some());
+        assert!(cache.get("key1", hash).is_some());
+    }
+}
@@ -14,24 +14,30 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Display,
 };
 
 use config::{
     TIMESTAMP_COL_NAME,
-    meta::{bitvec::BitVec, inverted_index::IndexOptimizeMode},
+    meta::{
+        bitvec::BitVec,
+        inverted_index::{IndexOptimizeMode, check_histogram_boundaries},
+    },
     utils::tantivy::query::contains_query::ContainsAutomaton,
 };
 use tantivy::{
-    Searcher,
+    DocId, DocSet, Score, Searcher, SegmentOrdinal, SegmentReader, TERMINATED,
     aggregation::{
         AggregationCollector, Key,
         agg_req::{Aggregation, AggregationVariants},
         agg_result::{AggregationResult, BucketResult},
         bucket::{CustomOrder, Order, OrderTarget, TermsAggregation},
     },
+    collector::{Collector, DocSetCollector, SegmentCollector},
+    columnar::Column,
     query::Query,
+    schema::IndexRecordOption,
 };
 
 use crate::service::search::index::IndexCondition;
@@ -147,6 +153,21 @@ impl TantivyResult {
         Ok(Self::Histogram(res))
     }
 
+    /// Counts the matched docs per bucket of `boundaries`, the result has the same shape as
+    /// [`Self::handle_simple_histogram`], one count per bucket.
+    pub fn handle_simple_histogram_buckets(
+        searcher: &Searcher,
+        query: Box<dyn Query>,
+        boundaries: Vec<i64>,
+    ) -> anyhow::Result<Self> {
+        check_histogram_boundaries(&boundaries)?;
+        let res = searcher.search(
+            &query,
+            &BucketHistogramCollector::new(TIMESTAMP_COL_NAME, boundaries),
+        )?;
+        Ok(Self::Histogram(res))
+    }
+
     pub fn handle_simple_top_n(
         searcher: &Searcher,
         query: Box<dyn Query>,
@@ -203,11 +224,23 @@ impl TantivyResult {
 
     pub fn handle_simple_distinct(
         searcher: &Searcher,
+        query: Box<dyn Query>,
         index_condition: &IndexCondition,
         field: &str,
         limit: usize,
         ascend: bool,
     ) -> anyhow::Result<Self> {
+        // only a str_match of the distinct field itself can filter the term dictionary directly
+        if !index_condition.is_simple_str_match(field) {
+            // the descending terms would need the postings of every term checked, let the
+            // filter be added back instead
+            if !ascend {
+                anyhow::bail!(
+                    "search->tantivy: descending distinct of {field} with other filters is not supported"
+                );
+            }
+            return Self::handle_filtered_distinct(searcher, query, field, limit);
+        }
         let mut distinct_values: Vec<String> = Vec::with_capacity(limit * 4);
         let field = searcher.schema().get_field(field).unwrap();
         if let Some((value, case_sensitive)) = index_condition.get_str_match_condition() {
@@ -243,21 +276,146 @@ impl TantivyResult {
         }
         Ok(Self::Distinct(distinct_values.into_iter().collect()))
     }
+
+    /// Returns the first `limit` terms of `field` in ascending order that appear in at least one
+    /// doc matched by the query, the postings of each term are checked against the matched docs
+    /// of the segment.
+    fn handle_filtered_distinct(
+        searcher: &Searcher,
+        query: Box<dyn Query>,
+        field: &str,
+        limit: usize,
+    ) -> anyhow::Result<Self> {
+        let field = searcher.schema().get_field(field)?;
+        let mut matched_docs = vec![HashSet::new(); searcher.segment_readers().len()];
+        for doc in searcher.search(&query, &DocSetCollector)? {
+            matched_docs[doc.segment_ord as usize].insert(doc.doc_id);
+        }
+
+        let mut distinct_values = BTreeSet::new();
+        for (seg, matched) in searcher.segment_readers().iter().zip(matched_docs) {
+            if matched.is_empty() {
+                continue;
+            }
+            let index = seg.inverted_index(field)?;
+            let mut terms = index.terms().stream()?;
+            // the terms are sorted, the first `limit` matched terms of each segment are enough
+            let mut seg_values = 0;
+            while let Some((term, term_info)) = terms.next() {
+                if seg_values >= limit {
+                    break;
+                }
+                let mut postings =
+                    index.read_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                let mut doc = postings.doc();
+                while doc != TERMINATED {
+                    if matched.contains(&doc) {
+                        distinct_values.insert(String::from_utf8(term.to_vec())?);
+                        seg_values += 1;
+                        break;
+                    }
+                    doc = postings.advance();
+                }
+            }
+        }
+
+        Ok(Self::Distinct(
+            distinct_values.into_iter().take(limit).collect(),
+        ))
+    }
+}
+
+/// Histogram collector of an i64 fast field with arbitrary bucket boundaries, the values out of
+/// the boundaries aren't counted. The boundaries must be checked by
+/// [`check_histogram_boundaries`].
+struct BucketHistogramCollector {
+    field: String,
+    boundaries: Vec<i64>,
+}
+
+impl BucketHistogramCollector {
+    fn new(field: &str, boundaries: Vec<i64>) -> Self {
+        Self {
+            field: field.to_string(),
+            boundaries,
+        }
+    }
+}
+
+impl Collector for BucketHistogramCollector {
+    type Fruit = Vec<u64>;
+    type Child = BucketHistogramSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let column = segment.fast_fields().i64(&self.field)?;
+        Ok(BucketHistogramSegmentCollector {
+            column,
+            boundaries: self.boundaries.clone(),
+            counts: vec![0; self.boundaries.len() - 1],
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<u64>>) -> tantivy::Result<Vec<u64>> {
+        let mut counts = vec![0; self.boundaries.len() - 1];
+        for fruit in segment_fruits {
+            for (count, v) in counts.iter_mut().zip(fruit) {
+                *count += v;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+struct BucketHistogramSegmentCollector {
+    column: Column<i64>,
+    boundaries: Vec<i64>,
+    counts: Vec<u64>,
+}
+
+impl SegmentCollector for BucketHistogramSegmentCollector {
+    type Fruit = Vec<u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        for value in self.column.values_for_doc(doc) {
+            // the number of boundaries not greater than the value, 0 or all of them means the
+            // value is out of the histogram
+            let pos = self.boundaries.partition_point(|b| *b <= value);
+            if pos > 0 && pos < self.boundaries.len() {
+                self.counts[pos - 1] += 1;
+            }
+        }
+    }
+
+    fn harvest(self) -> Vec<u64> {
+        self.counts
+    }
 }
 
 // TantivyMultiResultBuilder is used to build a TantivyMultiResult from multiple TantivyResult
 pub enum TantivyMultiResultBuilder {
     RowNums(u64),
     Histogram(Vec<Vec<u64>>),
-    TopN(Vec<(String, u64)>),
+    // the partial top n lists, the limit and whether sorted ascending by count
+    TopN(Vec<(String, u64)>, usize, bool),
     Distinct(HashSet<String>),
 }
 
 impl TantivyMultiResultBuilder {
     pub fn new(optimize_rule: &Option<IndexOptimizeMode>) -> Self {
         match optimize_rule {
-            Some(IndexOptimizeMode::SimpleHistogram(..)) => Self::Histogram(vec![]),
-            Some(IndexOptimizeMode::SimpleTopN(..)) => Self::TopN(vec![]),
+            Some(IndexOptimizeMode::SimpleHistogram(..))
+            | Some(IndexOptimizeMode::SimpleHistogramBuckets(..)) => Self::Histogram(vec![]),
+            Some(IndexOptimizeMode::SimpleTopN(_, limit, ascend)) => {
+                Self::TopN(vec![], *limit, *ascend)
+            }
             Some(IndexOptimizeMode::SimpleDistinct(..)) => Self::Distinct(HashSet::new()),
             Some(IndexOptimizeMode::SimpleSelect(..))
             | Some(IndexOptimizeMode::SimpleCount)
@@ -285,7 +443,7 @@ impl TantivyMultiResultBuilder {
 
     pub fn add_top_n(&mut self, top_n: Vec<(String, u64)>) {
         match self {
-            Self::TopN(a) => a.extend(top_n),
+            Self::TopN(a, ..) => a.extend(top_n),
             _ => unreachable!("unsupported tantivy multi result"),
         }
     }
@@ -297,6 +455,7 @@ impl TantivyMultiResultBuilder {
         }
     }
 
+    #[cfg(test)]
     pub fn num_rows(&self) -> usize {
         match self {
             Self::RowNums(a) => *a as usize,
@@ -322,12 +481,36 @@ impl TantivyMultiResultBuilder {
                     .collect();
                 TantivyMultiResult::Histogram(histogram)
             }
-            Self::TopN(a) => TantivyMultiResult::TopN(a),
+            Self::TopN(a, limit, ascend) => TantivyMultiResult::TopN(merge_top_n(a, limit, ascend)),
             Self::Distinct(a) => TantivyMultiResult::Distinct(a),
         }
     }
 }
 
+// merge the partial top n lists of each file, the same value may appear in many files so we
+// sum the counts of the same value, then sort by the merged counts and keep the first `limit`
+// values, the values with the same count keep the order of first appearance
+fn merge_top_n(top_n: Vec<(String, u64)>, limit: usize, ascend: bool) -> Vec<(String, u64)> {
+    let mut positions: HashMap<String, usize> = HashMap::with_capacity(top_n.len());
+    let mut merged: Vec<(String, u64)> = Vec::with_capacity(top_n.len());
+    for (value, count) in top_n {
+        match positions.get(&value) {
+            Some(&pos) => merged[pos].1 += count,
+            None => {
+                positions.insert(value.clone(), merged.len());
+                merged.push((value, count));
+            }
+        }
+    }
+    if ascend {
+        merged.sort_by_key(|(_, count)| *count);
+    } else {
+        merged.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    }
+    merged.truncate(limit);
+    merged
+}
+
 pub enum TantivyMultiResult {
     RowNums(u64),
     Histogram(Vec<u64>),
@@ -444,6 +627,103 @@ mod tests {
         assert!(memory_size >= std::mem::size_of::<Vec<u64>>());
     }
 
+    #[test]
+    fn test_handle_simple_histogram_buckets() {
+        let mut schema_builder = tantivy::schema::SchemaBuilder::new();
+        let ts_field = schema_builder.add_i64_field(TIMESTAMP_COL_NAME, tantivy::schema::FAST);
+        let index = tantivy::index::Index::create_in_ram(schema_builder.build());
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for ts in [-5i64, 0, 3, 9, 10, 99, 100, 5000, 20000] {
+            writer.add_document(tantivy::doc!(ts_field => ts)).unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let res = TantivyResult::handle_simple_histogram_buckets(
+            &searcher,
+            Box::new(tantivy::query::AllQuery),
+            vec![0, 10, 100, 10000],
+        )
+        .unwrap();
+        // -5 and 20000 are out of the boundaries
+        match res {
+            TantivyResult::Histogram(histogram) => assert_eq!(histogram, vec![3, 2, 2]),
+            _ => panic!("Expected Histogram result"),
+        }
+
+        let res = TantivyResult::handle_simple_histogram_buckets(
+            &searcher,
+            Box::new(tantivy::query::AllQuery),
+            vec![0, 100, 10],
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_handle_simple_distinct_with_filter() {
+        let mut schema_builder = tantivy::schema::SchemaBuilder::new();
+        let level = schema_builder.add_text_field("level", tantivy::schema::STRING);
+        let service = schema_builder.add_text_field("service", tantivy::schema::STRING);
+        let index = tantivy::index::Index::create_in_ram(schema_builder.build());
+        let mut writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for (l, s) in [
+            ("info", "api"),
+            ("error", "api"),
+            ("info", "web"),
+            ("debug", "web"),
+            ("warn", "db"),
+        ] {
+            writer
+                .add_document(tantivy::doc!(level => l, service => s))
+                .unwrap();
+        }
+        writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+
+        let condition = IndexCondition {
+            conditions: vec![crate::service::search::index::Condition::Equal(
+                "service".to_string(),
+                "web".to_string(),
+            )],
+        };
+        let query = Box::new(tantivy::query::TermQuery::new(
+            tantivy::Term::from_field_text(service, "web"),
+            IndexRecordOption::Basic,
+        ));
+        let res =
+            TantivyResult::handle_simple_distinct(&searcher, query, &condition, "level", 10, true)
+                .unwrap();
+        match res {
+            TantivyResult::Distinct(distinct) => assert_eq!(
+                distinct,
+                HashSet::from(["info".to_string(), "debug".to_string()])
+            ),
+            _ => panic!("Expected Distinct result"),
+        }
+
+        // the limit keeps the first terms
+        let res = TantivyResult::handle_filtered_distinct(
+            &searcher,
+            Box::new(tantivy::query::AllQuery),
+            "level",
+            2,
+        )
+        .unwrap();
+        match res {
+            TantivyResult::Distinct(distinct) => assert_eq!(
+                distinct,
+                HashSet::from(["debug".to_string(), "error".to_string()])
+            ),
+            _ => panic!("Expected Distinct result"),
+        }
+
+        // descending order adds the filter back
+        let query = Box::new(tantivy::query::AllQuery);
+        let res =
+            TantivyResult::handle_simple_distinct(&searcher, query, &condition, "level", 2, false);
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_tantivy_result_get_memory_size_top_n() {
         let top_n = vec![
@@ -484,7 +764,10 @@ mod tests {
         // Test with SimpleTopN
         let optimize_rule = Some(IndexOptimizeMode::SimpleTopN("field".to_string(), 10, true));
         let builder = TantivyMultiResultBuilder::new(&optimize_rule);
-        assert!(matches!(builder, TantivyMultiResultBuilder::TopN(_)));
+        assert!(matches!(
+            builder,
+            TantivyMultiResultBuilder::TopN(_, 10, true)
+        ));
 
         // Test with SimpleDistinct
         let optimize_rule = Some(IndexOptimizeMode::SimpleDistinct(
@@ -549,7 +832,7 @@ mod tests {
 
     #[test]
     fn test_tantivy_multi_result_builder_add_top_n() {
-        let mut builder = TantivyMultiResultBuilder::TopN(vec![]);
+        let mut builder = TantivyMultiResultBuilder::TopN(vec![], 10, false);
 
         let top_n1 = vec![("term1".to_string(), 100), ("term2".to_string(), 50)];
         let top_n2 = vec![("term3".to_string(), 75)];
@@ -558,7 +841,7 @@ mod tests {
         builder.add_top_n(top_n2);
 
         match &builder {
-            TantivyMultiResultBuilder::TopN(results) => {
+            TantivyMultiResultBuilder::TopN(results, ..) => {
                 assert_eq!(results.len(), 3);
                 assert_eq!(results[0].0, "term1");
                 assert_eq!(results[0].1, 100);
@@ -569,6 +852,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tantivy_multi_result_builder_build_top_n_merge() {
+        let mut builder = TantivyMultiResultBuilder::TopN(vec![], 10, false);
+        builder.add_top_n(vec![("500".to_string(), 10), ("404".to_string(), 5)]);
+        builder.add_top_n(vec![("404".to_string(), 7), ("503".to_string(), 1)]);
+        builder.add_top_n(vec![("500".to_string(), 2)]);
+
+        match builder.build() {
+            TantivyMultiResult::TopN(top_n) => {
+                assert_eq!(
+                    top_n,
+                    vec![
+                        ("500".to_string(), 12),
+                        ("404".to_string(), 12),
+                        ("503".to_string(), 1),
+                    ]
+                );
+            }
+            _ => panic!("Expected TopN result"),
+        }
+    }
+
+    #[test]
+    fn test_tantivy_multi_result_builder_build_top_n_sort_and_limit() {
+        let partial_top_n = [
+            vec![("500".to_string(), 3), ("404".to_string(), 2)],
+            vec![("200".to_string(), 4), ("404".to_string(), 6)],
+            vec![("503".to_string(), 1), ("200".to_string(), 1)],
+        ];
+
+        // the merged counts are re-sorted and truncated to the limit
+        let mut builder = TantivyMultiResultBuilder::TopN(vec![], 2, false);
+        for top_n in partial_top_n.iter() {
+            builder.add_top_n(top_n.clone());
+        }
+        assert_eq!(
+            builder.build().top_n(),
+            vec![("404".to_string(), 8), ("200".to_string(), 5)]
+        );
+
+        // ascending keeps the least frequent values
+        let mut builder = TantivyMultiResultBuilder::TopN(vec![], 2, true);
+        for top_n in partial_top_n.iter() {
+            builder.add_top_n(top_n.clone());
+        }
+        assert_eq!(
+            builder.build().top_n(),
+            vec![("503".to_string(), 1), ("500".to_string(), 3)]
+        );
+    }
+
     #[test]
     fn test_tantivy_multi_result_builder_add_distinct() {
         let mut builder = TantivyMultiResultBuilder::Distinct(HashSet::new());
@@ -603,7 +937,7 @@ mod tests {
         let builder = TantivyMultiResultBuilder::Histogram(vec![]);
         assert_eq!(builder.num_rows(), 0);
 
-        let builder = TantivyMultiResultBuilder::TopN(vec![]);
+        let builder = TantivyMultiResultBuilder::TopN(vec![], 10, false);
         assert_eq!(builder.num_rows(), 0);
 
         let builder = TantivyMultiResultBuilder::Distinct(HashSet::new());
@@ -644,7 +978,7 @@ mod tests {
         }
 
         // Test TopN build
-        let mut builder = TantivyMultiResultBuilder::TopN(vec![]);
+        let mut builder = TantivyMultiResultBuilder::TopN(vec![], 10, false);
         builder.add_top_n(vec![("term1".to_string(), 100)]);
         let result = builder.build();
         match result {
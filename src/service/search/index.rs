@@ -21,7 +21,10 @@ use std::{
 use config::{
     INDEX_FIELD_NAME_FOR_ALL, get_config,
     meta::inverted_index::UNKNOWN_NAME,
-    utils::tantivy::{query::contains_query::ContainsQuery, tokenizer::o2_collect_search_tokens},
+    utils::tantivy::{
+        query::contains_query::ContainsQuery,
+        tokenizer::{O2_TOKENIZER, collect_search_tokens, is_fts_tokenizer},
+    },
 };
 use datafusion::{
     arrow::datatypes::{DataType, SchemaRef},
@@ -43,20 +46,20 @@ use sqlparser::ast::{
 use tantivy::{
     Term,
     query::{
-        AllQuery, BooleanQuery, FuzzyTermQuery, Occur, PhrasePrefixQuery, Query, RegexQuery,
-        TermQuery,
+        AllQuery, BooleanQuery, FuzzyTermQuery, Occur, PhrasePrefixQuery, PhraseQuery, Query,
+        RegexQuery, TermQuery,
     },
-    schema::{Field, IndexRecordOption, Schema},
+    schema::{Field, FieldType, IndexRecordOption, Schema},
 };
 
 use super::{
-    datafusion::udf::fuzzy_match_udf,
+    datafusion::udf::{fuzzy_match_udf, match_phrase_udf},
     utils::{is_field, is_value, split_conjunction, trim_quotes},
 };
 use crate::service::search::{
     datafusion::udf::{
-        MATCH_FIELD_IGNORE_CASE_UDF_NAME, MATCH_FIELD_UDF_NAME, STR_MATCH_UDF_IGNORE_CASE_NAME,
-        STR_MATCH_UDF_NAME,
+        MATCH_FIELD_IGNORE_CASE_UDF_NAME, MATCH_FIELD_UDF_NAME, MATCH_PHRASE_UDF_NAME,
+        STR_MATCH_UDF_IGNORE_CASE_NAME, STR_MATCH_UDF_NAME,
         match_all_udf::{FUZZY_MATCH_ALL_UDF_NAME, MATCH_ALL_UDF_NAME},
         str_match_udf,
     },
@@ -126,18 +129,19 @@ impl IndexCondition {
             .join(" AND ")
     }
 
-    // get the tantivy query for the index condition
+    // get the tantivy query for the index condition, the match_all() function will search all the
+    // full text search fields
     pub fn to_tantivy_query(
         &self,
         schema: Schema,
-        default_field: Option<Field>,
+        fts_fields: &[Field],
     ) -> anyhow::Result<Box<dyn Query>> {
         let queries = self
             .conditions
             .iter()
             .map(|condition| {
                 condition
-                    .to_tantivy_query(&schema, default_field)
+                    .to_tantivy_query(&schema, fts_fields)
                     .map(|condition| (Occur::Must, condition))
             })
             .collect::<anyhow::Result<Vec<_>>>()?;
@@ -165,6 +169,54 @@ impl IndexCondition {
         projection
     }
 
+    // split the conditions into the part can be searched in the tantivy index and the residual part
+    // which needs to be filtered by datafusion, the conditions are connected by AND operator, so
+    // the result of the indexable part is a superset of the result of the full condition
+    pub fn split_indexable(
+        &self,
+        schema: &Schema,
+        fts_fields: &[Field],
+    ) -> (IndexCondition, IndexCondition) {
+        let (indexable, residual) = self
+            .conditions
+            .iter()
+            .cloned()
+            .partition(|condition| condition.is_indexable(schema, fts_fields));
+        (
+            IndexCondition {
+                conditions: indexable,
+            },
+            IndexCondition {
+                conditions: residual,
+            },
+        )
+    }
+
+    /// Checks the fields of the conditions against the tantivy schema upfront and returns the
+    /// sorted fields missing in the schema. The match_all() function is reported as the
+    /// catch-all field if the schema has no full text search field.
+    pub fn validate_against_schema(&self, schema: &Schema) -> Result<(), Vec<String>> {
+        let has_fts_fields = !get_fts_fields(schema, &[]).is_empty();
+        let mut missing = self
+            .conditions
+            .iter()
+            .flat_map(|condition| condition.get_tantivy_fields())
+            .filter(|field| {
+                if field == INDEX_FIELD_NAME_FOR_ALL {
+                    !has_fts_fields
+                } else {
+                    schema.get_field(field).is_err()
+                }
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        missing.sort();
+        missing.dedup();
+        Err(missing)
+    }
+
     pub fn need_all_term_fields(&self) -> Vec<String> {
         self.conditions
             .iter()
@@ -245,6 +297,8 @@ pub enum Condition {
     MatchAll(String),
     // term, distance
     FuzzyMatchAll(String, u8),
+    // field, phrase, slop
+    MatchPhrase(String, String, u32),
     All(),
     Or(Box<Condition>, Box<Condition>),
     And(Box<Condition>, Box<Condition>),
@@ -276,6 +330,9 @@ impl Condition {
             Condition::FuzzyMatchAll(value, distance) => {
                 format!("{INDEX_FIELD_NAME_FOR_ALL}:fuzzy({value}, {distance})")
             }
+            Condition::MatchPhrase(field, value, slop) => {
+                format!("match_phrase({field}, '{value}', {slop})")
+            }
             Condition::All() => "ALL".to_string(),
             Condition::Or(left, right) => format!("({} OR {})", left.to_query(), right.to_query()),
             Condition::And(left, right) => {
@@ -337,6 +394,17 @@ impl Condition {
                     } else {
                         unreachable!()
                     }
+                } else if fn_name == MATCH_PHRASE_UDF_NAME {
+                    if let FunctionArguments::List(list) = &func.args {
+                        let field = get_arg_name(&list.args[0]);
+                        let value = trim_quotes(list.args[1].to_string().as_str());
+                        let slop = trim_quotes(list.args[2].to_string().as_str())
+                            .parse()
+                            .unwrap_or(0);
+                        Condition::MatchPhrase(field, value, slop)
+                    } else {
+                        unreachable!()
+                    }
                 } else if fn_name == STR_MATCH_UDF_NAME || fn_name == MATCH_FIELD_UDF_NAME {
                     if let FunctionArguments::List(list) = &func.args {
                         let field = get_arg_name(&list.args[0]);
@@ -433,6 +501,12 @@ impl Condition {
                     let distance = get_physical_value(&expr.args()[1]).parse().unwrap_or(1);
                     Condition::FuzzyMatchAll(value, distance)
                 }
+                MATCH_PHRASE_UDF_NAME => {
+                    let field = get_physical_column_name(&expr.args()[0]).to_string();
+                    let value = get_physical_value(&expr.args()[1]);
+                    let slop = get_physical_value(&expr.args()[2]).parse().unwrap_or(0);
+                    Condition::MatchPhrase(field, value, slop)
+                }
                 STR_MATCH_UDF_NAME | MATCH_FIELD_UDF_NAME => {
                     let field = get_physical_column_name(&expr.args()[0]).to_string();
                     let value = get_physical_value(&expr.args()[1]);
@@ -455,7 +529,7 @@ impl Condition {
     pub fn to_tantivy_query(
         &self,
         schema: &Schema,
-        default_field: Option<Field>,
+        fts_fields: &[Field],
     ) -> anyhow::Result<Box<dyn Query>> {
         Ok(match self {
             Condition::Equal(field, value) => {
@@ -492,7 +566,11 @@ impl Condition {
                 }
             }
             Condition::Regex(field, value) => {
-                let field = schema.get_field(field)?;
+                // return error if the field is not indexed, the file will be added back to
+                // datafusion to apply the regex filter
+                let field = schema.get_field(field).map_err(|_| {
+                    anyhow::anyhow!("regex field {field} not found in tantivy index")
+                })?;
                 Box::new(RegexQuery::from_pattern(value, field)?)
             }
             Condition::StrMatch(field, value, case_sensitive) => {
@@ -500,86 +578,74 @@ impl Condition {
                 Box::new(ContainsQuery::new(value, field, *case_sensitive)?)
             }
             Condition::MatchAll(value) => {
-                let default_field = default_field.ok_or_else(|| {
-                    anyhow::anyhow!("There's no FullTextSearch field for match_all() function")
-                })?;
+                if fts_fields.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "There's no FullTextSearch field for match_all() function"
+                    ));
+                }
                 if value.is_empty() || value == "*" {
                     Box::new(AllQuery {})
                 } else {
-                    let mut tokens = o2_collect_search_tokens(value);
-                    let contains_search =
-                        tokens.len() == 1 && value.starts_with("*") && value.ends_with("*");
-                    let first_prefix = if value.starts_with("*") && !tokens.is_empty() {
-                        Some(tokens.remove(0))
-                    } else {
-                        None
-                    };
-                    let last_prefix = if value.ends_with("*") {
-                        tokens.pop()
-                    } else {
-                        None
-                    };
-                    let mut terms: Vec<Box<dyn Query>> = tokens
-                        .into_iter()
-                        .map(|value| {
-                            let term = Term::from_field_text(default_field, &value);
-                            Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as _
+                    let queries = fts_fields
+                        .iter()
+                        .map(|field| {
+                            match_all_query(value, *field, field_tokenizer(schema, *field))
                         })
-                        .collect();
-                    if let Some(value) = first_prefix {
-                        terms.push(if contains_search {
-                            Box::new(ContainsQuery::new_case_insensitive(&value, default_field)?)
-                        } else {
-                            let value = format!(".*{value}");
-                            Box::new(RegexQuery::from_pattern(&value, default_field)?)
-                        });
-                    }
-                    if let Some(value) = last_prefix {
-                        terms.push(Box::new(PhrasePrefixQuery::new_with_offset(vec![(
-                            0,
-                            Term::from_field_text(default_field, &value),
-                        )])));
-                    }
-                    if !terms.is_empty() {
-                        if terms.len() > 1 {
-                            Box::new(BooleanQuery::intersection(terms))
-                        } else {
-                            terms.remove(0)
-                        }
-                    } else {
-                        return Err(anyhow::anyhow!(
-                            "The value of match_all() function can't be empty"
-                        ));
-                    }
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    union_queries(queries)
                 }
             }
             Condition::FuzzyMatchAll(value, distance) => {
-                let default_field = default_field.ok_or_else(|| {
-                    anyhow::anyhow!(
+                if fts_fields.is_empty() {
+                    return Err(anyhow::anyhow!(
                         "There's no FullTextSearch field for fuzzy_match_all() function"
-                    )
-                })?;
+                    ));
+                }
                 if value.is_empty() {
                     return Err(anyhow::anyhow!(
                         "The value of fuzzy_match_all() function can't be empty"
                     ));
                 }
-                let term = Term::from_field_text(default_field, value);
-                Box::new(FuzzyTermQuery::new(term, *distance, false))
+                let queries = fts_fields
+                    .iter()
+                    .map(|field| {
+                        let term = Term::from_field_text(*field, value);
+                        Box::new(FuzzyTermQuery::new(term, *distance, false)) as _
+                    })
+                    .collect();
+                union_queries(queries)
+            }
+            Condition::MatchPhrase(field, value, slop) => {
+                // the full text search fields are indexed into _all, unless the field has its
+                // own full text search field in the index
+                let fields = match schema.get_field(field) {
+                    Ok(field) if fts_fields.contains(&field) => vec![field],
+                    _ => fts_fields.to_vec(),
+                };
+                if fields.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "There's no FullTextSearch field for match_phrase() function"
+                    ));
+                }
+                let queries = fields
+                    .iter()
+                    .map(|field| match_phrase_query(schema, *field, value, *slop))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                union_queries(queries)
             }
             Condition::All() => Box::new(AllQuery {}),
             Condition::Or(left, right) => {
-                let left_query = left.to_tantivy_query(schema, default_field)?;
-                let right_query = right.to_tantivy_query(schema, default_field)?;
+                let left_query = left.to_tantivy_query(schema, fts_fields)?;
+                let right_query = right.to_tantivy_query(schema, fts_fields)?;
                 Box::new(BooleanQuery::union(vec![left_query, right_query]))
             }
             Condition::And(left, right) => {
-                let left_query = left.to_tantivy_query(schema, default_field)?;
-                let right_query = right.to_tantivy_query(schema, default_field)?;
+                let left_query = left.to_tantivy_query(schema, fts_fields)?;
+                let right_query = right.to_tantivy_query(schema, fts_fields)?;
                 Box::new(BooleanQuery::intersection(vec![left_query, right_query]))
             }
             Condition::Not(condition) => {
-                let query = condition.to_tantivy_query(schema, default_field)?;
+                let query = condition.to_tantivy_query(schema, fts_fields)?;
                 Box::new(BooleanQuery::new(vec![
                     (Occur::MustNot, query),
                     (Occur::Must, Box::new(AllQuery {})),
@@ -615,11 +681,25 @@ impl Condition {
             Condition::In(field, _, negated) if *negated => {
                 fields.insert(field.clone());
             }
-            Condition::All() | Condition::Equal(..) | Condition::In(..) => {}
+            Condition::All()
+            | Condition::Equal(..)
+            | Condition::In(..)
+            | Condition::MatchPhrase(..) => {}
         }
         fields
     }
 
+    // check if all the fields of the condition are indexed in the tantivy schema
+    pub fn is_indexable(&self, schema: &Schema, fts_fields: &[Field]) -> bool {
+        self.get_tantivy_fields().iter().all(|field| {
+            if field == INDEX_FIELD_NAME_FOR_ALL {
+                !fts_fields.is_empty()
+            } else {
+                schema.get_field(field).is_ok()
+            }
+        })
+    }
+
     // get the fields use for search in tantivy
     pub fn get_tantivy_fields(&self) -> HashSet<String> {
         let mut fields = HashSet::new();
@@ -631,7 +711,7 @@ impl Condition {
             | Condition::StrMatch(field, ..) => {
                 fields.insert(field.clone());
             }
-            Condition::MatchAll(_) | Condition::FuzzyMatchAll(..) => {
+            Condition::MatchAll(_) | Condition::FuzzyMatchAll(..) | Condition::MatchPhrase(..) => {
                 fields.insert(INDEX_FIELD_NAME_FOR_ALL.to_string());
             }
             Condition::All() => {}
@@ -654,7 +734,8 @@ impl Condition {
             | Condition::NotEqual(field, _)
             | Condition::StrMatch(field, ..)
             | Condition::In(field, ..)
-            | Condition::Regex(field, _) => {
+            | Condition::Regex(field, _)
+            | Condition::MatchPhrase(field, ..) => {
                 fields.insert(field.clone());
             }
             Condition::MatchAll(_) | Condition::FuzzyMatchAll(..) => {
@@ -708,8 +789,25 @@ impl Condition {
                     left, values, *negated, schema,
                 )?))
             }
-            Condition::Regex(..) => {
-                unreachable!("Condition::Regex query only support for promql")
+            Condition::Regex(name, value) => {
+                // the filter is added back when the regex field isn't indexed, the tantivy regex
+                // matches the whole term, so the pattern is anchored
+                let index = schema.index_of(name)?;
+                let col = Arc::new(Column::new(name, index));
+                let left: Arc<dyn PhysicalExpr> =
+                    if *schema.field(index).data_type() == DataType::Utf8 {
+                        col
+                    } else {
+                        Arc::new(CastExpr::new(col, DataType::Utf8, None))
+                    };
+                let pattern = Arc::new(Literal::new(ScalarValue::Utf8(Some(format!(
+                    "^(?:{value})$"
+                )))));
+                Ok(Arc::new(BinaryExpr::new(
+                    left,
+                    Operator::RegexMatch,
+                    pattern,
+                )))
             }
             Condition::MatchAll(value) => {
                 let value = value
@@ -786,6 +884,25 @@ impl Condition {
                 }
                 Ok(disjunction(expr_list))
             }
+            Condition::MatchPhrase(name, value, slop) => {
+                let index = schema.index_of(name)?;
+                let col = Arc::new(Column::new(name, index));
+                // the match_phrase udf only accepts Utf8
+                let left: Arc<dyn PhysicalExpr> =
+                    if *schema.field(index).data_type() == DataType::Utf8 {
+                        col
+                    } else {
+                        Arc::new(CastExpr::new(col, DataType::Utf8, None))
+                    };
+                let phrase = Arc::new(Literal::new(ScalarValue::Utf8(Some(value.to_string()))));
+                let slop = Arc::new(Literal::new(ScalarValue::Int64(Some(*slop as i64))));
+                Ok(Arc::new(ScalarFunctionExpr::try_new(
+                    Arc::new(match_phrase_udf::MATCH_PHRASE_UDF.clone()),
+                    vec![left, phrase, slop],
+                    schema,
+                    Arc::new(ConfigOptions::default()),
+                )?))
+            }
             Condition::All() => Ok(Arc::new(Literal::new(ScalarValue::Boolean(Some(true))))),
             Condition::Or(left, right) => {
                 let left = left.to_physical_expr(schema, fst_fields)?;
@@ -813,6 +930,7 @@ impl Condition {
             Condition::Regex(..) => false,
             Condition::MatchAll(v) => is_alphanumeric(v),
             Condition::FuzzyMatchAll(..) => false,
+            Condition::MatchPhrase(..) => false,
             Condition::All() => true,
             Condition::Or(left, right) => left.can_remove_filter() && right.can_remove_filter(),
             Condition::And(left, right) => left.can_remove_filter() && right.can_remove_filter(),
@@ -821,6 +939,153 @@ impl Condition {
     }
 }
 
+/// Returns all the full text search fields in the tantivy schema, the catch-all field `_all`
+/// comes first, followed by the other text fields tokenized by a full text search tokenizer.
+///
+/// The `exclude_fields` of the stream settings are skipped so they aren't searched by
+/// `match_all()` even if the index file was built before they were excluded.
+pub fn get_fts_fields(schema: &Schema, exclude_fields: &[String]) -> Vec<Field> {
+    let mut fields = schema
+        .get_field(INDEX_FIELD_NAME_FOR_ALL)
+        .ok()
+        .into_iter()
+        .collect::<Vec<_>>();
+    for (field, entry) in schema.fields() {
+        if fields.contains(&field) || exclude_fields.iter().any(|f| f == entry.name()) {
+            continue;
+        }
+        if let FieldType::Str(opts) = entry.field_type()
+            && opts
+                .get_indexing_options()
+                .is_some_and(|opts| is_fts_tokenizer(opts.tokenizer()))
+        {
+            fields.push(field);
+        }
+    }
+    fields
+}
+
+// the tokenizer of the full text search field, the search tokens need to be collected by the
+// same tokenizer as the index
+fn field_tokenizer(schema: &Schema, field: Field) -> &str {
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(opts) => opts
+            .get_indexing_options()
+            .map(|opts| opts.tokenizer())
+            .unwrap_or(O2_TOKENIZER),
+        _ => O2_TOKENIZER,
+    }
+}
+
+// check if the positions of the field are indexed, the phrase query can't be searched without them
+fn has_positions(schema: &Schema, field: Field) -> bool {
+    match schema.get_field_entry(field).field_type() {
+        FieldType::Str(opts) => opts
+            .get_indexing_options()
+            .is_some_and(|opts| opts.index_option().has_positions()),
+        _ => false,
+    }
+}
+
+// build the tantivy query of match_phrase() function for one full text search field, the index
+// is built without positions by default, then the file is only pruned by requiring every term of
+// the phrase and datafusion filters the phrase
+fn match_phrase_query(
+    schema: &Schema,
+    field: Field,
+    value: &str,
+    slop: u32,
+) -> anyhow::Result<Box<dyn Query>> {
+    let mut terms = collect_search_tokens(field_tokenizer(schema, field), value)
+        .into_iter()
+        .map(|token| Term::from_field_text(field, &token))
+        .collect::<Vec<_>>();
+    Ok(match terms.len() {
+        0 => {
+            return Err(anyhow::anyhow!(
+                "The value of match_phrase() function can't be empty"
+            ));
+        }
+        1 => Box::new(TermQuery::new(terms.remove(0), IndexRecordOption::Basic)),
+        _ if has_positions(schema, field) => {
+            let mut query = PhraseQuery::new(terms);
+            query.set_slop(slop);
+            Box::new(query)
+        }
+        _ => Box::new(BooleanQuery::intersection(
+            terms
+                .into_iter()
+                .map(|term| Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as _)
+                .collect(),
+        )),
+    })
+}
+
+// build the tantivy query of match_all() function for one full text search field
+fn match_all_query(value: &str, field: Field, tokenizer: &str) -> anyhow::Result<Box<dyn Query>> {
+    let mut tokens = collect_search_tokens(tokenizer, value);
+    // a value shorter than the ngram size has no tokens, search the terms containing it instead
+    let text = value.trim_matches('*');
+    if tokens.is_empty()
+        && tokenizer != O2_TOKENIZER
+        && is_fts_tokenizer(tokenizer)
+        && !text.trim().is_empty()
+    {
+        return Ok(Box::new(ContainsQuery::new_case_insensitive(text, field)?));
+    }
+    let contains_search = tokens.len() == 1 && value.starts_with("*") && value.ends_with("*");
+    let first_prefix = if value.starts_with("*") && !tokens.is_empty() {
+        Some(tokens.remove(0))
+    } else {
+        None
+    };
+    let last_prefix = if value.ends_with("*") {
+        tokens.pop()
+    } else {
+        None
+    };
+    let mut terms: Vec<Box<dyn Query>> = tokens
+        .into_iter()
+        .map(|value| {
+            let term = Term::from_field_text(field, &value);
+            Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as _
+        })
+        .collect();
+    if let Some(value) = first_prefix {
+        terms.push(if contains_search {
+            Box::new(ContainsQuery::new_case_insensitive(&value, field)?)
+        } else {
+            let value = format!(".*{value}");
+            Box::new(RegexQuery::from_pattern(&value, field)?)
+        });
+    }
+    if let Some(value) = last_prefix {
+        terms.push(Box::new(PhrasePrefixQuery::new_with_offset(vec![(
+            0,
+            Term::from_field_text(field, &value),
+        )])));
+    }
+    if terms.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The value of match_all() function can't be empty"
+        ));
+    }
+    Ok(if terms.len() > 1 {
+        Box::new(BooleanQuery::intersection(terms))
+    } else {
+        terms.remove(0)
+    })
+}
+
+// union the queries, avoid wrapping a single query with BooleanQuery
+fn union_queries(mut queries: Vec<Box<dyn Query>>) -> Box<dyn Query> {
+    if queries.len() == 1 {
+        queries.remove(0)
+    } else {
+        Box::new(BooleanQuery::union(queries))
+    }
+}
+
 // check if function is match_all and only have one argument
 // check if binary operator is equal and one side is field and the other side is value
 // and the field is in the index_fields
@@ -1062,6 +1327,40 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_condition_regex_to_physical_expr() {
+        use arrow::{
+            array::{Array, BooleanArray, StringArray},
+            record_batch::RecordBatch,
+        };
+
+        let schema = Arc::new(arrow_schema::Schema::new(vec![arrow_schema::Field::new(
+            "path",
+            DataType::Utf8,
+            false,
+        )]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(StringArray::from(vec![
+                "/api/v1/users",
+                "/api/v2/orders",
+                "/api/v12/users/1",
+            ]))],
+        )
+        .unwrap();
+
+        // the regex filter added back matches the whole value like the tantivy regex query
+        let condition = Condition::Regex("path".to_string(), "/api/v[0-9]+/users".to_string());
+        let expr = condition.to_physical_expr(&schema, &[]).unwrap();
+        let result = expr
+            .evaluate(&batch)
+            .unwrap()
+            .into_array(batch.num_rows())
+            .unwrap();
+        let result = result.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(result, &BooleanArray::from(vec![true, false, false]));
+    }
+
     #[test]
     fn test_condition_get_tantivy_fields_equal() {
         let condition = Condition::Equal("field1".to_string(), "value1".to_string());
@@ -1730,6 +2029,68 @@ mod tests {
         assert_eq!(query_string, "");
     }
 
+    #[test]
+    fn test_index_condition_split_indexable() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("status", tantivy::schema::STRING);
+        let schema = schema_builder.build();
+
+        let mut index_condition = IndexCondition::new();
+        index_condition.add_condition(Condition::Equal("status".to_string(), "500".to_string()));
+        index_condition.add_condition(Condition::Equal("method".to_string(), "GET".to_string()));
+        index_condition.add_condition(Condition::MatchAll("error".to_string()));
+        index_condition.add_condition(Condition::Or(
+            Box::new(Condition::Equal("status".to_string(), "404".to_string())),
+            Box::new(Condition::Equal("method".to_string(), "POST".to_string())),
+        ));
+
+        let (indexable, residual) = index_condition.split_indexable(&schema, &[]);
+        assert_eq!(indexable.to_query(), "status=500");
+        assert_eq!(residual.conditions.len(), 3);
+
+        // match_all is indexable when there are full text search fields
+        let fts_fields = vec![schema.get_field("status").unwrap()];
+        let (indexable, residual) = index_condition.split_indexable(&schema, &fts_fields);
+        assert_eq!(indexable.conditions.len(), 2);
+        assert_eq!(residual.conditions.len(), 2);
+    }
+
+    #[test]
+    fn test_index_condition_validate_against_schema() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("status", tantivy::schema::STRING);
+        let schema = schema_builder.build();
+
+        let mut index_condition = IndexCondition::new();
+        index_condition.add_condition(Condition::Equal("status".to_string(), "500".to_string()));
+        assert_eq!(index_condition.validate_against_schema(&schema), Ok(()));
+
+        index_condition.add_condition(Condition::Or(
+            Box::new(Condition::Equal("status".to_string(), "404".to_string())),
+            Box::new(Condition::Equal("method".to_string(), "POST".to_string())),
+        ));
+        index_condition.add_condition(Condition::Not(Box::new(Condition::Equal(
+            "method".to_string(),
+            "GET".to_string(),
+        ))));
+        index_condition.add_condition(Condition::MatchAll("error".to_string()));
+        assert_eq!(
+            index_condition.validate_against_schema(&schema),
+            Err(vec![
+                INDEX_FIELD_NAME_FOR_ALL.to_string(),
+                "method".to_string()
+            ])
+        );
+
+        // match_all is searchable when there is a full text search field
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field(INDEX_FIELD_NAME_FOR_ALL, tantivy::schema::TEXT);
+        let schema = schema_builder.build();
+        let mut index_condition = IndexCondition::new();
+        index_condition.add_condition(Condition::MatchAll("error".to_string()));
+        assert_eq!(index_condition.validate_against_schema(&schema), Ok(()));
+    }
+
     #[test]
     fn test_index_condition_is_empty() {
         let mut index_condition = IndexCondition::new();
@@ -1847,6 +2208,20 @@ mod tests {
         assert_eq!(condition.to_query(), "_all:search_term");
     }
 
+    #[test]
+    fn test_match_all_query_short_ngram_value() {
+        use config::utils::tantivy::tokenizer::NGRAM_TOKENIZER;
+
+        let mut builder = Schema::builder();
+        let field = builder.add_text_field(INDEX_FIELD_NAME_FOR_ALL, tantivy::schema::TEXT);
+        for value in ["a", "*A*", "a*"] {
+            let query = match_all_query(value, field, NGRAM_TOKENIZER).unwrap();
+            assert!(query.downcast_ref::<ContainsQuery>().is_some());
+        }
+        assert!(match_all_query("*", field, NGRAM_TOKENIZER).is_err());
+        assert!(match_all_query("ab", field, NGRAM_TOKENIZER).is_ok());
+    }
+
     #[test]
     fn test_condition_to_query_fuzzy_match_all() {
         let condition = Condition::FuzzyMatchAll("search_term".to_string(), 2);
@@ -178,6 +178,8 @@ pub async fn search(
         request.set_local_mode(Some(v));
     }
     request.set_use_cache(in_req.use_cache);
+    request.set_disable_index_skip_threshold(in_req.disable_index_skip_threshold);
+    request.set_strict_index_only(in_req.strict_index_only);
     let meta = Sql::new_from_req(&request, &query).await?;
 
     #[cfg(feature = "enterprise")]
@@ -1251,6 +1253,14 @@ pub async fn query_status() -> Result<search::QueryStatusResponse, Error> {
                 file_list_took: scan_stats.file_list_took,
                 aggs_cache_ratio: scan_stats.aggs_cache_ratio,
                 peak_memory_usage: scan_stats.peak_memory_usage / 1024 / 1024, // change to MB
+                parquet_cache_hits: scan_stats.parquet_cache_hits,
+                parquet_cache_misses: scan_stats.parquet_cache_misses,
+                index_cache_hits: scan_stats.index_cache_hits,
+                index_cache_misses: scan_stats.index_cache_misses,
+                downloaded_size: scan_stats.downloaded_size / 1024 / 1024, // change to MB
+                download_skipped_size: scan_stats.download_skipped_size / 1024 / 1024, // change to MB
+                file_stat_cache_hits: scan_stats.file_stat_cache_hits,
+                file_stat_cache_misses: scan_stats.file_stat_cache_misses,
             });
         let query_status = if result.is_queue {
             "waiting"
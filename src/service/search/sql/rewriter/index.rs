@@ -144,12 +144,14 @@ impl VisitorMut for IndexVisitor {
                 }
 
                 // addational check for simple distinct query
-                // filter should all extract to index condition and index condition should have
-                // the same field as the distinct field
+                // filter should all extract to index condition, and the distinct field should be
+                // indexed and ordered ascending unless the condition is a str_match of the
+                // distinct field
                 if can_remove_filter
                     && let Some(distinct) = is_simple_distinct_query(query)
                     && let Some(index) = self.index_condition.as_ref()
                     && !index.is_simple_str_match(&distinct.0)
+                    && (!self.index_fields.contains(&distinct.0) || !distinct.2)
                 {
                     self.can_optimize = false;
                 }
@@ -266,6 +266,8 @@ pub async fn search(
         is_analyze: flight_request.search_info.is_analyze,
         sampling_config: flight_request.search_info.sampling_config.clone(),
         clear_cache: req.overwrite_cache,
+        disable_index_skip_threshold: req.disable_index_skip_threshold,
+        strict_index_only: req.strict_index_only,
     };
 
     let context = tracing::Span::current().context();
@@ -62,11 +62,10 @@ pub async fn check_work_group(
     } else {
         infra::dist_lock::lock_with_trace_id(trace_id, &locker_key, timeout)
             .await
-            .map_err(|e| {
+            .inspect_err(|_| {
                 metrics::QUERY_PENDING_NUMS
                     .with_label_values(&[org_id])
                     .dec();
-                Error::Message(e.to_string())
             })?
     };
 
@@ -117,11 +116,10 @@ pub async fn check_work_group(
     } else {
         infra::dist_lock::lock_with_trace_id(trace_id, &locker_key, timeout)
             .await
-            .map_err(|e| {
+            .inspect_err(|_| {
                 metrics::QUERY_PENDING_NUMS
                     .with_label_values(&[org_id])
                     .dec();
-                Error::Message(e.to_string())
             })?
     };
 
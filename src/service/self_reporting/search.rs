@@ -58,6 +58,8 @@ pub async fn get_usage(sql: String, start_time: i64, end_time: i64) -> Result<Ve
         use_cache: default_use_cache(),
         clear_cache: false,
         local_mode: None,
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     let trace_id = ider::uuid();
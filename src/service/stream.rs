@@ -31,7 +31,10 @@ use config::{
             StreamStats, StreamType, TimeRange, UpdateStreamSettings,
         },
     },
-    utils::{flatten::format_label_name, json, time::now_micros, util::get_distinct_stream_name},
+    utils::{
+        flatten::format_label_name, json, tantivy::tokenizer, time::now_micros,
+        util::get_distinct_stream_name,
+    },
 };
 use datafusion::arrow::datatypes::{DataType, Field, Schema};
 use hashbrown::{HashMap, HashSet};
@@ -569,6 +572,15 @@ pub async fn update_stream_settings(
     if let Err(err) = validate_index_field_conflicts(&settings, &new_settings) {
         return Ok(MetaHttpResponse::bad_request(err));
     }
+    if let Some(tokenizer) = new_settings.full_text_search_tokenizer.as_ref()
+        && !tokenizer.is_empty()
+        && !tokenizer::is_fts_tokenizer(tokenizer)
+    {
+        return Ok(MetaHttpResponse::bad_request(format!(
+            "invalid full text search tokenizer: {tokenizer}, supported tokenizers: {}",
+            tokenizer::fts_tokenizer_names().join(", ")
+        )));
+    }
 
     // process new fields first
     let new_fields = std::mem::take(&mut new_settings.fields);
@@ -614,6 +626,11 @@ pub async fn update_stream_settings(
         settings.flatten_level = Some(flatten_level);
     }
 
+    // an empty tokenizer resets it to the default, it only applies to the new index files
+    if let Some(tokenizer) = new_settings.full_text_search_tokenizer.take() {
+        settings.full_text_search_tokenizer = (!tokenizer.is_empty()).then_some(tokenizer);
+    }
+
     if let Some(data_retention) = new_settings.data_retention {
         #[cfg(feature = "enterprise")]
         if org_id == META_ORG_ID && stream_name == USAGE_STREAM {
@@ -637,6 +654,10 @@ pub async fn update_stream_settings(
         settings.index_all_values = index_all_values;
     }
 
+    if let Some(cache_policy) = new_settings.cache_policy.take() {
+        settings.cache_policy = (!cache_policy.is_empty()).then_some(cache_policy);
+    }
+
     // if index_original_data is true, store_original_data must be true
     if settings.index_original_data {
         settings.store_original_data = true;
@@ -845,6 +866,23 @@ pub async fn update_stream_settings(
             .retain(|field| !new_settings.full_text_search_keys.remove.contains(field));
     }
 
+    if !new_settings.full_text_search_exclude_keys.add.is_empty() {
+        settings
+            .full_text_search_exclude_keys
+            .extend(new_settings.full_text_search_exclude_keys.add);
+        settings.full_text_search_exclude_keys.sort();
+        settings.full_text_search_exclude_keys.dedup();
+    }
+
+    if !new_settings.full_text_search_exclude_keys.remove.is_empty() {
+        settings.full_text_search_exclude_keys.retain(|field| {
+            !new_settings
+                .full_text_search_exclude_keys
+                .remove
+                .contains(field)
+        });
+    }
+
     if !new_settings.partition_keys.add.is_empty() {
         settings
             .partition_keys
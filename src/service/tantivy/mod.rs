@@ -26,7 +26,7 @@ use config::{
     INDEX_FIELD_NAME_FOR_ALL, TIMESTAMP_COL_NAME, get_config,
     utils::{
         inverted_index::convert_parquet_file_name_to_tantivy_file,
-        tantivy::tokenizer::{CollectType, O2_TOKENIZER, o2_tokenizer_build},
+        tantivy::tokenizer::{CollectType, O2_TOKENIZER, fts_tokenizer_build, is_fts_tokenizer},
     },
 };
 use futures::TryStreamExt;
@@ -40,6 +40,7 @@ pub(crate) async fn create_tantivy_index(
     caller: &str,
     parquet_file_name: &str,
     full_text_search_fields: &[String],
+    fts_tokenizer: &str,
     index_fields: &[String],
     schema: Arc<Schema>,
     reader: ParquetRecordBatchStream<std::io::Cursor<Bytes>>,
@@ -52,6 +53,7 @@ pub(crate) async fn create_tantivy_index(
         dir.clone(),
         reader,
         full_text_search_fields,
+        fts_tokenizer,
         index_fields,
         schema,
     )
@@ -97,11 +99,13 @@ pub(crate) async fn create_tantivy_index(
     Ok(index_size)
 }
 
-/// Create a tantivy index in the given directory for the record batch
+/// Create a tantivy index in the given directory for the record batch, the full text search
+/// fields are tokenized by the `fts_tokenizer`, the unknown tokenizer falls back to o2
 pub(crate) async fn generate_tantivy_index<D: tantivy::Directory>(
     tantivy_dir: D,
     mut reader: ParquetRecordBatchStream<std::io::Cursor<Bytes>>,
     full_text_search_fields: &[String],
+    fts_tokenizer: &str,
     index_fields: &[String],
     schema: Arc<Schema>,
 ) -> Result<Option<tantivy::Index>, anyhow::Error> {
@@ -138,11 +142,17 @@ pub(crate) async fn generate_tantivy_index<D: tantivy::Directory>(
     }
 
     // add fields to tantivy schema
+    let fts_tokenizer = if is_fts_tokenizer(fts_tokenizer) {
+        fts_tokenizer
+    } else {
+        log::warn!("unknown full text search tokenizer: {fts_tokenizer}, use {O2_TOKENIZER}");
+        O2_TOKENIZER
+    };
     if !full_text_search_fields.is_empty() {
         let fts_opts = tantivy::schema::TextOptions::default().set_indexing_options(
             tantivy::schema::TextFieldIndexing::default()
                 .set_index_option(tantivy::schema::IndexRecordOption::Basic)
-                .set_tokenizer(O2_TOKENIZER)
+                .set_tokenizer(fts_tokenizer)
                 .set_fieldnorms(false),
         );
         tantivy_schema_builder.add_text_field(INDEX_FIELD_NAME_FOR_ALL, fts_opts);
@@ -168,7 +178,10 @@ pub(crate) async fn generate_tantivy_index<D: tantivy::Directory>(
     let fts_field = tantivy_schema.get_field(INDEX_FIELD_NAME_FOR_ALL).ok();
 
     let tokenizer_manager = tantivy::tokenizer::TokenizerManager::default();
-    tokenizer_manager.register(O2_TOKENIZER, o2_tokenizer_build(CollectType::Ingest));
+    tokenizer_manager.register(
+        fts_tokenizer,
+        fts_tokenizer_build(fts_tokenizer, CollectType::Ingest),
+    );
     let mut index_writer = tantivy::IndexBuilder::new()
         .schema(tantivy_schema.clone())
         .tokenizers(tokenizer_manager)
@@ -329,7 +342,10 @@ mod tests {
         record_batch::RecordBatch,
     };
     use bytes::Bytes;
-    use config::{INDEX_FIELD_NAME_FOR_ALL, TIMESTAMP_COL_NAME};
+    use config::{
+        INDEX_FIELD_NAME_FOR_ALL, TIMESTAMP_COL_NAME,
+        utils::tantivy::tokenizer::{NGRAM_TOKENIZER, WHITESPACE_TOKENIZER},
+    };
     use parquet::arrow::async_reader::ParquetRecordBatchStream;
     use tantivy::directory::RamDirectory;
 
@@ -432,6 +448,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             empty_batch.schema(),
         )
@@ -452,6 +469,7 @@ mod tests {
             dir,
             stream,
             &[], // No full-text search fields
+            O2_TOKENIZER,
             &[], // No index fields
             batch.schema(),
         )
@@ -467,9 +485,15 @@ mod tests {
         let batch = create_test_batch(10, true, true, false);
         let stream = create_test_stream(vec![batch.clone()]).await;
 
-        let result =
-            generate_tantivy_index(dir, stream, &["content".to_string()], &[], batch.schema())
-                .await;
+        let result = generate_tantivy_index(
+            dir,
+            stream,
+            &["content".to_string()],
+            O2_TOKENIZER,
+            &[],
+            batch.schema(),
+        )
+        .await;
 
         assert!(result.is_ok());
         let index = result.unwrap();
@@ -482,14 +506,101 @@ mod tests {
         assert!(schema.get_field(TIMESTAMP_COL_NAME).is_ok());
     }
 
+    #[tokio::test]
+    async fn test_generate_tantivy_index_match_phrase() {
+        use crate::service::search::index::{Condition, IndexCondition, get_fts_fields};
+
+        let dir = RamDirectory::create();
+        let batch = create_test_batch(10, true, true, false);
+        let stream = create_test_stream(vec![batch.clone()]).await;
+        let index = generate_tantivy_index(
+            dir,
+            stream,
+            &["content".to_string()],
+            O2_TOKENIZER,
+            &[],
+            batch.schema(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let schema = index.schema();
+        let fts_fields = get_fts_fields(&schema, &[]);
+        let searcher = index.reader().unwrap().searcher();
+
+        // the index has no positions, the phrase is pruned by requiring all of its terms in _all
+        let search = |phrase: &str| {
+            let mut condition = IndexCondition::new();
+            condition.add_condition(Condition::MatchPhrase(
+                "content".to_string(),
+                phrase.to_string(),
+                0,
+            ));
+            assert!(condition.split_indexable(&schema, &fts_fields).1.is_empty());
+            let query = condition
+                .to_tantivy_query(schema.clone(), &fts_fields)
+                .unwrap();
+            searcher.search(&query, &tantivy::collector::Count).unwrap()
+        };
+        assert_eq!(search("content number"), 10);
+        assert_eq!(search("Number 3"), 1);
+        assert_eq!(search("content missing"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_generate_tantivy_index_with_fts_tokenizer() {
+        let fts_tokenizer = |tokenizer: &str| {
+            let tokenizer = tokenizer.to_string();
+            async move {
+                let batch = create_test_batch(10, true, true, false);
+                let stream = create_test_stream(vec![batch.clone()]).await;
+                let index = generate_tantivy_index(
+                    RamDirectory::create(),
+                    stream,
+                    &["content".to_string()],
+                    &tokenizer,
+                    &[],
+                    batch.schema(),
+                )
+                .await
+                .unwrap()
+                .unwrap();
+                let schema = index.schema();
+                let field = schema.get_field(INDEX_FIELD_NAME_FOR_ALL).unwrap();
+                match schema.get_field_entry(field).field_type() {
+                    tantivy::schema::FieldType::Str(opts) => {
+                        opts.get_indexing_options().unwrap().tokenizer().to_string()
+                    }
+                    _ => panic!("text field expected"),
+                }
+            }
+        };
+
+        // the tokenizer is stored in the index schema for the search
+        assert_eq!(fts_tokenizer(NGRAM_TOKENIZER).await, NGRAM_TOKENIZER);
+        assert_eq!(
+            fts_tokenizer(WHITESPACE_TOKENIZER).await,
+            WHITESPACE_TOKENIZER
+        );
+        // the unknown tokenizer falls back to o2
+        assert_eq!(fts_tokenizer("unknown").await, O2_TOKENIZER);
+    }
+
     #[tokio::test]
     async fn test_generate_tantivy_index_with_index_fields() {
         let dir = RamDirectory::create();
         let batch = create_test_batch(10, true, false, true);
         let stream = create_test_stream(vec![batch.clone()]).await;
 
-        let result =
-            generate_tantivy_index(dir, stream, &[], &["status".to_string()], batch.schema()).await;
+        let result = generate_tantivy_index(
+            dir,
+            stream,
+            &[],
+            O2_TOKENIZER,
+            &["status".to_string()],
+            batch.schema(),
+        )
+        .await;
 
         assert!(result.is_ok());
         let index = result.unwrap();
@@ -512,6 +623,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             batch.schema(),
         )
@@ -540,6 +652,7 @@ mod tests {
             dir,
             stream,
             &["nonexistent_field".to_string()],
+            O2_TOKENIZER,
             &["another_nonexistent_field".to_string()],
             batch.schema(),
         )
@@ -563,6 +676,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string(), "nonexistent_field".to_string()],
+            O2_TOKENIZER,
             &[
                 "status".to_string(),
                 "another_nonexistent_field".to_string(),
@@ -594,6 +708,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             batch1.schema(),
         )
@@ -621,6 +736,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             batch.schema(),
         )
@@ -667,6 +783,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &["number_field".to_string()], // This field is not Utf8
             batch.schema(),
         )
@@ -695,6 +812,7 @@ mod tests {
             dir,
             stream,
             &["content".to_string()],
+            O2_TOKENIZER,
             &[TIMESTAMP_COL_NAME.to_string()], // This should be ignored
             batch.schema(),
         )
@@ -728,6 +846,7 @@ mod tests {
             "test_caller",
             "test_file.parquet",
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             empty_batch.schema(),
             stream,
@@ -748,6 +867,7 @@ mod tests {
             "test_caller",
             "test_file.parquet",
             &[], // No FTS fields
+            O2_TOKENIZER,
             &[], // No index fields
             batch.schema(),
             stream,
@@ -768,6 +888,7 @@ mod tests {
             "test_caller",
             "invalid_filename", // This won't convert to a valid tantivy filename
             &["content".to_string()],
+            O2_TOKENIZER,
             &["status".to_string()],
             batch.schema(),
             stream,
@@ -829,6 +950,7 @@ mod tests {
             dir,
             stream,
             &[], // No FTS fields
+            O2_TOKENIZER,
             &[
                 "continent".to_string(),
                 "name".to_string(),
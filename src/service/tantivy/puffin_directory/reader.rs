@@ -207,11 +207,11 @@ pub async fn warm_up_terms(
     let mut warm_up_fast_fields_futures = Vec::new();
     let mut warmed_segments = HashSet::new();
     for (field, terms) in terms_grouped_by_field {
+        if terms.is_empty() {
+            continue;
+        }
         for segment_reader in searcher.segment_readers() {
             let inv_idx = segment_reader.inverted_index(*field)?;
-            if terms.is_empty() {
-                continue;
-            }
             for (term, position_needed) in terms.iter() {
                 let inv_idx_clone = inv_idx.clone();
                 warm_up_terms_futures
@@ -180,6 +180,8 @@ pub async fn query_edges_from_stream_internal(
         use_cache: false,
         clear_cache: false,
         local_mode: Some(false),
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     // Check if stream exists (using Logs type since we write as logs stream)
@@ -185,6 +185,8 @@ async fn process_stream(
         use_cache: false,
         clear_cache: false,
         local_mode: Some(false),
+        disable_index_skip_threshold: false,
+        strict_index_only: false,
     };
 
     let trace_id = config::ider::generate();